@@ -0,0 +1,45 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    compile_grpc_service();
+}
+
+/// Generates the server of the gRPC service described in `proto/turing.proto`. The messages are
+/// written by hand in `src/grpc.rs`, so building doesn't need protoc.
+#[cfg(feature = "grpc")]
+fn compile_grpc_service() {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    let method = |name: &str, route_name: &str, input: &str, output: &str| {
+        Method::builder()
+            .name(name)
+            .route_name(route_name)
+            .input_type(format!("crate::grpc::{input}"))
+            .output_type(format!("crate::grpc::{output}"))
+            .codec_path("tonic::codec::ProstCodec")
+    };
+
+    let service = Service::builder()
+        .name("Simulator")
+        .package("turing")
+        .method(
+            method(
+                "create_session",
+                "CreateSession",
+                "CreateSessionRequest",
+                "Session",
+            )
+            .build(),
+        )
+        .method(method("step", "Step", "StepRequest", "StepReply").build())
+        .method(method("run", "Run", "RunRequest", "RunReply").build())
+        .method(method("get_tape", "GetTape", "GetTapeRequest", "Configuration").build())
+        .method(
+            method("stream_ticks", "StreamTicks", "StreamTicksRequest", "Tick")
+                .server_streaming()
+                .build(),
+        )
+        .build();
+
+    Builder::new().build_client(false).compile(&[service]);
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,317 @@
+use std::fs;
+
+use turing_lib::{
+    doc, grammar, report,
+    runner::{self, RunLimits},
+    svg,
+    timeseries::{self, TimeSeries},
+};
+
+use crate::source;
+
+pub const EXPORT_USAGE: &str = "Usage: turing export <filename.tng> <tape_data> [--steps <n>] [--svg <output.svg>] [--diagram <output.svg>] [--html <output.html>] [--scrubber] [--latex <output.tex>] [--head-chart <output.png|svg>] [--tape-chart <output.png|svg>] [--max-steps <n>]";
+
+pub const DOC_USAGE: &str = "Usage: turing doc <filename.tng> [--html] [--output <filename>]";
+
+pub const GRAMMAR_USAGE: &str =
+    "Usage: turing grammar <filename.tng> --alphabet <symbols> [--output <filename>]";
+
+const DEFAULT_REPORT_MAX_STEPS: usize = 10_000;
+
+struct ExportArgs<'a> {
+    filename: &'a str,
+    tape_data: &'a str,
+    steps: usize,
+    svg: Option<&'a str>,
+    diagram: Option<&'a str>,
+    html: Option<&'a str>,
+    scrubber: bool,
+    latex: Option<&'a str>,
+    head_chart: Option<&'a str>,
+    tape_chart: Option<&'a str>,
+    max_steps: Option<usize>,
+}
+
+/// Exports figures of a machine without opening a window: its configuration after some steps,
+/// its state diagram, and reports of a whole run. Returns the process exit code: 0 on success
+/// and 1 on error.
+pub fn export(args: &[String]) -> i32 {
+    let args = match parse_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            eprintln!("{EXPORT_USAGE}");
+            return 1;
+        }
+    };
+
+    match write_exports(&args) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            1
+        }
+    }
+}
+
+fn write_exports(args: &ExportArgs) -> Result<(), String> {
+    if let Some(output) = args.svg {
+        let mut machine = source::load(args.filename, args.tape_data)?;
+        let limits = RunLimits {
+            max_steps: Some(args.steps),
+            ..Default::default()
+        };
+        runner::run(machine.as_mut(), &limits);
+
+        write(output, &svg::configuration(machine.as_ref()))?;
+    }
+
+    if let Some(output) = args.diagram {
+        let machine = source::load_turing(args.filename, args.tape_data)?;
+        write(output, &svg::diagram(&machine))?;
+    }
+
+    let report_limits = RunLimits {
+        max_steps: Some(args.max_steps.unwrap_or(DEFAULT_REPORT_MAX_STEPS)),
+        ..Default::default()
+    };
+
+    if let Some(output) = args.html {
+        let file_data = source::read_definition(args.filename)?;
+
+        write(
+            output,
+            &report::html(&file_data, args.tape_data, &report_limits, args.scrubber)?,
+        )?;
+    }
+
+    if let Some(output) = args.latex {
+        let mut machine = source::load(args.filename, args.tape_data)?;
+        let (frames, _) = report::record(machine.as_mut(), &report_limits);
+
+        write(output, &report::latex(&frames, machine.blank_symbol()))?;
+    }
+
+    if args.head_chart.is_some() || args.tape_chart.is_some() {
+        let mut machine = source::load(args.filename, args.tape_data)?;
+        let series = timeseries::record(machine.as_mut(), &report_limits, 1);
+
+        if let Some(output) = args.head_chart {
+            write_chart(&series, output, true)?;
+        }
+        if let Some(output) = args.tape_chart {
+            write_chart(&series, output, false)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "charts")]
+fn write_chart(series: &TimeSeries, filename: &str, head: bool) -> Result<(), String> {
+    use turing_lib::chart::{self, Chart};
+
+    let kind = if head {
+        Chart::HeadPosition
+    } else {
+        Chart::TapeLength
+    };
+    chart::write(series, kind, std::path::Path::new(filename))
+}
+
+#[cfg(not(feature = "charts"))]
+fn write_chart(_: &TimeSeries, _: &str, _: bool) -> Result<(), String> {
+    Err("Charts need turing to be built with the \"charts\" feature.".to_string())
+}
+
+fn write(filename: &str, contents: &str) -> Result<(), String> {
+    fs::write(filename, contents).map_err(|_| format!("Could not write the file \"{filename}\""))
+}
+
+fn parse_args(args: &[String]) -> Result<ExportArgs<'_>, String> {
+    let [filename, tape_data, flags @ ..] = args else {
+        return Err("Missing filename or tape data.".to_string());
+    };
+
+    let mut export_args = ExportArgs {
+        filename,
+        tape_data,
+        steps: 0,
+        svg: None,
+        diagram: None,
+        html: None,
+        scrubber: false,
+        latex: None,
+        head_chart: None,
+        tape_chart: None,
+        max_steps: None,
+    };
+    let mut flags = flags.iter();
+
+    while let Some(flag) = flags.next() {
+        if flag == "--scrubber" {
+            export_args.scrubber = true;
+            continue;
+        }
+
+        let value = flags
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{flag}\"."))?;
+
+        match flag.as_str() {
+            "--steps" => {
+                export_args.steps = value.parse().map_err(|_| {
+                    format!("Invalid steps \"{value}\". It must be a non negative integer.")
+                })?;
+            }
+            "--svg" => export_args.svg = Some(value),
+            "--diagram" => export_args.diagram = Some(value),
+            "--html" => export_args.html = Some(value),
+            "--latex" => export_args.latex = Some(value),
+            "--head-chart" => export_args.head_chart = Some(value),
+            "--tape-chart" => export_args.tape_chart = Some(value),
+            "--max-steps" => {
+                export_args.max_steps = Some(value.parse().map_err(|_| {
+                    format!("Invalid step limit \"{value}\". It must be a non negative integer.")
+                })?);
+            }
+            _ => return Err(format!("Unexpected argument \"{flag}\".")),
+        }
+    }
+
+    if [
+        export_args.svg,
+        export_args.diagram,
+        export_args.html,
+        export_args.latex,
+        export_args.head_chart,
+        export_args.tape_chart,
+    ]
+    .iter()
+    .all(Option::is_none)
+    {
+        return Err(
+            "Missing output, use \"--svg\", \"--diagram\", \"--html\", \"--latex\", \"--head-chart\" or \"--tape-chart\"."
+                .to_string(),
+        );
+    }
+
+    Ok(export_args)
+}
+
+/// Generates the documentation of a machine from its doc comments, and prints it (or writes it
+/// to the output file). Returns the process exit code: 0 on success and 1 on error.
+pub fn doc(args: &[String]) -> i32 {
+    let (filename, flags) = match args {
+        [filename, flags @ ..] => (filename, flags),
+        [] => {
+            eprintln!("Error: Missing filename.");
+            eprintln!("{DOC_USAGE}");
+            return 1;
+        }
+    };
+
+    let mut as_html = false;
+    let mut output = None;
+    let mut flags = flags.iter();
+
+    while let Some(flag) = flags.next() {
+        match (flag.as_str(), flags.clone().next()) {
+            ("--html", _) => as_html = true,
+            ("--output", Some(value)) => {
+                output = Some(value);
+                flags.next();
+            }
+            _ => {
+                eprintln!("Error: Unexpected argument \"{flag}\".");
+                eprintln!("{DOC_USAGE}");
+                return 1;
+            }
+        }
+    }
+
+    let document = source::read_definition(filename).and_then(|file_data| {
+        if as_html {
+            doc::html(&file_data)
+        } else {
+            doc::markdown(&file_data)
+        }
+    });
+
+    let result = document.and_then(|document| match output {
+        Some(output) => write(output, &document),
+        None => {
+            print!("{document}");
+            Ok(())
+        }
+    });
+
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            1
+        }
+    }
+}
+
+/// Builds the unrestricted grammar that generates the words accepted by a Turing Machine, and
+/// prints it (or writes it to the output file). Returns the process exit code: 0 on success and
+/// 1 on error.
+pub fn grammar(args: &[String]) -> i32 {
+    let (filename, flags) = match args {
+        [filename, flags @ ..] => (filename, flags),
+        [] => {
+            eprintln!("Error: Missing filename.");
+            eprintln!("{GRAMMAR_USAGE}");
+            return 1;
+        }
+    };
+
+    let mut alphabet = None;
+    let mut output = None;
+    let mut flags = flags.iter();
+
+    while let Some(flag) = flags.next() {
+        match (flag.as_str(), flags.clone().next()) {
+            ("--alphabet", Some(value)) => {
+                alphabet = Some(value.chars().collect::<Vec<_>>());
+                flags.next();
+            }
+            ("--output", Some(value)) => {
+                output = Some(value);
+                flags.next();
+            }
+            _ => {
+                eprintln!("Error: Unexpected argument \"{flag}\".");
+                eprintln!("{GRAMMAR_USAGE}");
+                return 1;
+            }
+        }
+    }
+
+    let Some(alphabet) = alphabet else {
+        eprintln!("Error: Missing alphabet.");
+        eprintln!("{GRAMMAR_USAGE}");
+        return 1;
+    };
+
+    let grammar = source::load_turing(filename, "")
+        .and_then(|machine| grammar::unrestricted_grammar(&machine, &alphabet));
+
+    let result = grammar.and_then(|grammar| match output {
+        Some(output) => write(output, &grammar.to_string()),
+        None => {
+            print!("{grammar}");
+            Ok(())
+        }
+    });
+
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            1
+        }
+    }
+}
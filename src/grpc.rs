@@ -0,0 +1,525 @@
+// Every handler returns a tonic::Status, which is large but the error type of the service
+#![allow(clippy::result_large_err)]
+
+use std::{collections::HashMap, net::SocketAddr, sync::mpsc, thread};
+
+use tokio::sync::{mpsc as async_mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use turing_lib::{
+    automaton::{self, Automaton},
+    explain,
+    machine::Symbol,
+    runner::{self, CancellationToken, RunLimits, RunOutcome},
+};
+
+use crate::headless;
+
+use simulator_server::SimulatorServer;
+
+include!(concat!(env!("OUT_DIR"), "/turing.Simulator.rs"));
+
+pub const GRPC_USAGE: &str =
+    "Usage: turing grpc [--addr <address:port>] [--max-tape-cells <n>] [--max-memory <MiB>]";
+
+const DEFAULT_ADDR: &str = "127.0.0.1:50051";
+const DEFAULT_MAX_STEPS: u64 = 100_000;
+const MAX_STEP_COUNT: u64 = 10_000; // Per Step call, as every step is explained
+const TICK_BUFFER: usize = 64; // Ticks streamed ahead of a slow client
+
+// Messages of proto/turing.proto
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CreateSessionRequest {
+    #[prost(string, tag = "1")]
+    pub definition: String,
+    #[prost(string, tag = "2")]
+    pub tape: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Session {
+    #[prost(uint64, tag = "1")]
+    pub session_id: u64,
+    #[prost(message, optional, tag = "2")]
+    pub configuration: Option<Configuration>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StepRequest {
+    #[prost(uint64, tag = "1")]
+    pub session_id: u64,
+    #[prost(uint64, tag = "2")]
+    pub count: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StepReply {
+    #[prost(message, optional, tag = "1")]
+    pub configuration: Option<Configuration>,
+    #[prost(string, repeated, tag = "2")]
+    pub explanations: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RunRequest {
+    #[prost(uint64, tag = "1")]
+    pub session_id: u64,
+    #[prost(uint64, tag = "2")]
+    pub max_steps: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RunReply {
+    #[prost(string, tag = "1")]
+    pub verdict: String,
+    #[prost(uint64, tag = "2")]
+    pub steps: u64,
+    #[prost(message, optional, tag = "3")]
+    pub configuration: Option<Configuration>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetTapeRequest {
+    #[prost(uint64, tag = "1")]
+    pub session_id: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StreamTicksRequest {
+    #[prost(uint64, tag = "1")]
+    pub session_id: u64,
+    #[prost(uint64, tag = "2")]
+    pub max_steps: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Tick {
+    #[prost(uint64, tag = "1")]
+    pub step: u64,
+    #[prost(string, tag = "2")]
+    pub explanation: String,
+    #[prost(message, optional, tag = "3")]
+    pub configuration: Option<Configuration>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Configuration {
+    #[prost(string, tag = "1")]
+    pub state: String,
+    #[prost(uint64, tag = "2")]
+    pub head: u64,
+    #[prost(string, tag = "3")]
+    pub tape: String,
+    #[prost(bool, tag = "4")]
+    pub halted: bool,
+    #[prost(bool, tag = "5")]
+    pub accepting: bool,
+    #[prost(string, optional, tag = "6")]
+    pub stack: Option<String>,
+    #[prost(uint64, repeated, tag = "7")]
+    pub registers: Vec<u64>,
+}
+
+impl Configuration {
+    fn of(machine: &dyn Automaton) -> Self {
+        Self {
+            state: machine.current_state_name().to_string(),
+            head: machine.head_idx() as u64,
+            tape: machine
+                .tape()
+                .get_content()
+                .iter()
+                .map(|symbol| match symbol {
+                    Symbol::Mark(c) => *c,
+                    _ => machine.blank_symbol(),
+                })
+                .collect(),
+            halted: machine.is_halted(),
+            accepting: machine.is_accepting(),
+            stack: machine.stack().map(|stack| stack.iter().collect()),
+            registers: machine.registers().map(<[u64]>::to_vec).unwrap_or_default(),
+        }
+    }
+}
+
+/// The machines of every session. Machines can't be shared between threads, so they all live
+/// in one thread, which runs the jobs sent by the handlers of the requests one after another.
+struct Sessions {
+    machines: HashMap<u64, Box<dyn Automaton>>,
+    next_id: u64,
+    limits: RunLimits,
+}
+
+type Job = Box<dyn FnOnce(&mut Sessions) + Send>;
+
+struct Simulator {
+    jobs: mpsc::Sender<Job>,
+}
+
+/// Serves the simulator over gRPC (see `proto/turing.proto`), for backend services that drive
+/// many machines and want lower latency than the HTTP server. Every created machine is a
+/// session, which can be stepped, run and streamed. Returns the process exit code.
+pub fn grpc(args: &[String]) -> i32 {
+    let mut addr = DEFAULT_ADDR;
+    let mut limits = RunLimits::default();
+
+    for flag_and_value in args.chunks(2) {
+        match flag_and_value {
+            [flag, value] if flag == "--addr" => addr = value,
+            [flag, value] if flag == "--max-tape-cells" => {
+                match value.parse().ok().filter(|&cells| cells > 0) {
+                    Some(cells) => limits.max_tape_cells = Some(cells),
+                    None => {
+                        eprintln!("Error: Invalid tape cell limit \"{value}\". It must be a positive integer.");
+                        return 1;
+                    }
+                }
+            }
+            [flag, value] if flag == "--max-memory" => match headless::parse_memory_limit(value) {
+                Ok(bytes) => limits.max_memory = Some(bytes),
+                Err(err) => {
+                    eprintln!("Error: {err}");
+                    return 1;
+                }
+            },
+            _ => {
+                eprintln!("{GRPC_USAGE}");
+                return 1;
+            }
+        }
+    }
+
+    let socket_addr: SocketAddr = match addr.parse() {
+        Ok(socket_addr) => socket_addr,
+        Err(err) => {
+            eprintln!("Error: Could not listen on \"{addr}\": {err}");
+            return 1;
+        }
+    };
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!("Error: Could not start the gRPC server: {err}");
+            return 1;
+        }
+    };
+
+    let (jobs, job_receiver) = mpsc::channel::<Job>();
+    thread::spawn(move || {
+        let mut sessions = Sessions {
+            machines: HashMap::new(),
+            next_id: 1,
+            limits,
+        };
+
+        for job in job_receiver {
+            job(&mut sessions);
+        }
+    });
+
+    println!("Listening on grpc://{addr}");
+
+    let server = tonic::transport::Server::builder()
+        .add_service(SimulatorServer::new(Simulator { jobs }))
+        .serve(socket_addr);
+
+    match runtime.block_on(server) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Error: Could not listen on \"{addr}\": {err}");
+            1
+        }
+    }
+}
+
+impl Simulator {
+    /// Runs a job on the sessions and waits for its result.
+    async fn with_sessions<T, F>(&self, job: F) -> Result<T, Status>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Sessions) -> Result<T, Status> + Send + 'static,
+    {
+        let (reply, result) = oneshot::channel();
+        self.jobs
+            .send(Box::new(move |sessions| {
+                let _ = reply.send(job(sessions));
+            }))
+            .map_err(|_| Status::unavailable("The simulator stopped."))?;
+
+        result
+            .await
+            .map_err(|_| Status::internal("The simulator stopped."))?
+    }
+}
+
+#[tonic::async_trait]
+impl simulator_server::Simulator for Simulator {
+    type StreamTicksStream = ReceiverStream<Result<Tick, Status>>;
+
+    async fn create_session(
+        &self,
+        request: Request<CreateSessionRequest>,
+    ) -> Result<Response<Session>, Status> {
+        let CreateSessionRequest { definition, tape } = request.into_inner();
+        let session = self
+            .with_sessions(move |sessions| sessions.create(&definition, &tape))
+            .await?;
+        Ok(Response::new(session))
+    }
+
+    async fn step(&self, request: Request<StepRequest>) -> Result<Response<StepReply>, Status> {
+        let StepRequest { session_id, count } = request.into_inner();
+        let count = match count {
+            0 => 1,
+            count if count > MAX_STEP_COUNT => {
+                return Err(Status::invalid_argument(format!(
+                    "The step count must be at most {MAX_STEP_COUNT}."
+                )))
+            }
+            count => count,
+        };
+
+        let reply = self
+            .with_sessions(move |sessions| sessions.step(session_id, count as usize))
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn run(&self, request: Request<RunRequest>) -> Result<Response<RunReply>, Status> {
+        let RunRequest {
+            session_id,
+            max_steps,
+        } = request.into_inner();
+        let max_steps = if max_steps == 0 {
+            DEFAULT_MAX_STEPS
+        } else {
+            max_steps
+        };
+
+        // Dropped with the request if the client goes away or its deadline passes, which stops
+        // the run instead of keeping the simulator busy with it
+        let cancellation = CancellationToken::new();
+        let _guard = CancelOnDrop(cancellation.clone());
+
+        let reply = self
+            .with_sessions(move |sessions| {
+                sessions.run(session_id, max_steps as usize, cancellation)
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn get_tape(
+        &self,
+        request: Request<GetTapeRequest>,
+    ) -> Result<Response<Configuration>, Status> {
+        let session_id = request.into_inner().session_id;
+        let configuration = self
+            .with_sessions(move |sessions| {
+                Ok(Configuration::of(sessions.machine(session_id)?.as_ref()))
+            })
+            .await?;
+        Ok(Response::new(configuration))
+    }
+
+    async fn stream_ticks(
+        &self,
+        request: Request<StreamTicksRequest>,
+    ) -> Result<Response<Self::StreamTicksStream>, Status> {
+        let StreamTicksRequest {
+            session_id,
+            max_steps,
+        } = request.into_inner();
+        let max_steps = if max_steps == 0 {
+            DEFAULT_MAX_STEPS
+        } else {
+            max_steps
+        };
+
+        // Fails right away on unknown sessions, instead of in the stream
+        self.with_sessions(move |sessions| sessions.machine(session_id).map(|_| ()))
+            .await?;
+
+        let (ticks, stream) = async_mpsc::channel(TICK_BUFFER);
+        let jobs = self.jobs.clone();
+        tokio::spawn(async move {
+            let simulator = Simulator { jobs };
+            let mut steps = 0;
+
+            // Every tick is a separate job, so other sessions are not blocked by long streams
+            while steps < max_steps {
+                let tick = simulator
+                    .with_sessions(move |sessions| sessions.tick(session_id, steps))
+                    .await;
+
+                let done = match &tick {
+                    Ok(Some(tick)) => {
+                        steps = tick.step;
+                        tick.configuration.as_ref().is_some_and(|c| c.halted)
+                    }
+                    Ok(None) | Err(_) => true,
+                };
+
+                let sent = match tick {
+                    Ok(Some(tick)) => ticks.send(Ok(tick)).await,
+                    Ok(None) => Ok(()),
+                    Err(status) => ticks.send(Err(status)).await,
+                };
+
+                // The client went away
+                if done || sent.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(stream)))
+    }
+}
+
+impl Sessions {
+    fn machine(&mut self, session_id: u64) -> Result<&mut Box<dyn Automaton>, Status> {
+        self.machines
+            .get_mut(&session_id)
+            .ok_or_else(|| Status::not_found("Unknown session."))
+    }
+
+    fn create(&mut self, definition: &str, tape: &str) -> Result<Session, Status> {
+        let machine =
+            automaton::load_from_str(definition, tape).map_err(Status::invalid_argument)?;
+        let configuration = Configuration::of(machine.as_ref());
+
+        let session_id = self.next_id;
+        self.next_id += 1;
+        self.machines.insert(session_id, machine);
+
+        Ok(Session {
+            session_id,
+            configuration: Some(configuration),
+        })
+    }
+
+    fn step(&mut self, session_id: u64, count: usize) -> Result<StepReply, Status> {
+        let limits = self.limits.clone();
+        let machine = self.machine(session_id)?;
+        let tape_cell_limit = limits.tape_cell_limit(machine.as_ref());
+
+        let mut explanations = vec![];
+        let mut steps = 0;
+        while steps < count && !machine.is_halted() {
+            if tape_cell_limit.is_some_and(|max| machine.tape_cells() > max) {
+                return Err(tape_limit_exceeded());
+            }
+
+            if limits
+                .max_memory
+                .is_some_and(|max| machine.memory_usage() > max)
+            {
+                return Err(memory_limit_exceeded());
+            }
+
+            let tick_result = machine.tick();
+            explanations.extend(explain::explain(&tick_result));
+
+            // Like in the runners, the final halting tick is not counted as a step
+            if !machine.is_halted() {
+                steps += 1;
+            }
+        }
+
+        Ok(StepReply {
+            configuration: Some(Configuration::of(machine.as_ref())),
+            explanations,
+        })
+    }
+
+    fn run(
+        &mut self,
+        session_id: u64,
+        max_steps: usize,
+        cancellation: CancellationToken,
+    ) -> Result<RunReply, Status> {
+        let limits = RunLimits {
+            max_steps: Some(max_steps),
+            cancellation: Some(cancellation),
+            ..self.limits.clone()
+        };
+        let machine = self.machine(session_id)?;
+
+        let outcome = runner::run(machine.as_mut(), &limits);
+        let verdict = match outcome {
+            RunOutcome::Halted { accepted: true, .. } => "accepts",
+            RunOutcome::Halted {
+                accepted: false, ..
+            } => "rejects",
+            RunOutcome::StepLimitReached { .. } | RunOutcome::TimedOut { .. } => "step_limit",
+            RunOutcome::TapeLimitExceeded { .. } => "tape_limit",
+            RunOutcome::Cancelled { .. } => "cancelled",
+            RunOutcome::ResourceExhausted { .. } => "memory_limit",
+            RunOutcome::NeverHalts { .. } => "never_halts",
+        };
+
+        Ok(RunReply {
+            verdict: verdict.to_string(),
+            steps: outcome.steps() as u64,
+            configuration: Some(Configuration::of(machine.as_ref())),
+        })
+    }
+
+    /// Takes one tick of the machine, or returns `None` if it already halted. `steps` is how
+    /// many steps the stream took before.
+    fn tick(&mut self, session_id: u64, steps: u64) -> Result<Option<Tick>, Status> {
+        let limits = self.limits.clone();
+        let machine = self.machine(session_id)?;
+
+        if machine.is_halted() {
+            return Ok(None);
+        }
+
+        if limits
+            .tape_cell_limit(machine.as_ref())
+            .is_some_and(|max| machine.tape_cells() > max)
+        {
+            return Err(tape_limit_exceeded());
+        }
+
+        if limits
+            .max_memory
+            .is_some_and(|max| machine.memory_usage() > max)
+        {
+            return Err(memory_limit_exceeded());
+        }
+
+        let tick_result = machine.tick();
+        let step = if machine.is_halted() {
+            steps
+        } else {
+            steps + 1
+        };
+
+        Ok(Some(Tick {
+            step,
+            explanation: explain::explain(&tick_result).unwrap_or_default(),
+            configuration: Some(Configuration::of(machine.as_ref())),
+        }))
+    }
+}
+
+/// Cancels its token when dropped.
+struct CancelOnDrop(CancellationToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+fn tape_limit_exceeded() -> Status {
+    Status::resource_exhausted("The machine used more tape cells than allowed.")
+}
+
+fn memory_limit_exceeded() -> Status {
+    Status::resource_exhausted("The machine used more memory than allowed.")
+}
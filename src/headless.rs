@@ -0,0 +1,1870 @@
+use std::{
+    fs, thread,
+    time::{Duration, SystemTime},
+};
+
+use turing_lib::{
+    analysis,
+    automaton::Automaton,
+    bench::Timing,
+    compiled::{self, COMPILED_EXTENSION},
+    explain,
+    hook::{self, Hook, Intervention},
+    library,
+    machine::{Step, Symbol, TransitionSource, TuringMachine},
+    replay::{Replay, TRACE_EXTENSION},
+    report,
+    runner::{self, Progress, ProgressInterval, RunLimits, RunOutcome},
+    transform,
+    tree::{self, BranchEnd, ComputationTree, TreeLimits},
+};
+
+use crate::{
+    render::{RenderMode, Renderer},
+    source,
+};
+
+pub const USAGE: &str = "Usage: turing run <filename.tng> <tape_data|@input.txt> [--max-steps <n>] [--timeout <seconds>] [--max-tape-cells <n>] [--max-memory <MiB>] [--max-depth <n>] [--max-branches <n>] [--fast] [--break <condition>]... [--render <ansi|cast>] [--trace <output.trace|compact|configurations|tsv>] [--inject <step>:<cell|state>=<value>]... [--dump-tape <output.txt> [--untrimmed] [--mark-head]] [--watch] [--progress]";
+
+pub const ENUMERATE_USAGE: &str = "Usage: turing enumerate <filename.tng> [--max-words <n>] [--max-steps <n>] [--timeout <seconds>] [--max-tape-cells <n>] [--max-memory <MiB>]";
+
+pub const BENCH_USAGE: &str = "Usage: turing bench <filename.tng> --inputs <tape_data>... [--repetitions <n>] [--max-steps <n>] [--timeout <seconds>] [--max-tape-cells <n>] [--max-memory <MiB>]";
+
+pub const PROFILE_USAGE: &str = "Usage: turing profile <filename.tng> <tape_data> [--top <n>] [--max-steps <n>] [--timeout <seconds>] [--max-tape-cells <n>] [--max-memory <MiB>]";
+
+pub const EXPLORE_USAGE: &str = "Usage: turing explore <filename.tng> <tape_data> [--max-depth <n>] [--max-steps <n>] [--max-branches <n>]";
+
+pub const COVERAGE_USAGE: &str = "Usage: turing coverage <filename.tng> --inputs <tape_data>... [--max-steps <n>] [--timeout <seconds>] [--max-tape-cells <n>] [--max-memory <MiB>]";
+
+pub const GOLDEN_USAGE: &str = "Usage: turing golden <filename.tng> --inputs <tape_data>... [--dir <directory>] [--update] [--max-steps <n>] [--timeout <seconds>] [--max-tape-cells <n>] [--max-memory <MiB>]";
+
+pub const COMPLEXITY_USAGE: &str = "Usage: turing complexity <filename.tng> --alphabet <symbols> --max-len <n> [--max-steps <n>] [--csv <output.csv>]";
+
+pub const SWEEP_USAGE: &str = "Usage: turing sweep <filename.tng> --alphabet <symbols> --max-len <n> [--max-steps <n>] [--csv <output.csv>]";
+
+pub const GENERATE_USAGE: &str = "Usage: turing generate <script.rhai> [--output <filename.tng>]";
+
+pub const PRUNE_USAGE: &str = "Usage: turing prune <filename.tng> [--output <filename.tng>]";
+
+pub const COMPILE_USAGE: &str = "Usage: turing compile <filename.tng> [--output <filename.tngc>]";
+
+pub const VERIFY_USAGE: &str = "Usage: turing verify <run.trace> <filename.tng> <tape_data>";
+
+pub const EXAMPLES_USAGE: &str = "Usage: turing examples [<name>]";
+
+const DEFAULT_MAX_WORDS: usize = 10;
+const DEFAULT_REPETITIONS: usize = 10;
+const DEFAULT_PROFILE_TOP: usize = 5;
+const DEFAULT_COMPLEXITY_MAX_STEPS: usize = 100_000;
+const DEFAULT_GOLDEN_MAX_STEPS: usize = 100_000; // So a machine that never halts can't record forever
+const DEFAULT_WATCH_MAX_STEPS: usize = 100_000; // So an edit that makes the machine loop doesn't hang the watch
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+const WATCH_DIFF_LINES: usize = 20; // Changed lines of the trace printed after every change
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+const BYTES_PER_MIB: usize = 1024 * 1024;
+
+struct RunArgs<'a> {
+    filename: &'a str,
+    tape_data: &'a str,
+    limits: RunLimits,
+    tree_limits: TreeLimits, // Of non-deterministic machines, see [`tree::explore`]
+    fast: bool,
+    breakpoints: Vec<&'a str>,
+    render: Option<RenderMode>,
+    trace: Option<&'a str>,
+    trace_format: Option<TraceFormat>,
+    injections: Vec<(usize, Intervention)>, // Before the step with this number
+    dump: Option<TapeDump<'a>>,
+    watch: bool,
+    progress: bool,
+}
+
+/// How `--trace` prints the configurations of a run, instead of recording it into a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TraceFormat {
+    /// One line per step, see [`report::compact`].
+    Compact,
+    /// The chain of instantaneous descriptions, see [`report::configurations`].
+    Configurations,
+    /// Tab-separated values, see [`report::tsv`].
+    Tsv,
+}
+
+impl TraceFormat {
+    /// The format with this name, or `None` if `--trace` was given a file.
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "compact" => Some(TraceFormat::Compact),
+            "configurations" => Some(TraceFormat::Configurations),
+            "tsv" => Some(TraceFormat::Tsv),
+            _ => None,
+        }
+    }
+}
+
+/// What `turing run` found out: how the run ended, or every run of a non-deterministic machine.
+enum Verdict {
+    Run(RunOutcome),
+    Branches(ComputationTree),
+}
+
+/// Where and how to write the final tape of a run, see [`dump_tape`].
+struct TapeDump<'a> {
+    output: &'a str,
+    untrimmed: bool,
+    mark_head: bool,
+}
+
+/// Runs a machine without opening a window and returns the process exit code:
+/// 0 if the machine accepts, 1 if it rejects (or on error), 2 if it did not halt within the
+/// limits, and 3 if it stopped at a breakpoint. A tape data starting with `@` is the file to
+/// read the input from, e.g. `@input.txt`.
+///
+/// Non-deterministic machines accept if any of their runs does, so all of them are explored
+/// (see [`tree::explore`]) instead of making a single run, like `turing explore`.
+pub fn run(args: &[String]) -> i32 {
+    let RunArgs {
+        filename,
+        tape_data,
+        limits,
+        tree_limits,
+        fast,
+        breakpoints,
+        render,
+        trace,
+        trace_format,
+        injections,
+        dump,
+        watch,
+        progress,
+    } = match parse_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            eprintln!("{USAGE}");
+            return 1;
+        }
+    };
+
+    if watch {
+        return run_watched(filename, tape_data, limits);
+    }
+
+    let tape_data = match read_input(tape_data) {
+        Ok(tape_data) => tape_data,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+    let tape_data = tape_data.as_str();
+
+    if !breakpoints.is_empty() {
+        return run_with_breakpoints(filename, tape_data, &limits, &breakpoints, dump.as_ref());
+    }
+
+    if let Some(mode) = render {
+        return run_rendered(filename, tape_data, &limits, mode, dump.as_ref());
+    }
+
+    if !injections.is_empty() {
+        return run_injected(
+            filename,
+            tape_data,
+            &limits,
+            injections,
+            trace,
+            dump.as_ref(),
+        );
+    }
+
+    if let Some(format) = trace_format {
+        return run_printed(filename, tape_data, &limits, format, dump.as_ref());
+    }
+
+    if let Some(output) = trace {
+        return run_traced(filename, tape_data, &limits, output, dump.as_ref());
+    }
+
+    let interval = ProgressInterval {
+        every: Some(PROGRESS_INTERVAL).filter(|_| progress),
+        ..Default::default()
+    };
+    let mut reported = false;
+    let report_progress = |progress: Progress| {
+        eprint!(
+            "\r{} steps, {} tape cells, state {}\x1b[K",
+            progress.steps, progress.tape_cells, progress.state
+        );
+        reported = true;
+    };
+
+    let verdict = if fast {
+        source::load_turing(filename, tape_data).and_then(|machine| {
+            warn_about_input(&machine, tape_data);
+            if machine.is_nondeterministic() {
+                return Ok(Verdict::Branches(tree::explore(&machine, &tree_limits)));
+            }
+            turing_lib::fast::run_with_progress(&machine, &limits, interval, report_progress)
+                .map(Verdict::Run)
+        })
+    } else {
+        source::load(filename, tape_data).and_then(|mut machine| {
+            warn_about_input(machine.as_ref(), tape_data);
+            if machine.is_nondeterministic() {
+                if dump.is_some() {
+                    return Err("A non-deterministic machine has no single final tape to dump with \"--dump-tape\".".to_string());
+                }
+                if let Some(tree) = machine.computation_tree(&tree_limits) {
+                    return Ok(Verdict::Branches(tree));
+                }
+            }
+            let outcome =
+                runner::run_with_progress(machine.as_mut(), &limits, interval, report_progress);
+            dump_tape(machine.as_ref(), dump.as_ref())?;
+            Ok(Verdict::Run(outcome))
+        })
+    };
+
+    if reported {
+        eprintln!();
+    }
+
+    match verdict {
+        Ok(Verdict::Run(outcome)) => report_outcome(outcome, &limits),
+        Ok(Verdict::Branches(tree)) => report_branches(&tree),
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            1
+        }
+    }
+}
+
+/// The input given as the tape data of `turing run`: the tape data itself, or the contents of
+/// the file it names after an `@` (without the line break at its end).
+fn read_input(tape_data: &str) -> Result<String, String> {
+    match tape_data.strip_prefix('@') {
+        Some(path) => fs::read_to_string(path)
+            .map(|input| input.trim_end_matches(['\n', '\r']).to_string())
+            .map_err(|_| format!("Could not open the file \"{path}\"")),
+        None => Ok(tape_data.to_string()),
+    }
+}
+
+/// Runs a machine again whenever its definition (or the file of its input) changes, until
+/// interrupted, printing how its trace changed (see [`report::compact`]) and its new verdict.
+/// Errors, e.g. of a definition saved halfway through an edit, are printed and the watch goes
+/// on.
+fn run_watched(filename: &str, tape_data: &str, mut limits: RunLimits) -> i32 {
+    if source::is_url(filename) {
+        eprintln!("Error: \"--watch\" needs a file, not a URL.");
+        return 1;
+    }
+
+    limits.max_steps = limits.max_steps.or(Some(DEFAULT_WATCH_MAX_STEPS));
+    let watched = [Some(filename), tape_data.strip_prefix('@')]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    println!("Watching {} (Ctrl+C to stop)", watched.join(" and "));
+
+    let modified_times = || {
+        watched
+            .iter()
+            .map(|path| {
+                fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .collect::<Vec<Option<SystemTime>>>()
+    };
+    let mut last_modified = None;
+    let mut last_trace: Option<Vec<String>> = None;
+
+    loop {
+        let modified = modified_times();
+        if last_modified.as_ref() == Some(&modified) {
+            thread::sleep(WATCH_INTERVAL);
+            continue;
+        }
+
+        if last_modified.is_some() {
+            println!();
+        }
+        last_modified = Some(modified);
+
+        let run = read_input(tape_data).and_then(|tape_data| {
+            let mut machine = source::load(filename, &tape_data)?;
+            warn_about_input(machine.as_ref(), &tape_data);
+            Ok(report::record(machine.as_mut(), &limits))
+        });
+        let (frames, outcome) = match run {
+            Ok(run) => run,
+            Err(err) => {
+                eprintln!("Error: \"{err}\"");
+                continue;
+            }
+        };
+
+        let trace = report::compact(&frames)
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        if let Some(last_trace) = &last_trace {
+            print_trace_diff(last_trace, &trace);
+        }
+        println!("{}", describe_outcome(outcome, &limits).0);
+        last_trace = Some(trace);
+    }
+}
+
+/// Prints the lines that changed between two traces, as the removed lines (`-`) and the added
+/// ones (`+`) between the lines both traces start and end with, up to [`WATCH_DIFF_LINES`].
+fn print_trace_diff(old: &[String], new: &[String]) {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let removed = &old[prefix..old.len() - suffix];
+    let added = &new[prefix..new.len() - suffix];
+
+    if removed.is_empty() && added.is_empty() {
+        println!("Same trace");
+        return;
+    }
+
+    let changes = removed
+        .iter()
+        .map(|line| format!("- {line}"))
+        .chain(added.iter().map(|line| format!("+ {line}")))
+        .collect::<Vec<_>>();
+    for change in changes.iter().take(WATCH_DIFF_LINES) {
+        println!("{change}");
+    }
+    if changes.len() > WATCH_DIFF_LINES {
+        println!(
+            "... and {} more changed lines",
+            changes.len() - WATCH_DIFF_LINES
+        );
+    }
+}
+
+/// Warns about the symbols of the tape that the machine can never read, since they make it
+/// halt (and usually reject) as soon as it reaches them, and about inputs too short for the
+/// head to start where the definition says.
+pub(crate) fn warn_about_input<A: Automaton + ?Sized>(machine: &A, tape_data: &str) {
+    if let Some(note) = head_start_note(machine) {
+        eprintln!("Warning: {note}");
+    }
+
+    for warning in machine.loop_warnings() {
+        eprintln!("Warning: {warning}");
+    }
+
+    let symbols = machine.unreadable_symbols(tape_data);
+    if symbols.is_empty() {
+        return;
+    }
+
+    let noun = if symbols.len() == 1 {
+        "symbol"
+    } else {
+        "symbols"
+    };
+    let symbols = symbols
+        .iter()
+        .map(|symbol| format!("'{symbol}'"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    eprintln!("Warning: No transition reads the {noun} {symbols} of the tape, so the machine halts as soon as it reads one of them.");
+}
+
+/// Explains where the head started if the input was too short for it to start where the
+/// definition says.
+pub(crate) fn head_start_note<A: Automaton + ?Sized>(machine: &A) -> Option<String> {
+    match (machine.head_start(), machine.effective_head_start()) {
+        (Some(head_start), Some(effective)) if head_start != effective => Some(format!(
+            "The tape is too short for the head to start at cell {head_start}, so it starts at its last cell ({effective})."
+        )),
+        _ => None,
+    }
+}
+
+/// Writes the tape of the machine as its raw symbols (blanks included), without the blanks at
+/// its ends unless `untrimmed`. With `mark_head`, the symbol under the head is written between
+/// brackets, e.g. `01[1]0`.
+fn dump_tape<A: Automaton + ?Sized>(machine: &A, dump: Option<&TapeDump>) -> Result<(), String> {
+    let Some(dump) = dump else {
+        return Ok(());
+    };
+
+    let symbols = machine
+        .tape()
+        .get_content()
+        .iter()
+        .map(|symbol| match symbol {
+            Symbol::Mark(c) => *c,
+            _ => machine.blank_symbol(),
+        })
+        .collect::<Vec<_>>();
+    let head = machine.head_idx();
+
+    let range = if dump.untrimmed {
+        0..symbols.len()
+    } else {
+        let is_kept =
+            |idx: usize| symbols[idx] != machine.blank_symbol() || (dump.mark_head && idx == head);
+        match (0..symbols.len()).position(is_kept) {
+            Some(start) => start..(0..symbols.len()).rposition(is_kept).unwrap() + 1,
+            None => 0..0,
+        }
+    };
+
+    let mut contents = String::new();
+    for idx in range {
+        if dump.mark_head && idx == head {
+            contents += &format!("[{}]", symbols[idx]);
+        } else {
+            contents.push(symbols[idx]);
+        }
+    }
+    contents.push('\n');
+
+    fs::write(dump.output, contents)
+        .map_err(|_| format!("Could not write the file \"{}\"", dump.output))
+}
+
+fn report_outcome(outcome: RunOutcome, limits: &RunLimits) -> i32 {
+    let (verdict, exit_code) = describe_outcome(outcome, limits);
+    println!("{verdict}");
+    exit_code
+}
+
+/// The verdict of a run, and its exit code.
+pub(crate) fn describe_outcome(outcome: RunOutcome, limits: &RunLimits) -> (String, i32) {
+    match outcome {
+        RunOutcome::Halted {
+            accepted: true,
+            steps,
+        } => (format!("Halted, accepts ({steps} steps)"), 0),
+        RunOutcome::Halted {
+            accepted: false,
+            steps,
+        } => (format!("Halted, rejects ({steps} steps)"), 1),
+        RunOutcome::StepLimitReached { steps } => {
+            (format!("Did not halt within limit ({steps} steps)"), 2)
+        }
+        RunOutcome::TimedOut { steps } => (
+            format!(
+                "Did not halt within limit ({}s timeout, {steps} steps)",
+                limits.timeout.unwrap_or_default().as_secs_f64()
+            ),
+            2,
+        ),
+        RunOutcome::TapeLimitExceeded { steps } => (
+            format!("Did not halt within limit (tape cell limit exceeded, {steps} steps)"),
+            2,
+        ),
+        RunOutcome::Cancelled { steps } => (format!("Cancelled ({steps} steps)"), 2),
+        RunOutcome::ResourceExhausted { steps, memory } => (
+            format!(
+                "Did not halt within limit (memory limit exceeded with {} MiB, {steps} steps)",
+                memory.div_ceil(BYTES_PER_MIB)
+            ),
+            2,
+        ),
+        RunOutcome::NeverHalts { steps } => (
+            format!("Never halts (it loops forever after {steps} steps)"),
+            2,
+        ),
+    }
+}
+
+/// Runs a machine rendering every step, see [`Renderer`].
+fn run_rendered(
+    filename: &str,
+    tape_data: &str,
+    limits: &RunLimits,
+    mode: RenderMode,
+    dump: Option<&TapeDump>,
+) -> i32 {
+    let mut machine = match source::load(filename, tape_data) {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    warn_about_input(machine.as_ref(), tape_data);
+
+    let mut renderer = Renderer::new(mode, machine.name());
+    let outcome = runner::run_observed(machine.as_mut(), limits, |machine, steps| {
+        renderer.render(machine, steps)
+    });
+
+    let (verdict, exit_code) = describe_outcome(outcome, limits);
+    renderer.finish(&verdict);
+
+    if let Err(err) = dump_tape(machine.as_ref(), dump) {
+        eprintln!("Error: \"{err}\"");
+        return 1;
+    }
+
+    exit_code
+}
+
+/// Runs a machine along a recorded run and checks that it goes through the same ticks, so a
+/// `.trace` file can't be edited without it being noticed. Returns the process exit code: 0 if
+/// the trace matches, and 1 if it doesn't (or on error).
+pub fn verify(args: &[String]) -> i32 {
+    let [trace, filename, tape_data] = args else {
+        eprintln!("{VERIFY_USAGE}");
+        return 1;
+    };
+
+    let loaded = source::load_replay(trace)
+        .and_then(|replay| Ok((replay, source::load(filename, tape_data)?)));
+    let (mut replay, mut machine) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    let Some(tick) = replay.first_mismatch(machine.as_mut()) else {
+        let ticks = replay.ticks().len();
+        replay.seek(ticks);
+        let ending = if replay.is_halted() {
+            ""
+        } else {
+            ", although it stops before the machine halts"
+        };
+        println!("Verified: the trace matches the run of the machine ({ticks} ticks){ending}");
+        return 0;
+    };
+
+    replay.seek(tick);
+    if tick == 0 {
+        println!("Mismatch: the trace doesn't start like the run of the machine");
+    } else {
+        println!("Mismatch: the trace differs from the run of the machine at tick {tick}");
+    }
+    println!("Recorded: {}", configuration(&replay));
+    println!("Actual:   {}", configuration(machine.as_ref()));
+    1
+}
+
+fn configuration<A: Automaton + ?Sized>(machine: &A) -> String {
+    format!(
+        "\"{}\", state {}, head {}, tape {}",
+        machine.name(),
+        machine.current_state_name(),
+        machine.head_idx(),
+        machine.tape()
+    )
+}
+
+/// Runs a machine recording every tick into a `.trace` file, which can be played back without
+/// the machine, see [`Replay`].
+fn run_traced(
+    filename: &str,
+    tape_data: &str,
+    limits: &RunLimits,
+    output: &str,
+    dump: Option<&TapeDump>,
+) -> i32 {
+    let mut machine = match source::load(filename, tape_data) {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    warn_about_input(machine.as_ref(), tape_data);
+
+    let (replay, outcome) = Replay::record(machine.as_mut(), limits);
+    if fs::write(output, replay.to_trace_string()).is_err() {
+        eprintln!("Error: \"Could not write the file \"{output}\"\"");
+        return 1;
+    }
+
+    if let Err(err) = dump_tape(machine.as_ref(), dump) {
+        eprintln!("Error: \"{err}\"");
+        return 1;
+    }
+
+    report_outcome(outcome, limits)
+}
+
+/// Runs a machine printing every configuration it goes through in the given format, and then the
+/// verdict.
+fn run_printed(
+    filename: &str,
+    tape_data: &str,
+    limits: &RunLimits,
+    format: TraceFormat,
+    dump: Option<&TapeDump>,
+) -> i32 {
+    let mut machine = match source::load(filename, tape_data) {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    warn_about_input(machine.as_ref(), tape_data);
+
+    let (frames, outcome) = report::record(machine.as_mut(), limits);
+    print!(
+        "{}",
+        match format {
+            TraceFormat::Compact => report::compact(&frames),
+            TraceFormat::Configurations => report::configurations(&frames),
+            TraceFormat::Tsv => report::tsv(&frames),
+        }
+    );
+
+    if let Err(err) = dump_tape(machine.as_ref(), dump) {
+        eprintln!("Error: \"{err}\"");
+        return 1;
+    }
+
+    report_outcome(outcome, limits)
+}
+
+/// Runs a machine making the given changes before some of its steps, e.g. to flip a cell in
+/// the middle of a run, and optionally records the run with the changes into a `.trace` file.
+fn run_injected(
+    filename: &str,
+    tape_data: &str,
+    limits: &RunLimits,
+    injections: Vec<(usize, Intervention)>,
+    trace: Option<&str>,
+    dump: Option<&TapeDump>,
+) -> i32 {
+    let mut machine = match source::load_turing(filename, tape_data) {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    warn_about_input(&machine, tape_data);
+
+    let blank_symbol = machine.blank_symbol();
+    let injections = injections
+        .into_iter()
+        .map(|(step, intervention)| match intervention {
+            Intervention::SetCell {
+                cell,
+                symbol: Symbol::Mark(c),
+            } => (
+                step,
+                Intervention::SetCell {
+                    cell,
+                    symbol: Symbol::from_char(c, blank_symbol),
+                },
+            ),
+            intervention => (step, intervention),
+        })
+        .collect::<Vec<_>>();
+
+    let mut hooks: Vec<Box<dyn Hook>> = vec![Box::new(move |_: &TuringMachine, steps: usize| {
+        injections
+            .iter()
+            .filter(|(step, _)| *step == steps)
+            .map(|(_, intervention)| {
+                let step = Step::Intervention(intervention.clone());
+                eprintln!("Before step {steps}: {}", explain::explain_step(&step));
+                intervention.clone()
+            })
+            .collect()
+    })];
+
+    let outcome = match trace {
+        Some(output) => Replay::record_with_hooks(&mut machine, limits, &mut hooks).and_then(
+            |(replay, outcome)| {
+                fs::write(output, replay.to_trace_string())
+                    .map_err(|_| format!("Could not write the file \"{output}\""))?;
+                Ok(outcome)
+            },
+        ),
+        None => hook::run_with_hooks(&mut machine, limits, &mut hooks),
+    };
+
+    let outcome = outcome.and_then(|outcome| {
+        dump_tape(&machine, dump)?;
+        Ok(outcome)
+    });
+
+    match outcome {
+        Ok(outcome) => report_outcome(outcome, limits),
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            1
+        }
+    }
+}
+
+/// Parses the value of `--inject`, e.g. `100:7=1` (write `1` on the cell 7 before the step
+/// 100) or `100:state=q3` (move the machine to the state `q3` before the step 100).
+fn parse_injection(value: &str) -> Result<(usize, Intervention), String> {
+    let invalid = || {
+        format!("Invalid injection \"{value}\". It must be <step>:<cell>=<symbol> or <step>:state=<name>.")
+    };
+
+    let (step, change) = value.split_once(':').ok_or_else(invalid)?;
+    let (target, value) = change.split_once('=').ok_or_else(invalid)?;
+    let step = step.parse().map_err(|_| invalid())?;
+
+    if target == "state" {
+        return Ok((step, Intervention::SetState(value.to_string())));
+    }
+
+    let cell = target.parse().map_err(|_| invalid())?;
+    let mut chars = value.chars();
+    let (Some(symbol), None) = (chars.next(), chars.next()) else {
+        return Err(invalid());
+    };
+
+    Ok((
+        step,
+        Intervention::SetCell {
+            cell,
+            symbol: Symbol::Mark(symbol), // Blanks are only known once the machine is loaded
+        },
+    ))
+}
+
+#[cfg(feature = "scripting")]
+fn run_with_breakpoints(
+    filename: &str,
+    tape_data: &str,
+    limits: &RunLimits,
+    conditions: &[&str],
+    dump: Option<&TapeDump>,
+) -> i32 {
+    use turing_lib::breakpoint::{self, Breakpoint, BreakpointOutcome};
+
+    let breakpoints = conditions
+        .iter()
+        .map(|condition| Breakpoint::new(condition))
+        .collect::<Result<Vec<_>, _>>();
+
+    let outcome = breakpoints.and_then(|breakpoints| {
+        let mut machine = source::load(filename, tape_data)?;
+        warn_about_input(machine.as_ref(), tape_data);
+        let outcome = breakpoint::run_until_breakpoint(machine.as_mut(), limits, &breakpoints)?;
+        dump_tape(machine.as_ref(), dump)?;
+        Ok((machine, outcome))
+    });
+
+    match outcome {
+        Ok((machine, BreakpointOutcome::Hit { breakpoint, steps })) => {
+            println!(
+                "Stopped at breakpoint \"{}\" (state {}, head {}, {steps} steps)",
+                conditions[breakpoint],
+                machine.current_state_name(),
+                machine.head_idx()
+            );
+            3
+        }
+        Ok((_, BreakpointOutcome::Finished(outcome))) => report_outcome(outcome, limits),
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            1
+        }
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+fn run_with_breakpoints(_: &str, _: &str, _: &RunLimits, _: &[&str], _: Option<&TapeDump>) -> i32 {
+    eprintln!("Error: Breakpoints need turing to be built with the \"scripting\" feature.");
+    1
+}
+
+/// Runs an enumerator from a blank tape, printing the distinct words it emits. Returns the
+/// process exit code: 0 if the enumeration finished or collected all the words, and 1 on error
+/// or if it was stopped by a limit.
+pub fn enumerate(args: &[String]) -> i32 {
+    let (filename, max_words, limits) = match parse_enumerate_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            eprintln!("{ENUMERATE_USAGE}");
+            return 1;
+        }
+    };
+
+    let mut machine = match source::load_turing(filename, "") {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    let enumeration = runner::enumerate(&mut machine, max_words, &limits);
+
+    for word in &enumeration.words {
+        println!("{word:?}");
+    }
+
+    match enumeration.outcome {
+        None => {
+            println!("Collected {max_words} words");
+            0
+        }
+        Some(RunOutcome::Halted { steps, .. }) => {
+            println!("Halted ({steps} steps)");
+            0
+        }
+        Some(outcome) => {
+            println!("Stopped by limit ({} steps)", outcome.steps());
+            1
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<RunArgs<'_>, String> {
+    let [filename, tape_data, flags @ ..] = args else {
+        return Err("Missing filename or tape data.".to_string());
+    };
+
+    let mut limits = RunLimits::default();
+    let mut tree_limits = TreeLimits::default();
+    let mut fast = false;
+    let mut breakpoints = vec![];
+    let mut render = None;
+    let mut trace = None;
+    let mut trace_format = None;
+    let mut injections = vec![];
+    let mut dump_output = None;
+    let mut untrimmed = false;
+    let mut mark_head = false;
+    let mut watch = false;
+    let mut progress = false;
+    let mut flags = flags.iter();
+
+    while let Some(flag) = flags.next() {
+        if flag == "--fast" {
+            fast = true;
+            continue;
+        }
+
+        if flag == "--watch" {
+            watch = true;
+            continue;
+        }
+
+        if flag == "--progress" {
+            progress = true;
+            continue;
+        }
+
+        if flag == "--untrimmed" {
+            untrimmed = true;
+            continue;
+        }
+
+        if flag == "--mark-head" {
+            mark_head = true;
+            continue;
+        }
+
+        let value = flags
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{flag}\"."))?;
+
+        if flag == "--break" {
+            breakpoints.push(value.as_str());
+        } else if flag == "--render" {
+            render = Some(RenderMode::parse(value)?);
+        } else if flag == "--trace" {
+            trace_format = TraceFormat::parse(value);
+            trace = Some(value.as_str()).filter(|_| trace_format.is_none());
+        } else if flag == "--inject" {
+            injections.push(parse_injection(value)?);
+        } else if flag == "--dump-tape" {
+            dump_output = Some(value.as_str());
+        } else if flag == "--max-depth" || flag == "--max-branches" {
+            let limit = match flag.as_str() {
+                "--max-depth" => &mut tree_limits.max_depth,
+                _ => &mut tree_limits.max_branches,
+            };
+            *limit = value.parse().map_err(|_| {
+                format!("Invalid limit \"{value}\". It must be a non negative integer.")
+            })?;
+        } else if !parse_limit(flag, value, &mut limits)? {
+            return Err(format!("Unexpected argument \"{flag}\"."));
+        }
+    }
+
+    // Every branch of a non-deterministic machine runs up to the steps a run would
+    if let Some(max_steps) = limits.max_steps {
+        tree_limits.max_steps = max_steps;
+    }
+
+    if render.is_some() && (fast || !breakpoints.is_empty()) {
+        return Err("\"--render\" can't be combined with \"--fast\" nor \"--break\".".to_string());
+    }
+
+    let traced = trace.is_some() || trace_format.is_some();
+    if traced && (fast || !breakpoints.is_empty() || render.is_some()) {
+        return Err(
+            "\"--trace\" can't be combined with \"--fast\", \"--break\" nor \"--render\"."
+                .to_string(),
+        );
+    }
+
+    if trace_format.is_some() && !injections.is_empty() {
+        return Err("\"--trace\" can only record runs with \"--inject\" into a file.".to_string());
+    }
+
+    if !injections.is_empty() && (fast || !breakpoints.is_empty() || render.is_some()) {
+        return Err(
+            "\"--inject\" can't be combined with \"--fast\", \"--break\" nor \"--render\"."
+                .to_string(),
+        );
+    }
+
+    if dump_output.is_none() && (untrimmed || mark_head) {
+        return Err(
+            "\"--untrimmed\" and \"--mark-head\" can only be used with \"--dump-tape\"."
+                .to_string(),
+        );
+    }
+
+    if dump_output.is_some() && fast {
+        return Err("\"--dump-tape\" can't be combined with \"--fast\".".to_string());
+    }
+
+    if watch
+        && (fast
+            || !breakpoints.is_empty()
+            || render.is_some()
+            || trace.is_some()
+            || trace_format.is_some()
+            || !injections.is_empty()
+            || dump_output.is_some())
+    {
+        return Err("\"--watch\" can only be combined with the limits.".to_string());
+    }
+
+    if progress
+        && (!breakpoints.is_empty()
+            || render.is_some()
+            || trace.is_some()
+            || trace_format.is_some()
+            || !injections.is_empty()
+            || watch)
+    {
+        return Err(
+            "\"--progress\" can only be combined with \"--fast\", \"--dump-tape\" and the limits."
+                .to_string(),
+        );
+    }
+
+    Ok(RunArgs {
+        filename,
+        tape_data,
+        limits,
+        tree_limits,
+        fast,
+        breakpoints,
+        render,
+        trace,
+        trace_format,
+        injections,
+        dump: dump_output.map(|output| TapeDump {
+            output,
+            untrimmed,
+            mark_head,
+        }),
+        watch,
+        progress,
+    })
+}
+
+fn parse_enumerate_args(args: &[String]) -> Result<(&str, usize, RunLimits), String> {
+    let [filename, flags @ ..] = args else {
+        return Err("Missing filename.".to_string());
+    };
+
+    let mut max_words = DEFAULT_MAX_WORDS;
+    let mut limits = RunLimits::default();
+    let mut flags = flags.iter();
+
+    while let Some(flag) = flags.next() {
+        let value = flags
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{flag}\"."))?;
+
+        if flag == "--max-words" {
+            max_words = value.parse().map_err(|_| {
+                format!("Invalid word limit \"{value}\". It must be a non negative integer.")
+            })?;
+        } else if !parse_limit(flag, value, &mut limits)? {
+            return Err(format!("Unexpected argument \"{flag}\"."));
+        }
+    }
+
+    Ok((filename, max_words, limits))
+}
+
+/// Parses a `--max-steps`, `--timeout`, `--max-tape-cells` or `--max-memory` flag into `limits`.
+/// Returns false if it is another flag.
+pub(crate) fn parse_limit(flag: &str, value: &str, limits: &mut RunLimits) -> Result<bool, String> {
+    match flag {
+        "--max-steps" => {
+            limits.max_steps = Some(value.parse().map_err(|_| {
+                format!("Invalid step limit \"{value}\". It must be a non negative integer.")
+            })?);
+        }
+        "--timeout" => {
+            let seconds: f64 = value.parse().map_err(|_| {
+                format!("Invalid timeout \"{value}\". It must be a number of seconds.")
+            })?;
+            limits.timeout = Some(Duration::try_from_secs_f64(seconds).map_err(|_| {
+                format!("Invalid timeout \"{value}\". It must be a non negative number of seconds.")
+            })?);
+        }
+        "--max-tape-cells" => {
+            limits.max_tape_cells = Some(
+                value
+                    .parse()
+                    .ok()
+                    .filter(|&cells| cells > 0)
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid tape cell limit \"{value}\". It must be a positive integer."
+                        )
+                    })?,
+            );
+        }
+        "--max-memory" => limits.max_memory = Some(parse_memory_limit(value)?),
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Parses the value of `--max-memory`, in MiB, into bytes.
+pub(crate) fn parse_memory_limit(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|&mib| mib > 0)
+        .and_then(|mib| mib.checked_mul(BYTES_PER_MIB))
+        .ok_or_else(|| {
+            format!("Invalid memory limit \"{value}\". It must be a positive number of MiB.")
+        })
+}
+
+/// Measures how long a machine takes to run on every input, with the interpreter and the
+/// fast runner. Returns the process exit code: 0 on success and 1 on error.
+pub fn bench(args: &[String]) -> i32 {
+    let (filename, inputs, repetitions, limits) = match parse_bench_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            eprintln!("{BENCH_USAGE}");
+            return 1;
+        }
+    };
+
+    let file_data = match source::read_definition(filename) {
+        Ok(file_data) => file_data,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    let results = match turing_lib::bench::bench(&file_data, &inputs, &limits, repetitions) {
+        Ok(results) => results,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    let describe = |timing: &Timing| {
+        format!(
+            "{:?} ({:.0} steps/s, {:?}/step)",
+            timing.time,
+            timing.steps_per_second(),
+            timing.time_per_step()
+        )
+    };
+
+    for result in results {
+        let verdict = match result.outcome {
+            RunOutcome::Halted { accepted: true, .. } => "accepts",
+            RunOutcome::Halted {
+                accepted: false, ..
+            } => "rejects",
+            _ => "limit reached",
+        };
+
+        println!(
+            "{:?}: {} steps, {verdict}",
+            result.input,
+            result.outcome.steps()
+        );
+        println!("  interpreter: {}", describe(&result.interpreter));
+        match &result.fast {
+            Some(fast) => println!("  fast:        {}", describe(fast)),
+            None => println!("  fast:        not supported"),
+        }
+    }
+
+    0
+}
+
+fn parse_bench_args(args: &[String]) -> Result<(&str, Vec<&str>, usize, RunLimits), String> {
+    let [filename, flags @ ..] = args else {
+        return Err("Missing filename.".to_string());
+    };
+
+    let mut inputs = vec![];
+    let mut repetitions = DEFAULT_REPETITIONS;
+    let mut limits = RunLimits::default();
+    let mut flags = flags.iter().peekable();
+
+    while let Some(flag) = flags.next() {
+        if flag == "--inputs" {
+            while let Some(input) = flags.next_if(|input| !input.starts_with("--")) {
+                inputs.push(input.as_str());
+            }
+            continue;
+        }
+
+        let value = flags
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{flag}\"."))?;
+
+        if flag == "--repetitions" {
+            repetitions = value.parse().map_err(|_| {
+                format!("Invalid repetitions \"{value}\". It must be a positive integer.")
+            })?;
+        } else if !parse_limit(flag, value, &mut limits)? {
+            return Err(format!("Unexpected argument \"{flag}\"."));
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err("Missing inputs.".to_string());
+    }
+
+    Ok((filename, inputs, repetitions, limits))
+}
+
+/// Runs a machine and prints where the run spends its steps: the hottest states and
+/// transitions, the longest scans and how often the head was at every position. Returns the
+/// process exit code, like [`run`].
+pub fn profile(args: &[String]) -> i32 {
+    let (filename, tape_data, top, limits) = match parse_profile_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            eprintln!("{PROFILE_USAGE}");
+            return 1;
+        }
+    };
+
+    let mut machine = match source::load_turing(filename, tape_data) {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    warn_about_input(&machine, tape_data);
+
+    let profile = turing_lib::profile::profile(&mut machine, &limits);
+    print!("{}", profile.report(top));
+
+    describe_outcome(profile.outcome(), &limits).1
+}
+
+fn parse_profile_args(args: &[String]) -> Result<(&str, &str, usize, RunLimits), String> {
+    let [filename, tape_data, flags @ ..] = args else {
+        return Err("Missing filename or tape data.".to_string());
+    };
+
+    let mut top = DEFAULT_PROFILE_TOP;
+    let mut limits = RunLimits::default();
+    let mut flags = flags.iter();
+
+    while let Some(flag) = flags.next() {
+        let value = flags
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{flag}\"."))?;
+
+        if flag == "--top" {
+            top = value.parse().map_err(|_| {
+                format!("Invalid number \"{value}\". It must be a non negative integer.")
+            })?;
+        } else if !parse_limit(flag, value, &mut limits)? {
+            return Err(format!("Unexpected argument \"{flag}\"."));
+        }
+    }
+
+    Ok((filename, tape_data, top, limits))
+}
+
+/// Explores every run of a non-deterministic machine (or one with random transitions) breadth
+/// first, and prints whether any of them accepts. Returns the process exit code: 0 if a run
+/// accepts, 1 if every run rejects (or on error), and 2 if no run accepts within the limits but
+/// some were cut short.
+pub fn explore(args: &[String]) -> i32 {
+    let (filename, tape_data, limits) = match parse_explore_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            eprintln!("{EXPLORE_USAGE}");
+            return 1;
+        }
+    };
+
+    let machine = match source::load_turing(filename, tape_data) {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    warn_about_input(&machine, tape_data);
+
+    report_branches(&tree::explore(&machine, &limits))
+}
+
+/// Prints whether any branch of a computation tree accepts and returns the exit code, like the
+/// one of a run: 0 if one accepts, 1 if every branch rejects, and 2 if none accepts but some
+/// were cut short by the limits.
+fn report_branches(tree: &ComputationTree) -> i32 {
+    let branches = tree.branches().len();
+    let (accepted, pending) = (
+        tree.count(BranchEnd::Accepted),
+        tree.count(BranchEnd::Pending),
+    );
+
+    if accepted > 0 {
+        println!("Accepts ({accepted} of {branches} branches accept)");
+        0
+    } else if pending > 0 {
+        println!("No branch accepts within the limits ({pending} of {branches} branches pending)");
+        2
+    } else {
+        println!("Rejects (every branch rejects, {branches} branches)");
+        1
+    }
+}
+
+fn parse_explore_args(args: &[String]) -> Result<(&str, &str, TreeLimits), String> {
+    let [filename, tape_data, flags @ ..] = args else {
+        return Err("Missing filename or tape data.".to_string());
+    };
+
+    let mut limits = TreeLimits::default();
+    let mut flags = flags.iter();
+
+    while let Some(flag) = flags.next() {
+        let value = flags
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{flag}\"."))?;
+        let limit = match flag.as_str() {
+            "--max-depth" => &mut limits.max_depth,
+            "--max-steps" => &mut limits.max_steps,
+            "--max-branches" => &mut limits.max_branches,
+            _ => return Err(format!("Unexpected argument \"{flag}\".")),
+        };
+
+        *limit = value.parse().map_err(|_| {
+            format!("Invalid limit \"{value}\". It must be a non negative integer.")
+        })?;
+    }
+
+    Ok((filename, tape_data, limits))
+}
+
+/// Runs a machine on every input and reports which of its transitions were never taken, to
+/// check that the inputs exercise the whole machine. Returns the process exit code: 0 if every
+/// transition was taken, and 1 if some weren't (or on error).
+pub fn coverage(args: &[String]) -> i32 {
+    let (filename, inputs, limits) = match parse_coverage_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            eprintln!("{COVERAGE_USAGE}");
+            return 1;
+        }
+    };
+
+    let machine = match source::load_turing(filename, "") {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    let (coverage, outcomes) = turing_lib::coverage::coverage(&machine, &inputs, &limits);
+
+    for (input, outcome) in inputs.iter().zip(outcomes) {
+        let verdict = match outcome {
+            RunOutcome::Halted { accepted: true, .. } => "accepts",
+            RunOutcome::Halted {
+                accepted: false, ..
+            } => "rejects",
+            _ => "limit reached",
+        };
+        println!("{input:?}: {} steps, {verdict}", outcome.steps());
+    }
+    print!("{coverage}");
+
+    if coverage.is_complete() {
+        0
+    } else {
+        1
+    }
+}
+
+fn parse_coverage_args(args: &[String]) -> Result<(&str, Vec<&str>, RunLimits), String> {
+    let [filename, flags @ ..] = args else {
+        return Err("Missing filename.".to_string());
+    };
+
+    let mut inputs = vec![];
+    let mut limits = RunLimits::default();
+    let mut flags = flags.iter().peekable();
+
+    while let Some(flag) = flags.next() {
+        if flag == "--inputs" {
+            while let Some(input) = flags.next_if(|input| !input.starts_with("--")) {
+                inputs.push(input.as_str());
+            }
+            continue;
+        }
+
+        let value = flags
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{flag}\"."))?;
+
+        if !parse_limit(flag, value, &mut limits)? {
+            return Err(format!("Unexpected argument \"{flag}\"."));
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err("Missing inputs.".to_string());
+    }
+
+    Ok((filename, inputs, limits))
+}
+
+/// Runs a machine on every input and compares the runs with the golden traces recorded by the
+/// first run (recording those that are missing, or all of them with `--update`), so a machine
+/// can be refactored without changing what it does. Returns the process exit code: 0 if every
+/// run matches its golden trace, and 1 if any diverges (or on error).
+pub fn golden(args: &[String]) -> i32 {
+    let (filename, inputs, directory, update, limits) = match parse_golden_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            eprintln!("{GOLDEN_USAGE}");
+            return 1;
+        }
+    };
+
+    let directory = directory.map_or_else(
+        || {
+            let stem = filename.rsplit_once('.').map_or(filename, |(stem, _)| stem);
+            format!("{stem}.golden")
+        },
+        str::to_string,
+    );
+
+    if fs::create_dir_all(&directory).is_err() {
+        eprintln!("Error: \"Could not create the directory \"{directory}\"\"");
+        return 1;
+    }
+
+    let mut exit_code = 0;
+
+    for input in inputs {
+        let path = format!("{directory}/{}.{TRACE_EXTENSION}", golden_file_stem(input));
+
+        let golden = if update {
+            None
+        } else {
+            fs::read_to_string(&path).ok()
+        };
+        let compared = source::load(filename, input).and_then(|mut machine| {
+            let Some(golden) = golden else {
+                let (replay, _) = Replay::record(machine.as_mut(), &limits);
+                fs::write(&path, replay.to_trace_string())
+                    .map_err(|_| format!("Could not write the file \"{path}\""))?;
+                return Ok(GoldenComparison::Recorded);
+            };
+
+            let mut replay = Replay::parse(&golden)?;
+            Ok(match replay.first_divergence(machine.as_mut()) {
+                None => GoldenComparison::Matches,
+                Some(tick) => {
+                    replay.seek(tick);
+                    GoldenComparison::Diverges {
+                        tick,
+                        golden: configuration(&replay),
+                        actual: configuration(machine.as_ref()),
+                    }
+                }
+            })
+        });
+
+        match compared {
+            Ok(GoldenComparison::Recorded) => println!("{input:?}: recorded {path}"),
+            Ok(GoldenComparison::Matches) => println!("{input:?}: matches {path}"),
+            Ok(GoldenComparison::Diverges {
+                tick,
+                golden,
+                actual,
+            }) => {
+                println!("{input:?}: diverges from {path} at tick {tick}");
+                println!("  Golden: {golden}");
+                println!("  Actual: {actual}");
+                exit_code = 1;
+            }
+            Err(err) => {
+                eprintln!("Error: \"{err}\"");
+                return 1;
+            }
+        }
+    }
+
+    exit_code
+}
+
+enum GoldenComparison {
+    /// There was no golden trace (or it was updated), so the run was recorded as the new one.
+    Recorded,
+    Matches,
+    /// The run went through another configuration than the golden trace at this tick.
+    Diverges {
+        tick: usize,
+        golden: String,
+        actual: String,
+    },
+}
+
+/// The name of the golden trace of an input: the input itself, with the characters that are
+/// not safe in file names written as `%` and their code point in hexadecimal.
+fn golden_file_stem(input: &str) -> String {
+    if input.is_empty() {
+        return "%empty".to_string();
+    }
+
+    input
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_string()
+            } else {
+                format!("%{:x}", c as u32)
+            }
+        })
+        .collect()
+}
+
+type GoldenArgs<'a> = (&'a str, Vec<&'a str>, Option<&'a str>, bool, RunLimits);
+
+fn parse_golden_args(args: &[String]) -> Result<GoldenArgs<'_>, String> {
+    let [filename, flags @ ..] = args else {
+        return Err("Missing filename.".to_string());
+    };
+
+    let mut inputs = vec![];
+    let mut directory = None;
+    let mut update = false;
+    let mut limits = RunLimits {
+        max_steps: Some(DEFAULT_GOLDEN_MAX_STEPS),
+        ..Default::default()
+    };
+    let mut flags = flags.iter().peekable();
+
+    while let Some(flag) = flags.next() {
+        if flag == "--inputs" {
+            while let Some(input) = flags.next_if(|input| !input.starts_with("--")) {
+                inputs.push(input.as_str());
+            }
+            continue;
+        }
+
+        if flag == "--update" {
+            update = true;
+            continue;
+        }
+
+        let value = flags
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{flag}\"."))?;
+
+        if flag == "--dir" {
+            directory = Some(value.as_str());
+        } else if !parse_limit(flag, value, &mut limits)? {
+            return Err(format!("Unexpected argument \"{flag}\"."));
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err("Missing inputs.".to_string());
+    }
+
+    Ok((filename, inputs, directory, update, limits))
+}
+
+/// Measures the steps and tape cells a machine uses on every input up to some size, and prints
+/// them as a function of the size (or writes them as CSV). Returns the process exit code: 0 on
+/// success and 1 on error.
+pub fn complexity(args: &[String]) -> i32 {
+    let (filename, alphabet, max_len, max_steps, csv) = match parse_word_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            eprintln!("{COMPLEXITY_USAGE}");
+            return 1;
+        }
+    };
+
+    let machine = match source::load_turing(filename, "") {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    let points = analysis::complexity(&machine, &alphabet, max_len, max_steps);
+
+    if let Some(output) = csv {
+        if fs::write(output, analysis::complexity_csv(&points)).is_err() {
+            eprintln!("Error: \"Could not write the file \"{output}\"\"");
+            return 1;
+        }
+    }
+
+    println!(
+        "{:>4} {:>8} {:>10} {:>12} {:>10}",
+        "n", "inputs", "max steps", "mean steps", "max cells"
+    );
+    for point in &points {
+        let undecided = if point.undecided > 0 {
+            format!(" ({} did not halt)", point.undecided)
+        } else {
+            String::new()
+        };
+
+        println!(
+            "{:>4} {:>8} {:>10} {:>12.2} {:>10}{undecided}",
+            point.n, point.inputs, point.max_steps, point.mean_steps, point.max_cells
+        );
+    }
+
+    if let Some(exponent) = analysis::growth_exponent(&points) {
+        println!("Worst-case steps grow roughly like n^{exponent:.2}");
+    }
+
+    0
+}
+
+/// Runs a machine on every input up to some size, in parallel, and prints the inputs it
+/// accepts with statistics of the steps of the runs (or writes every verdict as CSV). Returns
+/// the process exit code: 0 on success and 1 on error.
+pub fn sweep(args: &[String]) -> i32 {
+    let (filename, alphabet, max_len, max_steps, csv) = match parse_word_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            eprintln!("{SWEEP_USAGE}");
+            return 1;
+        }
+    };
+
+    let machine = match source::load_turing(filename, "") {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    let results = analysis::sweep(&machine, &alphabet, max_len, max_steps);
+
+    if let Some(output) = csv {
+        if fs::write(output, analysis::sweep_csv(&results)).is_err() {
+            eprintln!("Error: \"Could not write the file \"{output}\"\"");
+            return 1;
+        }
+    }
+
+    let mut rejected = 0;
+    let mut undecided = 0;
+    for result in &results {
+        match result.outcome {
+            RunOutcome::Halted { accepted: true, .. } => {
+                let word = if result.word.is_empty() {
+                    "ε"
+                } else {
+                    &result.word
+                };
+                println!("{word}");
+            }
+            RunOutcome::Halted {
+                accepted: false, ..
+            } => rejected += 1,
+            _ => undecided += 1,
+        }
+    }
+
+    let accepted = results.len() - rejected - undecided;
+    println!(
+        "{} inputs: {accepted} accepted, {rejected} rejected, {undecided} did not halt within {max_steps} steps",
+        results.len()
+    );
+
+    let steps = results
+        .iter()
+        .filter(|result| result.outcome.halted())
+        .map(|result| result.outcome.steps())
+        .collect::<Vec<_>>();
+    if let (Some(min), Some(max)) = (steps.iter().min(), steps.iter().max()) {
+        let mean = steps.iter().sum::<usize>() as f64 / steps.len() as f64;
+        println!("Steps of the halting runs: min {min}, mean {mean:.2}, max {max}");
+    }
+
+    0
+}
+
+type WordArgs<'a> = (&'a str, Vec<char>, usize, usize, Option<&'a str>);
+
+fn parse_word_args(args: &[String]) -> Result<WordArgs<'_>, String> {
+    let [filename, flags @ ..] = args else {
+        return Err("Missing filename.".to_string());
+    };
+
+    let mut alphabet = None;
+    let mut max_len = None;
+    let mut max_steps = DEFAULT_COMPLEXITY_MAX_STEPS;
+    let mut csv = None;
+    let mut flags = flags.iter();
+
+    while let Some(flag) = flags.next() {
+        let value = flags
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{flag}\"."))?;
+
+        match flag.as_str() {
+            "--alphabet" => alphabet = Some(value.chars().collect::<Vec<_>>()),
+            "--max-len" => {
+                max_len = Some(value.parse().map_err(|_| {
+                    format!("Invalid length \"{value}\". It must be a non negative integer.")
+                })?);
+            }
+            "--max-steps" => {
+                max_steps = value.parse().map_err(|_| {
+                    format!("Invalid step limit \"{value}\". It must be a non negative integer.")
+                })?;
+            }
+            "--csv" => csv = Some(value.as_str()),
+            _ => return Err(format!("Unexpected argument \"{flag}\".")),
+        }
+    }
+
+    let alphabet = alphabet.ok_or("Missing alphabet.")?;
+    let max_len = max_len.ok_or("Missing maximum length.")?;
+
+    Ok((filename, alphabet, max_len, max_steps, csv))
+}
+
+/// Runs a generator script and prints the `.tng` file of the machine it builds (or writes it
+/// to the output file). Returns the process exit code: 0 on success and 1 on error.
+pub fn generate(args: &[String]) -> i32 {
+    let (script_filename, output) = match args {
+        [script_filename] => (script_filename, None),
+        [script_filename, flag, output] if flag == "--output" => (script_filename, Some(output)),
+        _ => {
+            eprintln!("{GENERATE_USAGE}");
+            return 1;
+        }
+    };
+
+    let script = match fs::read_to_string(script_filename) {
+        Ok(script) => script,
+        Err(_) => {
+            eprintln!("Error: \"Could not open the file \"{script_filename}\"\"");
+            return 1;
+        }
+    };
+
+    match (
+        generate_tng(&script, source::seed().unwrap_or_default()),
+        output,
+    ) {
+        (Ok(tng), None) => {
+            print!("{tng}");
+            0
+        }
+        (Ok(tng), Some(output)) => match fs::write(output, tng) {
+            Ok(()) => 0,
+            Err(_) => {
+                eprintln!("Error: \"Could not write the file \"{output}\"\"");
+                1
+            }
+        },
+        (Err(err), _) => {
+            eprintln!("Error: \"{err}\"");
+            1
+        }
+    }
+}
+
+/// Removes the unreachable states and the transitions that can never be taken from a
+/// definition, and prints the cleaned definition (or writes it to a file). What was removed is
+/// listed on stderr. Returns the process exit code: 0 on success and 1 on error.
+pub fn prune(args: &[String]) -> i32 {
+    let (filename, output) = match args {
+        [filename] => (filename, None),
+        [filename, flag, output] if flag == "--output" => (filename, Some(output)),
+        _ => {
+            eprintln!("{PRUNE_USAGE}");
+            return 1;
+        }
+    };
+
+    let cleaned = source::read_definition(filename)
+        .and_then(|file_data| transform::remove_dead_code_from_tng(&file_data));
+    let (tng, dead) = match cleaned {
+        Ok(cleaned) => cleaned,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    for state in &dead.states {
+        eprintln!("Removed the state {state}, which can't be reached");
+    }
+    for (state, source) in &dead.transitions {
+        let symbol = match source {
+            TransitionSource::Mark(c) => c.to_string(),
+            TransitionSource::Blank => "blank".to_string(),
+            TransitionSource::Default => "default".to_string(),
+        };
+        eprintln!(
+            "Removed the transition of the state {state} reading {symbol}, which is never taken"
+        );
+    }
+
+    match output {
+        None => {
+            print!("{tng}");
+            0
+        }
+        Some(output) => match fs::write(output, tng) {
+            Ok(()) => 0,
+            Err(_) => {
+                eprintln!("Error: \"Could not write the file \"{output}\"\"");
+                1
+            }
+        },
+    }
+}
+
+/// Compiles a Turing Machine into the binary `.tngc` format, which loads faster than its
+/// definition. By default, it is written next to the definition. Returns the process exit code:
+/// 0 on success and 1 on error.
+pub fn compile(args: &[String]) -> i32 {
+    let (filename, output) = match args {
+        [filename] => {
+            let stem = filename.strip_suffix(".tng").unwrap_or(filename);
+            (filename, format!("{stem}.{COMPILED_EXTENSION}"))
+        }
+        [filename, flag, output] if flag == "--output" => (filename, output.clone()),
+        _ => {
+            eprintln!("{COMPILE_USAGE}");
+            return 1;
+        }
+    };
+
+    let machine = match source::load_turing(filename, "") {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    match fs::write(&output, compiled::compile(&machine)) {
+        Ok(()) => 0,
+        Err(_) => {
+            eprintln!("Error: \"Could not write the file \"{output}\"\"");
+            1
+        }
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn generate_tng(script: &str, seed: u64) -> Result<String, String> {
+    let tng = turing_lib::generate::generate_tng(script, seed)?;
+
+    // Loading it reports any mistake of the script, like a missing initial state
+    turing_lib::machine::TuringMachine::new_from_str(&tng, "")?;
+    Ok(tng)
+}
+
+#[cfg(not(feature = "scripting"))]
+fn generate_tng(_: &str, _: u64) -> Result<String, String> {
+    Err("Generator scripts need turing to be built with the \"scripting\" feature.".to_string())
+}
+
+/// Lists the built-in machines, or prints the `.tng` file of one of them. Returns the process
+/// exit code: 0 on success and 1 if there is no machine with that name.
+pub fn examples(args: &[String]) -> i32 {
+    match args {
+        [] => {
+            for template in library::TEMPLATES {
+                println!("{:<18}{}", template.name(), template.description());
+            }
+            0
+        }
+        [name] => match library::find(name) {
+            Some(template) => {
+                print!("{}", template.source());
+                0
+            }
+            None => {
+                eprintln!("Error: \"There is no built-in machine named \"{name}\"\"");
+                1
+            }
+        },
+        _ => {
+            eprintln!("{EXAMPLES_USAGE}");
+            1
+        }
+    }
+}
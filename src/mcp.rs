@@ -0,0 +1,380 @@
+use std::io::{stdin, stdout, BufRead, Write};
+
+use serde_json::{json, Value};
+use turing_lib::{
+    automaton::{self, Automaton},
+    explain,
+    machine::Symbol,
+    runner::{self, RunLimits},
+};
+
+use crate::{headless, source};
+
+pub const MCP_USAGE: &str = "Usage: turing mcp [--max-tape-cells <n>] [--max-memory <MiB>]";
+
+// Protocol versions this server speaks, the latest first
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const DEFAULT_RUN_MAX_STEPS: u64 = 100_000;
+const MAX_STEP_COUNT: u64 = 10_000; // Per step call, as every step is explained
+const MAX_EXPLAINED_STEPS: usize = 50; // The explanations of the last ones are returned
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+struct McpServer {
+    machine: Option<Box<dyn Automaton>>,
+    limits: RunLimits,
+}
+
+/// Serves the simulator over the [Model Context Protocol](https://modelcontextprotocol.io),
+/// reading JSON-RPC messages from stdin and writing the responses to stdout, one per line, so
+/// assistants can load a machine, step it and inspect its tape. Returns the process exit code.
+pub fn mcp(args: &[String]) -> i32 {
+    let mut limits = RunLimits::default();
+
+    for flag_and_value in args.chunks(2) {
+        match flag_and_value {
+            [flag, value] if flag == "--max-tape-cells" => {
+                match value.parse().ok().filter(|&cells| cells > 0) {
+                    Some(cells) => limits.max_tape_cells = Some(cells),
+                    None => {
+                        eprintln!("Error: Invalid tape cell limit \"{value}\". It must be a positive integer.");
+                        return 1;
+                    }
+                }
+            }
+            [flag, value] if flag == "--max-memory" => match headless::parse_memory_limit(value) {
+                Ok(bytes) => limits.max_memory = Some(bytes),
+                Err(err) => {
+                    eprintln!("Error: {err}");
+                    return 1;
+                }
+            },
+            _ => {
+                eprintln!("{MCP_USAGE}");
+                return 1;
+            }
+        }
+    }
+
+    let mut server = McpServer {
+        machine: None,
+        limits,
+    };
+
+    for line in stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => server.handle_message(&message),
+            Err(err) => Some(error_response(
+                Value::Null,
+                PARSE_ERROR,
+                &format!("Invalid JSON: {err}"),
+            )),
+        };
+
+        if let Some(response) = response {
+            let mut stdout = stdout().lock();
+            if writeln!(stdout, "{response}")
+                .and_then(|_| stdout.flush())
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+
+    0
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+fn tool_result(text: String, is_error: bool) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+fn tools() -> Value {
+    json!([
+        {
+            "name": "load_machine",
+            "description": "Loads a machine (Turing machine, pushdown automaton, counter machine, finite automaton...) from the source of a .tng definition, or from a .tng file or URL, and writes the input on its tape. Replaces the machine loaded before.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "definition": { "type": "string", "description": "Source of the .tng definition." },
+                    "path": { "type": "string", "description": "Path or URL of a .tng file, if no definition is given." },
+                    "tape": { "type": "string", "description": "Input written on the tape (empty by default)." },
+                },
+            },
+        },
+        {
+            "name": "step",
+            "description": "Takes some steps of the loaded machine, and explains what every step did in plain words.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "count": { "type": "integer", "minimum": 1, "maximum": MAX_STEP_COUNT, "description": "Number of steps (1 by default)." },
+                },
+            },
+        },
+        {
+            "name": "run",
+            "description": "Runs the loaded machine from its current configuration until it halts or reaches the step limit, and reports the verdict and the steps taken.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "max_steps": { "type": "integer", "minimum": 1, "description": format!("Step limit ({DEFAULT_RUN_MAX_STEPS} by default).") },
+                },
+            },
+        },
+        {
+            "name": "read_tape",
+            "description": "Shows the current configuration of the loaded machine: its state, its tape with the position of the head, and whether it halted.",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+    ])
+}
+
+impl McpServer {
+    /// Handles a request or a notification, returning the response to requests.
+    fn handle_message(&mut self, message: &Value) -> Option<Value> {
+        // Notifications (like "notifications/initialized") have no id and get no response
+        let id = message.get("id")?.clone();
+        let params = message.get("params").cloned().unwrap_or(json!({}));
+
+        let result = match message.get("method").and_then(Value::as_str) {
+            Some("initialize") => {
+                let requested = params.get("protocolVersion").and_then(Value::as_str);
+                let version = requested
+                    .filter(|version| PROTOCOL_VERSIONS.contains(version))
+                    .unwrap_or(PROTOCOL_VERSIONS[0]);
+
+                json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "turing", "version": env!("CARGO_PKG_VERSION") },
+                })
+            }
+            Some("ping") => json!({}),
+            Some("tools/list") => json!({ "tools": tools() }),
+            Some("tools/call") => match self.call_tool(&params) {
+                Ok(result) => result,
+                Err(message) => return Some(error_response(id, INVALID_PARAMS, &message)),
+            },
+            Some(method) => {
+                return Some(error_response(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("Unknown method \"{method}\"."),
+                ))
+            }
+            None => {
+                return Some(error_response(
+                    id,
+                    INVALID_PARAMS,
+                    "The request has no method.",
+                ))
+            }
+        };
+
+        Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+
+    /// Calls a tool. Mistakes of the call itself (an unknown tool or invalid arguments) are
+    /// errors, and the ones of the machine are reported in the result of the tool.
+    fn call_tool(&mut self, params: &Value) -> Result<Value, String> {
+        let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+        let string_argument = |name: &str| arguments.get(name).and_then(Value::as_str);
+        let integer_argument = |name: &str, default: u64, max: u64| match arguments.get(name) {
+            None => Ok(default),
+            Some(value) => value
+                .as_u64()
+                .filter(|&value| value > 0 && value <= max)
+                .ok_or_else(|| {
+                    format!("The \"{name}\" argument must be an integer between 1 and {max}.")
+                }),
+        };
+
+        match params.get("name").and_then(Value::as_str) {
+            Some("load_machine") => {
+                let tape = string_argument("tape").unwrap_or("");
+                let loaded = match (string_argument("definition"), string_argument("path")) {
+                    (Some(definition), _) => {
+                        automaton::load_from_str(definition, tape).map_err(|err| err.to_string())
+                    }
+                    (None, Some(path)) => source::load(path, tape),
+                    (None, None) => {
+                        return Err("The \"definition\" or the \"path\" argument must be given."
+                            .to_string())
+                    }
+                };
+
+                Ok(match loaded {
+                    Ok(machine) => {
+                        let mut text = format!("Loaded \"{}\".", machine.name());
+                        if let Some(note) = headless::head_start_note(machine.as_ref()) {
+                            text.push_str(&format!(" {note}"));
+                        }
+                        text.push_str(&format!("\n{}", describe(machine.as_ref())));
+
+                        self.machine = Some(machine);
+                        tool_result(text, false)
+                    }
+                    Err(err) => tool_result(err, true),
+                })
+            }
+            Some("step") => {
+                let count = integer_argument("count", 1, MAX_STEP_COUNT)?;
+                Ok(self.step(count as usize))
+            }
+            Some("run") => {
+                let max_steps = integer_argument("max_steps", DEFAULT_RUN_MAX_STEPS, u64::MAX)?;
+                let limits = RunLimits {
+                    max_steps: Some(max_steps as usize),
+                    ..self.limits.clone()
+                };
+
+                Ok(match self.machine.as_mut() {
+                    Some(machine) => {
+                        let outcome = runner::run(machine.as_mut(), &limits);
+                        let (verdict, _) = headless::describe_outcome(outcome, &limits);
+                        tool_result(format!("{verdict}\n{}", describe(machine.as_ref())), false)
+                    }
+                    None => no_machine(),
+                })
+            }
+            Some("read_tape") => Ok(match &self.machine {
+                Some(machine) => tool_result(describe(machine.as_ref()), false),
+                None => no_machine(),
+            }),
+            Some(name) => Err(format!("Unknown tool \"{name}\".")),
+            None => Err("The tool call has no name.".to_string()),
+        }
+    }
+
+    fn step(&mut self, count: usize) -> Value {
+        let Some(machine) = self.machine.as_mut() else {
+            return no_machine();
+        };
+
+        let tape_cell_limit = self.limits.tape_cell_limit(machine.as_ref());
+
+        let mut explanations = vec![];
+        let mut steps = 0;
+        while steps < count && !machine.is_halted() {
+            if tape_cell_limit.is_some_and(|max| machine.tape_cells() > max) {
+                return tool_result(
+                    "The machine used more tape cells than allowed.".to_string(),
+                    true,
+                );
+            }
+
+            if self
+                .limits
+                .max_memory
+                .is_some_and(|max| machine.memory_usage() > max)
+            {
+                return tool_result(
+                    "The machine used more memory than allowed.".to_string(),
+                    true,
+                );
+            }
+
+            let tick_result = machine.tick();
+            explanations.extend(explain::explain(&tick_result));
+
+            // Like in the runners, the final halting tick is not counted as a step
+            if !machine.is_halted() {
+                steps += 1;
+            }
+        }
+
+        let skipped = explanations.len().saturating_sub(MAX_EXPLAINED_STEPS);
+        let mut text = String::new();
+        if skipped > 0 {
+            text.push_str(&format!("({skipped} earlier steps not shown)\n"));
+        }
+        for explanation in &explanations[skipped..] {
+            text.push_str(explanation);
+            text.push('\n');
+        }
+        text.push_str(&describe(machine.as_ref()));
+
+        tool_result(text, false)
+    }
+}
+
+fn no_machine() -> Value {
+    tool_result(
+        "No machine is loaded. Load one with the load_machine tool first.".to_string(),
+        true,
+    )
+}
+
+/// The current configuration in plain text, with a caret under the cell of the head.
+fn describe(machine: &dyn Automaton) -> String {
+    let tape = machine
+        .tape()
+        .get_content()
+        .iter()
+        .map(|symbol| match symbol {
+            Symbol::Mark(c) => *c,
+            _ => machine.blank_symbol(),
+        })
+        .collect::<String>();
+
+    let state = match machine.state_description() {
+        Some(description) => format!("{} ({description})", machine.current_state_name()),
+        None => machine.current_state_name().to_string(),
+    };
+
+    let status = match (machine.is_halted(), machine.is_accepting()) {
+        (false, _) => "running",
+        (true, true) => "halted, accepts",
+        (true, false) => "halted, rejects",
+    };
+
+    let mut text = format!(
+        "State: {state}\nStatus: {status}\nHead: {}\nTape: {tape}\n      {}^",
+        machine.head_idx(),
+        " ".repeat(machine.head_idx())
+    );
+
+    if let Some(stack) = machine.stack() {
+        text.push_str(&format!(
+            "\nStack (bottom first): {}",
+            stack.iter().collect::<String>()
+        ));
+    }
+
+    if let Some(registers) = machine.registers() {
+        let registers = registers
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| format!("r{idx}={value}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        text.push_str(&format!("\nRegisters: {registers}"));
+    }
+
+    text
+}
@@ -0,0 +1,143 @@
+use std::{
+    io::{stdout, Write},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use turing_lib::{automaton::Automaton, machine::Symbol};
+use unicode_width::UnicodeWidthChar;
+
+use crate::serve::json_string;
+
+const VISIBLE_CELLS: usize = 15; // Odd, so the head is in the middle
+const CELL_WIDTH: usize = 3; // In terminal columns
+const FRAME_DURATION: Duration = Duration::from_millis(100);
+
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+const REVERSE_VIDEO: &str = "\x1b[7m";
+const RESET: &str = "\x1b[0m";
+
+const CAST_WIDTH: usize = 80;
+const CAST_HEIGHT: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Animates the run in the terminal.
+    Ansi,
+    /// Prints the run as an [asciinema](https://asciinema.org) cast (v2).
+    Cast,
+}
+
+impl RenderMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "ansi" => Ok(RenderMode::Ansi),
+            "cast" => Ok(RenderMode::Cast),
+            _ => Err(format!(
+                "Invalid render mode \"{value}\". It must be \"ansi\" or \"cast\"."
+            )),
+        }
+    }
+}
+
+/// Renders every configuration of a run as a frame with the state, the step and the tape around
+/// the head, either straight to the terminal or into an asciinema cast.
+pub struct Renderer {
+    mode: RenderMode,
+    title: String,
+    cast_events: Vec<String>,
+}
+
+impl Renderer {
+    pub fn new(mode: RenderMode, title: &str) -> Self {
+        Self {
+            mode,
+            title: title.to_string(),
+            cast_events: vec![],
+        }
+    }
+
+    pub fn render<A: Automaton + ?Sized>(&mut self, machine: &A, steps: usize) {
+        let frame = format!("{CLEAR_SCREEN}{}", frame(machine, steps));
+        self.output(frame);
+    }
+
+    /// Shows the verdict after the last frame and, for casts, prints the whole cast.
+    pub fn finish(mut self, verdict: &str) {
+        self.output(format!("\r\n{verdict}\r\n"));
+
+        if self.mode == RenderMode::Cast {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs());
+
+            println!(
+                "{{\"version\":2,\"width\":{CAST_WIDTH},\"height\":{CAST_HEIGHT},\"timestamp\":{timestamp},\"title\":{}}}",
+                json_string(&self.title)
+            );
+            for event in &self.cast_events {
+                println!("{event}");
+            }
+        }
+    }
+
+    fn output(&mut self, data: String) {
+        match self.mode {
+            RenderMode::Ansi => {
+                print!("{data}");
+                let _ = stdout().flush();
+                thread::sleep(FRAME_DURATION);
+            }
+            RenderMode::Cast => {
+                let time = self.cast_events.len() as f64 * FRAME_DURATION.as_secs_f64();
+                self.cast_events
+                    .push(format!("[{time:.3}, \"o\", {}]", json_string(&data)));
+            }
+        }
+    }
+}
+
+fn frame<A: Automaton + ?Sized>(machine: &A, steps: usize) -> String {
+    let head_idx = machine.head_idx() as isize;
+    let first_idx = head_idx - (VISIBLE_CELLS / 2) as isize;
+
+    let cells = (first_idx..first_idx + VISIBLE_CELLS as isize)
+        .map(|idx| {
+            let symbol = match machine.tape()[idx] {
+                Symbol::Mark(c) => c,
+                _ => machine.blank_symbol(),
+            };
+
+            // Wide symbols (e.g. CJK) take two columns of the three of a cell
+            let width = symbol.width().unwrap_or(0).min(CELL_WIDTH);
+            let left = (CELL_WIDTH - width).div_ceil(2);
+            let cell = format!(
+                "{}{symbol}{}",
+                " ".repeat(left),
+                " ".repeat(CELL_WIDTH - width - left)
+            );
+
+            if idx == head_idx {
+                format!("{REVERSE_VIDEO}{cell}{RESET}")
+            } else {
+                cell
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let border = |left: &str, middle: &str, right: &str| {
+        format!(
+            "{left}{}{right}",
+            vec!["─".repeat(CELL_WIDTH); VISIBLE_CELLS].join(middle)
+        )
+    };
+
+    format!(
+        "State: {}    Step: {steps}\r\n{}\r\n│{}│\r\n{}\r\n{}▲\r\n",
+        machine.current_state_name(),
+        border("┌", "┬", "┐"),
+        cells.join("│"),
+        border("└", "┴", "┘"),
+        " ".repeat(VISIBLE_CELLS / 2 * (CELL_WIDTH + 1) + CELL_WIDTH / 2 + 1),
+    )
+}
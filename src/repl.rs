@@ -0,0 +1,504 @@
+use std::collections::VecDeque;
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+use turing_lib::{
+    explain,
+    machine::{Step, Symbol, TuringMachine},
+    watchpoint::{CellWrite, Watchpoints},
+};
+
+use crate::{headless, source};
+
+pub const REPL_USAGE: &str = "Usage: turing repl <filename.tng> [<tape_data>]";
+
+const PROMPT: &str = "turing> ";
+const DEFAULT_RUN_STEPS: usize = 1_000_000; // So `run` can't hang on a machine that never halts
+const BACKTRACE_LEN: usize = 20;
+
+const HELP: &str = "Commands:
+  step [<n>]                Takes one step (or n steps), explaining each of them
+  back [<n>]                Takes the last step (or the last n) back
+  run [<n>]                 Runs until the machine halts, or for at most n steps
+  continue                  Runs until the machine halts or stops at a breakpoint
+  tape                      Shows the tape, with the symbol under the head between brackets
+  state                     Shows the current state, the head and the steps taken
+  set-cell <index> <symbol> Writes a symbol on a cell of the tape
+  reset [<tape_data>]       Starts over, with a new input or with the last one
+  break state <name>        Stops whenever the machine enters the state
+  watch cell <index>        Stops whenever the symbol on the cell changes
+  delete <number>           Deletes a breakpoint
+  backtrace [<n>]           Shows the last steps (or the last n), the most recent first
+  info breakpoints          Lists the breakpoints
+  info stats                Shows statistics of the run so far
+  help                      Shows this help
+  quit                      Exits";
+
+/// Where a breakpoint stops the machine.
+enum Breakpoint {
+    /// Whenever it enters the state.
+    State(String),
+    /// Whenever the symbol on a cell changes. The cells are in `Session::watchpoints`, in the
+    /// same order as these breakpoints.
+    Cell,
+}
+
+/// Why the machine stopped running.
+enum Stop {
+    Halted,
+    /// It reached the step limit of the command.
+    Limit,
+    /// It entered the state of the breakpoint with this number.
+    State(usize),
+    /// The symbol on the cell of the breakpoint with this number changed.
+    Cell {
+        breakpoint: usize,
+        write: CellWrite,
+    },
+}
+
+/// A machine being driven from the REPL, with the steps it took since it was last reset.
+struct Session {
+    machine: TuringMachine,
+    tape_data: String,
+    steps: usize,
+    breakpoints: Vec<(usize, Breakpoint)>, // By number
+    next_breakpoint: usize,
+    watchpoints: Watchpoints,
+    backtrace: VecDeque<Step>, // The last steps, the most recent last
+}
+
+impl Session {
+    fn new(mut machine: TuringMachine, tape_data: &str) -> Self {
+        machine.enable_history(); // To take steps back
+        Self {
+            machine,
+            tape_data: tape_data.to_string(),
+            steps: 0,
+            breakpoints: vec![],
+            next_breakpoint: 1,
+            watchpoints: Watchpoints::default(),
+            backtrace: VecDeque::new(),
+        }
+    }
+
+    /// Takes a step, returning its explanation and the writes on the watched cells, or `None`
+    /// if the machine had already halted.
+    fn step(&mut self) -> Option<(String, Vec<CellWrite>)> {
+        if self.machine.is_halted() {
+            return None;
+        }
+
+        let (tick_result, writes) = self.watchpoints.tick(&mut self.machine);
+        if !self.machine.is_halted() {
+            self.steps += 1;
+        }
+
+        let explanation = explain::explain(&tick_result).unwrap_or_default();
+        if let Some(step) = tick_result.step {
+            if self.backtrace.len() == BACKTRACE_LEN {
+                self.backtrace.pop_front();
+            }
+            self.backtrace.push_back(step);
+        }
+
+        Some((explanation, writes))
+    }
+
+    /// Takes up to `max_steps` steps, stopping early if the machine halts or hits a breakpoint.
+    /// Returns the explanations of the steps taken and why it stopped.
+    fn advance(&mut self, max_steps: usize) -> (Vec<String>, Stop) {
+        let mut explanations = vec![];
+
+        for _ in 0..max_steps {
+            let Some((explanation, writes)) = self.step() else {
+                return (explanations, Stop::Halted);
+            };
+            explanations.push(explanation);
+
+            if let Some(write) = writes.first() {
+                let breakpoint = self
+                    .breakpoints
+                    .iter()
+                    .filter(|(_, breakpoint)| matches!(breakpoint, Breakpoint::Cell))
+                    .nth(write.watchpoint)
+                    .map(|(number, _)| *number)
+                    .expect("Every watchpoint has a cell breakpoint");
+
+                return (
+                    explanations,
+                    Stop::Cell {
+                        breakpoint,
+                        write: *write,
+                    },
+                );
+            }
+
+            if self.machine.is_halted() {
+                return (explanations, Stop::Halted);
+            }
+
+            let state = self.machine.current_state_name();
+            if let Some((number, _)) = self.breakpoints.iter().find(
+                |(_, breakpoint)| matches!(breakpoint, Breakpoint::State(name) if name == state),
+            ) {
+                return (explanations, Stop::State(*number));
+            }
+        }
+
+        (explanations, Stop::Limit)
+    }
+
+    fn symbol_char(&self, symbol: Symbol) -> char {
+        match symbol {
+            Symbol::Mark(c) => c,
+            _ => self.machine.blank_symbol(),
+        }
+    }
+
+    fn describe_stop(&self, stop: &Stop) -> String {
+        let reason = match stop {
+            Stop::Halted | Stop::Limit => return self.status(),
+            Stop::State(number) => format!(
+                "Breakpoint {number}, state {}",
+                self.machine.current_state_name()
+            ),
+            Stop::Cell { breakpoint, write } => format!(
+                "Breakpoint {breakpoint}, cell {} changed from '{}' to '{}'",
+                write.cell,
+                self.symbol_char(write.old),
+                self.symbol_char(write.new)
+            ),
+        };
+
+        format!("{reason}\n{}", self.status())
+    }
+
+    fn status(&self) -> String {
+        let status = if !self.machine.is_halted() {
+            "running"
+        } else if self.machine.is_accepting() {
+            "halted, accepts"
+        } else {
+            "halted, rejects"
+        };
+
+        format!(
+            "State {}, head {}, {} steps ({status})",
+            self.machine.current_state_name(),
+            self.machine.head_idx(),
+            self.steps
+        )
+    }
+
+    fn tape(&self) -> String {
+        self.machine
+            .tape()
+            .get_content()
+            .iter()
+            .enumerate()
+            .map(|(idx, symbol)| {
+                let symbol = self.symbol_char(*symbol);
+
+                if idx == self.machine.head_idx() {
+                    format!("[{symbol}]")
+                } else {
+                    symbol.to_string()
+                }
+            })
+            .collect()
+    }
+
+    fn stats(&self) -> String {
+        let heatmap = self.machine.heatmap();
+        let busiest = heatmap
+            .states()
+            .iter()
+            .max_by(|(name, visits), (other_name, other_visits)| {
+                visits.cmp(other_visits).then(other_name.cmp(name))
+            })
+            .map(|(name, visits)| format!("\nMost visited state: {name} ({visits} times)"))
+            .unwrap_or_default();
+
+        format!(
+            "Steps: {}\nTape cells: {}\nStates visited: {} of {}\nTransitions taken: {} of {}{busiest}",
+            self.steps,
+            self.machine.tape().len(),
+            heatmap.states().len(),
+            self.machine.states().len(),
+            heatmap.transitions_taken(),
+            self.machine.transition_count()
+        )
+    }
+
+    fn list_breakpoints(&self) -> String {
+        if self.breakpoints.is_empty() {
+            return "No breakpoints".to_string();
+        }
+
+        let mut cells = self.watchpoints.cells().iter();
+
+        self.breakpoints
+            .iter()
+            .map(|(number, breakpoint)| match breakpoint {
+                Breakpoint::State(name) => format!("{number}: state {name}"),
+                Breakpoint::Cell => format!("{number}: cell {}", cells.next().unwrap()),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Runs the command on the line, returning what to print, or `None` to exit.
+    fn execute(&mut self, line: &str) -> Option<Result<String, String>> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        let result = match command {
+            "step" | "s" => parse_count(rest, 1).map(|count| {
+                if self.machine.is_halted() {
+                    return "The machine has halted, use \"reset\" to start over".to_string();
+                }
+
+                let (mut explanations, stop) = self.advance(count);
+                explanations.push(self.describe_stop(&stop));
+                explanations.join("\n")
+            }),
+            "back" => parse_count(rest, 1).and_then(|count| self.back(count)),
+            "run" | "r" => parse_count(rest, DEFAULT_RUN_STEPS).map(|count| {
+                let (_, stop) = self.advance(count);
+                self.describe_stop(&stop)
+            }),
+            "continue" | "c" => {
+                let (_, stop) = self.advance(DEFAULT_RUN_STEPS);
+                Ok(self.describe_stop(&stop))
+            }
+            "tape" | "t" => Ok(self.tape()),
+            "state" => Ok(self.status()),
+            "set-cell" => self.set_cell(rest).map(|_| self.tape()),
+            "reset" => {
+                if !rest.is_empty() {
+                    self.tape_data = rest.trim_matches('"').to_string();
+                }
+                self.machine.reset(&self.tape_data);
+                self.steps = 0;
+                self.backtrace.clear();
+                headless::warn_about_input(&self.machine, &self.tape_data);
+                Ok(self.status())
+            }
+            "break" | "b" => self.add_breakpoint(rest, "state"),
+            "watch" | "w" => self.add_breakpoint(rest, "cell"),
+            "delete" | "d" => self.delete_breakpoint(rest),
+            "backtrace" | "bt" => parse_count(rest, BACKTRACE_LEN).map(|count| {
+                if self.backtrace.is_empty() {
+                    return "No steps taken".to_string();
+                }
+
+                self.backtrace
+                    .iter()
+                    .rev()
+                    .take(count)
+                    .enumerate()
+                    .map(|(idx, step)| format!("#{idx} {}", explain::explain_step(step)))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }),
+            "info" | "i" => match rest {
+                "breakpoints" | "b" => Ok(self.list_breakpoints()),
+                "stats" | "s" => Ok(self.stats()),
+                _ => Err("Usage: info <breakpoints|stats>".to_string()),
+            },
+            "help" | "h" => Ok(HELP.to_string()),
+            "quit" | "q" | "exit" => return None,
+            _ => Err(format!(
+                "Unknown command \"{command}\", use \"help\" to list the commands."
+            )),
+        };
+
+        Some(result)
+    }
+
+    /// Takes up to `count` steps back, and shows where the machine is then.
+    fn back(&mut self, count: usize) -> Result<String, String> {
+        if self.steps == 0 {
+            return Err("No steps taken".to_string());
+        }
+
+        let origin =
+            |machine: &TuringMachine| machine.history().map_or(0, |history| history.origin());
+        let origin_before = origin(&self.machine);
+        if self.machine.is_halted() {
+            // The tick that halted the machine is not counted as a step
+            self.machine.step_back()?;
+            if matches!(self.backtrace.back(), Some(Step::Halt { .. })) {
+                self.backtrace.pop_back();
+            }
+        }
+
+        let count = count.min(self.steps);
+        for _ in 0..count {
+            self.machine.step_back()?;
+            self.backtrace.pop_back();
+        }
+        self.steps -= count;
+
+        // The watched cells follow the tape back if it had grown to the left
+        let shrunk = origin_before - origin(&self.machine);
+        if shrunk > 0 {
+            self.watchpoints = Watchpoints::new(
+                self.watchpoints
+                    .cells()
+                    .iter()
+                    .map(|cell| cell.saturating_sub(shrunk)),
+            );
+        }
+
+        Ok(self.status())
+    }
+
+    fn set_cell(&mut self, args: &str) -> Result<(), String> {
+        let [idx, symbol] = args.split_whitespace().collect::<Vec<_>>()[..] else {
+            return Err("Usage: set-cell <index> <symbol>".to_string());
+        };
+
+        let idx = parse_index(idx)?;
+
+        let mut chars = symbol.chars();
+        let (Some(symbol), None) = (chars.next(), chars.next()) else {
+            return Err(format!(
+                "Invalid symbol \"{symbol}\". It must be a single character."
+            ));
+        };
+
+        self.machine
+            .set_cell(idx, Symbol::from_char(symbol, self.machine.blank_symbol()))
+            .map_err(|err| err.to_string())
+    }
+
+    /// Adds a breakpoint from the arguments of `break` (which expects a state) or `watch`
+    /// (which expects a cell).
+    fn add_breakpoint(&mut self, args: &str, kind: &str) -> Result<String, String> {
+        let usage = || {
+            if kind == "state" {
+                "Usage: break state <name>".to_string()
+            } else {
+                "Usage: watch cell <index>".to_string()
+            }
+        };
+
+        let [arg_kind, target] = args.split_whitespace().collect::<Vec<_>>()[..] else {
+            return Err(usage());
+        };
+        if arg_kind != kind {
+            return Err(usage());
+        }
+
+        let breakpoint = if kind == "state" {
+            if !self.machine.states().contains_key(target) {
+                return Err(format!("There is no state \"{target}\"."));
+            }
+            Breakpoint::State(target.to_string())
+        } else {
+            self.watchpoints.add(parse_index(target)?);
+            Breakpoint::Cell
+        };
+
+        let number = self.next_breakpoint;
+        self.next_breakpoint += 1;
+        self.breakpoints.push((number, breakpoint));
+
+        Ok(format!("Breakpoint {number}: {kind} {target}"))
+    }
+
+    fn delete_breakpoint(&mut self, arg: &str) -> Result<String, String> {
+        let number = arg
+            .parse::<usize>()
+            .map_err(|_| "Usage: delete <number>".to_string())?;
+
+        let Some(position) = self.breakpoints.iter().position(|(n, _)| *n == number) else {
+            return Err(format!("There is no breakpoint {number}."));
+        };
+        if let (_, Breakpoint::Cell) = self.breakpoints.remove(position) {
+            let watchpoint = self.breakpoints[..position]
+                .iter()
+                .filter(|(_, breakpoint)| matches!(breakpoint, Breakpoint::Cell))
+                .count();
+            self.watchpoints.remove(watchpoint);
+        }
+
+        Ok(format!("Deleted breakpoint {number}"))
+    }
+}
+
+fn parse_index(arg: &str) -> Result<usize, String> {
+    arg.parse()
+        .map_err(|_| format!("Invalid index \"{arg}\". It must be a non negative integer."))
+}
+
+fn parse_count(arg: &str, default: usize) -> Result<usize, String> {
+    if arg.is_empty() {
+        return Ok(default);
+    }
+
+    arg.parse().map_err(|_| {
+        format!("Invalid number of steps \"{arg}\". It must be a non negative integer.")
+    })
+}
+
+/// Loads a Turing Machine and lets the user drive it with commands typed in the terminal, with
+/// readline editing and history, like a debugger for its definition. Returns the process exit
+/// code: 0 when the user exits, and 1 on error.
+pub fn repl(args: &[String]) -> i32 {
+    let (filename, tape_data) = match args {
+        [filename] => (filename, ""),
+        [filename, tape_data] => (filename, tape_data.as_str()),
+        _ => {
+            eprintln!("{REPL_USAGE}");
+            return 1;
+        }
+    };
+
+    let machine = match source::load_turing(filename, tape_data) {
+        Ok(machine) => machine,
+        Err(err) => {
+            eprintln!("Error: \"{err}\"");
+            return 1;
+        }
+    };
+
+    let Ok(mut editor) = DefaultEditor::new() else {
+        eprintln!("Error: \"Could not open the terminal\"");
+        return 1;
+    };
+
+    headless::warn_about_input(&machine, tape_data);
+
+    let mut session = Session::new(machine, tape_data);
+
+    println!(
+        "\"{}\" loaded, use \"help\" to list the commands.",
+        session.machine.name()
+    );
+    println!("{}", session.status());
+
+    loop {
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => return 0,
+            Err(err) => {
+                eprintln!("Error: \"{err}\"");
+                return 1;
+            }
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+
+        match session.execute(line) {
+            Some(Ok(output)) => println!("{output}"),
+            Some(Err(err)) => println!("Error: {err}"),
+            None => return 0,
+        }
+    }
+}
@@ -0,0 +1,489 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+use turing_lib::{
+    automaton::{self, Automaton},
+    compiled,
+    machine::Symbol,
+    runner::RunLimits,
+};
+
+use crate::headless;
+
+pub const SERVE_USAGE: &str = "Usage: turing serve [--addr <address:port>] [--max-steps <n>] [--timeout <seconds>] [--max-tape-cells <n>] [--max-memory <MiB>] [--max-sessions <n>]";
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+const DEFAULT_MAX_SESSIONS: usize = 1000;
+const RATE_WINDOW: Duration = Duration::from_secs(60); // Window of the steps/sec metric
+const MAX_BODY_LEN: usize = 1 << 20;
+const MAX_STEP_COUNT: usize = 1_000_000; // Per step request, so a machine that never halts can't hold the server
+const IO_TIMEOUT: Duration = Duration::from_secs(10); // So a silent client can't hold the server
+const IDLE_SESSION_TIMEOUT: Duration = Duration::from_secs(60 * 60); // Sessions unused for longer are closed
+const TIMEOUT_CHECK_INTERVAL: usize = 4096; // Steps between every check of the timeout
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn new(status: &'static str, body: String) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        Self::new(status, format!("{{\"error\":{}}}", json_string(message)))
+    }
+}
+
+#[derive(Default)]
+struct Metrics {
+    steps_total: u64,
+    accepted_total: u64,
+    rejected_total: u64,
+    recent_steps: VecDeque<(Instant, usize)>,
+}
+
+impl Metrics {
+    fn record_steps(&mut self, steps: usize) {
+        self.steps_total += steps as u64;
+        self.recent_steps.push_back((Instant::now(), steps));
+    }
+
+    fn steps_per_second(&mut self, started: Instant) -> f64 {
+        while self
+            .recent_steps
+            .front()
+            .is_some_and(|(time, _)| time.elapsed() > RATE_WINDOW)
+        {
+            self.recent_steps.pop_front();
+        }
+
+        let window = started.elapsed().min(RATE_WINDOW).as_secs_f64();
+        let steps = self
+            .recent_steps
+            .iter()
+            .map(|(_, steps)| steps)
+            .sum::<usize>();
+
+        if window > 0.0 {
+            steps as f64 / window
+        } else {
+            0.0
+        }
+    }
+}
+
+struct Session {
+    machine: Box<dyn Automaton>,
+    steps: usize, // Since it was loaded, for the step limit
+    last_used: Instant,
+}
+
+struct Server {
+    sessions: HashMap<u64, Session>,
+    max_sessions: usize,
+    next_id: u64,
+    metrics: Metrics,
+    started: Instant,
+    limits: RunLimits,
+}
+
+/// Serves machines over HTTP, so a web playground can run them. Every loaded machine is a
+/// session, which can be stepped until it halts, and which is closed after an hour without
+/// requests. Returns the process exit code.
+pub fn serve(args: &[String]) -> i32 {
+    let mut addr = DEFAULT_ADDR;
+    let mut limits = RunLimits::default();
+    let mut max_sessions = DEFAULT_MAX_SESSIONS;
+
+    for flag_and_value in args.chunks(2) {
+        let parsed = match flag_and_value {
+            [flag, value] if flag == "--addr" => {
+                addr = value;
+                Ok(true)
+            }
+            [flag, value] if flag == "--max-sessions" => {
+                match value.parse().ok().filter(|&sessions| sessions > 0) {
+                    Some(sessions) => {
+                        max_sessions = sessions;
+                        Ok(true)
+                    }
+                    None => Err(format!(
+                        "Invalid session limit \"{value}\". It must be a positive integer."
+                    )),
+                }
+            }
+            [flag, value] => headless::parse_limit(flag, value, &mut limits),
+            _ => Ok(false),
+        };
+
+        match parsed {
+            Ok(true) => {}
+            Ok(false) => {
+                eprintln!("{SERVE_USAGE}");
+                return 1;
+            }
+            Err(err) => {
+                eprintln!("Error: {err}");
+                return 1;
+            }
+        }
+    }
+
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Error: Could not listen on \"{addr}\": {err}");
+            return 1;
+        }
+    };
+
+    println!("Listening on http://{addr}");
+
+    let mut server = Server {
+        sessions: HashMap::new(),
+        max_sessions,
+        next_id: 1,
+        metrics: Metrics::default(),
+        started: Instant::now(),
+        limits,
+    };
+
+    for stream in listener.incoming().flatten() {
+        if let Err(err) = server.handle_connection(stream) {
+            eprintln!("Error while handling a request: {err}");
+        }
+    }
+
+    0
+}
+
+impl Server {
+    fn handle_connection(&mut self, mut stream: TcpStream) -> std::io::Result<()> {
+        // Requests are handled one at a time, so a client must not make the others wait long
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+        let mut reader = BufReader::new(&mut stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+
+        let response = if content_length > MAX_BODY_LEN {
+            Response::error("413 Payload Too Large", "The request body is too large.")
+        } else {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+
+            match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+                [method, target, _] => self.route(method, target, &body),
+                _ => Response::error("400 Bad Request", "Malformed request line."),
+            }
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status,
+            response.content_type,
+            response.body.len(),
+            response.body
+        )
+    }
+
+    fn route(&mut self, method: &str, target: &str, body: &[u8]) -> Response {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = parse_query(query);
+        let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+
+        self.sessions
+            .retain(|_, session| session.last_used.elapsed() < IDLE_SESSION_TIMEOUT);
+
+        match (method, &segments[..]) {
+            ("GET", ["metrics"]) => self.metrics(),
+            ("POST", ["sessions"]) => {
+                let tape_data = query.get("tape").map(String::as_str).unwrap_or("");
+                self.create_session(body, tape_data)
+            }
+            (method, ["sessions", id, rest @ ..]) => {
+                let Ok(id) = id.parse::<u64>() else {
+                    return Response::error("404 Not Found", "Unknown session.");
+                };
+
+                match (method, rest) {
+                    ("GET", []) => self.session_status(id),
+                    ("DELETE", []) => match self.sessions.remove(&id) {
+                        Some(_) => Response::new("200 OK", "{}".to_string()),
+                        None => Response::error("404 Not Found", "Unknown session."),
+                    },
+                    ("POST", ["step"]) => {
+                        match query.get("count").map(|count| count.parse::<usize>()) {
+                            None => self.step_session(id, 1),
+                            Some(Ok(count)) if count > MAX_STEP_COUNT => Response::error(
+                                "400 Bad Request",
+                                &format!("The step count must be at most {MAX_STEP_COUNT}."),
+                            ),
+                            Some(Ok(count)) => self.step_session(id, count),
+                            Some(Err(_)) => Response::error(
+                                "400 Bad Request",
+                                "The step count must be a non negative integer.",
+                            ),
+                        }
+                    }
+                    _ => Response::error("404 Not Found", "Unknown endpoint."),
+                }
+            }
+            _ => Response::error("404 Not Found", "Unknown endpoint."),
+        }
+    }
+
+    fn create_session(&mut self, body: &[u8], tape_data: &str) -> Response {
+        if self.sessions.len() >= self.max_sessions {
+            return Response::error(
+                "503 Service Unavailable",
+                "Too many open sessions. Close one or try again later.",
+            );
+        }
+
+        // Compiled machines load without parsing their definition
+        let machine = if compiled::is_compiled(body) {
+            compiled::load_compiled(body, tape_data).map(|machine| Box::new(machine) as _)
+        } else {
+            automaton::load_from_str(&String::from_utf8_lossy(body), tape_data)
+                .map_err(|err| err.to_string())
+        };
+
+        match machine {
+            Ok(machine) => {
+                let id = self.next_id;
+                self.next_id += 1;
+                self.sessions.insert(
+                    id,
+                    Session {
+                        machine,
+                        steps: 0,
+                        last_used: Instant::now(),
+                    },
+                );
+
+                Response::new("201 Created", format!("{{\"id\":{id}}}"))
+            }
+            Err(err) => Response::error("400 Bad Request", &err),
+        }
+    }
+
+    fn session_status(&mut self, id: u64) -> Response {
+        match self.sessions.get_mut(&id) {
+            Some(session) => {
+                session.last_used = Instant::now();
+                Response::new("200 OK", status_json(id, session.machine.as_ref()))
+            }
+            None => Response::error("404 Not Found", "Unknown session."),
+        }
+    }
+
+    fn step_session(&mut self, id: u64, count: usize) -> Response {
+        let Some(session) = self.sessions.get_mut(&id) else {
+            return Response::error("404 Not Found", "Unknown session.");
+        };
+
+        let started = Instant::now();
+        session.last_used = started;
+        let machine = &mut session.machine;
+        let tape_cell_limit = self.limits.tape_cell_limit(machine.as_ref());
+
+        let mut steps = 0;
+        while steps < count && !machine.is_halted() {
+            if self
+                .limits
+                .max_steps
+                .is_some_and(|max| session.steps >= max)
+            {
+                self.metrics.record_steps(steps);
+                return Response::error(
+                    "409 Conflict",
+                    "The machine did not halt within the step limit.",
+                );
+            }
+
+            if steps.is_multiple_of(TIMEOUT_CHECK_INTERVAL)
+                && self
+                    .limits
+                    .timeout
+                    .is_some_and(|timeout| started.elapsed() >= timeout)
+            {
+                self.metrics.record_steps(steps);
+                return Response::error("409 Conflict", "The steps took longer than the timeout.");
+            }
+
+            if tape_cell_limit.is_some_and(|max| machine.tape_cells() > max) {
+                self.metrics.record_steps(steps);
+                return Response::error(
+                    "409 Conflict",
+                    "The machine used more tape cells than allowed.",
+                );
+            }
+
+            if self
+                .limits
+                .max_memory
+                .is_some_and(|max| machine.memory_usage() > max)
+            {
+                self.metrics.record_steps(steps);
+                return Response::error(
+                    "409 Conflict",
+                    "The machine used more memory than allowed.",
+                );
+            }
+
+            machine.advance();
+
+            if machine.is_halted() {
+                if machine.is_accepting() {
+                    self.metrics.accepted_total += 1;
+                } else {
+                    self.metrics.rejected_total += 1;
+                }
+            } else {
+                steps += 1;
+                session.steps += 1;
+            }
+        }
+
+        self.metrics.record_steps(steps);
+
+        Response::new("200 OK", status_json(id, session.machine.as_ref()))
+    }
+
+    /// Metrics in the Prometheus text exposition format.
+    fn metrics(&mut self) -> Response {
+        let steps_per_second = self.metrics.steps_per_second(self.started);
+
+        let body = format!(
+            "# HELP turing_active_sessions Number of open sessions.\n\
+             # TYPE turing_active_sessions gauge\n\
+             turing_active_sessions {}\n\
+             # HELP turing_steps_total Steps executed by every session.\n\
+             # TYPE turing_steps_total counter\n\
+             turing_steps_total {}\n\
+             # HELP turing_steps_per_second Steps executed per second during the last minute.\n\
+             # TYPE turing_steps_per_second gauge\n\
+             turing_steps_per_second {}\n\
+             # HELP turing_halts_total Sessions whose machine halted, by verdict.\n\
+             # TYPE turing_halts_total counter\n\
+             turing_halts_total{{verdict=\"accept\"}} {}\n\
+             turing_halts_total{{verdict=\"reject\"}} {}\n",
+            self.sessions.len(),
+            self.metrics.steps_total,
+            steps_per_second,
+            self.metrics.accepted_total,
+            self.metrics.rejected_total,
+        );
+
+        Response {
+            status: "200 OK",
+            content_type: "text/plain; version=0.0.4",
+            body,
+        }
+    }
+}
+
+fn status_json(id: u64, machine: &dyn Automaton) -> String {
+    let tape = machine
+        .tape()
+        .get_content()
+        .iter()
+        .map(|symbol| match symbol {
+            Symbol::Mark(c) => *c,
+            _ => machine.blank_symbol(),
+        })
+        .collect::<String>();
+
+    format!(
+        "{{\"id\":{id},\"state\":{},\"head\":{},\"tape\":{},\"halted\":{},\"accepting\":{}}}",
+        json_string(machine.current_state_name()),
+        machine.head_idx(),
+        json_string(&tape),
+        machine.is_halted(),
+        machine.is_accepting()
+    )
+}
+
+pub fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (percent_decode(key), percent_decode(value)))
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        let escaped = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[idx], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                idx += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
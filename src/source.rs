@@ -0,0 +1,141 @@
+use std::{fs, sync::OnceLock};
+
+use turing_lib::{
+    automaton::{self, Automaton},
+    compiled::{self, COMPILED_EXTENSION},
+    machine::TuringMachine,
+    replay::Replay,
+};
+
+/// The seed given with `--seed`, see [`set_seed`].
+static SEED: OnceLock<u64> = OnceLock::new();
+
+/// Makes every machine loaded from now on start its random transitions from `seed`, in place of
+/// the seed of its definition, and generator scripts draw their random numbers from it. Only the
+/// first seed is kept.
+pub fn set_seed(seed: u64) {
+    let _ = SEED.set(seed);
+}
+
+/// The seed given with `--seed`, if any.
+pub fn seed() -> Option<u64> {
+    SEED.get().copied()
+}
+
+pub fn is_url(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+/// Reads a machine definition from a file, or fetches it if `location` is an `http://` or
+/// `https://` URL (which needs the `net` feature).
+pub fn read_definition(location: &str) -> Result<String, String> {
+    if is_url(location) {
+        fetch(location)
+    } else {
+        fs::read_to_string(location).map_err(|_| format!("Could not open the file \"{location}\""))
+    }
+}
+
+/// Loads any kind of machine from a file or a URL, see [`read_definition`], or a Turing Machine
+/// from a compiled `.tngc` file, a turingmachine.io `.yaml` file or a JFLAP `.jff` file.
+pub fn load(location: &str, tape_data: &str) -> Result<Box<dyn Automaton>, String> {
+    if is_compiled_file(location) || is_tm_yaml_file(location) || is_jflap_file(location) {
+        return load_turing(location, tape_data).map(|machine| Box::new(machine) as _);
+    }
+
+    let mut machine = automaton::load_from_str(&read_definition(location)?, tape_data)?;
+    if let Some(seed) = seed() {
+        machine.set_seed(seed);
+    }
+    Ok(machine)
+}
+
+/// Loads a Turing Machine from a file or a URL, see [`read_definition`], from a compiled
+/// `.tngc` file, from a turingmachine.io `.yaml` file, or from a JFLAP `.jff` file.
+pub fn load_turing(location: &str, tape_data: &str) -> Result<TuringMachine, String> {
+    let mut machine = if is_compiled_file(location) {
+        let bytes =
+            fs::read(location).map_err(|_| format!("Could not open the file \"{location}\""))?;
+        compiled::load_compiled(&bytes, tape_data)?
+    } else if is_tm_yaml_file(location) {
+        TuringMachine::new_from_tm_yaml(&read_definition(location)?, tape_data)?
+    } else if is_jflap_file(location) {
+        TuringMachine::new_from_jflap(&read_definition(location)?, tape_data)?
+    } else {
+        TuringMachine::new_from_str(&read_definition(location)?, tape_data)?
+    };
+
+    if let Some(seed) = seed() {
+        machine.set_seed(seed);
+    }
+    Ok(machine)
+}
+
+fn is_compiled_file(location: &str) -> bool {
+    !is_url(location) && location.ends_with(&format!(".{COMPILED_EXTENSION}"))
+}
+
+/// Whether it is a machine in the YAML format of turingmachine.io, which can also be fetched.
+fn is_tm_yaml_file(location: &str) -> bool {
+    location.ends_with(".yaml") || location.ends_with(".yml")
+}
+
+/// Whether it is a Turing Machine saved by JFLAP, which can also be fetched.
+fn is_jflap_file(location: &str) -> bool {
+    location.ends_with(".jff")
+}
+
+/// Loads the replay of a recorded run from a `.trace` file or a URL, see [`read_definition`].
+pub fn load_replay(location: &str) -> Result<Replay, String> {
+    Replay::parse(&read_definition(location)?)
+}
+
+/// Fetches the definition over HTTP or HTTPS, following redirects and the proxy of the
+/// environment (`HTTPS_PROXY`...).
+#[cfg(feature = "net")]
+fn fetch(url: &str) -> Result<String, String> {
+    use std::{io::Read, time::Duration};
+
+    const MAX_DOWNLOAD_BYTES: u64 = 1024 * 1024; // Machine definitions are small text files
+    const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+    let agent = ureq::AgentBuilder::new()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .try_proxy_from_env(true)
+        .build();
+
+    let response = agent.get(url).call().map_err(|err| match err {
+        ureq::Error::Status(status, response) => format!(
+            "Could not fetch \"{url}\". The server answered {status} {}.",
+            response.status_text()
+        ),
+        ureq::Error::Transport(transport) => {
+            // Without the URL it starts with, which the message already names
+            let reason = transport.to_string();
+            let reason = reason.strip_prefix(&format!("{url}: ")).unwrap_or(&reason);
+            format!("Could not fetch \"{url}\". {reason}.")
+        }
+    })?;
+
+    // One byte more than allowed, to tell larger definitions apart
+    let mut bytes = vec![];
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|err| format!("Could not fetch \"{url}\". {err}."))?;
+
+    if bytes.len() as u64 > MAX_DOWNLOAD_BYTES {
+        return Err(format!(
+            "Could not fetch \"{url}\". The definition is larger than {MAX_DOWNLOAD_BYTES} bytes."
+        ));
+    }
+
+    String::from_utf8(bytes)
+        .map_err(|_| format!("Could not fetch \"{url}\". The definition is not valid UTF-8."))
+}
+
+#[cfg(not(feature = "net"))]
+fn fetch(url: &str) -> Result<String, String> {
+    Err(format!("Could not fetch \"{url}\". Loading machines from URLs needs turing to be built with the \"net\" feature."))
+}
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use turing_lib::automaton;
+
+// The input is a `.tng` file, optionally followed by a NUL byte and the tape data
+fuzz_target!(|data: &[u8]| {
+    let Ok(data) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let (file_data, tape_data) = data.split_once('\0').unwrap_or((data, ""));
+    let _ = automaton::load_from_str(file_data, tape_data);
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use turing_lib::tape::Tape;
+
+// The first char of the input is the blank symbol, and the rest is the tape data
+fuzz_target!(|data: &[u8]| {
+    let Ok(data) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let mut chars = data.chars();
+    if let Some(blank_symbol) = chars.next() {
+        let _ = Tape::parse(chars.as_str(), blank_symbol);
+    }
+});
@@ -0,0 +1,798 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::{self, Write},
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    machine::{HeadMovement, Symbol, TransitionSource, TuringMachine},
+    random::Rng,
+    runner::{self, RunLimits, RunOutcome},
+};
+
+/// Words up to some length, classified by the verdict of a machine on them, see [`sample_language`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LanguageSample {
+    pub accepted: Vec<String>,
+    pub rejected: Vec<String>,
+    /// Words on which the machine did not halt within the step limit (or the tape cell limit set
+    /// by its definition).
+    pub undecided: Vec<String>,
+}
+
+/// Every word over `alphabet` with up to `max_len` symbols, shortest first and then in the
+/// order of the alphabet (the number of words grows exponentially with `max_len`).
+pub fn words(alphabet: &[char], max_len: usize) -> impl Iterator<Item = String> + '_ {
+    let mut word: Vec<usize> = vec![];
+    let mut done = false;
+
+    std::iter::from_fn(move || {
+        if done || word.len() > max_len {
+            return None;
+        }
+
+        let current = word.iter().map(|&idx| alphabet[idx]).collect::<String>();
+
+        // Next word: increment like a number in base alphabet.len(), growing when it overflows
+        let mut pos = word.len();
+        loop {
+            if pos == 0 {
+                if alphabet.is_empty() {
+                    done = true;
+                } else {
+                    word = vec![0; word.len() + 1];
+                }
+                break;
+            }
+
+            pos -= 1;
+            word[pos] += 1;
+            if word[pos] < alphabet.len() {
+                break;
+            }
+            word[pos] = 0;
+        }
+
+        Some(current)
+    })
+}
+
+/// Runs the machine on every word over `alphabet` with up to `max_len` symbols (see [`words`]),
+/// allowing up to `max_steps` steps each, and classifies them by its verdict, to check which
+/// language the machine actually decides. Words too short for the head to start on them
+/// (see [`TuringMachine::effective_head_start`]) are skipped.
+pub fn sample_language(
+    machine: &TuringMachine,
+    alphabet: &[char],
+    max_len: usize,
+    max_steps: usize,
+) -> LanguageSample {
+    let limits = RunLimits {
+        max_steps: Some(max_steps),
+        ..Default::default()
+    };
+
+    let mut machine = machine.clone();
+    let mut sample = LanguageSample::default();
+
+    for word in words(alphabet, max_len) {
+        machine.reset(&word);
+        if machine.effective_head_start() != machine.head_start() {
+            continue;
+        }
+
+        match runner::run(&mut machine, &limits) {
+            RunOutcome::Halted { accepted: true, .. } => sample.accepted.push(word),
+            RunOutcome::Halted {
+                accepted: false, ..
+            } => sample.rejected.push(word),
+            RunOutcome::StepLimitReached { .. }
+            | RunOutcome::TimedOut { .. }
+            | RunOutcome::TapeLimitExceeded { .. }
+            | RunOutcome::Cancelled { .. }
+            | RunOutcome::ResourceExhausted { .. }
+            | RunOutcome::NeverHalts { .. } => sample.undecided.push(word),
+        }
+    }
+
+    sample
+}
+
+/// The shortest word over `alphabet` with up to `max_len` symbols that the machine accepts within
+/// `max_steps` steps, the first one in the order of [`words`] if there are several, which helps
+/// characterizing what an unknown machine does. Words are run in parallel, one length at a
+/// time, and words too short for the head to start on them are skipped, like in
+/// [`sample_language`].
+pub fn shortest_accepted(
+    machine: &TuringMachine,
+    alphabet: &[char],
+    max_len: usize,
+    max_steps: usize,
+) -> Option<String> {
+    let limits = RunLimits {
+        max_steps: Some(max_steps),
+        ..Default::default()
+    };
+
+    (0..=max_len).find_map(|len| {
+        let word_count = alphabet.len().checked_pow(len as u32)?;
+
+        (0..word_count)
+            .into_par_iter()
+            .map_init(
+                || machine.clone(),
+                |machine, idx| {
+                    let word = nth_word(alphabet, len, idx);
+
+                    machine.reset(&word);
+                    if machine.effective_head_start() != machine.head_start() {
+                        return None;
+                    }
+
+                    match runner::run(machine, &limits) {
+                        RunOutcome::Halted { accepted: true, .. } => Some(word),
+                        _ => None,
+                    }
+                },
+            )
+            .find_map_first(|word| word)
+    })
+}
+
+/// The outcome of running a machine on one word, see [`sweep`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepResult {
+    pub word: String,
+    pub outcome: RunOutcome,
+}
+
+/// Runs the machine on every word over `alphabet` with up to `max_len` symbols, allowing up to
+/// `max_steps` steps each, and returns the outcome of every run in the order of [`words`]. The
+/// words are run in parallel on all the cores, and words too short for the head to start on
+/// them are skipped, like in [`sample_language`]. Every word starts from the current seed of the
+/// machine, so sweeps of machines with random transitions don't depend on how the words were
+/// split between the cores.
+pub fn sweep(
+    machine: &TuringMachine,
+    alphabet: &[char],
+    max_len: usize,
+    max_steps: usize,
+) -> Vec<SweepResult> {
+    let limits = RunLimits {
+        max_steps: Some(max_steps),
+        ..Default::default()
+    };
+    let seed = machine.rng.state();
+
+    words(alphabet, max_len)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map_init(
+            || machine.clone(),
+            |machine, word| {
+                machine.reset(&word);
+                machine.rng = Rng::new(seed);
+                if machine.effective_head_start() != machine.head_start() {
+                    return None;
+                }
+
+                let outcome = runner::run(machine, &limits);
+                Some(SweepResult { word, outcome })
+            },
+        )
+        .flatten()
+        .collect()
+}
+
+/// The results of a [`sweep`] as CSV, one line per word with its verdict (`accepts`, `rejects`
+/// or `undecided`) and steps.
+pub fn sweep_csv(results: &[SweepResult]) -> String {
+    let mut csv = String::from("word,verdict,steps\n");
+
+    for result in results {
+        let verdict = match result.outcome {
+            RunOutcome::Halted { accepted: true, .. } => "accepts",
+            RunOutcome::Halted {
+                accepted: false, ..
+            } => "rejects",
+            _ => "undecided",
+        };
+        let _ = writeln!(csv, "{},{verdict},{}", result.word, result.outcome.steps());
+    }
+
+    csv
+}
+
+/// The word of `len` symbols at `idx` in the order of [`words`].
+fn nth_word(alphabet: &[char], len: usize, mut idx: usize) -> String {
+    let mut reversed = Vec::with_capacity(len);
+    for _ in 0..len {
+        reversed.push(alphabet[idx % alphabet.len()]);
+        idx /= alphabet.len();
+    }
+    reversed.into_iter().rev().collect()
+}
+
+/// The result of checking that a machine is a decider, see [`check_decider`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeciderCheck {
+    /// The machine halted on every checked word within the step bound.
+    Decider {
+        words_checked: usize,
+        /// The most steps the machine took on any checked word.
+        max_steps: usize,
+    },
+    /// The machine did not halt on `word` within the step bound (the first such word, in the
+    /// order of [`words`]).
+    ExceedsBound { word: String },
+}
+
+/// Checks that the machine halts within `step_bound` steps on every word over `alphabet` with
+/// up to `max_len` symbols (see [`words`]), which is the usual way of asserting that a machine
+/// is a decider in assignments. Stops at the first word on which it exceeds the bound. Words
+/// too short for the head to start on them are skipped, like in [`sample_language`].
+pub fn check_decider(
+    machine: &TuringMachine,
+    alphabet: &[char],
+    max_len: usize,
+    step_bound: usize,
+) -> DeciderCheck {
+    let limits = RunLimits {
+        max_steps: Some(step_bound),
+        ..Default::default()
+    };
+
+    let mut machine = machine.clone();
+    let mut words_checked = 0;
+    let mut max_steps = 0;
+
+    for word in words(alphabet, max_len) {
+        machine.reset(&word);
+        if machine.effective_head_start() != machine.head_start() {
+            continue;
+        }
+
+        match runner::run(&mut machine, &limits) {
+            RunOutcome::Halted { steps, .. } => {
+                words_checked += 1;
+                max_steps = max_steps.max(steps);
+            }
+            RunOutcome::StepLimitReached { .. }
+            | RunOutcome::TimedOut { .. }
+            | RunOutcome::TapeLimitExceeded { .. }
+            | RunOutcome::Cancelled { .. }
+            | RunOutcome::ResourceExhausted { .. }
+            | RunOutcome::NeverHalts { .. } => {
+                return DeciderCheck::ExceedsBound { word };
+            }
+        }
+    }
+
+    DeciderCheck::Decider {
+        words_checked,
+        max_steps,
+    }
+}
+
+/// The resources a machine used on the inputs of one size, see [`complexity`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComplexityPoint {
+    /// The size of the inputs.
+    pub n: usize,
+    /// The number of inputs of this size the machine was run on.
+    pub inputs: usize,
+    /// The most steps the machine took on an input of this size (the worst case).
+    pub max_steps: usize,
+    pub mean_steps: f64,
+    /// The most tape cells the machine used on an input of this size.
+    pub max_cells: usize,
+    /// The number of inputs on which the machine did not halt within the step limit (their steps
+    /// count as the limit).
+    pub undecided: usize,
+}
+
+/// Runs the machine on every word over `alphabet` of every size from 0 to `max_len` (see
+/// [`words`]), allowing up to `max_steps` steps each, and measures the steps and tape cells it
+/// used as a function of the size of the input, to empirically compare the complexity of
+/// machines. Sizes with no inputs the head can start on (see [`sample_language`]) are skipped.
+pub fn complexity(
+    machine: &TuringMachine,
+    alphabet: &[char],
+    max_len: usize,
+    max_steps: usize,
+) -> Vec<ComplexityPoint> {
+    let limits = RunLimits {
+        max_steps: Some(max_steps),
+        ..Default::default()
+    };
+
+    let mut machine = machine.clone();
+    let mut points: Vec<ComplexityPoint> = vec![];
+    let mut total_steps = 0;
+
+    for word in words(alphabet, max_len) {
+        let n = word.chars().count();
+        machine.reset(&word);
+        if machine.effective_head_start() != machine.head_start() {
+            continue;
+        }
+
+        let mut max_cells = 0;
+        let outcome = runner::run_observed(&mut machine, &limits, |machine, _| {
+            max_cells = max_cells.max(machine.tape().len());
+        });
+
+        if points.last().is_none_or(|point| point.n != n) {
+            total_steps = 0;
+            points.push(ComplexityPoint {
+                n,
+                inputs: 0,
+                max_steps: 0,
+                mean_steps: 0.0,
+                max_cells: 0,
+                undecided: 0,
+            });
+        }
+
+        let point = points.last_mut().expect("A point was just pushed");
+        point.inputs += 1;
+        point.max_steps = point.max_steps.max(outcome.steps());
+        point.max_cells = point.max_cells.max(max_cells);
+        if !outcome.halted() {
+            point.undecided += 1;
+        }
+
+        total_steps += outcome.steps();
+        point.mean_steps = total_steps as f64 / point.inputs as f64;
+    }
+
+    points
+}
+
+/// Estimates the exponent `k` for which the worst-case steps grow like `n^k`, by fitting a line
+/// to `log(max_steps)` against `log(n)`. Returns `None` if there are less than two sizes with
+/// `n > 0` and some steps to fit.
+pub fn growth_exponent(points: &[ComplexityPoint]) -> Option<f64> {
+    let samples = points
+        .iter()
+        .filter(|point| point.n > 0 && point.max_steps > 0)
+        .map(|point| ((point.n as f64).ln(), (point.max_steps as f64).ln()))
+        .collect::<Vec<_>>();
+
+    if samples.len() < 2 {
+        return None;
+    }
+
+    let count = samples.len() as f64;
+    let mean_x = samples.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = samples.iter().map(|(_, y)| y).sum::<f64>() / count;
+
+    let covariance = samples
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum::<f64>();
+    let variance = samples
+        .iter()
+        .map(|(x, _)| (x - mean_x).powi(2))
+        .sum::<f64>();
+
+    Some(covariance / variance)
+}
+
+/// The series of a [`complexity`] measurement as CSV, one line per input size.
+pub fn complexity_csv(points: &[ComplexityPoint]) -> String {
+    let mut csv = String::from("n,inputs,max_steps,mean_steps,max_cells,undecided\n");
+
+    for point in points {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{:.2},{},{}",
+            point.n,
+            point.inputs,
+            point.max_steps,
+            point.mean_steps,
+            point.max_cells,
+            point.undecided
+        );
+    }
+
+    csv
+}
+
+/// A structure of a machine that makes it run forever, found without running it, see
+/// [`infinite_loops`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoopWarning {
+    /// Reading `symbol` in `state` starts a cycle of transitions that don't move the head, so
+    /// the machine loops forever on the same cell.
+    StayLoop {
+        state: String,
+        symbol: TransitionSource,
+    },
+    /// Reading a blank in `state` moves the head and stays in `state`, so the machine runs
+    /// forever once the head passes the end of the tape in that direction.
+    RunsOffTape {
+        state: String,
+        direction: HeadMovement,
+    },
+    /// Every state the machine can reach from `state` has a `default` transition, so it never
+    /// halts once it enters `state`.
+    NeverHalts { state: String },
+}
+
+impl fmt::Display for LoopWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoopWarning::StayLoop { state, symbol } => {
+                let symbol = match symbol {
+                    TransitionSource::Mark(c) => format!("'{c}'"),
+                    TransitionSource::Blank => "a blank".to_string(),
+                    TransitionSource::Default => "a symbol without a transition of its own".to_string(),
+                };
+                write!(f, "Reading {symbol} in state {state} starts a cycle of transitions that never move the head, so the machine loops forever on the same cell.")
+            }
+            LoopWarning::RunsOffTape { state, direction } => {
+                let direction = match direction {
+                    HeadMovement::Left => "left",
+                    _ => "right",
+                };
+                write!(f, "Reading a blank in state {state} moves the head {direction} and stays in state {state}, so the machine runs forever once the head passes the {direction} end of the tape.")
+            }
+            LoopWarning::NeverHalts { state } => write!(f, "Every state reachable from state {state} has a default transition, so the machine never halts once it enters it."),
+        }
+    }
+}
+
+/// Looks for simple structures that make the machine run forever, without running it: cycles
+/// of transitions that don't move the head, states that keep moving over blanks, and states
+/// from which the machine can't halt. Only the states reachable from the initial state are
+/// checked. The checks are heuristics, which don't find every machine that runs forever, but
+/// every warning is a real way of running forever (if the machine gets to it).
+pub fn infinite_loops(machine: &TuringMachine) -> Vec<LoopWarning> {
+    let states = machine.states();
+    let reachable = reachable_states(machine, &machine.initial_state);
+    let mut warnings = vec![];
+
+    for state_name in &reachable {
+        let state = &states[state_name.as_str()];
+
+        let mut sources = state.transitions().keys().copied().collect::<Vec<_>>();
+        sources.sort();
+
+        for source in sources {
+            if let Some((state, symbol)) = stay_cycle(machine, state_name, source) {
+                let warning = LoopWarning::StayLoop { state, symbol };
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+        }
+
+        // Bounded machines halt when the head passes an end marker
+        if machine.is_bounded() || state.is_random() {
+            continue;
+        }
+
+        if let Some(transition) = state.transition_for(Symbol::Blank) {
+            if transition.head_movement() != HeadMovement::Stay
+                && transition.new_state() == state_name
+            {
+                warnings.push(LoopWarning::RunsOffTape {
+                    state: state_name.clone(),
+                    direction: transition.head_movement(),
+                });
+            }
+        }
+    }
+
+    // States that halt on some symbol (or may halt, for queries and bounded machines)
+    let may_halt = |name: &str| {
+        let state = &states[name];
+        state.query().is_some()
+            || (state.emit().is_none()
+                && !state.transitions().contains_key(&TransitionSource::Default)
+                && !state
+                    .random_transitions()
+                    .contains_key(&TransitionSource::Default))
+            || (machine.is_bounded()
+                && state
+                    .transitions()
+                    .values()
+                    .chain(
+                        state
+                            .random_transitions()
+                            .values()
+                            .flatten()
+                            .map(|(_, t)| t),
+                    )
+                    .any(|transition| transition.head_movement() != HeadMovement::Stay))
+    };
+
+    let never_halts = reachable
+        .iter()
+        .filter(|name| {
+            !reachable_states(machine, name)
+                .iter()
+                .any(|name| may_halt(name))
+        })
+        .collect::<HashSet<_>>();
+
+    // Only the states through which the machine enters the part where it never halts
+    for name in &never_halts {
+        let entered_from_outside = **name == machine.initial_state
+            || reachable.iter().any(|other| {
+                !never_halts.contains(other) && successors(machine, other).contains(&name.as_str())
+            });
+
+        if entered_from_outside {
+            warnings.push(LoopWarning::NeverHalts {
+                state: name.to_string(),
+            });
+        }
+    }
+
+    warnings
+}
+
+/// The states entered right after `name`.
+fn successors<'a>(machine: &'a TuringMachine, name: &str) -> Vec<&'a str> {
+    let state = &machine.states()[name];
+
+    let mut successors = state
+        .transitions()
+        .values()
+        .chain(
+            state
+                .random_transitions()
+                .values()
+                .flatten()
+                .map(|(_, t)| t),
+        )
+        .map(|transition| transition.new_state())
+        .collect::<Vec<_>>();
+
+    if let Some(query) = state.query() {
+        successors.extend([query.yes_state(), query.no_state()]);
+    }
+    successors.extend(state.emit());
+
+    successors
+}
+
+/// The states reachable from `start`, `start` included, sorted by name.
+fn reachable_states(machine: &TuringMachine, start: &str) -> BTreeSet<String> {
+    let mut reachable = BTreeSet::from([start.to_string()]);
+    let mut pending = vec![start.to_string()];
+
+    while let Some(name) = pending.pop() {
+        for successor in successors(machine, &name) {
+            if machine.states().contains_key(successor) && reachable.insert(successor.to_string()) {
+                pending.push(successor.to_string());
+            }
+        }
+    }
+
+    reachable
+}
+
+/// Follows the transitions that don't move the head from reading `source` in `state`, and
+/// returns the first transition of the cycle they end in (in the order of states and sources),
+/// if they do. Symbols that are not known exactly (read by a `default` transition) are only
+/// followed into states that read every symbol the same way.
+fn stay_cycle(
+    machine: &TuringMachine,
+    state: &str,
+    source: TransitionSource,
+) -> Option<(String, TransitionSource)> {
+    let states = machine.states();
+
+    // The symbol under the head, or None if it is not known exactly
+    let mut symbol = match source {
+        TransitionSource::Mark(c) => Some(Symbol::Mark(c)),
+        TransitionSource::Blank => Some(Symbol::Blank),
+        TransitionSource::Default => None,
+    };
+    let mut current = (state.to_string(), source);
+    let mut path = vec![];
+
+    loop {
+        if let Some(start) = path.iter().position(|taken| *taken == current) {
+            return path[start..].iter().min().cloned();
+        }
+
+        let state = states.get(&current.0)?;
+        if state.is_random() || state.query().is_some() || state.emit().is_some() {
+            return None;
+        }
+
+        let transition = state.transitions().get(&current.1)?;
+        if transition.head_movement() != HeadMovement::Stay {
+            return None;
+        }
+
+        if transition.new_symbol() != Symbol::Default {
+            symbol = Some(transition.new_symbol());
+        }
+        path.push(current);
+
+        let next = states.get(transition.new_state())?;
+        let next_source = match symbol {
+            Some(Symbol::Mark(c))
+                if next.transitions().contains_key(&TransitionSource::Mark(c)) =>
+            {
+                TransitionSource::Mark(c)
+            }
+            Some(Symbol::Blank) if next.transitions().contains_key(&TransitionSource::Blank) => {
+                TransitionSource::Blank
+            }
+            None if next.transitions().len() > 1 => return None,
+            _ => TransitionSource::Default,
+        };
+
+        current = (transition.new_state().to_string(), next_source);
+    }
+}
+
+/// The parts of a machine that can never be used, see [`dead_code`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeadCode {
+    /// States that can't be reached from the initial state, sorted by name.
+    pub states: Vec<String>,
+    /// Transitions of the reachable states that can never be taken, by their state and source
+    /// symbol, in order. The weighted random transitions of a source symbol count as one.
+    pub transitions: Vec<(String, TransitionSource)>,
+}
+
+impl DeadCode {
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty() && self.transitions.is_empty()
+    }
+}
+
+/// Finds the states that can't be reached from the initial state and the transitions that can
+/// never be taken, without running the machine.
+///
+/// Besides following the transitions from the initial state, it keeps track of the symbols
+/// that can be under the head when entering every state: a state only entered by transitions
+/// that don't move the head can only read the symbols they leave, so its transitions reading
+/// other symbols (including a `default` one, if every symbol it can read has its own
+/// transition) never fire. Entering a state by moving the head, or as the initial state, it may
+/// read any symbol.
+pub fn dead_code(machine: &TuringMachine) -> DeadCode {
+    let states = machine.states();
+
+    // The symbols that may be under the head in every state entered so far, None being any
+    let mut readable: HashMap<String, Option<BTreeSet<Symbol>>> =
+        HashMap::from([(machine.initial_state.clone(), None)]);
+    let mut fired = HashSet::new();
+
+    // Adds symbols that may be read in a state, returning whether they were new
+    let enter = |readable: &mut HashMap<String, Option<BTreeSet<Symbol>>>,
+                 state: &str,
+                 symbols: Option<BTreeSet<Symbol>>| {
+        if !states.contains_key(state) {
+            return false;
+        }
+
+        match (readable.get_mut(state), symbols) {
+            (None, symbols) => {
+                readable.insert(state.to_string(), symbols);
+                true
+            }
+            (Some(None), _) => false,
+            (Some(known), None) => {
+                *known = None;
+                true
+            }
+            (Some(Some(known)), Some(symbols)) => {
+                let before = known.len();
+                known.extend(symbols);
+                known.len() != before
+            }
+        }
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        let entered = readable.keys().cloned().collect::<Vec<_>>();
+        for name in entered {
+            let state = &states[&name];
+            let symbols = readable[&name].clone();
+
+            if let Some(query) = state.query() {
+                changed |= enter(&mut readable, query.yes_state(), symbols.clone());
+                changed |= enter(&mut readable, query.no_state(), symbols);
+                continue;
+            }
+
+            if let Some(next_state) = state.emit() {
+                changed |= enter(&mut readable, next_state, symbols);
+                continue;
+            }
+
+            let sources = state
+                .transitions()
+                .keys()
+                .chain(state.random_transitions().keys())
+                .copied()
+                .collect::<HashSet<_>>();
+
+            for &source in &sources {
+                // The symbols this transition may read
+                let read = match source {
+                    TransitionSource::Mark(c) => Some(BTreeSet::from([Symbol::Mark(c)])),
+                    TransitionSource::Blank => Some(BTreeSet::from([Symbol::Blank])),
+                    TransitionSource::Default => symbols.as_ref().map(|symbols| {
+                        symbols
+                            .iter()
+                            .copied()
+                            .filter(|symbol| {
+                                let source = match symbol {
+                                    Symbol::Mark(c) => TransitionSource::Mark(*c),
+                                    _ => TransitionSource::Blank,
+                                };
+                                !sources.contains(&source)
+                            })
+                            .collect()
+                    }),
+                };
+
+                let can_fire = symbols.as_ref().is_none_or(|symbols| {
+                    read.as_ref()
+                        .is_some_and(|read| read.iter().any(|symbol| symbols.contains(symbol)))
+                });
+                if !can_fire {
+                    continue;
+                }
+                fired.insert((name.clone(), source));
+
+                let choices = state.transitions().get(&source).into_iter().chain(
+                    state
+                        .random_transitions()
+                        .get(&source)
+                        .into_iter()
+                        .flatten()
+                        .map(|(_, t)| t),
+                );
+
+                for transition in choices {
+                    let left = if transition.head_movement() != HeadMovement::Stay {
+                        None
+                    } else if transition.new_symbol() != Symbol::Default {
+                        Some(BTreeSet::from([transition.new_symbol()]))
+                    } else {
+                        read.clone()
+                    };
+
+                    changed |= enter(&mut readable, transition.new_state(), left);
+                }
+            }
+        }
+    }
+
+    let mut dead = DeadCode::default();
+
+    for (name, state) in states {
+        if !readable.contains_key(name) {
+            dead.states.push(name.clone());
+            continue;
+        }
+
+        for &source in state
+            .transitions()
+            .keys()
+            .chain(state.random_transitions().keys())
+        {
+            if !fired.contains(&(name.clone(), source)) {
+                dead.transitions.push((name.clone(), source));
+            }
+        }
+    }
+
+    dead.states.sort();
+    dead.transitions.sort();
+    dead
+}
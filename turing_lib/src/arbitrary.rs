@@ -0,0 +1,168 @@
+use std::collections::{HashMap, HashSet};
+
+use proptest::prelude::*;
+
+use crate::{
+    heatmap::Heatmap,
+    machine::{
+        HeadMovement, OutputConvention, State, Symbol, Transition, TransitionSource, TuringMachine,
+    },
+    random::Rng,
+    tape::Tape,
+};
+
+/// Marks used by the generated symbols, kept small so generated machines actually read what they write.
+const ALPHABET: &[char] = &['0', '1', 'a', 'b'];
+const BLANK_SYMBOL: char = '_';
+
+const MAX_TAPE_LEN: usize = 16;
+const MAX_STATES: usize = 4;
+const MAX_TRANSITIONS_PER_STATE: usize = 5;
+
+/// Only generates symbols that can be on a tape (never [`Symbol::Default`]).
+impl Arbitrary for Symbol {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(Symbol::Blank),
+            prop::sample::select(ALPHABET).prop_map(Symbol::Mark),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for TransitionSource {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(TransitionSource::Default),
+            Just(TransitionSource::Blank),
+            prop::sample::select(ALPHABET).prop_map(TransitionSource::Mark),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for HeadMovement {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(HeadMovement::Left),
+            Just(HeadMovement::Right),
+            Just(HeadMovement::Stay),
+        ]
+        .boxed()
+    }
+}
+
+/// Generates non empty tapes of up to 16 cells.
+impl Arbitrary for Tape {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop::collection::vec(any::<Symbol>(), 1..=MAX_TAPE_LEN)
+            .prop_map(Tape::new)
+            .boxed()
+    }
+}
+
+/// Generates small machines (up to 4 states named `q0`, `q1`, ..., with `q0` as the initial
+/// one) over a small alphabet, with `_` as the blank symbol and the head somewhere on the tape.
+impl Arbitrary for TuringMachine {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        let new_symbol = prop_oneof![Just(Symbol::Default), any::<Symbol>()];
+
+        (1..=MAX_STATES)
+            .prop_flat_map(move |state_count| {
+                let transition = (any::<HeadMovement>(), new_symbol.clone(), 0..state_count);
+                let transitions = prop::collection::vec(
+                    prop::collection::hash_map(
+                        any::<TransitionSource>(),
+                        transition,
+                        0..=MAX_TRANSITIONS_PER_STATE,
+                    ),
+                    state_count,
+                );
+                let final_states = prop::collection::hash_set(0..state_count, 0..=state_count);
+
+                (transitions, final_states, any::<Tape>())
+            })
+            .prop_flat_map(|(transitions, final_states, tape)| {
+                let head_idx = 0..tape.len();
+                (Just(transitions), Just(final_states), Just(tape), head_idx)
+            })
+            .prop_map(|(transitions, final_states, tape, head_idx)| {
+                let state_name = |idx: usize| format!("q{idx}");
+
+                let states = transitions
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, transitions)| {
+                        let transitions = transitions
+                            .into_iter()
+                            .map(|(source, (head_movement, new_symbol, new_state))| {
+                                let transition = Transition::new(
+                                    head_movement,
+                                    new_symbol,
+                                    state_name(new_state),
+                                );
+                                (source, transition)
+                            })
+                            .collect();
+
+                        (state_name(idx), State::new(state_name(idx), transitions))
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                TuringMachine {
+                    name: "Arbitrary machine".to_string(),
+                    blank_symbol: BLANK_SYMBOL,
+
+                    states,
+                    final_states: final_states
+                        .into_iter()
+                        .map(state_name)
+                        .collect::<HashSet<_>>(),
+
+                    head_start: head_idx,
+                    effective_head_start: head_idx,
+                    initial_state: state_name(0),
+
+                    head_idx,
+                    current_state: state_name(0),
+                    tape,
+
+                    halted: false,
+
+                    bounded: false,
+                    out_of_bounds: false,
+
+                    nondeterministic: false,
+
+                    max_tape_cells: None,
+
+                    oracle: None,
+                    rng: Rng::new(0),
+
+                    emitted: vec![],
+
+                    output_convention: OutputConvention::FromHead,
+
+                    heatmap: Heatmap::new(&state_name(0)),
+
+                    history: None,
+                }
+            })
+            .boxed()
+    }
+}
@@ -0,0 +1,443 @@
+use std::{collections::BTreeSet, fs, mem::size_of};
+
+use crate::{
+    analysis::{self, LoopWarning},
+    counter::CounterMachine,
+    diagram::StateDiagram,
+    error::{MachineError, MachineErrorKind, ParseError},
+    finite::FiniteAutomaton,
+    history::History,
+    machine::{State, Symbol, TickResult, TuringMachine},
+    multitape::MultiTapeMachine,
+    parser,
+    pda::PushdownAutomaton,
+    tape::Tape,
+    tree::{self, ComputationTree, TreeLimits},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachineType {
+    Turing,
+    Pushdown,
+    Counter,
+    Finite,
+    MultiTape,
+}
+
+/// Common interface of every machine kind, used by frontends and runners that
+/// don't care about what kind of machine they are driving.
+pub trait Automaton {
+    fn tick(&mut self) -> TickResult;
+
+    /// Ticks the machine without needing a description of the step, for runs that only care
+    /// about where the machine ends up.
+    fn advance(&mut self) {
+        self.tick();
+    }
+
+    fn name(&self) -> &str;
+    fn blank_symbol(&self) -> char;
+    fn head_idx(&self) -> usize;
+    fn current_state_name(&self) -> &str;
+    fn is_halted(&self) -> bool;
+    fn is_accepting(&self) -> bool;
+    fn tape(&self) -> &Tape;
+
+    /// Every tape of the machine, the one of [`Automaton::tape`] first.
+    fn tapes(&self) -> &[Tape] {
+        std::slice::from_ref(self.tape())
+    }
+
+    /// The index of the head on every tape, in the order of [`Automaton::tapes`].
+    fn heads(&self) -> Vec<usize> {
+        vec![self.head_idx()]
+    }
+
+    /// Stack contents, bottom first, for machines that have one.
+    fn stack(&self) -> Option<&[char]> {
+        None
+    }
+
+    /// Register values, for machines that have registers instead of a tape.
+    fn registers(&self) -> Option<&[u64]> {
+        None
+    }
+
+    /// The description of the current state, for machines whose states can have one.
+    fn state_description(&self) -> Option<&str> {
+        None
+    }
+
+    /// How many tape cells the machine is using, on all of its tapes.
+    fn tape_cells(&self) -> usize {
+        self.tape().len()
+    }
+
+    /// The most tape cells the machine may use, if its definition sets a limit.
+    fn max_tape_cells(&self) -> Option<usize> {
+        None
+    }
+
+    /// Approximate number of bytes the configuration of the machine takes in memory: its tapes
+    /// and, for machines that record it, their history.
+    fn memory_usage(&self) -> usize {
+        self.tape_cells() * size_of::<Symbol>()
+    }
+
+    /// The index the head starts at as set by the definition, for machines that set one.
+    fn head_start(&self) -> Option<usize> {
+        None
+    }
+
+    /// The index the head started at on the current input, which is lower than
+    /// [`Automaton::head_start`] if the input was too short to start there.
+    fn effective_head_start(&self) -> Option<usize> {
+        self.head_start()
+    }
+
+    /// Symbols of `tape_data` that the machine can never read, for machines that check them.
+    fn unreadable_symbols(&self, _tape_data: &str) -> BTreeSet<char> {
+        BTreeSet::new()
+    }
+
+    /// Structures that make the machine run forever, found without running it, for machines
+    /// that check them (see [`analysis::infinite_loops`]).
+    fn loop_warnings(&self) -> Vec<LoopWarning> {
+        vec![]
+    }
+
+    /// How many ticks were played and how many there are, for replays of recorded runs (see
+    /// [`crate::replay`]) and machines that record their history (see
+    /// [`Automaton::enable_history`]), which frontends can move through with [`Automaton::seek`].
+    fn timeline(&self) -> Option<(usize, usize)> {
+        None
+    }
+
+    /// Moves a replay or a machine that records its history to the configuration after
+    /// `position` ticks. Other machines can't go back and ignore it.
+    fn seek(&mut self, _position: usize) {}
+
+    /// Starts recording the configurations the machine goes through, for machines that can go
+    /// back to them (see [`TuringMachine::enable_history`]). Other machines ignore it.
+    fn enable_history(&mut self) {}
+
+    /// The recorded configurations, for machines that record their history.
+    fn history(&self) -> Option<&History> {
+        None
+    }
+
+    /// Restarts the random number generator of the random transitions from the given seed (see
+    /// [`TuringMachine::set_seed`]). Machines without random transitions ignore it.
+    fn set_seed(&mut self, _seed: u64) {}
+
+    /// Whether the machine accepts an input if any of its runs does, instead of following a
+    /// single run that makes its choices at random (see [`TuringMachine::is_nondeterministic`]).
+    fn is_nondeterministic(&self) -> bool {
+        false
+    }
+
+    /// Every run the machine may make from its current configuration, for machines that choose
+    /// some of their transitions at random (see [`tree::explore`]).
+    fn computation_tree(&self, _limits: &TreeLimits) -> Option<ComputationTree> {
+        None
+    }
+
+    /// The states of the machine and the arrows between them, laid out to be drawn, for machines
+    /// with a state diagram.
+    fn state_diagram(&self) -> Option<StateDiagram> {
+        None
+    }
+}
+
+impl Automaton for TuringMachine {
+    fn tick(&mut self) -> TickResult {
+        self.tick()
+    }
+
+    fn advance(&mut self) {
+        self.advance();
+    }
+
+    fn name(&self) -> &str {
+        self.name()
+    }
+
+    fn blank_symbol(&self) -> char {
+        self.blank_symbol()
+    }
+
+    fn head_idx(&self) -> usize {
+        self.head_idx()
+    }
+
+    fn current_state_name(&self) -> &str {
+        self.current_state_name()
+    }
+
+    fn is_halted(&self) -> bool {
+        self.is_halted()
+    }
+
+    fn is_accepting(&self) -> bool {
+        self.is_accepting()
+    }
+
+    fn tape(&self) -> &Tape {
+        self.tape()
+    }
+
+    fn max_tape_cells(&self) -> Option<usize> {
+        self.max_tape_cells()
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.tape_cells() * size_of::<Symbol>() + self.history().map_or(0, History::memory_usage)
+    }
+
+    fn state_description(&self) -> Option<&str> {
+        self.states()[self.current_state_name()].description()
+    }
+
+    fn head_start(&self) -> Option<usize> {
+        Some(self.head_start())
+    }
+
+    fn effective_head_start(&self) -> Option<usize> {
+        Some(self.effective_head_start())
+    }
+
+    fn unreadable_symbols(&self, tape_data: &str) -> BTreeSet<char> {
+        self.unreadable_symbols(tape_data)
+    }
+
+    fn loop_warnings(&self) -> Vec<LoopWarning> {
+        analysis::infinite_loops(self)
+    }
+
+    fn timeline(&self) -> Option<(usize, usize)> {
+        self.history()
+            .map(|history| (history.position(), history.len()))
+    }
+
+    fn seek(&mut self, position: usize) {
+        if let Some(history) = self.history() {
+            let _ = TuringMachine::seek(self, position.min(history.len()));
+        }
+    }
+
+    fn enable_history(&mut self) {
+        self.enable_history()
+    }
+
+    fn history(&self) -> Option<&History> {
+        self.history()
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.set_seed(seed)
+    }
+
+    fn is_nondeterministic(&self) -> bool {
+        self.is_nondeterministic()
+    }
+
+    fn computation_tree(&self, limits: &TreeLimits) -> Option<ComputationTree> {
+        self.states()
+            .values()
+            .any(State::is_random)
+            .then(|| tree::explore(self, limits))
+    }
+
+    fn state_diagram(&self) -> Option<StateDiagram> {
+        Some(StateDiagram::new(self))
+    }
+}
+
+/// Loads any kind of machine, depending on the `machine_type` of its configuration.
+pub fn load_from_file(filename: &str, tape_data: &str) -> Result<Box<dyn Automaton>, MachineError> {
+    let file_data = fs::read_to_string(filename).map_err(|_| {
+        MachineError::new(
+            MachineErrorKind::Io,
+            format!("Could not open the file \"{}\"", filename),
+        )
+    })?;
+
+    Ok(load_from_str(&file_data, tape_data)?)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(file_data), err(level = "debug"))
+)]
+pub fn load_from_str(file_data: &str, tape_data: &str) -> Result<Box<dyn Automaton>, ParseError> {
+    let file_lines = parser::file_lines(file_data);
+
+    Ok(match parser::parse_machine_type(&file_lines)? {
+        MachineType::Turing => Box::new(TuringMachine::new_from_str(file_data, tape_data)?),
+        MachineType::Pushdown => Box::new(PushdownAutomaton::new_from_str(file_data, tape_data)?),
+        MachineType::Counter => Box::new(CounterMachine::new_from_str(file_data, tape_data)?),
+        MachineType::Finite => Box::new(FiniteAutomaton::new_from_str(file_data, tape_data)?),
+        MachineType::MultiTape => Box::new(MultiTapeMachine::new_from_str(file_data, tape_data)?),
+    })
+}
+
+impl Automaton for PushdownAutomaton {
+    fn tick(&mut self) -> TickResult {
+        self.tick()
+    }
+
+    fn name(&self) -> &str {
+        self.name()
+    }
+
+    fn blank_symbol(&self) -> char {
+        self.blank_symbol()
+    }
+
+    fn head_idx(&self) -> usize {
+        self.head_idx()
+    }
+
+    fn current_state_name(&self) -> &str {
+        self.current_state_name()
+    }
+
+    fn is_halted(&self) -> bool {
+        self.is_halted()
+    }
+
+    fn is_accepting(&self) -> bool {
+        self.is_accepting()
+    }
+
+    fn tape(&self) -> &Tape {
+        self.input()
+    }
+
+    fn stack(&self) -> Option<&[char]> {
+        Some(self.stack())
+    }
+}
+
+impl Automaton for CounterMachine {
+    fn tick(&mut self) -> TickResult {
+        self.tick()
+    }
+
+    fn name(&self) -> &str {
+        self.name()
+    }
+
+    fn blank_symbol(&self) -> char {
+        ' '
+    }
+
+    fn head_idx(&self) -> usize {
+        0
+    }
+
+    fn current_state_name(&self) -> &str {
+        self.current_instruction_name()
+    }
+
+    fn is_halted(&self) -> bool {
+        self.is_halted()
+    }
+
+    fn is_accepting(&self) -> bool {
+        self.is_accepting()
+    }
+
+    fn tape(&self) -> &Tape {
+        &self.empty_tape
+    }
+
+    fn registers(&self) -> Option<&[u64]> {
+        Some(self.registers())
+    }
+}
+
+impl Automaton for FiniteAutomaton {
+    fn tick(&mut self) -> TickResult {
+        self.tick()
+    }
+
+    fn name(&self) -> &str {
+        self.name()
+    }
+
+    fn blank_symbol(&self) -> char {
+        self.blank_symbol()
+    }
+
+    fn head_idx(&self) -> usize {
+        self.head_idx()
+    }
+
+    fn current_state_name(&self) -> &str {
+        self.current_state_name()
+    }
+
+    fn is_halted(&self) -> bool {
+        self.is_halted()
+    }
+
+    fn is_accepting(&self) -> bool {
+        self.is_accepting()
+    }
+
+    fn tape(&self) -> &Tape {
+        self.input()
+    }
+}
+
+impl Automaton for MultiTapeMachine {
+    fn tick(&mut self) -> TickResult {
+        self.tick()
+    }
+
+    fn name(&self) -> &str {
+        self.name()
+    }
+
+    fn blank_symbol(&self) -> char {
+        self.blank_symbol()
+    }
+
+    fn head_idx(&self) -> usize {
+        self.heads()[0]
+    }
+
+    fn current_state_name(&self) -> &str {
+        self.current_state_name()
+    }
+
+    fn is_halted(&self) -> bool {
+        self.is_halted()
+    }
+
+    fn is_accepting(&self) -> bool {
+        self.is_accepting()
+    }
+
+    fn tape(&self) -> &Tape {
+        &self.tapes()[0]
+    }
+
+    fn tapes(&self) -> &[Tape] {
+        self.tapes()
+    }
+
+    fn heads(&self) -> Vec<usize> {
+        self.heads().to_vec()
+    }
+
+    fn tape_cells(&self) -> usize {
+        self.tapes().iter().map(Tape::len).sum()
+    }
+
+    // The first tape grows to the head start, so the head always starts there
+    fn head_start(&self) -> Option<usize> {
+        Some(self.head_start())
+    }
+}
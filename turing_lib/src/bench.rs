@@ -0,0 +1,109 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    automaton::{self, MachineType},
+    fast,
+    machine::TuringMachine,
+    parser,
+    runner::{self, RunLimits, RunOutcome},
+};
+
+/// Wall-clock time taken by a run, averaged over every repetition.
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    pub time: Duration,
+    pub steps: usize,
+}
+
+impl Timing {
+    pub fn steps_per_second(&self) -> f64 {
+        self.steps as f64 / self.time.as_secs_f64()
+    }
+
+    /// Average cost of a single step.
+    pub fn time_per_step(&self) -> Duration {
+        self.time.div_f64(self.steps.max(1) as f64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub input: String,
+    pub outcome: RunOutcome,
+    pub interpreter: Timing,
+    /// Only measured for machines supported by the fast runner.
+    pub fast: Option<Timing>,
+}
+
+/// Runs the machine on every input `repetitions` times with the interpreter
+/// ([`runner::run`]) and, if possible, with the fast runner ([`fast::run`]), measuring how
+/// long the runs take. Loading the machine is not included in the measured time, but the
+/// preparation the fast runner does before running is.
+pub fn bench(
+    file_data: &str,
+    inputs: &[&str],
+    limits: &RunLimits,
+    repetitions: usize,
+) -> Result<Vec<BenchResult>, String> {
+    if repetitions == 0 {
+        return Err(
+            "[turing_lib] Error while benchmarking. There must be at least one repetition."
+                .to_string(),
+        );
+    }
+
+    let file_lines = parser::file_lines(file_data);
+    let is_turing = parser::parse_machine_type(&file_lines)? == MachineType::Turing;
+
+    let mut results = vec![];
+
+    for input in inputs {
+        let mut outcome = None;
+        let mut time = Duration::ZERO;
+
+        for _ in 0..repetitions {
+            let mut machine = automaton::load_from_str(file_data, input)?;
+
+            let start = Instant::now();
+            outcome = Some(runner::run(machine.as_mut(), limits));
+            time += start.elapsed();
+        }
+
+        let outcome = outcome.unwrap();
+        let interpreter = Timing {
+            time: time / repetitions as u32,
+            steps: outcome.steps(),
+        };
+
+        let fast = if is_turing {
+            let machine = TuringMachine::new_from_str(file_data, input)?;
+            let mut time = Duration::ZERO;
+            let mut supported = true;
+
+            for _ in 0..repetitions {
+                let start = Instant::now();
+                if fast::run(&machine, limits).is_err() {
+                    supported = false;
+                    break;
+                }
+                time += start.elapsed();
+            }
+
+            supported.then(|| Timing {
+                time: time / repetitions as u32,
+                steps: outcome.steps(),
+            })
+        } else {
+            None
+        };
+
+        results.push(BenchResult {
+            input: input.to_string(),
+            outcome,
+            interpreter,
+            fast,
+        });
+    }
+
+    Ok(results)
+}
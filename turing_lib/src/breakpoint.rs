@@ -0,0 +1,131 @@
+use std::time::Instant;
+
+use rhai::{Array, Dynamic, Engine, Scope, AST, INT};
+
+use crate::{
+    automaton::Automaton,
+    machine::Symbol,
+    runner::{self, RunLimits, RunOutcome},
+};
+
+const MAX_OPERATIONS: u64 = 100_000; // Per evaluation, so a condition can't hang the run
+
+/// A condition written as a [Rhai](https://rhai.rs) expression, evaluated before every tick,
+/// e.g. `state == "carry" && tape[head] == '1' && step > 1000`.
+///
+/// The expression can use these variables:
+/// - `state`: Name of the current state.
+/// - `head`: Index of the head in the tape.
+/// - `symbol`: Symbol under the head, as a char (the blank symbol if blank).
+/// - `tape`: Array with the symbols of the tape, as chars.
+/// - `step`: Number of steps taken so far.
+pub struct Breakpoint {
+    condition: String,
+    engine: Engine,
+    ast: AST,
+    uses_tape: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakpointOutcome {
+    /// The condition of the breakpoint with this index was met before the step `steps`.
+    Hit {
+        breakpoint: usize,
+        steps: usize,
+    },
+    Finished(RunOutcome),
+}
+
+impl Breakpoint {
+    pub fn new(condition: &str) -> Result<Breakpoint, String> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let ast = engine.compile_expression(condition).map_err(|err| {
+            format!("[turing_lib] Error while parsing the breakpoint \"{condition}\". {err}.")
+        })?;
+
+        Ok(Breakpoint {
+            condition: condition.to_string(),
+            engine,
+            ast,
+            uses_tape: condition.contains("tape"),
+        })
+    }
+
+    pub fn condition(&self) -> &str {
+        &self.condition
+    }
+
+    /// Evaluates the condition for the current configuration of the machine.
+    pub fn is_hit<A: Automaton + ?Sized>(&self, machine: &A, step: usize) -> Result<bool, String> {
+        let to_char = |symbol: &Symbol| match symbol {
+            Symbol::Mark(c) => *c,
+            _ => machine.blank_symbol(),
+        };
+
+        let tape = machine.tape();
+        let symbol = to_char(&tape[machine.head_idx() as isize]);
+
+        let mut scope = Scope::new();
+        scope.push_constant("state", machine.current_state_name().to_string());
+        scope.push_constant("head", machine.head_idx() as INT);
+        scope.push_constant("symbol", symbol);
+        scope.push_constant("step", step as INT);
+
+        // Copying the tape is only worth it if the condition reads it
+        if self.uses_tape {
+            let tape = tape
+                .cells()
+                .map(|symbol| Dynamic::from(to_char(&symbol)))
+                .collect::<Array>();
+            scope.push_constant("tape", tape);
+        }
+
+        self.engine
+            .eval_ast_with_scope::<bool>(&mut scope, &self.ast)
+            .map_err(|err| {
+                format!(
+                    "[turing_lib] Error while evaluating the breakpoint \"{}\". {err}.",
+                    self.condition
+                )
+            })
+    }
+}
+
+/// Runs the machine like [`crate::runner::run`], but stops as soon as the condition of any of
+/// the breakpoints is met. The conditions are checked before every tick.
+pub fn run_until_breakpoint<A: Automaton + ?Sized>(
+    machine: &mut A,
+    limits: &RunLimits,
+    breakpoints: &[Breakpoint],
+) -> Result<BreakpointOutcome, String> {
+    let start = Instant::now();
+    let mut steps = 0;
+
+    loop {
+        if let Some(outcome) = runner::check_limits(limits, machine, start, steps) {
+            return Ok(BreakpointOutcome::Finished(outcome));
+        }
+
+        for (idx, breakpoint) in breakpoints.iter().enumerate() {
+            if breakpoint.is_hit(machine, steps)? {
+                return Ok(BreakpointOutcome::Hit {
+                    breakpoint: idx,
+                    steps,
+                });
+            }
+        }
+
+        machine.advance();
+
+        if machine.is_halted() {
+            return Ok(BreakpointOutcome::Finished(RunOutcome::Halted {
+                accepted: machine.is_accepting(),
+                steps,
+            }));
+        }
+
+        steps += 1;
+    }
+}
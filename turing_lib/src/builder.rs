@@ -0,0 +1,452 @@
+//! Builds Turing Machines in code, for machines that are generated or embedded in a program
+//! instead of written in a `.tng` file.
+
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+use crate::{
+    error::{Location, ParseError, ParseErrorKind},
+    heatmap::Heatmap,
+    machine::{
+        OutputConvention, State, Symbol, Transition, TransitionSource, TuringMachine,
+        LEFT_END_MARKER, RIGHT_END_MARKER,
+    },
+    oracle::OracleQuery,
+    random::Rng,
+    tape::{Tape, DEFAULT_BLANK_SYMBOL},
+};
+
+/// A [`TuringMachine`] under construction. Everything is checked when building it, like when
+/// parsing a `.tng` file.
+#[derive(Debug, Clone)]
+pub struct TuringMachineBuilder {
+    name: String,
+    blank_symbol: char,
+    states: Vec<BuilderState>,
+    initial_state: Option<String>,
+    final_states: HashSet<String>,
+    head_start: usize,
+    output_convention: OutputConvention,
+    seed: u64,
+    bounded: bool,
+    nondeterministic: bool,
+    max_tape_cells: Option<usize>,
+}
+
+/// A state under construction, with its lines in the order they were added.
+#[derive(Debug, Clone)]
+struct BuilderState {
+    name: String,
+    description: Option<String>,
+    lines: Vec<(StateLine, Option<LineOrigin>)>,
+}
+
+/// A line of a state, like the ones of the states of `.tng` files.
+#[derive(Debug, Clone)]
+pub(crate) enum StateLine {
+    Transition {
+        read: TransitionSource,
+        transition: Transition,
+        weight: Option<u32>, // Of random transitions
+    },
+    Emit(String),          // State entered after emitting
+    Query(String, String), // The states entered on yes and on no
+}
+
+impl StateLine {
+    /// The states the line enters.
+    fn new_states(&self) -> Vec<&str> {
+        match self {
+            StateLine::Transition { transition, .. } => vec![transition.new_state()],
+            StateLine::Emit(next_state) => vec![next_state],
+            StateLine::Query(yes_state, no_state) => vec![yes_state, no_state],
+        }
+    }
+}
+
+/// Where a line of a state is in the file it was parsed from, so the errors about it point
+/// there.
+#[derive(Debug, Clone)]
+pub(crate) struct LineOrigin {
+    pub(crate) line: Location,
+    pub(crate) new_states: Vec<Location>, // In the order of `StateLine::new_states`
+    pub(crate) weight: Option<Location>,
+}
+
+impl TuringMachineBuilder {
+    /// A machine without states, with `_` as its blank symbol and the head starting on the
+    /// first cell.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            blank_symbol: DEFAULT_BLANK_SYMBOL,
+            states: vec![],
+            initial_state: None,
+            final_states: HashSet::new(),
+            head_start: 0,
+            output_convention: OutputConvention::FromHead,
+            seed: 0,
+            bounded: false,
+            nondeterministic: false,
+            max_tape_cells: None,
+        }
+    }
+
+    /// Adds a state without transitions, if there isn't one with that name yet.
+    pub fn add_state(mut self, name: &str) -> Self {
+        self.state(name);
+        self
+    }
+
+    /// Sets the description of the state (which is added if needed), like the quoted text after
+    /// its name in a `.tng` file.
+    pub fn set_description(mut self, state: &str, description: &str) -> Self {
+        self.state(state).description = Some(description.to_string());
+        self
+    }
+
+    /// Adds a transition of the state `state` (which is added if needed) taken when reading
+    /// `read`. Non-deterministic machines may have several for the same symbol.
+    pub fn add_transition(
+        self,
+        state: &str,
+        read: TransitionSource,
+        transition: Transition,
+    ) -> Self {
+        let line = StateLine::Transition {
+            read,
+            transition,
+            weight: None,
+        };
+        self.add_line(state, line, None)
+    }
+
+    /// Adds a transition of the state `state` (which is added if needed) that is one of the
+    /// choices made at random when reading `read`, with a probability proportional to its
+    /// positive `weight`.
+    pub fn add_random_transition(
+        self,
+        state: &str,
+        read: TransitionSource,
+        transition: Transition,
+        weight: u32,
+    ) -> Self {
+        let line = StateLine::Transition {
+            read,
+            transition,
+            weight: Some(weight),
+        };
+        self.add_line(state, line, None)
+    }
+
+    /// Makes the state (which is added if needed) emit the word under the head and enter
+    /// `next_state`, instead of having transitions.
+    pub fn set_emit(mut self, state: &str, next_state: &str) -> Self {
+        let lines = &mut self.state(state).lines;
+        lines.retain(|(line, _)| !matches!(line, StateLine::Emit(_)));
+        lines.push((StateLine::Emit(next_state.to_string()), None));
+        self
+    }
+
+    /// Makes the state (which is added if needed) ask the oracle of the machine about the word
+    /// under the head and enter `yes_state` or `no_state`, instead of having transitions.
+    pub fn set_query(mut self, state: &str, yes_state: &str, no_state: &str) -> Self {
+        let lines = &mut self.state(state).lines;
+        lines.retain(|(line, _)| !matches!(line, StateLine::Query(..)));
+        lines.push((
+            StateLine::Query(yes_state.to_string(), no_state.to_string()),
+            None,
+        ));
+        self
+    }
+
+    /// Adds a line to the state (which is added if needed), after the ones it has, with where
+    /// it is in the file it was parsed from.
+    pub(crate) fn add_line(
+        mut self,
+        state: &str,
+        line: StateLine,
+        origin: Option<LineOrigin>,
+    ) -> Self {
+        self.state(state).lines.push((line, origin));
+        self
+    }
+
+    /// Sets the state the machine starts (and is reset) in, which is added if needed.
+    pub fn set_initial(mut self, state: &str) -> Self {
+        self.state(state);
+        self.initial_state = Some(state.to_string());
+        self
+    }
+
+    /// Makes the state (which is added if needed) a final one, where the machine accepts.
+    pub fn mark_final(mut self, state: &str) -> Self {
+        self.state(state);
+        self.final_states.insert(state.to_string());
+        self
+    }
+
+    /// Sets the blank symbol. Marks with this char are read and written as blanks.
+    pub fn set_blank(mut self, blank_symbol: char) -> Self {
+        self.blank_symbol = blank_symbol;
+        self
+    }
+
+    pub fn set_head_start(mut self, head_start: usize) -> Self {
+        self.head_start = head_start;
+        self
+    }
+
+    pub fn set_output_convention(mut self, output_convention: OutputConvention) -> Self {
+        self.output_convention = output_convention;
+        self
+    }
+
+    /// Sets the seed of the random number generator used by random transitions.
+    pub fn set_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Makes the machine a linear bounded automaton (`machine_type: lba`), whose input goes
+    /// between end markers that the head can't move past.
+    pub fn set_bounded(mut self, bounded: bool) -> Self {
+        self.bounded = bounded;
+        self
+    }
+
+    /// Makes the machine non-deterministic (`machine_type: ndtm`), so it may have several
+    /// transitions for the same symbol, and accepts if any of its runs accepts.
+    pub fn set_nondeterministic(mut self, nondeterministic: bool) -> Self {
+        self.nondeterministic = nondeterministic;
+        self
+    }
+
+    /// Sets the most cells the tape may have, see [`TuringMachine::max_tape_cells`].
+    pub fn set_max_tape_cells(mut self, max_tape_cells: Option<usize>) -> Self {
+        self.max_tape_cells = max_tape_cells;
+        self
+    }
+
+    /// Builds the machine with `tape_data` as its input. Fails where parsing the same machine
+    /// from a `.tng` file would: if there is no initial state, if an option can't be used, if a
+    /// state enters a state that doesn't exist, if a state that emits or queries has other
+    /// lines, or if a deterministic machine has more than one transition for the same symbol
+    /// (or random and plain ones for it).
+    pub fn build(self, tape_data: &str) -> Result<TuringMachine, ParseError> {
+        let blank_symbol = self.blank_symbol;
+        let nondeterministic = self.nondeterministic;
+
+        if blank_symbol == ',' || blank_symbol.is_whitespace() {
+            return Err(ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while building the machine. Invalid blank symbol '{blank_symbol}'. It can't be a comma nor a whitespace, since transitions couldn't read or write it.")));
+        }
+
+        if self.bounded && [LEFT_END_MARKER, RIGHT_END_MARKER].contains(&blank_symbol) {
+            return Err(ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while building the machine. Invalid blank symbol '{blank_symbol}'. It can't be an end marker of linear bounded automata ('{LEFT_END_MARKER}' nor '{RIGHT_END_MARKER}').")));
+        }
+
+        if self.bounded && nondeterministic {
+            return Err(ParseError::new(ParseErrorKind::InvalidOption, "[turing_lib] Error while building the machine. A machine can't be both linear bounded and non-deterministic, as it only has one machine type."));
+        }
+
+        if self.max_tape_cells == Some(0) {
+            return Err(ParseError::new(ParseErrorKind::InvalidOption, "[turing_lib] Error while building the machine. Invalid maximum tape cells provided (0). It must be a positive integer."));
+        }
+
+        let mut states = HashMap::new();
+        for state in &self.states {
+            let new_state = state.build(blank_symbol, nondeterministic)?;
+            states.insert(state.name.clone(), new_state);
+        }
+
+        for state in &self.states {
+            for (line, origin) in &state.lines {
+                for (idx, new_state) in line.new_states().into_iter().enumerate() {
+                    if !states.contains_key(new_state) {
+                        let error = ParseError::new(ParseErrorKind::UndefinedState, format!("[turing_lib] Error while building the machine. The state \"{}\" transitions into the state \"{new_state}\", which is not defined.", state.name));
+                        return Err(match origin {
+                            Some(origin) => error.at_location(&origin.new_states[idx]),
+                            None => error.at(new_state),
+                        });
+                    }
+                }
+            }
+        }
+
+        let initial_state = self.initial_state.ok_or_else(|| {
+            ParseError::new(
+                ParseErrorKind::MissingInitialState,
+                "[turing_lib] Error while building the machine. No initial state was provided.",
+            )
+        })?;
+
+        let mut machine = TuringMachine {
+            name: self.name,
+            blank_symbol,
+
+            states,
+            final_states: self.final_states,
+
+            head_start: self.head_start,
+            effective_head_start: self.head_start,
+            initial_state: initial_state.clone(),
+
+            head_idx: self.head_start,
+            current_state: initial_state.clone(),
+            tape: Tape::new(vec![]),
+
+            halted: false,
+
+            bounded: self.bounded,
+            out_of_bounds: false,
+
+            nondeterministic,
+
+            max_tape_cells: self.max_tape_cells,
+
+            oracle: None,
+            rng: Rng::new(self.seed),
+
+            emitted: vec![],
+
+            output_convention: self.output_convention,
+
+            heatmap: Heatmap::new(&initial_state),
+
+            history: None,
+        };
+        machine.reset(tape_data);
+
+        Ok(machine)
+    }
+
+    fn state(&mut self, name: &str) -> &mut BuilderState {
+        let idx = match self.states.iter().position(|state| state.name == name) {
+            Some(idx) => idx,
+            None => {
+                self.states.push(BuilderState {
+                    name: name.to_string(),
+                    description: None,
+                    lines: vec![],
+                });
+                self.states.len() - 1
+            }
+        };
+
+        &mut self.states[idx]
+    }
+}
+
+impl BuilderState {
+    /// The state with its lines, checked like the ones of a state of a `.tng` file.
+    fn build(&self, blank_symbol: char, nondeterministic: bool) -> Result<State, ParseError> {
+        let name = &self.name;
+
+        let mut transitions = HashMap::new();
+        let mut random_transitions: HashMap<_, Vec<_>> = HashMap::new();
+
+        for (idx, (line, origin)) in self.lines.iter().enumerate() {
+            let at_line = |error: ParseError| match origin {
+                Some(origin) => error.at_location(&origin.line),
+                None => error.at(name),
+            };
+
+            // A query or an emit must be the only line of its state
+            let only_line = match (line, &self.lines[0].0) {
+                (StateLine::Query(..), _)
+                | (StateLine::Transition { .. }, StateLine::Query(..)) => Some("A query"),
+                (StateLine::Emit(_), _) | (StateLine::Transition { .. }, StateLine::Emit(_)) => {
+                    Some("An emit")
+                }
+                _ => None,
+            };
+            if let Some(only_line) = only_line.filter(|_| idx > 0) {
+                return Err(at_line(ParseError::new(ParseErrorKind::InvalidStateBody, format!("[turing_lib] Error while building the machine. {only_line} must be the only line of the state \"{name}\"."))));
+            }
+
+            let StateLine::Transition {
+                read,
+                transition,
+                weight,
+            } = line
+            else {
+                continue;
+            };
+
+            if *weight == Some(0) {
+                let error = ParseError::new(ParseErrorKind::InvalidWeight, format!("[turing_lib] Error while building the machine. Invalid weight of a transition of the state \"{name}\". It must be a positive integer."));
+                return Err(
+                    match origin.as_ref().and_then(|origin| origin.weight.as_ref()) {
+                        Some(weight) => error.at_location(weight),
+                        None => at_line(error),
+                    },
+                );
+            }
+
+            let read = match *read {
+                TransitionSource::Mark(symbol) if symbol == blank_symbol => TransitionSource::Blank,
+                read => read,
+            };
+            let new_symbol = match transition.new_symbol() {
+                Symbol::Mark(symbol) if symbol == blank_symbol => Symbol::Blank,
+                new_symbol => new_symbol,
+            };
+            let transition = Transition::new_jump(
+                transition.head_movement(),
+                transition.distance(),
+                new_symbol,
+                transition.new_state().to_string(),
+            )
+            .with_pacing(transition.pacing());
+
+            // Weighted transitions can share their source symbol, but not with plain ones,
+            // unless the machine is non-deterministic
+            let source_taken = match weight {
+                None => random_transitions.contains_key(&read),
+                Some(_) => transitions.contains_key(&read),
+            };
+
+            if source_taken && !nondeterministic {
+                return Err(at_line(ParseError::new(ParseErrorKind::Nondeterminism, format!("[turing_lib] Error while building the machine. Weighted and unweighted transitions can not share the source symbol {read:?} in the state \"{name}\"."))));
+            }
+
+            match weight {
+                None if !source_taken => match transitions.entry(read) {
+                    // Every transition of the symbol is a choice of equal weight
+                    Entry::Occupied(entry) if nondeterministic => {
+                        let first = entry.remove();
+                        random_transitions.insert(read, vec![(1, first), (1, transition)]);
+                    }
+                    Entry::Occupied(_) => {
+                        return Err(at_line(ParseError::new(ParseErrorKind::Nondeterminism, format!("[turing_lib] Error while building the machine. Non-determinism not allowed. The transition source symbol {read:?} has already been defined for the state \"{name}\" (make the machine non-deterministic, e.g. with `machine_type: ndtm`, to allow it)."))));
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(transition);
+                    }
+                },
+                _ => {
+                    let choices = random_transitions.entry(read).or_default();
+                    if let Some(first) = transitions.remove(&read) {
+                        choices.push((1, first));
+                    }
+                    choices.push((weight.unwrap_or(1), transition));
+                }
+            }
+        }
+
+        let mut state = match self.lines.first() {
+            Some((StateLine::Query(yes_state, no_state), _)) => State::new_query(
+                name.clone(),
+                OracleQuery::new(yes_state.clone(), no_state.clone()),
+            ),
+            Some((StateLine::Emit(next_state), _)) => {
+                State::new_emit(name.clone(), next_state.clone())
+            }
+            _ => State::new_random(name.clone(), transitions, random_transitions),
+        };
+        if let Some(description) = &self.description {
+            state = state.with_description(description.clone());
+        }
+
+        Ok(state)
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use plotters::{coord::Shift, prelude::*};
+
+use crate::timeseries::{Sample, TimeSeries};
+
+const SIZE: (u32, u32) = (800, 480); // In pixels
+const MAX_POINTS: usize = 2000; // Longer series are downsampled, more points wouldn't be visible
+
+/// What a chart of a [`TimeSeries`] shows, over the steps of the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chart {
+    /// The position of the head, counted from the cell the tape started at.
+    HeadPosition,
+    /// The number of cells of the tape.
+    TapeLength,
+}
+
+impl Chart {
+    fn title(self) -> &'static str {
+        match self {
+            Chart::HeadPosition => "Head position",
+            Chart::TapeLength => "Tape length",
+        }
+    }
+
+    fn value(self, sample: &Sample) -> i64 {
+        match self {
+            Chart::HeadPosition => sample.head_position as i64,
+            Chart::TapeLength => sample.tape_len as i64,
+        }
+    }
+}
+
+/// Draws the chart of the series to `path`, as a PNG or an SVG depending on its extension.
+pub fn write(series: &TimeSeries, chart: Chart, path: &Path) -> Result<(), String> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+
+    let result = match extension.as_deref() {
+        Some("png") => draw(
+            BitMapBackend::new(path, SIZE).into_drawing_area(),
+            series,
+            chart,
+        )
+        .map_err(|err| err.to_string()),
+        Some("svg") => draw(
+            SVGBackend::new(path, SIZE).into_drawing_area(),
+            series,
+            chart,
+        )
+        .map_err(|err| err.to_string()),
+        _ => {
+            return Err(format!(
+                "[turing_lib] Error while drawing the chart. \"{}\" must be a .png or .svg file.",
+                path.display()
+            ))
+        }
+    };
+
+    result.map_err(|err| {
+        format!(
+            "[turing_lib] Error while drawing the chart to \"{}\". {err}",
+            path.display()
+        )
+    })
+}
+
+fn draw<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    series: &TimeSeries,
+    chart: Chart,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let series = series.downsample(MAX_POINTS);
+    let samples = series.samples();
+
+    let last_step = samples.last().map_or(0, |sample| sample.step).max(1);
+    let values = samples.iter().map(|sample| chart.value(sample));
+    let min = values.clone().min().unwrap_or(0).min(0);
+    let max = values.max().unwrap_or(0).max(min + 1);
+
+    root.fill(&WHITE)?;
+
+    let mut context = ChartBuilder::on(&root)
+        .caption(chart.title(), ("sans-serif", 24))
+        .margin(12)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0..last_step, min..max)?;
+
+    context
+        .configure_mesh()
+        .x_desc("Step")
+        .y_desc(chart.title())
+        .draw()?;
+
+    context.draw_series(LineSeries::new(
+        samples
+            .iter()
+            .map(|sample| (sample.step, chart.value(sample))),
+        &BLUE,
+    ))?;
+
+    root.present()
+}
@@ -0,0 +1,349 @@
+//! A compact binary format for Turing Machines (`.tngc` files), which loads much faster than
+//! parsing their `.tng` definition, for servers and batch runners that load the same large
+//! machines over and over.
+//!
+//! A compiled machine starts with the bytes `TNGC` and the version of the format, followed by
+//! the machine encoded with [bincode](https://github.com/bincode-org/bincode): the states are
+//! stored once in a table, and transitions refer to them by their index in it. Only the
+//! definition is stored, so the input is given when loading it, like with `.tng` files.
+
+use std::collections::{HashMap, HashSet};
+
+use bincode::{Decode, Encode};
+
+use crate::{
+    heatmap::Heatmap,
+    machine::{
+        HeadMovement, OutputConvention, Pacing, State, Symbol, Transition, TransitionSource,
+        TuringMachine,
+    },
+    oracle::OracleQuery,
+    random::Rng,
+    tape::Tape,
+};
+
+pub const COMPILED_EXTENSION: &str = "tngc";
+
+const MAGIC: &[u8] = b"TNGC";
+const VERSION: u8 = 2;
+const MAX_DECODED_LEN: usize = 1 << 30; // So a corrupted file can't allocate without bound
+
+#[derive(Encode, Decode)]
+enum CompiledSymbol {
+    Default,
+    Mark(char),
+    Blank,
+}
+
+#[derive(Encode, Decode)]
+struct CompiledTransition {
+    movement: u8, // 0 is left, 1 is stay and 2 is right
+    distance: u32,
+    new_symbol: CompiledSymbol,
+    new_state: u32,
+    pacing: u8, // 0 is normal, 1 is slow and 2 is fast
+}
+
+#[derive(Encode, Decode)]
+enum CompiledBehavior {
+    Transitions {
+        transitions: Vec<(CompiledSymbol, CompiledTransition)>,
+        random_transitions: Vec<(CompiledSymbol, Vec<(u32, CompiledTransition)>)>,
+    },
+    Query {
+        yes_state: u32,
+        no_state: u32,
+    },
+    Emit(u32),
+}
+
+#[derive(Encode, Decode)]
+struct CompiledState {
+    name: String,
+    description: Option<String>,
+    is_final: bool,
+    behavior: CompiledBehavior,
+}
+
+#[derive(Encode, Decode)]
+struct CompiledMachine {
+    name: String,
+    blank_symbol: char,
+    head_start: u64,
+    initial_state: u32,
+    states: Vec<CompiledState>,
+    bounded: bool,
+    nondeterministic: bool,
+    max_tape_cells: Option<u64>,
+    output_whole_tape: bool,
+    rng_state: u64,
+}
+
+/// Whether `bytes` look like a compiled machine, to tell them apart from `.tng` definitions.
+pub fn is_compiled(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Compiles the definition of a machine (not its tape nor its head) into the `.tngc` format.
+/// The random number generator is stored as it is, so a machine compiled right after loading
+/// it makes the same random choices as the original one.
+pub fn compile(machine: &TuringMachine) -> Vec<u8> {
+    let mut names = machine.states.keys().collect::<Vec<_>>();
+    names.sort();
+    let indices = names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| (name.as_str(), idx as u32))
+        .collect::<HashMap<_, _>>();
+
+    let symbol = |symbol: Symbol| match symbol {
+        Symbol::Default => CompiledSymbol::Default,
+        Symbol::Mark(c) => CompiledSymbol::Mark(c),
+        Symbol::Blank => CompiledSymbol::Blank,
+    };
+    let source = |source: TransitionSource| match source {
+        TransitionSource::Default => CompiledSymbol::Default,
+        TransitionSource::Mark(c) => CompiledSymbol::Mark(c),
+        TransitionSource::Blank => CompiledSymbol::Blank,
+    };
+    let transition = |transition: &Transition| CompiledTransition {
+        movement: match transition.head_movement() {
+            HeadMovement::Left => 0,
+            HeadMovement::Stay => 1,
+            HeadMovement::Right => 2,
+        },
+        distance: transition.distance() as u32,
+        new_symbol: symbol(transition.new_symbol()),
+        new_state: indices[transition.new_state()],
+        pacing: match transition.pacing() {
+            Pacing::Normal => 0,
+            Pacing::Slow => 1,
+            Pacing::Fast => 2,
+        },
+    };
+
+    let states = names
+        .iter()
+        .map(|name| {
+            let state = &machine.states[name.as_str()];
+
+            let behavior = if let Some(query) = state.query() {
+                CompiledBehavior::Query {
+                    yes_state: indices[query.yes_state()],
+                    no_state: indices[query.no_state()],
+                }
+            } else if let Some(next_state) = state.emit() {
+                CompiledBehavior::Emit(indices[next_state])
+            } else {
+                let mut transitions = state.transitions().iter().collect::<Vec<_>>();
+                transitions.sort_by_key(|(source, _)| **source);
+
+                let mut random_transitions = state.random_transitions().iter().collect::<Vec<_>>();
+                random_transitions.sort_by_key(|(source, _)| **source);
+
+                CompiledBehavior::Transitions {
+                    transitions: transitions
+                        .into_iter()
+                        .map(|(from, to)| (source(*from), transition(to)))
+                        .collect(),
+                    random_transitions: random_transitions
+                        .into_iter()
+                        .map(|(from, choices)| {
+                            let choices = choices
+                                .iter()
+                                .map(|(weight, to)| (*weight, transition(to)))
+                                .collect();
+                            (source(*from), choices)
+                        })
+                        .collect(),
+                }
+            };
+
+            CompiledState {
+                name: name.to_string(),
+                description: state.description().map(str::to_string),
+                is_final: machine.final_states.contains(name.as_str()),
+                behavior,
+            }
+        })
+        .collect();
+
+    let compiled = CompiledMachine {
+        name: machine.name.clone(),
+        blank_symbol: machine.blank_symbol,
+        head_start: machine.head_start as u64,
+        initial_state: indices[machine.initial_state.as_str()],
+        states,
+        bounded: machine.bounded,
+        nondeterministic: machine.nondeterministic,
+        max_tape_cells: machine.max_tape_cells.map(|cells| cells as u64),
+        output_whole_tape: machine.output_convention == OutputConvention::WholeTape,
+        rng_state: machine.rng.state(),
+    };
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    bytes.extend(
+        bincode::encode_to_vec(compiled, bincode::config::standard())
+            .expect("Compiled machines can always be encoded"),
+    );
+    bytes
+}
+
+/// Loads a machine compiled with [`compile`], with `tape_data` on its tape, like
+/// [`TuringMachine::new_from_str`] does with `.tng` definitions.
+pub fn load_compiled(bytes: &[u8], tape_data: &str) -> Result<TuringMachine, String> {
+    let Some(rest) = bytes.strip_prefix(MAGIC) else {
+        return Err(
+            "[turing_lib] Error while loading a compiled machine. It is not a compiled machine."
+                .to_string(),
+        );
+    };
+
+    match rest.first() {
+        Some(&VERSION) => {}
+        Some(version) => return Err(format!("[turing_lib] Error while loading a compiled machine. Unsupported version {version}, it must be compiled again.")),
+        None => return Err("[turing_lib] Error while loading a compiled machine. It is truncated.".to_string()),
+    }
+
+    let invalid =
+        || "[turing_lib] Error while loading a compiled machine. It is corrupted.".to_string();
+
+    let (compiled, _): (CompiledMachine, _) = bincode::decode_from_slice(
+        &rest[1..],
+        bincode::config::standard().with_limit::<MAX_DECODED_LEN>(),
+    )
+    .map_err(|_| invalid())?;
+
+    let names = compiled
+        .states
+        .iter()
+        .map(|state| state.name.clone())
+        .collect::<Vec<_>>();
+    let name = |idx: u32| names.get(idx as usize).cloned().ok_or_else(invalid);
+
+    let symbol = |symbol: &CompiledSymbol| match symbol {
+        CompiledSymbol::Default => Symbol::Default,
+        CompiledSymbol::Mark(c) => Symbol::Mark(*c),
+        CompiledSymbol::Blank => Symbol::Blank,
+    };
+    let source = |source: &CompiledSymbol| match source {
+        CompiledSymbol::Default => TransitionSource::Default,
+        CompiledSymbol::Mark(c) => TransitionSource::Mark(*c),
+        CompiledSymbol::Blank => TransitionSource::Blank,
+    };
+    let transition = |transition: &CompiledTransition| -> Result<Transition, String> {
+        let movement = match transition.movement {
+            0 => HeadMovement::Left,
+            1 => HeadMovement::Stay,
+            2 => HeadMovement::Right,
+            _ => return Err(invalid()),
+        };
+        let pacing = match transition.pacing {
+            0 => Pacing::Normal,
+            1 => Pacing::Slow,
+            2 => Pacing::Fast,
+            _ => return Err(invalid()),
+        };
+
+        Ok(Transition::new_jump(
+            movement,
+            transition.distance as usize,
+            symbol(&transition.new_symbol),
+            name(transition.new_state)?,
+        )
+        .with_pacing(pacing))
+    };
+
+    let mut states = HashMap::new();
+    let mut final_states = HashSet::new();
+
+    for state in &compiled.states {
+        let mut new_state = match &state.behavior {
+            CompiledBehavior::Query {
+                yes_state,
+                no_state,
+            } => State::new_query(
+                state.name.clone(),
+                OracleQuery::new(name(*yes_state)?, name(*no_state)?),
+            ),
+            CompiledBehavior::Emit(next_state) => {
+                State::new_emit(state.name.clone(), name(*next_state)?)
+            }
+            CompiledBehavior::Transitions {
+                transitions,
+                random_transitions,
+            } => {
+                let transitions = transitions
+                    .iter()
+                    .map(|(from, to)| Ok((source(from), transition(to)?)))
+                    .collect::<Result<_, String>>()?;
+                let random_transitions = random_transitions
+                    .iter()
+                    .map(|(from, choices)| {
+                        let choices = choices
+                            .iter()
+                            .map(|(weight, to)| Ok((*weight, transition(to)?)))
+                            .collect::<Result<_, String>>()?;
+                        Ok((source(from), choices))
+                    })
+                    .collect::<Result<_, String>>()?;
+
+                State::new_random(state.name.clone(), transitions, random_transitions)
+            }
+        };
+        if let Some(description) = &state.description {
+            new_state = new_state.with_description(description.clone());
+        }
+
+        if state.is_final {
+            final_states.insert(state.name.clone());
+        }
+        states.insert(state.name.clone(), new_state);
+    }
+
+    let initial_state = name(compiled.initial_state)?;
+    let head_start = compiled.head_start as usize;
+
+    let mut machine = TuringMachine {
+        name: compiled.name,
+        blank_symbol: compiled.blank_symbol,
+
+        states,
+        final_states,
+
+        head_start,
+        effective_head_start: head_start,
+        initial_state: initial_state.clone(),
+
+        head_idx: head_start,
+        current_state: initial_state.clone(),
+        tape: Tape::new(vec![]),
+
+        halted: false,
+
+        bounded: compiled.bounded,
+        out_of_bounds: false,
+        nondeterministic: compiled.nondeterministic,
+
+        max_tape_cells: compiled.max_tape_cells.map(|cells| cells as usize),
+
+        oracle: None,
+        rng: Rng::new(compiled.rng_state),
+
+        emitted: vec![],
+
+        output_convention: if compiled.output_whole_tape {
+            OutputConvention::WholeTape
+        } else {
+            OutputConvention::FromHead
+        },
+
+        heatmap: Heatmap::new(&initial_state),
+
+        history: None,
+    };
+    machine.reset(tape_data);
+
+    Ok(machine)
+}
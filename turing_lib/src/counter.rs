@@ -0,0 +1,148 @@
+use std::fs;
+
+use crate::{
+    error::{MachineError, MachineErrorKind, ParseError, ParseErrorKind},
+    machine::{HeadMovement, TickResult},
+    parser,
+    tape::Tape,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Inc(usize),
+    Dec(usize),       // Decrementing a zero register leaves it at zero
+    Jz(usize, usize), // Register, and index of the instruction to jump to if it is zero
+    Halt,
+}
+
+pub struct CounterMachine {
+    pub(crate) name: String,
+
+    pub(crate) program: Vec<Instruction>,
+    pub(crate) instruction_names: Vec<String>, // Source text of every instruction, shown as the current state
+
+    pub(crate) registers: Vec<u64>,
+    pub(crate) program_counter: usize,
+
+    pub(crate) halted: bool,
+    pub(crate) empty_tape: Tape,
+}
+
+impl CounterMachine {
+    pub fn new_from_file(
+        filename: &str,
+        registers_data: &str,
+    ) -> Result<CounterMachine, MachineError> {
+        let file_data = fs::read_to_string(filename).map_err(|_| {
+            MachineError::new(
+                MachineErrorKind::Io,
+                format!("Could not open the file \"{}\"", filename),
+            )
+        })?;
+
+        Ok(Self::new_from_str(&file_data, registers_data)?)
+    }
+
+    /// `registers_data` holds the initial register values separated by commas (e.g. `3,4`).
+    /// Registers not given start at zero.
+    pub fn new_from_str(
+        file_data: &str,
+        registers_data: &str,
+    ) -> Result<CounterMachine, ParseError> {
+        let file_lines = parser::file_lines(file_data);
+
+        let mut machine = parser::parse_counter_file(&file_lines)?;
+
+        let values = registers_data
+            .split(",")
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>();
+
+        if values.len() > machine.registers.len() {
+            return Err(ParseError::new(
+                ParseErrorKind::InvalidInput,
+                format!(
+                    "[turing_lib] Error while setting the registers. {} values were provided, but the machine only has {} registers.",
+                    values.len(),
+                    machine.registers.len()
+                ),
+            ));
+        }
+
+        for (register, value) in machine.registers.iter_mut().zip(values) {
+            *register = value.parse().map_err(|_| {
+                ParseError::new(ParseErrorKind::InvalidInput, format!("[turing_lib] Error while setting the registers. Invalid register value \"{value}\". It must be a non negative integer."))
+            })?;
+        }
+
+        Ok(machine)
+    }
+
+    pub fn tick(&mut self) -> TickResult {
+        let result = TickResult {
+            written_different_symbol: false,
+            extended_tape_on_side: None,
+            head_movement: HeadMovement::Stay,
+            step: None,
+        };
+
+        if self.halted {
+            return result;
+        }
+
+        match self.program.get(self.program_counter) {
+            Some(Instruction::Inc(register)) => {
+                self.registers[*register] = self.registers[*register].saturating_add(1);
+                self.program_counter += 1;
+            }
+            Some(Instruction::Dec(register)) => {
+                self.registers[*register] = self.registers[*register].saturating_sub(1);
+                self.program_counter += 1;
+            }
+            Some(Instruction::Jz(register, target)) => {
+                if self.registers[*register] == 0 {
+                    self.program_counter = *target;
+                } else {
+                    self.program_counter += 1;
+                }
+            }
+            Some(Instruction::Halt) | None => {
+                self.halted = true;
+            }
+        }
+
+        result
+    }
+
+    /// A counter machine accepts when it halts through a `HALT` instruction, instead of running past the end of the program.
+    pub fn is_accepting(&self) -> bool {
+        self.halted && self.program_counter < self.program.len()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn program(&self) -> &[Instruction] {
+        &self.program
+    }
+
+    pub fn registers(&self) -> &[u64] {
+        &self.registers
+    }
+
+    pub fn program_counter(&self) -> usize {
+        self.program_counter
+    }
+
+    pub fn current_instruction_name(&self) -> &str {
+        self.instruction_names
+            .get(self.program_counter)
+            .map_or("end of program", String::as_str)
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+}
@@ -0,0 +1,138 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    heatmap::Heatmap,
+    machine::{TransitionSource, TuringMachine},
+    runner::{self, RunLimits, RunOutcome},
+};
+
+/// Which transitions of a machine were taken during one or several runs, like code coverage
+/// for the definition of the machine. The weighted random transitions of the same source
+/// symbol count as a single transition.
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    blank_symbol: char,
+    transitions: BTreeMap<(String, TransitionSource), usize>, // Times each transition has been taken
+    runs: usize,
+}
+
+impl Coverage {
+    /// The coverage of a machine that hasn't run yet: every transition is uncovered.
+    pub fn new(machine: &TuringMachine) -> Self {
+        let mut transitions = BTreeMap::new();
+
+        for (name, state) in machine.states() {
+            for &source in state
+                .transitions()
+                .keys()
+                .chain(state.random_transitions().keys())
+            {
+                transitions.insert((name.clone(), source), 0);
+            }
+        }
+
+        Self {
+            blank_symbol: machine.blank_symbol,
+            transitions,
+            runs: 0,
+        }
+    }
+
+    /// Adds the transitions taken during a run, from the heatmap of the machine after it.
+    pub fn add_run(&mut self, heatmap: &Heatmap) {
+        for (state, source, uses) in heatmap.transitions() {
+            if let Some(total) = self.transitions.get_mut(&(state.to_string(), source)) {
+                *total += uses;
+            }
+        }
+
+        self.runs += 1;
+    }
+
+    /// Times each transition has been taken, by their state and source symbol, in order.
+    pub fn transitions(&self) -> &BTreeMap<(String, TransitionSource), usize> {
+        &self.transitions
+    }
+
+    /// The transitions never taken, by their state and source symbol, in order.
+    pub fn uncovered(&self) -> Vec<(&str, TransitionSource)> {
+        self.transitions
+            .iter()
+            .filter(|(_, uses)| **uses == 0)
+            .map(|((state, source), _)| (state.as_str(), *source))
+            .collect()
+    }
+
+    pub fn covered_count(&self) -> usize {
+        self.transitions.values().filter(|uses| **uses > 0).count()
+    }
+
+    pub fn total_count(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    /// Share of the transitions taken at least once, from 0.0 to 100.0. A machine without
+    /// transitions is fully covered.
+    pub fn percentage(&self) -> f64 {
+        if self.transitions.is_empty() {
+            100.0
+        } else {
+            self.covered_count() as f64 * 100.0 / self.total_count() as f64
+        }
+    }
+
+    /// Whether every transition has been taken at least once.
+    pub fn is_complete(&self) -> bool {
+        self.covered_count() == self.total_count()
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let runs = if self.runs == 1 { "run" } else { "runs" };
+        writeln!(
+            f,
+            "Covered {} of {} transitions ({:.1}%) in {} {runs}",
+            self.covered_count(),
+            self.total_count(),
+            self.percentage(),
+            self.runs
+        )?;
+
+        for (state, source) in self.uncovered() {
+            let symbol = match source {
+                TransitionSource::Mark(c) => c.to_string(),
+                TransitionSource::Blank => self.blank_symbol.to_string(),
+                TransitionSource::Default => "default".to_string(),
+            };
+            writeln!(f, "  Uncovered: state {state}, reading {symbol}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs the machine on every input (from its initial configuration) and returns which of its
+/// transitions the runs took, with the outcome of every run, e.g. to check that a set of test
+/// inputs exercises every transition.
+pub fn coverage(
+    machine: &TuringMachine,
+    inputs: &[&str],
+    limits: &RunLimits,
+) -> (Coverage, Vec<RunOutcome>) {
+    let mut coverage = Coverage::new(machine);
+    let mut outcomes = vec![];
+    let mut machine = machine.clone();
+
+    for input in inputs {
+        machine.reset(input);
+        outcomes.push(runner::run(&mut machine, limits));
+        coverage.add_run(machine.heatmap());
+    }
+
+    (coverage, outcomes)
+}
@@ -0,0 +1,205 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    f64::consts::PI,
+    fs,
+};
+
+use crate::{machine::TuringMachine, svg};
+
+const ITERATIONS: usize = 300;
+const MARGIN: f64 = 0.05; // Kept free on every side of the unit square
+
+/// The states of a machine and the arrows between them, laid out in a unit square so they can
+/// be drawn at any size (see [`StateDiagram::new`]), and moved around one by one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateDiagram {
+    states: Vec<String>,        // The initial state first, then by name
+    positions: Vec<(f64, f64)>, // Of every state, from (0, 0) on the top left to (1, 1)
+    edges: Vec<(usize, usize)>, // One per pair of states with transitions between them
+    final_states: Vec<bool>,    // By state
+    pinned: Vec<bool>,          // States placed by hand, which the layout doesn't move
+    state_ids: HashMap<String, usize>,
+}
+
+impl StateDiagram {
+    /// Lays out the states of the machine with a force-directed layout: states repel each other
+    /// and arrows pull the states they join together, starting from a circle (the initial state
+    /// on its left). The layout is always the same for the same machine.
+    pub fn new(machine: &TuringMachine) -> Self {
+        let mut names = machine.states.keys().collect::<BTreeSet<_>>();
+        names.remove(&machine.initial_state);
+        let states = std::iter::once(&machine.initial_state)
+            .chain(names)
+            .cloned()
+            .collect::<Vec<_>>();
+        let state_ids = states
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (name.clone(), idx))
+            .collect::<HashMap<_, _>>();
+
+        let edges = svg::edges(machine, "→")
+            .into_keys()
+            .filter_map(|(from, to)| Some((*state_ids.get(&from)?, *state_ids.get(&to)?)))
+            .collect();
+        let final_states = states
+            .iter()
+            .map(|name| machine.final_states.contains(name))
+            .collect();
+
+        let mut diagram = Self {
+            positions: vec![(0.5, 0.5); states.len()],
+            pinned: vec![false; states.len()],
+            states,
+            edges,
+            final_states,
+            state_ids,
+        };
+        diagram.lay_out();
+        diagram
+    }
+
+    /// Names of the states, the initial one first.
+    pub fn states(&self) -> &[String] {
+        &self.states
+    }
+
+    /// Index in [`StateDiagram::states`] of the state with this name.
+    pub fn state_idx(&self, name: &str) -> Option<usize> {
+        self.state_ids.get(name).copied()
+    }
+
+    /// Positions of the states, in the order of [`StateDiagram::states`].
+    pub fn positions(&self) -> &[(f64, f64)] {
+        &self.positions
+    }
+
+    /// The pairs of states (by index) with transitions from the first one to the second one.
+    /// States with transitions to themselves are paired with themselves.
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    pub fn is_final(&self, idx: usize) -> bool {
+        self.final_states[idx]
+    }
+
+    /// Moves the state at `idx`, keeping it in the unit square. The layout won't move it again.
+    pub fn set_position(&mut self, idx: usize, (x, y): (f64, f64)) {
+        self.positions[idx] = (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0));
+        self.pinned[idx] = true;
+    }
+
+    /// Moves the states to the positions saved in the file with [`StateDiagram::save_positions`],
+    /// and lays out the states that are not in it (e.g. added to the machine since) around them.
+    /// Saved states that are no longer in the machine are ignored.
+    pub fn load_positions(&mut self, path: &str) -> Result<(), String> {
+        let contents =
+            fs::read_to_string(path).map_err(|_| format!("Could not open the file \"{path}\""))?;
+
+        for (line_idx, line) in contents.lines().enumerate() {
+            let parse = || {
+                let mut parts = line.splitn(3, ' ');
+                let x = parts.next()?.parse::<f64>().ok()?;
+                let y = parts.next()?.parse::<f64>().ok()?;
+                Some((x, y, parts.next()?))
+            };
+            let Some((x, y, name)) = parse() else {
+                return Err(format!(
+                    "[turing_lib] Error while loading the diagram positions. Line {} should be `<x> <y> <state>`.",
+                    line_idx + 1
+                ));
+            };
+
+            if let Some(idx) = self.state_idx(name) {
+                self.set_position(idx, (x, y));
+            }
+        }
+
+        if self.pinned.contains(&false) {
+            self.lay_out();
+        }
+        Ok(())
+    }
+
+    /// Saves the position of every state in the file, one per line as `<x> <y> <state>`.
+    pub fn save_positions(&self, path: &str) -> Result<(), String> {
+        let contents = self
+            .states
+            .iter()
+            .zip(&self.positions)
+            .map(|(name, (x, y))| format!("{x} {y} {name}\n"))
+            .collect::<String>();
+
+        fs::write(path, contents).map_err(|_| format!("Could not write the file \"{path}\""))
+    }
+
+    /// Places the states that were not moved by hand: on a circle first, and then they are
+    /// pushed apart and pulled by their arrows, less and less at every iteration.
+    fn lay_out(&mut self) {
+        let count = self.states.len();
+        let radius = 0.5 - MARGIN;
+        for idx in 0..count {
+            if !self.pinned[idx] {
+                let angle = PI + 2.0 * PI * idx as f64 / count as f64;
+                self.positions[idx] = (0.5 + radius * angle.cos(), 0.5 + radius * angle.sin());
+            }
+        }
+
+        // Ideal distance between two states, so they fill the square
+        let k = (1.0 / count.max(1) as f64).sqrt();
+
+        for iteration in 0..ITERATIONS {
+            let temperature = 0.1 * (1.0 - iteration as f64 / ITERATIONS as f64);
+            let mut displacements = vec![(0.0, 0.0); count];
+
+            for (i, displacement) in displacements.iter_mut().enumerate() {
+                for (j, other) in self.positions.iter().enumerate() {
+                    if i == j {
+                        continue;
+                    }
+                    let (dx, dy, distance) = offset(*other, self.positions[i]);
+                    let force = k * k / distance;
+                    displacement.0 += dx / distance * force;
+                    displacement.1 += dy / distance * force;
+                }
+            }
+
+            for &(from, to) in &self.edges {
+                if from == to {
+                    continue;
+                }
+                let (dx, dy, distance) = offset(self.positions[from], self.positions[to]);
+                let force = distance * distance / k;
+                displacements[from].0 += dx / distance * force;
+                displacements[from].1 += dy / distance * force;
+                displacements[to].0 -= dx / distance * force;
+                displacements[to].1 -= dy / distance * force;
+            }
+
+            for (idx, (dx, dy)) in displacements.into_iter().enumerate() {
+                let length = dx.hypot(dy);
+                if self.pinned[idx] || length == 0.0 {
+                    continue;
+                }
+                let step = length.min(temperature);
+                let (x, y) = self.positions[idx];
+                self.positions[idx] = (
+                    (x + dx / length * step).clamp(MARGIN, 1.0 - MARGIN),
+                    (y + dy / length * step).clamp(MARGIN, 1.0 - MARGIN),
+                );
+            }
+        }
+    }
+}
+
+/// The file next to a machine file where the positions of its states are saved.
+pub fn positions_path(machine_file: &str) -> String {
+    format!("{machine_file}.layout")
+}
+
+/// The vector from `from` to `to`, and its length (never 0, so it can be divided by).
+fn offset(from: (f64, f64), to: (f64, f64)) -> (f64, f64, f64) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    (dx, dy, dx.hypot(dy).max(1e-3))
+}
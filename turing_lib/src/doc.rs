@@ -0,0 +1,290 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::{
+    machine::{State, Symbol, Transition, TransitionSource, TuringMachine},
+    parser,
+    report::STYLE,
+    svg::{self, escape},
+};
+
+/// Doc comments of a `.tng` file: the `//!` lines describe the machine, and the `///` lines
+/// right before a state declaration describe that state (or, without them, the description in
+/// its declaration, e.g. `state carry "propagates the carry" {`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MachineDocs {
+    pub description: String,
+    /// Every declared state with its doc comment (empty if none), in the order of the file.
+    pub states: Vec<(String, String)>,
+}
+
+pub fn extract(file_data: &str) -> MachineDocs {
+    let mut docs = MachineDocs::default();
+    let mut pending = vec![];
+
+    let doc_text = |line: &str, prefix: &str| {
+        let text = line.trim_start().trim_start_matches(prefix);
+        text.strip_prefix(' ').unwrap_or(text).to_string()
+    };
+
+    for line in file_data.lines() {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("//!") {
+            docs.description += &doc_text(line, "//!");
+            docs.description.push('\n');
+        } else if trimmed.starts_with("///") {
+            pending.push(doc_text(line, "///"));
+        } else if let Some((name, description)) = parser::declared_state(line) {
+            let doc = if pending.is_empty() {
+                description.unwrap_or_default().to_string()
+            } else {
+                pending.join("\n")
+            };
+            docs.states.push((name.to_string(), doc));
+            pending.clear();
+        } else {
+            pending.clear();
+        }
+    }
+
+    docs.description = docs.description.trim().to_string();
+    docs
+}
+
+/// A row of the transition table of a state: read, write, movement and next state.
+type Row = [String; 4];
+
+/// Generates the documentation of a Turing Machine as Markdown: its description, a summary of
+/// its configuration, its state diagram, and every state with its doc comment and transitions.
+pub fn markdown(file_data: &str) -> Result<String, String> {
+    let machine = TuringMachine::new_from_str(file_data, "")?;
+    let docs = extract(file_data);
+
+    let mut md = format!("# {}\n\n", machine.name);
+    if !docs.description.is_empty() {
+        let _ = write!(md, "{}\n\n", docs.description);
+    }
+    for (label, value) in summary(&machine) {
+        let _ = writeln!(md, "- {label}: {value}");
+    }
+
+    let _ = write!(
+        md,
+        "\n## State diagram\n\n{}\n## States\n",
+        svg::diagram(&machine)
+    );
+
+    for (name, doc) in &docs.states {
+        let _ = write!(md, "\n### `{name}`{}\n\n", state_kind(&machine, name));
+        if !doc.is_empty() {
+            let _ = write!(md, "{doc}\n\n");
+        }
+
+        let rows = rows(&machine, &machine.states[name]);
+        if rows.is_empty() {
+            md += "No transitions.\n";
+            continue;
+        }
+
+        md += "| Read | Write | Move | Next state |\n|---|---|---|---|\n";
+        for row in rows {
+            let _ = writeln!(
+                md,
+                "| {} |",
+                row.map(|cell| cell.replace('|', "\\|")).join(" | ")
+            );
+        }
+    }
+
+    Ok(md)
+}
+
+/// Generates the same documentation as [`markdown`], as a self-contained HTML file.
+pub fn html(file_data: &str) -> Result<String, String> {
+    let machine = TuringMachine::new_from_str(file_data, "")?;
+    let docs = extract(file_data);
+    let name = escape(&machine.name);
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{name}</title>\n{STYLE}</head>\n<body>\n<h1>{name}</h1>\n"
+    );
+    if !docs.description.is_empty() {
+        let _ = writeln!(html, "{}", paragraphs(&docs.description));
+    }
+
+    html += "<ul>\n";
+    for (label, value) in summary(&machine) {
+        let _ = writeln!(html, "<li>{label}: {}</li>", inline_html(&value));
+    }
+    html += "</ul>\n";
+
+    let _ = write!(
+        html,
+        "<h2>State diagram</h2>\n{}<h2>States</h2>\n",
+        svg::diagram(&machine)
+    );
+
+    for (name, doc) in &docs.states {
+        let _ = writeln!(
+            html,
+            "<h3><code>{}</code>{}</h3>",
+            escape(name),
+            state_kind(&machine, name)
+        );
+        if !doc.is_empty() {
+            let _ = writeln!(html, "{}", paragraphs(doc));
+        }
+
+        let rows = rows(&machine, &machine.states[name]);
+        if rows.is_empty() {
+            html += "<p>No transitions.</p>\n";
+            continue;
+        }
+
+        html += "<table>\n<tr><th>Read</th><th>Write</th><th>Move</th><th>Next state</th></tr>\n";
+        for row in rows {
+            let cells = row
+                .iter()
+                .map(|cell| format!("<td>{}</td>", inline_html(cell)))
+                .collect::<String>();
+            let _ = writeln!(html, "<tr>{cells}</tr>");
+        }
+        html += "</table>\n";
+    }
+
+    html += "</body>\n</html>\n";
+    Ok(html)
+}
+
+/// The summary of the configuration, as Markdown.
+fn summary(machine: &TuringMachine) -> Vec<(&'static str, String)> {
+    let final_states = machine
+        .final_states
+        .iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>();
+
+    vec![
+        ("Blank symbol", code(machine.blank_symbol)),
+        ("Head start", machine.head_start.to_string()),
+        ("Initial state", format!("`{}`", machine.initial_state)),
+        (
+            "Final states",
+            if final_states.is_empty() {
+                "none".to_string()
+            } else {
+                final_states.join(", ")
+            },
+        ),
+    ]
+}
+
+fn state_kind(machine: &TuringMachine, name: &str) -> &'static str {
+    let is_initial = machine.initial_state == name;
+    let is_final = machine.final_states.contains(name);
+
+    match (is_initial, is_final) {
+        (true, true) => " (initial and final)",
+        (true, false) => " (initial)",
+        (false, true) => " (final)",
+        (false, false) => "",
+    }
+}
+
+/// The transition table of a state, as Markdown cells.
+fn rows(machine: &TuringMachine, state: &State) -> Vec<Row> {
+    if let Some(query) = state.query() {
+        return vec![[
+            "query".to_string(),
+            String::new(),
+            String::new(),
+            format!("yes: `{}`, no: `{}`", query.yes_state(), query.no_state()),
+        ]];
+    }
+
+    if let Some(next_state) = state.emit() {
+        return vec![[
+            "emit".to_string(),
+            String::new(),
+            String::new(),
+            format!("`{next_state}`"),
+        ]];
+    }
+
+    let mut rows = vec![];
+
+    for source in state.transitions().keys().collect::<BTreeSet<_>>() {
+        rows.push(row(machine, source, &state.transitions()[source], None));
+    }
+
+    for source in state.random_transitions().keys().collect::<BTreeSet<_>>() {
+        for (weight, transition) in &state.random_transitions()[source] {
+            rows.push(row(machine, source, transition, Some(*weight)));
+        }
+    }
+
+    rows
+}
+
+fn row(
+    machine: &TuringMachine,
+    source: &TransitionSource,
+    transition: &Transition,
+    weight: Option<u32>,
+) -> Row {
+    let read = match source {
+        TransitionSource::Default => "default".to_string(),
+        TransitionSource::Mark(c) => code(*c),
+        TransitionSource::Blank => code(machine.blank_symbol),
+    };
+    let write = match transition.new_symbol() {
+        Symbol::Default => "default".to_string(),
+        Symbol::Mark(c) => code(c),
+        Symbol::Blank => code(machine.blank_symbol),
+    };
+    let movement = transition.movement_label();
+    let next_state = match weight {
+        Some(weight) => format!("`{}` (weight {weight})", transition.new_state()),
+        None => format!("`{}`", transition.new_state()),
+    };
+
+    [read, write, movement, next_state]
+}
+
+/// A symbol as Markdown inline code.
+fn code(c: char) -> String {
+    if c == '`' {
+        "`` ` ``".to_string()
+    } else {
+        format!("`{c}`")
+    }
+}
+
+/// Converts the Markdown inline code of a summary or table cell to HTML.
+fn inline_html(markdown: &str) -> String {
+    if markdown == "`` ` ``" {
+        return "<code>`</code>".to_string();
+    }
+
+    markdown
+        .split('`')
+        .enumerate()
+        .map(|(idx, part)| {
+            if idx % 2 == 1 {
+                format!("<code>{}</code>", escape(part))
+            } else {
+                escape(part)
+            }
+        })
+        .collect()
+}
+
+/// Doc comment text as HTML paragraphs, separated by empty lines.
+fn paragraphs(text: &str) -> String {
+    text.split("\n\n")
+        .map(|paragraph| format!("<p>{}</p>", escape(paragraph.trim())))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,59 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::{machine::TuringMachine, svg};
+
+impl TuringMachine {
+    /// The state diagram of the machine in the [Graphviz](https://graphviz.org) DOT language,
+    /// to be rendered with e.g. `dot -Tsvg`. Final states are double circles, the initial one is
+    /// filled and pointed at by an arrow, and every arrow is labeled with its transitions as
+    /// `read/write,movement`, where `*` stands for `default`.
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!(
+            "digraph \"{}\" {{\n\trankdir=LR;\n\tnode [shape=circle];\n\n",
+            escape(&self.name)
+        );
+
+        // An invisible node, for the arrow into the initial state
+        dot += "\t\"\" [shape=none, width=0, height=0, label=\"\"];\n";
+        let _ = writeln!(dot, "\t\"\" -> \"{}\";\n", escape(&self.initial_state));
+
+        for name in self.states.keys().collect::<BTreeSet<_>>() {
+            let mut attributes = vec![];
+            if self.final_states.contains(name) {
+                attributes.push("shape=doublecircle");
+            }
+            if *name == self.initial_state {
+                attributes.push("style=filled, fillcolor=lightblue");
+            }
+
+            if attributes.is_empty() {
+                let _ = writeln!(dot, "\t\"{}\";", escape(name));
+            } else {
+                let _ = writeln!(dot, "\t\"{}\" [{}];", escape(name), attributes.join(", "));
+            }
+        }
+        dot += "\n";
+
+        for ((from, to), labels) in svg::edges(self, "/") {
+            let label = labels
+                .iter()
+                .map(|label| escape(label))
+                .collect::<Vec<_>>()
+                .join("\\n");
+            let _ = writeln!(
+                dot,
+                "\t\"{}\" -> \"{}\" [label=\"{label}\"];",
+                escape(&from),
+                escape(&to)
+            );
+        }
+
+        dot += "}\n";
+        dot
+    }
+}
+
+/// Escapes the text of a quoted DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,219 @@
+//! The errors of parsing and using machines, with a kind to tell them apart and, for parsing,
+//! where the offending token is in the file.
+
+use std::{error::Error, fmt};
+
+/// What was wrong with the definition of a machine, see [`ParseError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseErrorKind {
+    /// An imported file is not valid YAML or XML.
+    InvalidSyntax,
+    /// There is no `config` block, or it is empty.
+    MissingConfig,
+    /// A required option of the `config` block was not provided.
+    MissingOption,
+    /// An option of the `config` block has an invalid value.
+    InvalidOption,
+    /// An option of the `config` block is not used by this kind of machine.
+    UnexpectedOption,
+    /// The `machine_type` is unknown, or not the kind of machine being parsed.
+    InvalidMachineType,
+    /// A line that can't be where it is, or doesn't have the expected values.
+    UnexpectedLine,
+    /// A symbol read, written, pushed or on top of the stack that can't be used.
+    InvalidSymbol,
+    InvalidHeadMovement,
+    InvalidWeight,
+    InvalidPacing,
+    /// A query or emit line which is not the only line of its state.
+    InvalidStateBody,
+    /// More than one transition for the same symbols in a deterministic machine.
+    Nondeterminism,
+    MissingInitialState,
+    DuplicateInitialState,
+    /// A transition into a state that is not defined.
+    UndefinedState,
+    /// An invalid, duplicated or undefined label of a counter machine program.
+    InvalidLabel,
+    InvalidRegister,
+    /// A counter machine program without instructions.
+    EmptyProgram,
+    /// The values given to start a machine with, like the registers of a counter machine, are
+    /// invalid.
+    InvalidInput,
+}
+
+/// An error while parsing the definition of a machine. When it is caused by a token of the
+/// file (a line, a symbol, an option value...), it has the token and where it is in the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    message: String,
+    token: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+impl ParseError {
+    pub(crate) fn new(kind: ParseErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            token: None,
+            line: None,
+            column: None,
+        }
+    }
+
+    pub(crate) fn at(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    pub(crate) fn with_location(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    pub(crate) fn at_location(self, location: &Location) -> Self {
+        self.at(&location.token)
+            .with_location(location.line, location.column)
+    }
+
+    pub fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    /// The description of the error, without its location.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// The line of the token in the file, starting at 1.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// The column of the token in its line, in chars and starting at 1.
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+}
+
+/// A token of a parsed file, and where it is, for the errors about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Location {
+    pub(crate) token: String,
+    pub(crate) line: usize,
+    pub(crate) column: usize, // In chars, starting at 1
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, " At line {line}, column {column}.")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<ParseError> for String {
+    fn from(err: ParseError) -> Self {
+        err.to_string()
+    }
+}
+
+/// What went wrong while loading or using a machine, see [`MachineError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MachineErrorKind {
+    /// The file of the machine could not be read.
+    Io,
+    /// The definition of the machine is invalid, see [`MachineError::parse_error`].
+    Parse,
+    /// A symbol that can't be written on the tape.
+    InvalidSymbol,
+    /// A state that the machine doesn't have.
+    UnknownState,
+    /// A cell of the tape that can't be changed, because it is too far from the tape or, for
+    /// linear bounded automata, not between the end markers.
+    InvalidCell,
+    /// The history is needed but not enabled.
+    HistoryDisabled,
+    /// A step that is not in the history, because it was not executed yet or is before the
+    /// first recorded one.
+    StepNotRecorded,
+}
+
+/// An error while loading or using a machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineError {
+    kind: MachineErrorKind,
+    message: String,
+    parse_error: Option<Box<ParseError>>, // Boxed to keep results small
+}
+
+impl MachineError {
+    pub(crate) fn new(kind: MachineErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            parse_error: None,
+        }
+    }
+
+    pub fn kind(&self) -> MachineErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The error of the parser, if the definition of the machine is invalid.
+    pub fn parse_error(&self) -> Option<&ParseError> {
+        self.parse_error.as_deref()
+    }
+}
+
+impl fmt::Display for MachineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.parse_error {
+            Some(parse_error) => write!(f, "{parse_error}"),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl Error for MachineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.parse_error
+            .as_deref()
+            .map(|parse_error| parse_error as &(dyn Error + 'static))
+    }
+}
+
+impl From<ParseError> for MachineError {
+    fn from(parse_error: ParseError) -> Self {
+        Self {
+            kind: MachineErrorKind::Parse,
+            message: parse_error.message.clone(),
+            parse_error: Some(Box::new(parse_error)),
+        }
+    }
+}
+
+impl From<MachineError> for String {
+    fn from(err: MachineError) -> Self {
+        err.to_string()
+    }
+}
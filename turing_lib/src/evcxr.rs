@@ -0,0 +1,69 @@
+use std::fmt::Write;
+
+use crate::{
+    machine::TuringMachine,
+    report::{self, Recording},
+    svg::{self, escape},
+};
+
+const MAX_DISPLAYED_FRAMES: usize = 200; // Longer runs show their first and last steps
+
+/// Shows HTML as the output of a Jupyter notebook cell run by the
+/// [evcxr](https://github.com/evcxr/evcxr) kernel, which calls the `evcxr_display` method of the
+/// value of the last expression of a cell.
+fn display_html(html: &str) {
+    println!("EVCXR_BEGIN_CONTENT text/html\n{html}\nEVCXR_END_CONTENT");
+}
+
+impl TuringMachine {
+    /// Shows the machine in a notebook: its name, its state diagram and its current
+    /// configuration.
+    pub fn evcxr_display(&self) {
+        display_html(&format!(
+            "<h3>{}</h3>\n{}\n{}",
+            escape(self.name()),
+            svg::diagram(self),
+            svg::configuration(self)
+        ));
+    }
+}
+
+impl Recording {
+    /// Shows the run in a notebook: its verdict, and a table with every configuration it went
+    /// through (only the first and last ones of long runs).
+    pub fn evcxr_display(&self) {
+        let mut html = format!(
+            "<p><b>{}</b> ({} steps)</p>\n<table>\n<tr><th>Step</th><th>State</th><th>Head</th><th>Tape</th></tr>\n",
+            report::verdict(self.outcome),
+            self.outcome.steps()
+        );
+
+        let half = MAX_DISPLAYED_FRAMES / 2;
+        let skipped = self.frames.len().saturating_sub(MAX_DISPLAYED_FRAMES);
+
+        for (idx, frame) in self.frames.iter().enumerate() {
+            if skipped > 0 && idx == half {
+                let _ = writeln!(
+                    html,
+                    "<tr><td colspan=\"4\">({skipped} steps not shown)</td></tr>"
+                );
+            }
+
+            if skipped > 0 && idx >= half && idx < half + skipped {
+                continue;
+            }
+
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td style=\"font-family: monospace\">{}</td></tr>",
+                frame.step,
+                escape(&frame.state),
+                frame.head_idx,
+                report::tape_html(frame)
+            );
+        }
+
+        html += "</table>";
+        display_html(&html);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{
+    hook::Intervention,
+    machine::{HeadMovement, Step, Symbol, TickResult},
+};
+
+/// Explains a tick in plain words, e.g. `In state q1 the head reads '0', writes '1', moves
+/// right, and enters state q2.` Returns `None` if the tick has no step to explain (the machine
+/// had already halted, or it is a kind of machine that doesn't describe its steps).
+pub fn explain(tick_result: &TickResult) -> Option<String> {
+    tick_result.step.as_ref().map(explain_step)
+}
+
+pub fn explain_step(step: &Step) -> String {
+    match step {
+        Step::Transition {
+            state,
+            read,
+            written,
+            head_movement,
+            distance,
+            new_state,
+            ..
+        } => {
+            let write = if written == read {
+                "leaves it as it is".to_string()
+            } else {
+                format!("writes {}", symbol_name(*written))
+            };
+            let movement = match (head_movement, distance) {
+                (HeadMovement::Stay, _) => "stays in place".to_string(),
+                (HeadMovement::Left, 1) => "moves left".to_string(),
+                (HeadMovement::Right, 1) => "moves right".to_string(),
+                (HeadMovement::Left, distance) => format!("moves {distance} cells left"),
+                (HeadMovement::Right, distance) => format!("moves {distance} cells right"),
+            };
+            let enter = if new_state == state {
+                format!("stays in state {new_state}")
+            } else {
+                format!("enters state {new_state}")
+            };
+
+            format!(
+                "In state {state} the head reads {}, {write}, {movement}, and {enter}.",
+                symbol_name(*read)
+            )
+        }
+        Step::Halt { state, read } => format!(
+            "In state {state} the head reads {}, but there is no transition for it, so the machine halts.",
+            symbol_name(*read)
+        ),
+        Step::Emit {
+            state,
+            word,
+            new_state,
+        } => format!("In state {state} the machine emits the word \"{word}\", and enters state {new_state}."),
+        Step::Query {
+            state,
+            word,
+            answer: Some((answer, new_state)),
+        } => format!(
+            "In state {state} the machine asks the oracle about the word \"{word}\", which answers {}, so it enters state {new_state}.",
+            if *answer { "yes" } else { "no" }
+        ),
+        Step::Query {
+            state,
+            word,
+            answer: None,
+        } => format!(
+            "In state {state} the machine would ask an oracle about the word \"{word}\", but it has none, so it halts."
+        ),
+        Step::OutOfBounds {
+            state,
+            read,
+            head_movement,
+        } => format!(
+            "In state {state} the head reads {} and would move {} past the end marker, so the machine rejects.",
+            symbol_name(*read),
+            if *head_movement == HeadMovement::Left { "left" } else { "right" }
+        ),
+        Step::Intervention(Intervention::SetCell { cell, symbol }) => {
+            format!("A hook writes {} on cell {cell}.", symbol_name(*symbol))
+        }
+        Step::Intervention(Intervention::SetState(state)) => {
+            format!("A hook moves the machine to state {state}.")
+        }
+    }
+}
+
+/// Writes a tick in the formal notation of the transition function, e.g. `δ(q1, 0) = (q2, 1, R)`,
+/// or `δ(q1, 0) is undefined` when the machine halts for lack of a transition. Blanks are written
+/// with `blank_symbol`. Returns `None` if the tick has no such step (e.g. emits and oracle
+/// queries, which [`explain`] describes in plain words).
+pub fn formula(tick_result: &TickResult, blank_symbol: char) -> Option<String> {
+    tick_result
+        .step
+        .as_ref()
+        .and_then(|step| formula_step(step, blank_symbol))
+}
+
+pub fn formula_step(step: &Step, blank_symbol: char) -> Option<String> {
+    let symbol = |symbol: Symbol| match symbol {
+        Symbol::Mark(c) => c,
+        _ => blank_symbol,
+    };
+
+    match step {
+        Step::Transition {
+            state,
+            read,
+            written,
+            head_movement,
+            distance,
+            new_state,
+            ..
+        } => {
+            let movement = match head_movement {
+                HeadMovement::Left => "L",
+                HeadMovement::Right => "R",
+                HeadMovement::Stay => "S",
+            };
+            let distance = if *distance > 1 && *head_movement != HeadMovement::Stay {
+                distance.to_string()
+            } else {
+                String::new()
+            };
+
+            Some(format!(
+                "δ({state}, {}) = ({new_state}, {}, {movement}{distance})",
+                symbol(*read),
+                symbol(*written)
+            ))
+        }
+        Step::Halt { state, read } => Some(format!("δ({state}, {}) is undefined", symbol(*read))),
+        _ => None,
+    }
+}
+
+fn symbol_name(symbol: Symbol) -> String {
+    match symbol {
+        Symbol::Mark(c) => format!("'{c}'"),
+        Symbol::Blank => "a blank".to_string(),
+        Symbol::Default => "any symbol".to_string(),
+    }
+}
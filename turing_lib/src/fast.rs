@@ -0,0 +1,735 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    time::Instant,
+};
+
+use crate::{
+    machine::{HeadMovement, Symbol, TransitionSource, TuringMachine},
+    runner::{Progress, ProgressInterval, ProgressThrottle, RunLimits, RunOutcome},
+};
+
+type Runs = Vec<(Symbol, usize)>;
+
+const TIMEOUT_CHECK_INTERVAL: usize = 4096; // Iterations between every check of the timeout and the cancellation
+const MAX_CYCLE_LENGTH: usize = 4096; // Macro-steps, so looking for a cycle never takes long
+const MAX_CYCLE_FAILURES: u32 = 16; // Per situation, so machines without cycles aren't slowed down
+
+/// What a cycle is looked for from, and what must repeat for one to be found: the state, the
+/// symbol under the head, and the number of runs and the nearest one at each side of the head.
+type Situation = (usize, Symbol, usize, Option<Symbol>, usize, Option<Symbol>);
+
+#[derive(Clone, Copy)]
+struct FastTransition {
+    new_symbol: Symbol, // Symbol::Default keeps the symbol read
+    head_movement: HeadMovement,
+    new_state: usize,
+}
+
+struct FastState {
+    transitions: HashMap<Symbol, FastTransition>,
+    default: Option<FastTransition>,
+    is_final: bool,
+}
+
+impl FastState {
+    fn transition_for(&self, symbol: Symbol) -> Option<FastTransition> {
+        self.transitions.get(&symbol).copied().or(self.default)
+    }
+}
+
+/// Tape stored as runs of equal symbols at each side of the head, with the nearest runs last.
+/// Blanks beyond the last run of a side are not stored.
+struct RunTape {
+    left: Runs,
+    head: Symbol,
+    right: Runs,
+}
+
+impl RunTape {
+    fn new(machine: &TuringMachine) -> Self {
+        let content = machine.tape().get_content();
+        let head_idx = machine.head_idx();
+
+        let mut tape = Self {
+            left: vec![],
+            head: content.get(head_idx).copied().unwrap_or(Symbol::Blank),
+            right: vec![],
+        };
+
+        for symbol in content.iter().take(head_idx) {
+            Self::push(&mut tape.left, *symbol, 1);
+        }
+
+        for symbol in content.iter().skip(head_idx + 1).rev() {
+            Self::push(&mut tape.right, *symbol, 1);
+        }
+
+        tape
+    }
+
+    fn push(side: &mut Runs, symbol: Symbol, count: usize) {
+        if count == 0 || (side.is_empty() && symbol == Symbol::Blank) {
+            return;
+        }
+
+        match side.last_mut() {
+            Some((last_symbol, last_count)) if *last_symbol == symbol => *last_count += count,
+            _ => side.push((symbol, count)),
+        }
+    }
+
+    fn pop(side: &mut Runs) -> Symbol {
+        match side.last_mut() {
+            None => Symbol::Blank,
+            Some((symbol, count)) => {
+                let symbol = *symbol;
+                *count -= 1;
+                if *count == 0 {
+                    side.pop();
+                }
+                symbol
+            }
+        }
+    }
+
+    /// Number of cells stored, from the first run on the left to the last run on the right.
+    fn len(&self) -> usize {
+        let cells = |side: &Runs| side.iter().map(|(_, count)| count).sum::<usize>();
+        cells(&self.left) + 1 + cells(&self.right)
+    }
+
+    fn situation(&self, state: usize) -> Situation {
+        let nearest = |side: &Runs| side.last().map(|(symbol, _)| *symbol);
+        (
+            state,
+            self.head,
+            self.left.len(),
+            nearest(&self.left),
+            self.right.len(),
+            nearest(&self.right),
+        )
+    }
+
+    fn sides(&mut self, head_movement: HeadMovement) -> (&mut Runs, &mut Runs) {
+        match head_movement {
+            HeadMovement::Left => (&mut self.right, &mut self.left),
+            _ => (&mut self.left, &mut self.right),
+        }
+    }
+
+    /// Number of cells equal to the head symbol from the head (included) towards the given
+    /// direction, or `None` if they never end (blanks up to the end of the tape).
+    fn run_length(&mut self, head_movement: HeadMovement) -> Option<usize> {
+        let head = self.head;
+        let (_, ahead) = self.sides(head_movement);
+
+        let run = match ahead.last() {
+            Some((symbol, count)) if *symbol == head => *count,
+            _ => 0,
+        };
+
+        if head == Symbol::Blank && ahead.len() <= usize::from(run > 0) {
+            None
+        } else {
+            Some(run + 1)
+        }
+    }
+
+    /// Writes `new_symbol` over the next `count` cells (the head included) towards the given
+    /// direction, which must hold the head symbol, leaving the head after them.
+    fn sweep(&mut self, new_symbol: Symbol, head_movement: HeadMovement, count: usize) {
+        let head = self.head;
+        let (behind, ahead) = self.sides(head_movement);
+
+        Self::push(behind, new_symbol, count);
+
+        let mut skipped = count - 1;
+        if let Some((symbol, run)) = ahead.last_mut() {
+            if *symbol == head {
+                let taken = skipped.min(*run);
+                *run -= taken;
+                skipped -= taken;
+                if *run == 0 {
+                    ahead.pop();
+                }
+            }
+        }
+
+        // The only symbols left to skip are the blanks beyond the end of the tape
+        debug_assert!(skipped == 0 || head == Symbol::Blank);
+
+        self.head = Self::pop(ahead);
+    }
+}
+
+/// A number of cells while looking for a cycle: the number of cells of one of the runs of the
+/// tape when the cycle started (see [`CycleRun`]), if any, plus a constant.
+#[derive(Clone, Copy)]
+struct Cells {
+    run: Option<usize>,
+    constant: i128,
+}
+
+impl Cells {
+    fn constant(constant: i128) -> Self {
+        Self {
+            run: None,
+            constant,
+        }
+    }
+}
+
+/// A run of the tape that a cycle goes through.
+struct CycleRun {
+    left: bool,
+    idx: usize,
+    count: usize,
+    /// The least number of cells it must have for the cycle to take the same steps.
+    min_count: i128,
+    /// How many cells it gains (or loses, if negative) every time the cycle is taken.
+    growth: i128,
+}
+
+/// The runs of a side of the head while looking for a cycle: the ones of the tape that haven't
+/// been reached yet, and the ones the machine has been through above them, nearest last.
+struct CycleSide<'t> {
+    left: bool,
+    tape: &'t Runs,
+    untouched: usize,
+    touched: Vec<(Symbol, Cells)>,
+}
+
+impl CycleSide<'_> {
+    fn len(&self) -> usize {
+        self.untouched + self.touched.len()
+    }
+}
+
+/// A sequence of steps that takes the machine back to the same state and the same runs of
+/// symbols on its tape, except for the number of cells of some of them, which every pass of
+/// the cycle changes by the same amount. It can then be taken many times at once, as long as
+/// none of those runs gets too short for it.
+///
+/// The cycle is found by running the machine with the number of cells of every run it reaches
+/// written in terms of the number it had at the start. It is not a cycle if the steps depend
+/// on those numbers other than through the length of sweeps, i.e. if a run that was reached
+/// is used up cell by cell, or if two of them merge.
+struct Cycle {
+    runs: Vec<CycleRun>,
+    /// Steps taken by a pass: a constant, plus the number of cells of every run (see
+    /// [`Cycle::runs`]) when the pass starts times its coefficient.
+    steps: i128,
+    steps_per_cell: Vec<i128>,
+}
+
+/// The machine run from a configuration looking for a cycle, see [`Cycle`].
+struct CycleSearch<'t> {
+    left: CycleSide<'t>,
+    right: CycleSide<'t>,
+    head: Symbol,
+    cycle: Cycle,
+}
+
+impl<'t> CycleSearch<'t> {
+    fn new(tape: &'t RunTape) -> Self {
+        let side = |left: bool, tape: &'t Runs| CycleSide {
+            left,
+            tape,
+            untouched: tape.len(),
+            touched: vec![],
+        };
+
+        Self {
+            left: side(true, &tape.left),
+            right: side(false, &tape.right),
+            head: tape.head,
+            cycle: Cycle {
+                runs: vec![],
+                steps: 0,
+                steps_per_cell: vec![],
+            },
+        }
+    }
+
+    fn count(runs: &[CycleRun], cells: Cells) -> i128 {
+        cells.constant + cells.run.map_or(0, |run| runs[run].count as i128)
+    }
+
+    /// Moves the nearest run of the tape that hasn't been reached yet to the touched ones.
+    fn reach(runs: &mut Vec<CycleRun>, side: &mut CycleSide) {
+        side.untouched -= 1;
+        let (symbol, count) = side.tape[side.untouched];
+
+        side.touched.push((
+            symbol,
+            Cells {
+                run: Some(runs.len()),
+                constant: 0,
+            },
+        ));
+        runs.push(CycleRun {
+            left: side.left,
+            idx: side.untouched,
+            count,
+            min_count: 1,
+            growth: 0,
+        });
+    }
+
+    /// Like [`RunTape::push`], or `None` if two runs of the tape merge.
+    fn push(
+        runs: &mut Vec<CycleRun>,
+        side: &mut CycleSide,
+        symbol: Symbol,
+        cells: Cells,
+    ) -> Option<()> {
+        if side.len() == 0 && symbol == Symbol::Blank {
+            return Some(());
+        }
+
+        if side.touched.is_empty() && side.untouched > 0 {
+            Self::reach(runs, side);
+        }
+
+        match side.touched.last_mut() {
+            Some((last_symbol, last_cells)) if *last_symbol == symbol => {
+                if last_cells.run.is_some() && cells.run.is_some() {
+                    return None;
+                }
+                last_cells.run = last_cells.run.or(cells.run);
+                last_cells.constant += cells.constant;
+            }
+            _ => side.touched.push((symbol, cells)),
+        }
+
+        Some(())
+    }
+
+    /// Like [`RunTape::pop`], or `None` if it uses up a run of the tape.
+    fn pop(runs: &mut Vec<CycleRun>, side: &mut CycleSide) -> Option<Symbol> {
+        if side.touched.is_empty() {
+            if side.untouched == 0 {
+                return Some(Symbol::Blank);
+            }
+            Self::reach(runs, side);
+        }
+
+        let (symbol, cells) = side.touched.last_mut()?;
+        let symbol = *symbol;
+        cells.constant -= 1;
+
+        match cells.run {
+            None if cells.constant == 0 => {
+                side.touched.pop();
+            }
+            None => {}
+            Some(_) if Self::count(runs, *cells) == 0 => return None,
+            Some(run) => runs[run].min_count = runs[run].min_count.max(1 - cells.constant),
+        }
+
+        Some(symbol)
+    }
+
+    /// Moves the head like [`RunTape::sweep`], over the whole run ahead if `whole_run`, and
+    /// returns the number of steps it takes, or `None` if it never ends.
+    fn sweep(
+        &mut self,
+        new_symbol: Symbol,
+        head_movement: HeadMovement,
+        whole_run: bool,
+    ) -> Option<Cells> {
+        let head = self.head;
+        let runs = &mut self.cycle.runs;
+        let (behind, ahead) = match head_movement {
+            HeadMovement::Left => (&mut self.right, &mut self.left),
+            _ => (&mut self.left, &mut self.right),
+        };
+
+        if ahead.touched.is_empty() && ahead.untouched > 0 {
+            Self::reach(runs, ahead);
+        }
+
+        let mut cells = Cells::constant(1);
+        if whole_run {
+            if head == Symbol::Blank
+                && ahead.len()
+                    <= usize::from(
+                        matches!(ahead.touched.last(), Some((symbol, _)) if *symbol == head),
+                    )
+            {
+                return None;
+            }
+            if let Some((symbol, run_cells)) = ahead.touched.last() {
+                if *symbol == head {
+                    cells = Cells {
+                        run: run_cells.run,
+                        constant: run_cells.constant + 1,
+                    };
+                    ahead.touched.pop();
+                }
+            }
+        }
+
+        Self::push(runs, behind, new_symbol, cells)?;
+        self.head = Self::pop(runs, ahead)?;
+        Some(cells)
+    }
+
+    /// The cycle, if the machine is back to where the search started, with the same runs.
+    fn cycle(&mut self) -> Option<&Cycle> {
+        for side in [&self.left, &self.right] {
+            if side.len() != side.tape.len() {
+                return None;
+            }
+
+            for (offset, (symbol, cells)) in side.touched.iter().enumerate() {
+                let idx = side.untouched + offset;
+                let run = &self.cycle.runs[cells.run?];
+                if *symbol != side.tape[idx].0 || run.left != side.left || run.idx != idx {
+                    return None;
+                }
+            }
+        }
+
+        for side in [&self.left, &self.right] {
+            for (_, cells) in &side.touched {
+                if let Some(run) = cells.run {
+                    self.cycle.runs[run].growth = cells.constant;
+                }
+            }
+        }
+
+        Some(&self.cycle)
+    }
+}
+
+impl Cycle {
+    /// Looks for a cycle of at most `max_length` macro-steps from the configuration of the
+    /// machine, taking the same macro-steps as [`run_with_progress`].
+    fn find(states: &[FastState], tape: &RunTape, state: usize, max_length: usize) -> Option<Self> {
+        let mut search = CycleSearch::new(tape);
+        let mut current_state = state;
+
+        for _ in 0..max_length {
+            let transition = states[current_state].transition_for(search.head)?;
+            let new_symbol = match transition.new_symbol {
+                Symbol::Default => search.head,
+                new_symbol => new_symbol,
+            };
+            let self_loop = transition.new_state == current_state;
+
+            let steps = match transition.head_movement {
+                HeadMovement::Stay if self_loop && new_symbol == search.head => return None,
+                HeadMovement::Stay => {
+                    search.head = new_symbol;
+                    Cells::constant(1)
+                }
+                head_movement => search.sweep(new_symbol, head_movement, self_loop)?,
+            };
+
+            search.cycle.steps += steps.constant;
+            if let Some(run) = steps.run {
+                search
+                    .cycle
+                    .steps_per_cell
+                    .resize(search.cycle.runs.len(), 0);
+                search.cycle.steps_per_cell[run] += 1;
+            }
+
+            current_state = transition.new_state;
+            if current_state == state && search.head == tape.head && search.cycle().is_some() {
+                search
+                    .cycle
+                    .steps_per_cell
+                    .resize(search.cycle.runs.len(), 0);
+                return Some(search.cycle);
+            }
+        }
+
+        None
+    }
+
+    /// How many times in a row the cycle can be taken, or `None` if forever.
+    fn passes(&self) -> Option<i128> {
+        self.runs
+            .iter()
+            .filter(|run| run.growth < 0)
+            .map(|run| (run.count as i128 - run.min_count) / -run.growth + 1)
+            .min()
+    }
+
+    /// The steps taken by the given number of passes, or `None` if there are too many to count.
+    fn steps(&self, passes: i128) -> Option<i128> {
+        let mut steps = self.steps.checked_mul(passes)?;
+
+        // Every pass starts with `growth` more cells than the last one
+        let later_passes = passes.checked_mul(passes - 1)? / 2;
+        for (run, per_cell) in self.runs.iter().zip(&self.steps_per_cell) {
+            let cells = (run.count as i128)
+                .checked_mul(passes)?
+                .checked_add(run.growth.checked_mul(later_passes)?)?;
+            steps = steps.checked_add(per_cell.checked_mul(cells)?)?;
+        }
+
+        Some(steps)
+    }
+
+    /// The most passes that take at most `max_steps` steps, and the steps they take.
+    fn passes_within(&self, max_passes: i128, max_steps: i128) -> (i128, i128) {
+        let (mut low, mut high) = (0, max_passes);
+        while low < high {
+            let mid = low + (high - low + 1) / 2;
+            match self.steps(mid) {
+                Some(steps) if steps <= max_steps => low = mid,
+                _ => high = mid - 1,
+            }
+        }
+
+        (low, self.steps(low).unwrap_or_default())
+    }
+
+    /// Takes the cycle `passes` times over the tape.
+    fn take(&self, tape: &mut RunTape, passes: i128) {
+        for run in &self.runs {
+            let side = if run.left {
+                &mut tape.left
+            } else {
+                &mut tape.right
+            };
+            side[run.idx].1 = (run.count as i128 + run.growth * passes) as usize;
+        }
+    }
+}
+
+/// Runs a Turing Machine from its current configuration until it halts or one of the limits
+/// is reached, like [`crate::runner::run`] does, but much faster for machines that spend most of
+/// their steps sweeping over long blocks of cells, or going through the same cycle of states
+/// over and over, like counters.
+///
+/// The tape is stored as runs of equal symbols, and every time the machine reaches a situation
+/// it has already been in one step before (the same state reading the same symbol, after moving
+/// in the same direction), the whole repetition is skipped at once: the machine sweeps the
+/// rest of the run in a single macro-step. When it comes back to a situation it was in some
+/// macro-steps before (the same state and symbol under the head, with as many runs at each
+/// side), the macro-steps in between are checked to be a cycle that only makes some runs longer
+/// or shorter, and it is taken as many times as those runs allow at once. The step count is
+/// the same as if every step had been taken.
+///
+/// If the machine would sweep forever over the blanks at an end of the tape, stay forever in
+/// the same cell, or go through a cycle that never makes its runs shorter, the run ends with
+/// [`RunOutcome::NeverHalts`] straight away.
+///
+/// The machine itself is not modified. Linear bounded automata, tape cell and memory limits, and machines
+/// with oracle queries, emitting states, random transitions or head jumps are not supported.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip(machine),
+        fields(machine = machine.name()),
+        ret,
+        err(level = "debug")
+    )
+)]
+pub fn run(machine: &TuringMachine, limits: &RunLimits) -> Result<RunOutcome, String> {
+    run_with_progress(machine, limits, ProgressInterval::default(), |_| {})
+}
+
+/// Like [`run`], but calls `callback` with the progress of the run as often as `interval` says
+/// (see [`crate::runner::run_with_progress`]). The reported tape cells don't count the blanks at
+/// the ends of the tape.
+pub fn run_with_progress<F>(
+    machine: &TuringMachine,
+    limits: &RunLimits,
+    interval: ProgressInterval,
+    mut callback: F,
+) -> Result<RunOutcome, String>
+where
+    F: FnMut(Progress),
+{
+    if machine.is_bounded() {
+        return Err("[turing_lib] Error while running the machine. Linear bounded automata are not supported by the fast runner.".to_string());
+    }
+
+    if limits.tape_cell_limit(machine).is_some() {
+        return Err("[turing_lib] Error while running the machine. Tape cell limits are not supported by the fast runner.".to_string());
+    }
+
+    if limits.max_memory.is_some() {
+        return Err("[turing_lib] Error while running the machine. Memory limits are not supported by the fast runner.".to_string());
+    }
+
+    let names = machine.states().keys().cloned().collect::<Vec<_>>();
+    let indices = names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| (name.as_str(), idx))
+        .collect::<HashMap<_, _>>();
+
+    let mut states = Vec::with_capacity(names.len());
+
+    for name in &names {
+        let state = &machine.states()[name];
+
+        if state.query().is_some() || state.emit().is_some() || state.is_random() {
+            return Err(format!("[turing_lib] Error while running the machine. The state \"{name}\" has a query, an emit or random transitions, which are not supported by the fast runner."));
+        }
+
+        let mut fast_state = FastState {
+            transitions: HashMap::new(),
+            default: None,
+            is_final: machine.final_states().contains(name),
+        };
+
+        for (source, transition) in state.transitions() {
+            if transition.distance() > 1 {
+                return Err(format!("[turing_lib] Error while running the machine. The state \"{name}\" has head jumps, which are not supported by the fast runner."));
+            }
+
+            let fast_transition = FastTransition {
+                new_symbol: transition.new_symbol(),
+                head_movement: transition.head_movement(),
+                new_state: indices[transition.new_state()],
+            };
+
+            match source {
+                TransitionSource::Default => fast_state.default = Some(fast_transition),
+                TransitionSource::Mark(c) => {
+                    fast_state
+                        .transitions
+                        .insert(Symbol::Mark(*c), fast_transition);
+                }
+                TransitionSource::Blank => {
+                    fast_state
+                        .transitions
+                        .insert(Symbol::Blank, fast_transition);
+                }
+            }
+        }
+
+        states.push(fast_state);
+    }
+
+    let mut current_state = indices[machine.current_state_name()];
+    let mut tape = RunTape::new(machine);
+
+    let start = Instant::now();
+    let mut steps: usize = 0;
+    let mut iterations: usize = 0;
+    let mut throttle = ProgressThrottle::new(interval);
+    let mut seen: HashMap<Situation, (usize, u32)> = HashMap::new(); // Last iteration, failures
+
+    if machine.is_halted() {
+        return Ok(RunOutcome::Halted {
+            accepted: machine.is_accepting(),
+            steps,
+        });
+    }
+
+    loop {
+        if limits.max_steps.is_some_and(|max| steps >= max) {
+            return Ok(RunOutcome::StepLimitReached { steps });
+        }
+
+        iterations += 1;
+        if iterations.is_multiple_of(TIMEOUT_CHECK_INTERVAL) {
+            if limits
+                .timeout
+                .is_some_and(|timeout| start.elapsed() >= timeout)
+            {
+                return Ok(RunOutcome::TimedOut { steps });
+            }
+
+            if limits.is_cancelled() {
+                return Ok(RunOutcome::Cancelled { steps });
+            }
+        }
+
+        if throttle.is_due(steps) {
+            callback(Progress {
+                steps,
+                tape_cells: tape.len(),
+                state: &names[current_state],
+            });
+        }
+
+        match seen.entry(tape.situation(current_state)) {
+            Entry::Vacant(entry) => {
+                entry.insert((iterations, 0));
+            }
+            Entry::Occupied(mut entry) => {
+                let (last, failures) = *entry.get();
+                let length = iterations - last;
+
+                if failures < MAX_CYCLE_FAILURES && length <= MAX_CYCLE_LENGTH {
+                    match Cycle::find(&states, &tape, current_state, length) {
+                        Some(cycle) => {
+                            let Some(max_passes) = cycle.passes() else {
+                                return Ok(RunOutcome::NeverHalts { steps });
+                            };
+
+                            let remaining_steps = limits.max_steps.unwrap_or(usize::MAX) - steps;
+                            let (passes, cycle_steps) =
+                                cycle.passes_within(max_passes, remaining_steps as i128);
+                            cycle.take(&mut tape, passes);
+                            steps += cycle_steps as usize;
+                            entry.insert((iterations, failures));
+
+                            if limits.max_steps.is_some_and(|max| steps >= max) {
+                                return Ok(RunOutcome::StepLimitReached { steps });
+                            }
+                        }
+                        None => {
+                            entry.insert((iterations, failures + 1));
+                        }
+                    }
+                } else {
+                    entry.insert((iterations, failures));
+                }
+            }
+        }
+
+        let state = &states[current_state];
+        let Some(transition) = state.transition_for(tape.head) else {
+            return Ok(RunOutcome::Halted {
+                accepted: state.is_final,
+                steps,
+            });
+        };
+
+        let new_symbol = match transition.new_symbol {
+            Symbol::Default => tape.head,
+            new_symbol => new_symbol,
+        };
+
+        let remaining_steps = limits.max_steps.map_or(usize::MAX, |max| max - steps);
+
+        if transition.new_state == current_state {
+            let repetitions = match transition.head_movement {
+                HeadMovement::Stay if new_symbol == tape.head => None,
+                HeadMovement::Stay => Some(1),
+                head_movement => tape.run_length(head_movement),
+            };
+
+            let Some(repetitions) = repetitions else {
+                // The machine never leaves this state
+                return Ok(RunOutcome::NeverHalts { steps });
+            };
+
+            if !matches!(transition.head_movement, HeadMovement::Stay) {
+                let repetitions = repetitions.min(remaining_steps);
+                tape.sweep(new_symbol, transition.head_movement, repetitions);
+                steps += repetitions;
+                continue;
+            }
+        }
+
+        match transition.head_movement {
+            HeadMovement::Stay => tape.head = new_symbol,
+            head_movement => tape.sweep(new_symbol, head_movement, 1),
+        }
+
+        current_state = transition.new_state;
+        steps += 1;
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs,
+};
+
+use crate::{
+    error::{MachineError, MachineErrorKind, ParseError},
+    machine::{HeadMovement, Symbol, TickResult},
+    parser,
+    tape::Tape,
+};
+
+pub struct FiniteState {
+    name: String,
+    transitions: HashMap<Option<char>, Vec<String>>, // None for epsilon transitions
+}
+
+impl FiniteState {
+    pub fn new(name: String, transitions: HashMap<Option<char>, Vec<String>>) -> Self {
+        Self { name, transitions }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn transitions(&self) -> &HashMap<Option<char>, Vec<String>> {
+        &self.transitions
+    }
+}
+
+/// A finite automaton, which reads its input from left to right without writing, and decides
+/// once the whole input has been read. Several transitions for the same symbol (and epsilon
+/// transitions) are allowed, in which case every possible state is followed at once.
+pub struct FiniteAutomaton {
+    name: String,
+    blank_symbol: char,
+
+    states: HashMap<String, FiniteState>,
+    final_states: HashSet<String>,
+    initial_state: String,
+
+    head_idx: usize,
+    current_states: BTreeSet<String>,
+    current_states_name: String,
+    input: Tape,
+
+    halted: bool,
+}
+
+impl FiniteAutomaton {
+    pub(crate) fn new(
+        name: String,
+        blank_symbol: char,
+        states: HashMap<String, FiniteState>,
+        final_states: HashSet<String>,
+        initial_state: String,
+    ) -> Self {
+        let mut automaton = Self {
+            name,
+            blank_symbol,
+
+            states,
+            final_states,
+            initial_state,
+
+            head_idx: 0,
+            current_states: BTreeSet::new(),
+            current_states_name: String::new(),
+            input: Tape::new(vec![]),
+
+            halted: false,
+        };
+
+        automaton.set_current_states([automaton.initial_state.clone()].into());
+        automaton
+    }
+
+    pub fn new_from_file(
+        filename: &str,
+        input_data: &str,
+    ) -> Result<FiniteAutomaton, MachineError> {
+        let file_data = fs::read_to_string(filename).map_err(|_| {
+            MachineError::new(
+                MachineErrorKind::Io,
+                format!("Could not open the file \"{}\"", filename),
+            )
+        })?;
+
+        Ok(Self::new_from_str(&file_data, input_data)?)
+    }
+
+    pub fn new_from_str(file_data: &str, input_data: &str) -> Result<FiniteAutomaton, ParseError> {
+        let file_lines = parser::file_lines(file_data);
+
+        let mut automaton = parser::parse_finite_file(&file_lines)?;
+        automaton.input = Tape::parse(input_data, automaton.blank_symbol);
+
+        Ok(automaton)
+    }
+
+    pub fn tick(&mut self) -> TickResult {
+        let result = TickResult {
+            written_different_symbol: false,
+            extended_tape_on_side: None,
+            head_movement: HeadMovement::Stay,
+            step: None,
+        };
+
+        if self.halted {
+            return result;
+        }
+
+        let current_input = match self.input.get_content().get(self.head_idx) {
+            Some(Symbol::Mark(c)) => Some(*c),
+            Some(_) => None, // Blank symbols in the input can't be read by any transition
+            None => {
+                self.halted = true;
+                return result;
+            }
+        };
+
+        let next_states = current_input
+            .map(|input| {
+                self.current_states
+                    .iter()
+                    .filter_map(|state| self.states[state].transitions.get(&Some(input)))
+                    .flatten()
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        self.set_current_states(next_states);
+        self.head_idx += 1;
+
+        // Without any possible state, the rest of the input can't change the verdict
+        if self.current_states.is_empty() {
+            self.halted = true;
+        }
+
+        TickResult {
+            head_movement: HeadMovement::Right,
+            ..result
+        }
+    }
+
+    fn set_current_states(&mut self, states: BTreeSet<String>) {
+        let mut pending = states.iter().cloned().collect::<Vec<_>>();
+        let mut closure = states;
+
+        while let Some(state) = pending.pop() {
+            for next_state in self.states[&state]
+                .transitions
+                .get(&None)
+                .into_iter()
+                .flatten()
+            {
+                if closure.insert(next_state.clone()) {
+                    pending.push(next_state.clone());
+                }
+            }
+        }
+
+        self.current_states_name = match closure.len() {
+            1 => closure.first().unwrap().clone(),
+            _ => format!(
+                "{{{}}}",
+                closure.iter().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        };
+        self.current_states = closure;
+    }
+
+    /// A finite automaton accepts when, after reading all of its input, any of its current states is final.
+    pub fn is_accepting(&self) -> bool {
+        self.halted
+            && self.head_idx >= self.input.len()
+            && self
+                .current_states
+                .iter()
+                .any(|state| self.final_states.contains(state))
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.states.values().all(|state| {
+            !state.transitions.contains_key(&None)
+                && state.transitions.values().all(|targets| targets.len() == 1)
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn blank_symbol(&self) -> char {
+        self.blank_symbol
+    }
+
+    pub fn head_idx(&self) -> usize {
+        self.head_idx
+    }
+
+    /// Name of the current state, or the set of current states (e.g. `{q0, q2}`) when there are several.
+    pub fn current_state_name(&self) -> &str {
+        &self.current_states_name
+    }
+
+    pub fn current_states(&self) -> &BTreeSet<String> {
+        &self.current_states
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn input(&self) -> &Tape {
+        &self.input
+    }
+
+    pub fn states(&self) -> &HashMap<String, FiniteState> {
+        &self.states
+    }
+}
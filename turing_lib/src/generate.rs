@@ -0,0 +1,214 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rhai::{Dynamic, Engine, EvalAltResult, INT};
+
+use crate::{machine::TuringMachine, parser, random::Rng};
+
+const MAX_OPERATIONS: u64 = 10_000_000; // So a generator script can't hang
+
+struct GeneratedState {
+    name: String,
+    is_initial: bool,
+    is_final: bool,
+    transitions: Vec<String>,
+}
+
+struct Generated {
+    name: String,
+    blank_symbol: char,
+    head_start: INT,
+    states: Vec<GeneratedState>,
+}
+
+impl Generated {
+    fn state(&mut self, name: &str) -> Result<&mut GeneratedState, Box<EvalAltResult>> {
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || "{},".contains(c)) {
+            return Err(format!("Invalid state name \"{name}\"").into());
+        }
+
+        let idx = match self.states.iter().position(|state| state.name == name) {
+            Some(idx) => idx,
+            None => {
+                self.states.push(GeneratedState {
+                    name: name.to_string(),
+                    is_initial: false,
+                    is_final: false,
+                    transitions: vec![],
+                });
+                self.states.len() - 1
+            }
+        };
+
+        Ok(&mut self.states[idx])
+    }
+
+    fn to_tng(&self) -> String {
+        let mut tng = format!(
+            "config {{\n\tname: \"{}\"\n\tblank_symbol: '{}'\n\thead_start: {}\n}}\n\nstates {{\n",
+            self.name, self.blank_symbol, self.head_start
+        );
+
+        for state in &self.states {
+            let header = match (state.is_initial, state.is_final) {
+                (true, true) => format!("state {} is initial and final", state.name),
+                (true, false) => format!("state {} is initial", state.name),
+                (false, true) => format!("state {} is final", state.name),
+                (false, false) => format!("state {}", state.name),
+            };
+
+            if state.transitions.is_empty() {
+                tng += &format!("\t{header} {{}}\n");
+            } else {
+                tng += &format!("\t{header} {{\n");
+                for transition in &state.transitions {
+                    tng += &format!("\t\t{transition}\n");
+                }
+                tng += "\t}\n";
+            }
+        }
+
+        tng += "}\n";
+        tng
+    }
+}
+
+/// A symbol given to the script functions, either a char or the string `"default"`.
+fn symbol_token(symbol: &Dynamic) -> Result<String, Box<EvalAltResult>> {
+    if let Some(c) = symbol.clone().try_cast::<char>() {
+        return Ok(c.to_string());
+    }
+
+    match symbol.clone().into_string() {
+        Ok(symbol) if symbol == "default" || symbol.chars().count() == 1 => Ok(symbol),
+        _ => Err(format!("Invalid symbol {symbol}, it must be a char or \"default\"").into()),
+    }
+}
+
+/// Runs a [Rhai](https://rhai.rs) script that generates a Turing Machine, and returns its
+/// definition in the `.tng` format. The script can call these functions:
+/// - `machine(name, blank_symbol, head_start)`: Sets the configuration (by default, an unnamed
+///   machine with `'_'` as the blank symbol and the head starting at 0).
+/// - `state(name)`: Declares a state (states are also declared when used in a transition).
+/// - `initial(name)` and `accept(name)`: Declare a state as the initial one, or as final.
+/// - `transition(state, read, write, movement, new_state)`: Adds a transition, where symbols
+///   are chars or `"default"`, and the movement is `"L"`, `"R"` or `"S"` (or a jump like `"R3"`).
+/// - `random(bound)`: A random integer from 0 to `bound - 1`, to generate random machines. The
+///   numbers come from `seed`, so the same script and seed always generate the same machine.
+pub fn generate_tng(script: &str, seed: u64) -> Result<String, String> {
+    let generated = Rc::new(RefCell::new(Generated {
+        name: "Generated machine".to_string(),
+        blank_symbol: '_',
+        head_start: 0,
+        states: vec![],
+    }));
+
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let g = generated.clone();
+    engine.register_fn(
+        "machine",
+        move |name: &str, blank_symbol: char, head_start: INT| -> Result<(), Box<EvalAltResult>> {
+            if head_start < 0 {
+                return Err("The head start must be a non negative integer".into());
+            }
+
+            let mut generated = g.borrow_mut();
+            generated.name = name.replace('"', "'");
+            generated.blank_symbol = blank_symbol;
+            generated.head_start = head_start;
+            Ok(())
+        },
+    );
+
+    let g = generated.clone();
+    engine.register_fn(
+        "state",
+        move |name: &str| -> Result<(), Box<EvalAltResult>> {
+            g.borrow_mut().state(name).map(|_| ())
+        },
+    );
+
+    let g = generated.clone();
+    engine.register_fn(
+        "initial",
+        move |name: &str| -> Result<(), Box<EvalAltResult>> {
+            let mut generated = g.borrow_mut();
+            if generated
+                .states
+                .iter()
+                .any(|state| state.is_initial && state.name != name)
+            {
+                return Err("There can only be one initial state".into());
+            }
+
+            generated.state(name)?.is_initial = true;
+            Ok(())
+        },
+    );
+
+    let g = generated.clone();
+    engine.register_fn(
+        "accept",
+        move |name: &str| -> Result<(), Box<EvalAltResult>> {
+            g.borrow_mut().state(name)?.is_final = true;
+            Ok(())
+        },
+    );
+
+    let g = generated.clone();
+    engine.register_fn(
+        "transition",
+        move |state: &str,
+              read: Dynamic,
+              write: Dynamic,
+              movement: &str,
+              new_state: &str|
+              -> Result<(), Box<EvalAltResult>> {
+            if parser::parse_head_movement(movement).is_none() {
+                return Err(format!(
+                    "Invalid movement \"{movement}\", it must be L, R or S (or a jump like R3)"
+                )
+                .into());
+            }
+
+            let transition = format!(
+                "{},{},{movement},{new_state}",
+                symbol_token(&read)?,
+                symbol_token(&write)?
+            );
+
+            let mut generated = g.borrow_mut();
+            generated.state(new_state)?;
+            generated.state(state)?.transitions.push(transition);
+            Ok(())
+        },
+    );
+
+    let rng = Rc::new(RefCell::new(Rng::new(seed)));
+    engine.register_fn(
+        "random",
+        move |bound: INT| -> Result<INT, Box<EvalAltResult>> {
+            if bound <= 0 {
+                return Err("The bound of random must be a positive integer".into());
+            }
+
+            Ok(rng.borrow_mut().below(bound as u64) as INT)
+        },
+    );
+
+    engine
+        .run(script)
+        .map_err(|err| format!("[turing_lib] Error while running the generator script. {err}."))?;
+
+    let tng = generated.borrow().to_tng();
+    Ok(tng)
+}
+
+/// Generates a Turing Machine with a script (see [`generate_tng`]), and loads it with the given tape.
+pub fn generate_machine(script: &str, seed: u64, tape_data: &str) -> Result<TuringMachine, String> {
+    Ok(TuringMachine::new_from_str(
+        &generate_tng(script, seed)?,
+        tape_data,
+    )?)
+}
@@ -0,0 +1,381 @@
+use std::{collections::BTreeSet, fmt};
+
+use crate::machine::{HeadMovement, Symbol, TransitionSource, TuringMachine};
+
+/// A variable of the grammar built by [`unrestricted_grammar`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Variable {
+    /// The start variable.
+    Start,
+    /// Generates the blank cells to the left of the input, which the machine may move into.
+    Padding,
+    /// Generates the input up to the cell where the head starts, from the cell at this index.
+    Input(usize),
+    /// Generates the rest of the input, after the cell where the head starts.
+    Rest,
+    /// Generates the blank cells to the right of the input.
+    Blanks,
+    /// The machine in this state, with the head on the cell to the right of the variable.
+    State(String),
+    /// A cell of the tape, with the symbol of the input it started with (if it was part of the
+    /// input) and the symbol it holds now.
+    Cell(Option<char>, Symbol),
+    /// Erases the tape back into the input once the machine accepts.
+    Halt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GrammarSymbol {
+    Terminal(char),
+    Variable(Variable),
+}
+
+/// A production of an unrestricted grammar, which replaces `left` (with at least one variable)
+/// by `right` (which may be empty).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Production {
+    pub left: Vec<GrammarSymbol>,
+    pub right: Vec<GrammarSymbol>,
+}
+
+/// An unrestricted grammar that generates the words accepted by a machine, see
+/// [`unrestricted_grammar`]. It is shown one production per line, with the symbols separated
+/// by spaces: terminals are the symbols of the input, states are between angle brackets
+/// (`⟨q0⟩`), cells are between square brackets with their input and tape symbols (`[a,x]`,
+/// with `ε` if the cell was not part of the input), and the other variables are words
+/// starting with an uppercase letter (`Start` is the start variable).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grammar {
+    blank_symbol: char,
+    productions: Vec<Production>,
+}
+
+impl Grammar {
+    pub fn productions(&self) -> &[Production] {
+        &self.productions
+    }
+
+    fn symbol_name(&self, symbol: &GrammarSymbol) -> String {
+        let variable = match symbol {
+            GrammarSymbol::Terminal(c) => return c.to_string(),
+            GrammarSymbol::Variable(variable) => variable,
+        };
+
+        match variable {
+            Variable::Start => "Start".to_string(),
+            Variable::Padding => "Padding".to_string(),
+            Variable::Input(idx) => format!("Input{idx}"),
+            Variable::Rest => "Rest".to_string(),
+            Variable::Blanks => "Blanks".to_string(),
+            Variable::State(name) => format!("⟨{name}⟩"),
+            Variable::Cell(input, symbol) => {
+                let input = input.unwrap_or('ε');
+                let symbol = match symbol {
+                    Symbol::Mark(c) => *c,
+                    _ => self.blank_symbol,
+                };
+                format!("[{input},{symbol}]")
+            }
+            Variable::Halt => "Halt".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Grammar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = |symbols: &[GrammarSymbol]| {
+            if symbols.is_empty() {
+                "ε".to_string()
+            } else {
+                symbols
+                    .iter()
+                    .map(|symbol| self.symbol_name(symbol))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+        };
+
+        for production in &self.productions {
+            writeln!(
+                f,
+                "{} → {}",
+                side(&production.left),
+                side(&production.right)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds an unrestricted grammar that generates the words over `alphabet` accepted by the
+/// machine, following the standard construction.
+///
+/// The grammar first generates a tape with two tracks, both holding the same word of the
+/// input, surrounded by any number of blank cells and with the initial state before the cell
+/// where the head starts. Then it simulates the machine on the second track: every transition
+/// becomes productions that rewrite a state variable and the cells around it. Once the
+/// machine halts in a final state, the tape is erased leaving only the first track, the input.
+///
+/// Random transitions become several productions, since grammars are nondeterministic anyway.
+/// Machines that jump, consult an oracle, emit words or are bounded can't be converted.
+pub fn unrestricted_grammar(machine: &TuringMachine, alphabet: &[char]) -> Result<Grammar, String> {
+    let states = machine.states();
+
+    if states.values().any(|state| state.query().is_some()) {
+        return Err("[turing_lib] Error while building the grammar. Machines that consult an oracle can't be converted.".to_string());
+    }
+
+    if states.values().any(|state| state.emit().is_some()) {
+        return Err(
+            "[turing_lib] Error while building the grammar. Enumerators can't be converted."
+                .to_string(),
+        );
+    }
+
+    if machine.is_bounded() {
+        return Err("[turing_lib] Error while building the grammar. Linear bounded automata can't be converted.".to_string());
+    }
+
+    let transitions = states
+        .values()
+        .flat_map(|state| {
+            state.transitions().values().chain(
+                state
+                    .random_transitions()
+                    .values()
+                    .flatten()
+                    .map(|(_, transition)| transition),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if transitions.iter().any(|transition| {
+        transition.head_movement() != HeadMovement::Stay && transition.distance() != 1
+    }) {
+        return Err("[turing_lib] Error while building the grammar. Machines that jump over several cells can't be converted.".to_string());
+    }
+
+    if alphabet.contains(&machine.blank_symbol) {
+        return Err(format!("[turing_lib] Error while building the grammar. The alphabet can't contain the blank symbol '{}'.", machine.blank_symbol));
+    }
+
+    let alphabet = alphabet.iter().copied().collect::<BTreeSet<_>>();
+
+    // Every symbol that may be on the tape
+    let mut tape_symbols = BTreeSet::from([Symbol::Blank]);
+    tape_symbols.extend(alphabet.iter().map(|c| Symbol::Mark(*c)));
+    for state in states.values() {
+        for source in state
+            .transitions()
+            .keys()
+            .chain(state.random_transitions().keys())
+        {
+            if let TransitionSource::Mark(c) = source {
+                tape_symbols.insert(Symbol::Mark(*c));
+            }
+        }
+    }
+    for transition in &transitions {
+        if transition.new_symbol() != Symbol::Default {
+            tape_symbols.insert(transition.new_symbol());
+        }
+    }
+
+    // The symbols of the input a cell may have started with, None if it was not part of it
+    let inputs = [None]
+        .into_iter()
+        .chain(alphabet.iter().copied().map(Some))
+        .collect::<Vec<_>>();
+
+    let variable = |variable| GrammarSymbol::Variable(variable);
+    let state = |name: &str| variable(Variable::State(name.to_string()));
+    let cell = |input, symbol| variable(Variable::Cell(input, symbol));
+    let input_cell = |c: char| cell(Some(c), Symbol::Mark(c));
+    let blank_cell = || cell(None, Symbol::Blank);
+
+    let mut productions = vec![];
+    let mut add = |left: Vec<GrammarSymbol>, right: Vec<GrammarSymbol>| {
+        productions.push(Production { left, right });
+    };
+
+    // Generate the initial configuration
+    let initial = &machine.initial_state;
+    let head_start = machine.head_start;
+
+    add(
+        vec![variable(Variable::Start)],
+        vec![variable(Variable::Padding), variable(Variable::Input(0))],
+    );
+    add(
+        vec![variable(Variable::Padding)],
+        vec![blank_cell(), variable(Variable::Padding)],
+    );
+    add(vec![variable(Variable::Padding)], vec![]);
+
+    if head_start == 0 {
+        add(
+            vec![variable(Variable::Input(0))],
+            vec![state(initial), variable(Variable::Rest)],
+        );
+    } else {
+        // Inputs too short for the head start start on their last cell (or the blank cell of
+        // the empty input)
+        add(
+            vec![variable(Variable::Input(0))],
+            vec![state(initial), variable(Variable::Blanks)],
+        );
+
+        for idx in 0..head_start {
+            for &c in &alphabet {
+                add(
+                    vec![variable(Variable::Input(idx))],
+                    vec![input_cell(c), variable(Variable::Input(idx + 1))],
+                );
+                add(
+                    vec![variable(Variable::Input(idx))],
+                    vec![state(initial), input_cell(c), variable(Variable::Blanks)],
+                );
+            }
+        }
+
+        for &c in &alphabet {
+            add(
+                vec![variable(Variable::Input(head_start))],
+                vec![state(initial), input_cell(c), variable(Variable::Rest)],
+            );
+        }
+    }
+
+    for &c in &alphabet {
+        add(
+            vec![variable(Variable::Rest)],
+            vec![input_cell(c), variable(Variable::Rest)],
+        );
+    }
+    add(
+        vec![variable(Variable::Rest)],
+        vec![variable(Variable::Blanks)],
+    );
+    add(
+        vec![variable(Variable::Blanks)],
+        vec![blank_cell(), variable(Variable::Blanks)],
+    );
+    add(vec![variable(Variable::Blanks)], vec![]);
+
+    // Simulate the machine
+    let mut names = states.keys().collect::<Vec<_>>();
+    names.sort();
+
+    for name in names {
+        let machine_state = &states[name];
+
+        for &read in &tape_symbols {
+            let source = match read {
+                Symbol::Mark(c) => TransitionSource::Mark(c),
+                _ => TransitionSource::Blank,
+            };
+
+            // The transitions the machine may take, like `State::choose_transition`
+            let choices = [source, TransitionSource::Default]
+                .iter()
+                .find_map(|source| {
+                    if let Some(transition) = machine_state.transitions().get(source) {
+                        return Some(vec![transition]);
+                    }
+
+                    machine_state
+                        .random_transitions()
+                        .get(source)
+                        .map(|choices| {
+                            choices
+                                .iter()
+                                .map(|(_, transition)| transition)
+                                .collect::<Vec<_>>()
+                        })
+                })
+                .unwrap_or_default();
+
+            if choices.is_empty() && machine.final_states().contains(name) {
+                // Accepting: erase the tape around the head
+                for &input in &inputs {
+                    add(
+                        vec![state(name), cell(input, read)],
+                        [variable(Variable::Halt)]
+                            .into_iter()
+                            .chain(input.map(GrammarSymbol::Terminal))
+                            .chain([variable(Variable::Halt)])
+                            .collect(),
+                    );
+                }
+            }
+
+            for transition in choices {
+                let written = match transition.new_symbol() {
+                    Symbol::Default => read,
+                    symbol => symbol,
+                };
+                let new_state = transition.new_state();
+
+                for &input in &inputs {
+                    match transition.head_movement() {
+                        HeadMovement::Right => add(
+                            vec![state(name), cell(input, read)],
+                            vec![cell(input, written), state(new_state)],
+                        ),
+                        HeadMovement::Stay => add(
+                            vec![state(name), cell(input, read)],
+                            vec![state(new_state), cell(input, written)],
+                        ),
+                        HeadMovement::Left => {
+                            for &left_input in &inputs {
+                                for &left in &tape_symbols {
+                                    add(
+                                        vec![
+                                            cell(left_input, left),
+                                            state(name),
+                                            cell(input, read),
+                                        ],
+                                        vec![
+                                            state(new_state),
+                                            cell(left_input, left),
+                                            cell(input, written),
+                                        ],
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Erase the rest of the tape, leaving the input
+    for &input in &inputs {
+        for &symbol in &tape_symbols {
+            let erased = || {
+                [variable(Variable::Halt)]
+                    .into_iter()
+                    .chain(input.map(GrammarSymbol::Terminal))
+                    .chain([variable(Variable::Halt)])
+                    .collect::<Vec<_>>()
+            };
+
+            add(
+                vec![cell(input, symbol), variable(Variable::Halt)],
+                erased(),
+            );
+            add(
+                vec![variable(Variable::Halt), cell(input, symbol)],
+                erased(),
+            );
+        }
+    }
+    add(vec![variable(Variable::Halt)], vec![]);
+
+    Ok(Grammar {
+        blank_symbol: machine.blank_symbol,
+        productions,
+    })
+}
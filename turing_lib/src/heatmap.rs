@@ -0,0 +1,118 @@
+use std::{collections::HashMap, mem::size_of};
+
+use crate::machine::TransitionSource;
+
+/// Execution counts of a machine, to see which states and transitions are the most used.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Heatmap {
+    states: HashMap<String, usize>, // Times each state has been entered
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::nested_pairs"))]
+    transitions: HashMap<String, HashMap<TransitionSource, usize>>, // Times each transition has been taken, by state
+}
+
+impl Heatmap {
+    /// A heatmap where only the initial state has been entered once.
+    pub fn new(initial_state: &str) -> Self {
+        let mut heatmap = Self::default();
+        heatmap.visit_state(initial_state);
+        heatmap
+    }
+
+    /// Counts a visit to the state. This happens on every tick, so the name of the state is
+    /// only copied the first time.
+    pub(crate) fn visit_state(&mut self, state: &str) {
+        match self.states.get_mut(state) {
+            Some(visits) => *visits += 1,
+            None => {
+                self.states.insert(state.to_string(), 1);
+            }
+        }
+    }
+
+    pub(crate) fn take_transition(&mut self, state: &str, source: TransitionSource) {
+        match self.transitions.get_mut(state) {
+            Some(sources) => *sources.entry(source).or_default() += 1,
+            None => {
+                self.transitions
+                    .insert(state.to_string(), HashMap::from([(source, 1)]));
+            }
+        }
+    }
+
+    /// Approximate number of bytes the counts take in memory, see [`crate::history::History::memory_usage`].
+    pub(crate) fn memory_usage(&self) -> usize {
+        let name = |state: &String| size_of::<String>() + state.len();
+
+        self.states
+            .keys()
+            .map(|state| name(state) + size_of::<usize>())
+            .sum::<usize>()
+            + self
+                .transitions
+                .iter()
+                .map(|(state, sources)| {
+                    name(state)
+                        + size_of::<HashMap<TransitionSource, usize>>()
+                        + sources.len() * size_of::<(TransitionSource, usize)>()
+                })
+                .sum::<usize>()
+    }
+
+    pub fn states(&self) -> &HashMap<String, usize> {
+        &self.states
+    }
+
+    /// Counts of the transitions taken, with their state and source symbol, in no particular
+    /// order. The weighted random transitions of the same source symbol are counted together.
+    pub fn transitions(&self) -> impl Iterator<Item = (&str, TransitionSource, usize)> + '_ {
+        self.transitions.iter().flat_map(|(state, sources)| {
+            sources
+                .iter()
+                .map(move |(source, uses)| (state.as_str(), *source, *uses))
+        })
+    }
+
+    /// How many different transitions have been taken.
+    pub fn transitions_taken(&self) -> usize {
+        self.transitions.values().map(HashMap::len).sum()
+    }
+
+    pub fn state_visits(&self, state: &str) -> usize {
+        self.states.get(state).copied().unwrap_or(0)
+    }
+
+    pub fn transition_uses(&self, state: &str, source: TransitionSource) -> usize {
+        self.transitions
+            .get(state)
+            .and_then(|sources| sources.get(&source))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Visits of the state relative to the most visited one, from 0.0 (never visited) to 1.0.
+    pub fn state_heat(&self, state: &str) -> f32 {
+        let max = self.states.values().copied().max().unwrap_or(0);
+
+        if max == 0 {
+            0.0
+        } else {
+            self.state_visits(state) as f32 / max as f32
+        }
+    }
+
+    /// Uses of the transition relative to the most used one, from 0.0 (never used) to 1.0.
+    pub fn transition_heat(&self, state: &str, source: TransitionSource) -> f32 {
+        let max = self
+            .transitions()
+            .map(|(_, _, uses)| uses)
+            .max()
+            .unwrap_or(0);
+
+        if max == 0 {
+            0.0
+        } else {
+            self.transition_uses(state, source) as f32 / max as f32
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use std::{collections::HashMap, mem::size_of};
+
+use crate::{
+    heatmap::Heatmap,
+    machine::{Step, Symbol, TickResult, TransitionSource, TuringMachine},
+    random::Rng,
+    tape::{Tape, TapeSide},
+};
+
+/// Steps between two keyframes, so going to any step applies at most this many deltas.
+const KEYFRAME_INTERVAL: usize = 1024;
+
+/// Everything about a machine that changes while it runs, to bring it back to this point.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    tape: Tape,
+    head_idx: usize,
+    current_state: String,
+    halted: bool,
+    out_of_bounds: bool,
+    emitted: Vec<String>,
+    rng: Rng,
+    heatmap: Heatmap,
+}
+
+impl Snapshot {
+    fn of(machine: &TuringMachine) -> Self {
+        Self {
+            tape: machine.tape.clone(),
+            head_idx: machine.head_idx,
+            current_state: machine.current_state.clone(),
+            halted: machine.halted,
+            out_of_bounds: machine.out_of_bounds,
+            emitted: machine.emitted.clone(),
+            rng: machine.rng.clone(),
+            heatmap: machine.heatmap.clone(),
+        }
+    }
+
+    /// Approximate number of bytes the snapshot takes in memory.
+    fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.tape.len() * size_of::<Symbol>()
+            + self.current_state.len()
+            + self
+                .emitted
+                .iter()
+                .map(|word| size_of::<String>() + word.len())
+                .sum::<usize>()
+            + self.heatmap.memory_usage()
+    }
+
+    fn restore(&self, machine: &mut TuringMachine) {
+        machine.tape.clone_from(&self.tape);
+        machine.head_idx = self.head_idx;
+        machine.current_state.clone_from(&self.current_state);
+        machine.halted = self.halted;
+        machine.out_of_bounds = self.out_of_bounds;
+        machine.emitted.clone_from(&self.emitted);
+        machine.rng = self.rng.clone();
+        machine.heatmap = self.heatmap.clone();
+    }
+}
+
+/// What a step changed, to make it again from the configuration before it.
+#[derive(Debug, Clone)]
+struct Delta {
+    extended_left: usize, // Blank cells added on each side, before writing
+    extended_right: usize,
+    write: Option<(usize, Symbol)>, // Index of the cell after extending the tape
+    head_idx: usize,
+    new_state: Option<u32>, // Index in `History::states`, if the state changed
+    transition: Option<TransitionSource>, // Taken from the state before the step
+    entered_state: bool,    // Whether the heatmap counts the state as entered
+    halted: bool,
+    out_of_bounds: bool,
+    emitted: Option<String>,
+    rng_state: Option<u64>, // If a random transition was chosen
+}
+
+impl Delta {
+    fn apply(&self, machine: &mut TuringMachine, states: &[String]) {
+        for _ in 0..self.extended_left {
+            machine.tape.extend_left();
+        }
+        for _ in 0..self.extended_right {
+            machine.tape.extend_right();
+        }
+        if let Some((idx, symbol)) = self.write {
+            machine.tape.write(idx, symbol);
+        }
+        machine.head_idx = self.head_idx;
+
+        if let Some(source) = self.transition {
+            machine
+                .heatmap
+                .take_transition(&machine.current_state, source);
+        }
+        if let Some(state) = self.new_state {
+            machine.current_state.clone_from(&states[state as usize]);
+        }
+        if self.entered_state {
+            machine.heatmap.visit_state(&machine.current_state);
+        }
+
+        machine.halted = self.halted;
+        machine.out_of_bounds = self.out_of_bounds;
+        if let Some(word) = &self.emitted {
+            machine.emitted.push(word.clone());
+        }
+        if let Some(rng_state) = self.rng_state {
+            machine.rng = Rng::new(rng_state);
+        }
+    }
+}
+
+/// A full copy of the configuration after a step, to replay the next deltas from.
+#[derive(Debug, Clone)]
+struct Keyframe {
+    step: usize,
+    origin: usize, // See `History::origin`
+    snapshot: Snapshot,
+}
+
+/// The configuration of a machine before a tick, which the tick result doesn't tell.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BeforeTick {
+    tape_len: usize,
+    head_idx: usize,
+    rng_state: u64,
+}
+
+impl BeforeTick {
+    pub(crate) fn of(machine: &TuringMachine) -> Self {
+        Self {
+            tape_len: machine.tape.len(),
+            head_idx: machine.head_idx,
+            rng_state: machine.rng.state(),
+        }
+    }
+}
+
+/// The configurations a machine went through since its history was enabled (or since it was
+/// reset), so it can go back to any of them, see [`TuringMachine::seek`].
+///
+/// Only what every step changed is stored (the cell written, the head move, the state change,
+/// ...), along with a full copy of the configuration every 1024 steps, so long runs fit in
+/// memory and any step is still quick to go back to.
+#[derive(Debug, Clone)]
+pub struct History {
+    keyframes: Vec<Keyframe>, // By step, starting with the configuration before the first one
+    deltas: Vec<Delta>,       // The one at `i` goes from the step `i` to the next one
+    states: Vec<String>,      // Entered by the steps, referenced by their index
+    state_ids: HashMap<String, u32>,
+    position: usize,       // Step the machine is at
+    origin: usize,         // At the current step
+    keyframe_bytes: usize, // Approximate memory taken by the keyframes, see `memory_usage`
+    emitted_bytes: usize,  // Taken by the words emitted in the deltas
+}
+
+impl History {
+    pub(crate) fn new(machine: &TuringMachine) -> Self {
+        let snapshot = Snapshot::of(machine);
+
+        Self {
+            keyframe_bytes: snapshot.memory_usage(),
+            keyframes: vec![Keyframe {
+                step: 0,
+                origin: 0,
+                snapshot,
+            }],
+            deltas: vec![],
+            states: vec![],
+            state_ids: HashMap::new(),
+            position: 0,
+            origin: 0,
+            emitted_bytes: 0,
+        }
+    }
+
+    /// How many steps were recorded.
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Approximate number of bytes the recorded configurations take in memory, e.g. to stop a
+    /// run before the history of a long one uses all the memory (see
+    /// [`crate::runner::RunLimits::max_memory`]).
+    pub fn memory_usage(&self) -> usize {
+        self.keyframe_bytes
+            + self.deltas.len() * size_of::<Delta>()
+            + self.emitted_bytes
+            + self
+                .states
+                .iter()
+                .map(|state| 2 * (size_of::<String>() + state.len()) + size_of::<u32>())
+                .sum::<usize>()
+    }
+
+    /// The step the machine is at, from 0 (before the first one) to [`History::len`].
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Index on the tape of the cell that was the first one when the history was enabled (or
+    /// the machine was reset). It moves right when the tape grows to the left, so positions
+    /// counted from it, like the ones of [`History::last_writes`], don't.
+    pub fn origin(&self) -> usize {
+        self.origin
+    }
+
+    /// The last `count` steps up to the current one that wrote a new symbol on the cell at
+    /// `position`, counted from the [`History::origin`] (negative on its left), the latest first.
+    /// Every write is given as the step (counting from 1) and the symbol it wrote. Changes made
+    /// between steps, like with [`TuringMachine::set_cell`], are not included.
+    pub fn last_writes(&self, position: isize, count: usize) -> Vec<(usize, Symbol)> {
+        let mut writes = vec![];
+        let mut origin = self.origin as isize; // After the step of the delta
+
+        for (idx, delta) in self.deltas[..self.position].iter().enumerate().rev() {
+            if writes.len() == count {
+                break;
+            }
+
+            if let Some((cell, symbol)) = delta.write {
+                if cell as isize - origin == position {
+                    writes.push((idx + 1, symbol));
+                }
+            }
+            origin -= delta.extended_left as isize;
+        }
+
+        writes
+    }
+
+    /// Forgets the steps after the current one, when the machine goes on from an earlier step.
+    fn truncate(&mut self) {
+        for delta in self.deltas.drain(self.position..) {
+            self.emitted_bytes -= delta.emitted.map_or(0, |word| word.len());
+        }
+
+        let keyframes = self.keyframes.len();
+        self.keyframes
+            .retain(|keyframe| keyframe.step <= self.position);
+        if self.keyframes.len() != keyframes {
+            self.keyframe_bytes = self
+                .keyframes
+                .iter()
+                .map(|keyframe| keyframe.snapshot.memory_usage())
+                .sum();
+        }
+    }
+
+    fn state_id(&mut self, state: &str) -> u32 {
+        if let Some(id) = self.state_ids.get(state) {
+            return *id;
+        }
+
+        let id = self.states.len() as u32;
+        self.states.push(state.to_string());
+        self.state_ids.insert(state.to_string(), id);
+        id
+    }
+
+    /// Records the step the machine just made with `tick_result`.
+    pub(crate) fn record(
+        &mut self,
+        machine: &TuringMachine,
+        tick_result: &TickResult,
+        before: BeforeTick,
+    ) {
+        self.truncate();
+
+        let added_cells = machine.tape.len() - before.tape_len;
+        let (extended_left, extended_right) = match tick_result.extended_tape_on_side {
+            Some(TapeSide::Left) => (added_cells, 0),
+            Some(TapeSide::Right) => (0, added_cells),
+            None => (0, 0),
+        };
+
+        let (state, transition, entered_state, emitted) = match &tick_result.step {
+            Some(Step::Transition { state, read, .. }) => {
+                let source = transition_source(machine, state, *read);
+                (Some(state), Some(source), true, None)
+            }
+            Some(Step::Emit { state, word, .. }) => (Some(state), None, true, Some(word.clone())),
+            Some(Step::Query { state, answer, .. }) => (Some(state), None, answer.is_some(), None),
+            Some(Step::Halt { state, .. } | Step::OutOfBounds { state, .. }) => {
+                (Some(state), None, false, None)
+            }
+            Some(Step::Intervention(_)) | None => (None, None, false, None),
+        };
+
+        let new_state =
+            (state != Some(&machine.current_state)).then(|| self.state_id(&machine.current_state));
+
+        // A transition writes on the cell under the head before moving it
+        let write = tick_result.written_different_symbol.then(|| {
+            let idx = before.head_idx + extended_left;
+            (idx, machine.tape.read(idx))
+        });
+
+        self.emitted_bytes += emitted.as_ref().map_or(0, String::len);
+
+        let rng_state = machine.rng.state();
+        self.deltas.push(Delta {
+            extended_left,
+            extended_right,
+            write,
+            head_idx: machine.head_idx,
+            new_state,
+            transition,
+            entered_state,
+            halted: machine.halted,
+            out_of_bounds: machine.out_of_bounds,
+            emitted,
+            rng_state: (rng_state != before.rng_state).then_some(rng_state),
+        });
+        self.position += 1;
+        self.origin += extended_left;
+
+        if self.position.is_multiple_of(KEYFRAME_INTERVAL) {
+            let snapshot = Snapshot::of(machine);
+            self.keyframe_bytes += snapshot.memory_usage();
+            self.keyframes.push(Keyframe {
+                step: self.position,
+                origin: self.origin,
+                snapshot,
+            });
+        }
+    }
+
+    /// Replaces the configuration of the current step, after the machine was changed between two
+    /// steps. The steps after it are forgotten.
+    pub(crate) fn amend(&mut self, machine: &TuringMachine) {
+        self.truncate();
+
+        let snapshot = Snapshot::of(machine);
+        self.keyframe_bytes += snapshot.memory_usage();
+        match self.keyframes.last_mut() {
+            Some(keyframe) if keyframe.step == self.position => {
+                self.keyframe_bytes -= keyframe.snapshot.memory_usage();
+                keyframe.snapshot = snapshot;
+            }
+            _ => self.keyframes.push(Keyframe {
+                step: self.position,
+                origin: self.origin,
+                snapshot,
+            }),
+        }
+    }
+
+    /// Brings the machine to its configuration after `step` steps, from the closest keyframe
+    /// before it, or from the current step if it is closer. Returns `false` if the step was not
+    /// executed yet.
+    pub(crate) fn seek(&mut self, step: usize, machine: &mut TuringMachine) -> bool {
+        if step > self.len() {
+            return false;
+        }
+
+        let keyframe_idx = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.step <= step)
+            - 1;
+        let keyframe = &self.keyframes[keyframe_idx];
+
+        let from = if (keyframe.step..=step).contains(&self.position) {
+            self.position
+        } else {
+            keyframe.snapshot.restore(machine);
+            self.origin = keyframe.origin;
+            keyframe.step
+        };
+
+        for delta in &self.deltas[from..step] {
+            delta.apply(machine, &self.states);
+            self.origin += delta.extended_left;
+        }
+        self.position = step;
+
+        true
+    }
+}
+
+/// The source of the transition the state took when reading `read`, like
+/// [`crate::machine::State::choose_transition`] finds it.
+fn transition_source(machine: &TuringMachine, state: &str, read: Symbol) -> TransitionSource {
+    let state = &machine.states[state];
+    let source = match read {
+        Symbol::Default => TransitionSource::Default,
+        Symbol::Mark(c) => TransitionSource::Mark(c),
+        Symbol::Blank => TransitionSource::Blank,
+    };
+
+    if state.transitions().contains_key(&source) || state.random_transitions().contains_key(&source)
+    {
+        source
+    } else {
+        TransitionSource::Default
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::Instant;
+
+use crate::{
+    machine::{Symbol, TickResult, TuringMachine},
+    runner::{self, RunLimits, RunOutcome},
+    tape::Tape,
+};
+
+/// A change made to a machine from outside of its definition, between two of its ticks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Intervention {
+    /// Writes the symbol on the cell at this index, extending the tape to the right with blanks
+    /// if it is shorter.
+    SetCell { cell: usize, symbol: Symbol },
+    /// Moves the machine to the state, without moving the head.
+    SetState(String),
+}
+
+/// Code that runs before every tick of a machine and may change it, e.g. to flip a cell in the
+/// middle of a run and see what the machine does then, or to play the part of an oracle.
+///
+/// Closures taking the machine and the steps taken so far are hooks too.
+pub trait Hook {
+    /// The changes to make before the next tick, in order.
+    fn before_tick(&mut self, machine: &TuringMachine, steps: usize) -> Vec<Intervention>;
+}
+
+impl<F> Hook for F
+where
+    F: FnMut(&TuringMachine, usize) -> Vec<Intervention>,
+{
+    fn before_tick(&mut self, machine: &TuringMachine, steps: usize) -> Vec<Intervention> {
+        self(machine, steps)
+    }
+}
+
+/// Runs the hooks in order and makes the changes they ask for, calling `on_intervention` with
+/// the result of each of them, the tape before it and the machine after it.
+pub(crate) fn run_hooks<F>(
+    machine: &mut TuringMachine,
+    steps: usize,
+    hooks: &mut [Box<dyn Hook>],
+    mut on_intervention: F,
+) -> Result<(), String>
+where
+    F: FnMut(TickResult, &Tape, &TuringMachine),
+{
+    for hook in hooks {
+        for intervention in hook.before_tick(machine, steps) {
+            let before = machine.tape().clone();
+            let tick_result = machine.intervene(&intervention)?;
+            on_intervention(tick_result, &before, machine);
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the machine like [`runner::run`], but runs the hooks before every tick, letting them
+/// change the tape and the state. The changes are not counted as steps. Use
+/// [`crate::replay::Replay::record_with_hooks`] to record them along with the run.
+pub fn run_with_hooks(
+    machine: &mut TuringMachine,
+    limits: &RunLimits,
+    hooks: &mut [Box<dyn Hook>],
+) -> Result<RunOutcome, String> {
+    let start = Instant::now();
+    let mut steps = 0;
+
+    loop {
+        if let Some(outcome) = runner::check_limits(limits, machine, start, steps) {
+            return Ok(outcome);
+        }
+
+        run_hooks(machine, steps, hooks, |_, _, _| {})?;
+        machine.advance();
+
+        if machine.is_halted() {
+            return Ok(RunOutcome::Halted {
+                accepted: machine.is_accepting(),
+                steps,
+            });
+        }
+
+        steps += 1;
+    }
+}
@@ -0,0 +1,102 @@
+use std::fs;
+
+/// When an annotation of a lesson is shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cue {
+    /// After this many steps (`0` before the first one).
+    Step(usize),
+    /// Whenever the machine enters the state, including the initial state at the start.
+    State(String),
+    /// When the machine halts.
+    Halt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub cue: Cue,
+    pub caption: String,
+}
+
+/// Captions tied to moments of a run, that turn it into a guided walkthrough, e.g. "Now the
+/// machine starts comparing the two halves" when it enters the state that does it.
+///
+/// Lessons are written one annotation per line, as a cue and a caption separated by `:`:
+/// `step <n>`, `state <name>` or `halt`, e.g. `state q2: "Now the machine compares the two
+/// halves"`. The quotes around the caption are optional, and lines starting with `//` are
+/// comments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lesson {
+    annotations: Vec<Annotation>,
+}
+
+impl Lesson {
+    pub fn new_from_file(filename: &str) -> Result<Lesson, String> {
+        let file_data = fs::read_to_string(filename)
+            .map_err(|_| format!("Could not open the file \"{}\"", filename))?;
+
+        Self::new_from_str(&file_data)
+    }
+
+    pub fn new_from_str(file_data: &str) -> Result<Lesson, String> {
+        let mut annotations = vec![];
+
+        for (line_idx, line) in file_data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            let Some((cue, caption)) = line.split_once(':') else {
+                return Err(format!("[turing_lib] Error while parsing the lesson. Line {} should be `<cue>: <caption>`.", line_idx + 1));
+            };
+
+            let cue = match cue.split_whitespace().collect::<Vec<_>>()[..] {
+                ["step", steps] => Cue::Step(steps.parse().map_err(|_| {
+                    format!("[turing_lib] Error while parsing the lesson. Invalid step \"{steps}\" on line {}.", line_idx + 1)
+                })?),
+                ["state", state] => Cue::State(state.to_string()),
+                ["halt"] => Cue::Halt,
+                _ => {
+                    return Err(format!("[turing_lib] Error while parsing the lesson. Unknown cue \"{}\" on line {}, expected `step <n>`, `state <name>` or `halt`.", cue.trim(), line_idx + 1));
+                }
+            };
+
+            let caption = caption.trim();
+            let caption = caption
+                .strip_prefix('"')
+                .and_then(|caption| caption.strip_suffix('"'))
+                .unwrap_or(caption);
+
+            annotations.push(Annotation {
+                cue,
+                caption: caption.to_string(),
+            });
+        }
+
+        Ok(Lesson { annotations })
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// The caption of the first annotation whose cue is met after `steps` steps, with the machine
+    /// in `state`. `entered_state` tells whether the last step moved the machine to it (or it is
+    /// the start of the run).
+    pub fn caption(
+        &self,
+        steps: usize,
+        state: &str,
+        entered_state: bool,
+        halted: bool,
+    ) -> Option<&str> {
+        self.annotations
+            .iter()
+            .find(|annotation| match &annotation.cue {
+                Cue::Step(cue_steps) => *cue_steps == steps,
+                Cue::State(cue_state) => entered_state && cue_state == state,
+                Cue::Halt => halted,
+            })
+            .map(|annotation| annotation.caption.as_str())
+    }
+}
@@ -1,3 +1,50 @@
+pub mod analysis;
+#[cfg(feature = "proptest")]
+mod arbitrary;
+pub mod automaton;
+pub mod bench;
+#[cfg(feature = "scripting")]
+pub mod breakpoint;
+pub mod builder;
+#[cfg(feature = "charts")]
+pub mod chart;
+pub mod compiled;
+pub mod counter;
+pub mod coverage;
+pub mod diagram;
+pub mod doc;
+mod dot;
+pub mod error;
+#[cfg(feature = "evcxr")]
+mod evcxr;
+pub mod explain;
+pub mod fast;
+pub mod finite;
+#[cfg(feature = "scripting")]
+pub mod generate;
+pub mod grammar;
+pub mod heatmap;
+pub mod history;
+pub mod hook;
+pub mod lesson;
+pub mod library;
 pub mod machine;
+pub mod multitape;
+pub mod oracle;
 mod parser;
+pub mod pda;
+pub mod profile;
+pub mod random;
+pub mod replay;
+pub mod report;
+pub mod runner;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod svg;
 pub mod tape;
+pub mod tikz;
+pub mod timeseries;
+mod tng;
+pub mod transform;
+pub mod tree;
+pub mod watchpoint;
@@ -0,0 +1,87 @@
+use crate::machine::TuringMachine;
+
+/// A classic machine shipped with the library, defined in the `.tng` format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Template {
+    name: &'static str,
+    description: &'static str,
+    source: &'static str,
+}
+
+impl Template {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// The definition of the machine, as the contents of a `.tng` file.
+    pub fn source(&self) -> &'static str {
+        self.source
+    }
+
+    pub fn load(&self, tape_data: &str) -> TuringMachine {
+        TuringMachine::new_from_str(self.source, tape_data).unwrap_or_else(|err| {
+            panic!("The built-in machine \"{}\" is invalid. {err}", self.name)
+        })
+    }
+}
+
+pub const UNARY_ADD: Template = Template {
+    name: "unary_add",
+    description: "Adds two unary numbers separated by '+', e.g. 111+11 to 11111.",
+    source: include_str!("../library/unary_add.tng"),
+};
+
+pub const BINARY_INCREMENT: Template = Template {
+    name: "binary_increment",
+    description: "Adds one to a binary number, e.g. 1011 to 1100.",
+    source: include_str!("../library/binary_increment.tng"),
+};
+
+pub const PALINDROME: Template = Template {
+    name: "palindrome",
+    description: "Accepts the palindromes over {a, b}.",
+    source: include_str!("../library/palindrome.tng"),
+};
+
+pub const EQUAL_AB: Template = Template {
+    name: "equal_ab",
+    description: "Accepts the words over {a, b} with as many a's as b's.",
+    source: include_str!("../library/equal_ab.tng"),
+};
+
+pub const COPY: Template = Template {
+    name: "copy",
+    description: "Copies a binary word w to w#w.",
+    source: include_str!("../library/copy.tng"),
+};
+
+/// Every built-in machine.
+pub const TEMPLATES: &[Template] = &[UNARY_ADD, BINARY_INCREMENT, PALINDROME, EQUAL_AB, COPY];
+
+pub fn find(name: &str) -> Option<&'static Template> {
+    TEMPLATES.iter().find(|template| template.name == name)
+}
+
+pub fn unary_add(tape_data: &str) -> TuringMachine {
+    UNARY_ADD.load(tape_data)
+}
+
+pub fn binary_increment(tape_data: &str) -> TuringMachine {
+    BINARY_INCREMENT.load(tape_data)
+}
+
+pub fn palindrome(tape_data: &str) -> TuringMachine {
+    PALINDROME.load(tape_data)
+}
+
+pub fn equal_ab(tape_data: &str) -> TuringMachine {
+    EQUAL_AB.load(tape_data)
+}
+
+pub fn copy(tape_data: &str) -> TuringMachine {
+    COPY.load(tape_data)
+}
@@ -1,71 +1,356 @@
-use crate::{parser, tape::TapeSide};
+use crate::{
+    error::{MachineError, MachineErrorKind, ParseError},
+    heatmap::Heatmap,
+    history::{BeforeTick, History},
+    hook::Intervention,
+    oracle::{Oracle, OracleQuery},
+    parser,
+    random::Rng,
+    runner::{self, RunLimits, RunOutcome},
+    tape::TapeSide,
+    tree::{self, BranchEnd, TreeLimits},
+};
 
-use super::tape::Tape;
+use super::tape::{Tape, TapeStorage};
 use std::{
-    collections::{HashMap, HashSet},
-    fs,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt, fs,
+    sync::Arc,
 };
 
-#[derive(Debug, Clone, Copy)]
+/// The end markers around the input on the tape of a linear bounded automaton.
+pub const LEFT_END_MARKER: char = '⊢';
+pub const RIGHT_END_MARKER: char = '⊣';
+
+const MAX_CELL_EXTENSION: usize = 1 << 20; // Most cells `set_cell` may add past the end of the tape
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HeadMovement {
     Left,
     Right,
     Stay,
 }
 
-#[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
+#[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Symbol {
     Default, // Only used in Transition declarations (source symbol, new symbol)
     Mark(char),
     Blank,
 }
 
-#[derive(Debug, Hash, Eq, PartialEq)]
+impl Symbol {
+    /// The symbol written as `c`, which is blank if it is the blank symbol.
+    pub fn from_char(c: char, blank_symbol: char) -> Symbol {
+        if c == blank_symbol {
+            Symbol::Blank
+        } else {
+            Symbol::Mark(c)
+        }
+    }
+}
+
+/// A mark with the given char. Use [`Symbol::from_char`] to take the blank symbol into account.
+impl From<char> for Symbol {
+    fn from(c: char) -> Self {
+        Symbol::Mark(c)
+    }
+}
+
+#[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TransitionSource {
     Default,
     Mark(char),
     Blank,
 }
 
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct State {
     name: String,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pairs"))]
     transitions: HashMap<TransitionSource, Transition>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pairs"))]
+    random_transitions: HashMap<TransitionSource, Vec<(u32, Transition)>>, // Weighted choices
+    query: Option<OracleQuery>,
+    emit: Option<String>, // State entered after emitting
+    description: Option<String>,
 }
 
 impl State {
     pub fn new(name: String, transitions: HashMap<TransitionSource, Transition>) -> Self {
-        Self { name, transitions }
+        Self {
+            name,
+            transitions,
+            random_transitions: HashMap::new(),
+            query: None,
+            emit: None,
+            description: None,
+        }
+    }
+
+    /// A state where reading some symbols chooses one of several transitions at random,
+    /// each one with a probability proportional to its weight.
+    pub fn new_random(
+        name: String,
+        transitions: HashMap<TransitionSource, Transition>,
+        random_transitions: HashMap<TransitionSource, Vec<(u32, Transition)>>,
+    ) -> Self {
+        Self {
+            name,
+            transitions,
+            random_transitions,
+            query: None,
+            emit: None,
+            description: None,
+        }
+    }
+
+    /// A state without transitions, which consults the oracle of the machine instead.
+    pub fn new_query(name: String, query: OracleQuery) -> Self {
+        Self {
+            name,
+            transitions: HashMap::new(),
+            random_transitions: HashMap::new(),
+            query: Some(query),
+            emit: None,
+            description: None,
+        }
+    }
+
+    /// A state without transitions, which emits the word under the head and enters `next_state`.
+    pub fn new_emit(name: String, next_state: String) -> Self {
+        Self {
+            name,
+            transitions: HashMap::new(),
+            random_transitions: HashMap::new(),
+            query: None,
+            emit: Some(next_state),
+            description: None,
+        }
+    }
+
+    /// The state with a description of what it does, e.g. `state carry "propagates the carry" {`.
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
     }
 
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
     pub fn transitions(&self) -> &HashMap<TransitionSource, Transition> {
         &self.transitions
     }
+
+    pub fn random_transitions(&self) -> &HashMap<TransitionSource, Vec<(u32, Transition)>> {
+        &self.random_transitions
+    }
+
+    pub fn is_random(&self) -> bool {
+        !self.random_transitions.is_empty()
+    }
+
+    pub fn query(&self) -> Option<&OracleQuery> {
+        self.query.as_ref()
+    }
+
+    /// State entered after emitting, if this is an emitting state.
+    pub fn emit(&self) -> Option<&str> {
+        self.emit.as_deref()
+    }
+
+    /// Transition taken when reading `symbol` in this state, falling back to the default one.
+    pub fn transition_for(&self, symbol: Symbol) -> Option<&Transition> {
+        let transition = match symbol {
+            Symbol::Default => self.transitions.get(&TransitionSource::Default),
+            Symbol::Mark(c) => self.transitions.get(&TransitionSource::Mark(c)),
+            Symbol::Blank => self.transitions.get(&TransitionSource::Blank),
+        };
+
+        // Search for a default transition if none
+        transition.or_else(|| self.transitions.get(&TransitionSource::Default))
+    }
+
+    /// Every transition the state may take when reading `symbol`: the one of
+    /// [`State::transition_for`], or else every weighted random transition of the symbol (or the
+    /// default ones).
+    pub fn transition_choices(&self, symbol: Symbol) -> Vec<(TransitionSource, &Transition)> {
+        let source = match symbol {
+            Symbol::Default => TransitionSource::Default,
+            Symbol::Mark(c) => TransitionSource::Mark(c),
+            Symbol::Blank => TransitionSource::Blank,
+        };
+
+        for source in [source, TransitionSource::Default] {
+            if let Some(transition) = self.transitions.get(&source) {
+                return vec![(source, transition)];
+            }
+
+            if let Some(choices) = self.random_transitions.get(&source) {
+                return choices
+                    .iter()
+                    .map(|(_, transition)| (source, transition))
+                    .collect();
+            }
+        }
+
+        vec![]
+    }
+
+    /// Like [`State::transition_for`], but also considering the weighted random transitions.
+    /// The source of the transition is returned along with it.
+    pub fn choose_transition(
+        &self,
+        symbol: Symbol,
+        rng: &mut Rng,
+    ) -> Option<(TransitionSource, &Transition)> {
+        let source = match symbol {
+            Symbol::Default => TransitionSource::Default,
+            Symbol::Mark(c) => TransitionSource::Mark(c),
+            Symbol::Blank => TransitionSource::Blank,
+        };
+
+        for source in [source, TransitionSource::Default] {
+            if let Some(transition) = self.transitions.get(&source) {
+                return Some((source, transition));
+            }
+
+            if let Some(choices) = self.random_transitions.get(&source) {
+                let total = choices.iter().map(|(weight, _)| *weight as u64).sum();
+                let mut chosen = rng.below(total);
+
+                for (weight, transition) in choices {
+                    if chosen < *weight as u64 {
+                        return Some((source, transition));
+                    }
+                    chosen -= *weight as u64;
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Transitions sorted by their source, so they are always printed in the same order.
+fn sorted_transitions<T>(
+    transitions: &HashMap<TransitionSource, T>,
+) -> Vec<(&TransitionSource, &T)> {
+    let mut transitions = transitions.iter().collect::<Vec<_>>();
+    transitions.sort_by_key(|(source, _)| **source);
+    transitions
+}
+
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("State")
+            .field("name", &self.name)
+            .field("transitions", &sorted_transitions(&self.transitions))
+            .field(
+                "random_transitions",
+                &sorted_transitions(&self.random_transitions),
+            )
+            .field("query", &self.query)
+            .field("emit", &self.emit)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+/// How fast the GUI animates a transition, set with `@slow` or `@fast` after it, so machine
+/// authors can slow down the important steps and speed through the boring ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pacing {
+    #[default]
+    Normal,
+    Slow,
+    Fast,
 }
 
-#[derive(Debug)]
+impl Pacing {
+    /// How much longer (or shorter) than normal the animation of the transition takes.
+    pub fn duration_factor(&self) -> f32 {
+        match self {
+            Pacing::Normal => 1.0,
+            Pacing::Slow => 3.0,
+            Pacing::Fast => 0.25,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Transition {
     head_movement: HeadMovement,
+    distance: usize,
     new_symbol: Symbol,
     new_state: String,
+    pacing: Pacing,
 }
 
 impl Transition {
     pub fn new(head_movement: HeadMovement, new_symbol: Symbol, new_state: String) -> Self {
+        Self::new_jump(head_movement, 1, new_symbol, new_state)
+    }
+
+    /// A transition that moves the head `distance` cells at once (e.g. `R3`), instead of one.
+    pub fn new_jump(
+        head_movement: HeadMovement,
+        distance: usize,
+        new_symbol: Symbol,
+        new_state: String,
+    ) -> Self {
         Self {
             head_movement,
+            distance,
             new_symbol,
             new_state,
+            pacing: Pacing::Normal,
         }
     }
 
+    pub fn with_pacing(mut self, pacing: Pacing) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    pub fn pacing(&self) -> Pacing {
+        self.pacing
+    }
+
     pub fn head_movement(&self) -> HeadMovement {
         self.head_movement
     }
 
+    /// How many cells the head moves (1 unless it is a jump, and irrelevant if it stays).
+    pub fn distance(&self) -> usize {
+        self.distance
+    }
+
+    /// The head movement as written in `.tng` files, e.g. `L`, `S` or `R3`.
+    pub fn movement_label(&self) -> String {
+        let movement = match self.head_movement {
+            HeadMovement::Left => "L",
+            HeadMovement::Right => "R",
+            HeadMovement::Stay => return "S".to_string(),
+        };
+
+        if self.distance == 1 {
+            movement.to_string()
+        } else {
+            format!("{movement}{}", self.distance)
+        }
+    }
+
     pub fn new_symbol(&self) -> Symbol {
         self.new_symbol
     }
@@ -79,6 +364,45 @@ pub struct TickResult {
     pub written_different_symbol: bool,
     pub extended_tape_on_side: Option<TapeSide>,
     pub head_movement: HeadMovement,
+    /// What the tick did, for machines that describe their steps (see [`crate::explain`]).
+    pub step: Option<Step>,
+}
+
+/// A step of a Turing Machine, in terms of its definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Transition {
+        state: String,
+        read: Symbol,
+        written: Symbol,
+        head_movement: HeadMovement,
+        /// How many cells the head moved.
+        distance: usize,
+        new_state: String,
+        pacing: Pacing,
+    },
+    /// There was no transition for the symbol under the head, so the machine halted.
+    Halt { state: String, read: Symbol },
+    Emit {
+        state: String,
+        word: String,
+        new_state: String,
+    },
+    /// The oracle was asked about the word under the head. Without an oracle, there is no
+    /// answer and the machine halted.
+    Query {
+        state: String,
+        word: String,
+        answer: Option<(bool, String)>, // The answer and the state entered
+    },
+    /// A linear bounded automaton tried to move past an end marker, so it halted and rejected.
+    OutOfBounds {
+        state: String,
+        read: Symbol,
+        head_movement: HeadMovement,
+    },
+    /// A hook changed the machine between two ticks, see [`crate::hook`].
+    Intervention(Intervention),
 }
 
 impl TickResult {
@@ -95,6 +419,22 @@ impl TickResult {
     }
 }
 
+/// Which part of the tape is the output of a machine used as a function, see [`TuringMachine::run_function`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputConvention {
+    FromHead,  // From the head up to the first blank
+    WholeTape, // The whole tape, without the blanks at its ends
+}
+
+/// Cloning a machine forks its current configuration (tape, head, state, random number
+/// generator, counts and history), so the clone can be run without disturbing the original.
+/// Both machines share the same oracle.
+///
+/// With the `serde` feature, a machine is serialized with its definition and its current
+/// configuration, but without its oracle nor its history.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TuringMachine {
     pub(crate) name: String,
     pub(crate) blank_symbol: char,
@@ -102,78 +442,501 @@ pub struct TuringMachine {
     pub(crate) states: HashMap<String, State>,
     pub(crate) final_states: HashSet<String>,
 
+    pub(crate) head_start: usize,
+    /// Where the head actually started on the current input: `head_start`, or the last cell of
+    /// the tape if the input is too short for it.
+    pub(crate) effective_head_start: usize,
+    pub(crate) initial_state: String,
+
     pub(crate) head_idx: usize,
     pub(crate) current_state: String,
     pub(crate) tape: Tape,
 
     pub(crate) halted: bool,
+
+    /// Whether it is a linear bounded automaton, whose tape is the input between end markers.
+    pub(crate) bounded: bool,
+    /// Whether a bounded machine rejected by trying to move past an end marker.
+    pub(crate) out_of_bounds: bool,
+    /// Whether it is a non-deterministic machine, whose runs accept if any choice accepts.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) nondeterministic: bool,
+
+    pub(crate) max_tape_cells: Option<usize>,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) oracle: Option<Arc<dyn Oracle>>,
+    pub(crate) rng: Rng,
+
+    pub(crate) emitted: Vec<String>,
+
+    pub(crate) output_convention: OutputConvention,
+
+    pub(crate) heatmap: Heatmap,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) history: Option<History>,
+}
+
+/// Two machines are equal if they have the same definition, no matter their tape, head,
+/// current state or anything else that changes while running.
+impl PartialEq for TuringMachine {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.blank_symbol == other.blank_symbol
+            && self.states == other.states
+            && self.final_states == other.final_states
+            && self.head_start == other.head_start
+            && self.initial_state == other.initial_state
+            && self.output_convention == other.output_convention
+            && self.bounded == other.bounded
+            && self.nondeterministic == other.nondeterministic
+            && self.max_tape_cells == other.max_tape_cells
+    }
+}
+
+impl Eq for TuringMachine {}
+
+/// A one line summary, e.g. `"Flipper" (alphabet: {0, 1}, blank: '_', 2 states, 4 transitions)`.
+impl fmt::Display for TuringMachine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let alphabet = self
+            .alphabet()
+            .iter()
+            .map(char::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        write!(
+            f,
+            "\"{}\" (alphabet: {{{alphabet}}}, blank: '{}', {} states, {} transitions)",
+            self.name,
+            self.blank_symbol,
+            self.states.len(),
+            self.transition_count()
+        )
+    }
+}
+
+/// The whole definition, transition table included, and the current configuration.
+impl fmt::Debug for TuringMachine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TuringMachine")
+            .field("name", &self.name)
+            .field("blank_symbol", &self.blank_symbol)
+            .field("initial_state", &self.initial_state)
+            .field(
+                "final_states",
+                &self.final_states.iter().collect::<BTreeSet<_>>(),
+            )
+            .field("head_start", &self.head_start)
+            .field("effective_head_start", &self.effective_head_start)
+            .field("output_convention", &self.output_convention)
+            .field("bounded", &self.bounded)
+            .field("max_tape_cells", &self.max_tape_cells)
+            .field("states", &self.states.iter().collect::<BTreeMap<_, _>>())
+            .field("current_state", &self.current_state)
+            .field("head_idx", &self.head_idx)
+            .field("tape", &self.tape.to_string())
+            .field("halted", &self.halted)
+            .field("has_oracle", &self.oracle.is_some())
+            .finish()
+    }
 }
 
 impl TuringMachine {
-    pub fn new_from_file(filename: &str, tape_data: &str) -> Result<TuringMachine, String> {
-        let file_data = fs::read_to_string(filename)
-            .map_err(|_| format!("Could not open the file \"{}\"", filename))?;
+    pub fn new_from_file(filename: &str, tape_data: &str) -> Result<TuringMachine, MachineError> {
+        let file_data = fs::read_to_string(filename).map_err(|_| {
+            MachineError::new(
+                MachineErrorKind::Io,
+                format!("Could not open the file \"{}\"", filename),
+            )
+        })?;
+
+        Ok(Self::new_from_str(&file_data, tape_data)?)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(file_data), err(level = "debug"))
+    )]
+    pub fn new_from_str(file_data: &str, tape_data: &str) -> Result<TuringMachine, ParseError> {
+        let file_lines = parser::file_lines(file_data);
+
+        parser::parse_file(&file_lines, tape_data)
+    }
+
+    /// Loads a machine written for [turingmachine.io](https://turingmachine.io) in its YAML
+    /// format, so its examples run unchanged. The `input` of the file is replaced by
+    /// `tape_data`, a space as blank symbol becomes `_`, and the states without transitions are
+    /// the final states.
+    pub fn new_from_tm_yaml(file_data: &str, tape_data: &str) -> Result<TuringMachine, ParseError> {
+        parser::from_tm_yaml(file_data, tape_data)
+    }
+
+    /// Loads a single tape Turing Machine saved by [JFLAP](https://www.jflap.org) in its `.jff`
+    /// format. An empty read or write is the blank symbol, `_`.
+    pub fn new_from_jflap(file_data: &str, tape_data: &str) -> Result<TuringMachine, ParseError> {
+        parser::from_jflap(file_data, tape_data)
+    }
+
+    fn parse_tape(&self, tape_data: &str) -> Tape {
+        let tape = Tape::parse(tape_data, self.blank_symbol);
+
+        if self.bounded {
+            let mut content = vec![Symbol::Mark(LEFT_END_MARKER)];
+            content.extend_from_slice(tape.get_content());
+            content.push(Symbol::Mark(RIGHT_END_MARKER));
+            Tape::new(content)
+        } else if tape.is_empty() {
+            Tape::new(vec![Symbol::Blank]) // The empty input is a blank tape
+        } else {
+            tape
+        }
+    }
+
+    /// Brings the machine back to its initial state and head position, with a new tape.
+    /// If the tape is too short for `head_start`, the head starts at its last cell instead
+    /// (see [`TuringMachine::effective_head_start`]).
+    pub fn reset(&mut self, tape_data: &str) {
+        let tape = self.parse_tape(tape_data);
+        self.tape.set_cells(tape.get_content());
+        self.effective_head_start = self.head_start.min(self.tape.len() - 1);
+        self.head_idx = self.effective_head_start;
+        self.current_state = self.initial_state.clone();
+        self.halted = false;
+        self.out_of_bounds = false;
+        self.emitted.clear();
+        self.heatmap = Heatmap::new(&self.initial_state);
+
+        if self.history.is_some() {
+            self.history = Some(History::new(self));
+        }
+    }
+
+    /// Writes `symbol` on the cell at `idx` of the tape, extending the tape to the right with
+    /// blanks if it is shorter, without changing the state nor the head. Fails if the cell is
+    /// past the `max_tape_cells` of the machine or too far from the end of the tape, or, for
+    /// linear bounded automata, if it is not between the end markers.
+    pub fn set_cell(&mut self, idx: usize, symbol: Symbol) -> Result<(), MachineError> {
+        if self.bounded {
+            if !(1..self.tape.len() - 1).contains(&idx) {
+                return Err(MachineError::new(MachineErrorKind::InvalidCell, format!("[turing_lib] Error while changing a cell of the tape. The cell {idx} is not between the end markers, which are at the cells 0 and {}.", self.tape.len() - 1)));
+            }
+        } else if idx >= self.tape.len() {
+            let limit = (self.tape.len() + MAX_CELL_EXTENSION)
+                .min(self.max_tape_cells.unwrap_or(usize::MAX));
+            if idx >= limit {
+                return Err(MachineError::new(MachineErrorKind::InvalidCell, format!("[turing_lib] Error while changing a cell of the tape. The cell {idx} is too far, the tape can only be extended up to the cell {}.", limit.saturating_sub(1))));
+            }
+        }
+
+        while idx >= self.tape.len() {
+            self.tape.extend_right();
+        }
+        self.tape.write(idx, symbol);
+        self.amend_history();
+        Ok(())
+    }
+
+    /// Makes a change from outside of the definition of the machine, see [`crate::hook`], and
+    /// returns it as a tick with an [`Step::Intervention`] step, so it can be shown and
+    /// recorded like the ticks of the machine. Fails if the state doesn't exist, or if the cell
+    /// can't be changed (see [`TuringMachine::set_cell`]).
+    pub fn intervene(&mut self, intervention: &Intervention) -> Result<TickResult, MachineError> {
+        let mut tick_result = TickResult {
+            written_different_symbol: false,
+            extended_tape_on_side: None,
+            head_movement: HeadMovement::Stay,
+            step: Some(Step::Intervention(intervention.clone())),
+        };
+
+        match intervention {
+            Intervention::SetCell { cell, symbol } => {
+                if *symbol == Symbol::Default {
+                    return Err(MachineError::new(MachineErrorKind::InvalidSymbol, "[turing_lib] Error while changing a cell of the tape. The symbol can't be `default`."));
+                }
+
+                if *cell >= self.tape.len() {
+                    tick_result.extended_tape_on_side = Some(TapeSide::Right);
+                }
+                let old = if *cell < self.tape.len() {
+                    self.tape.read(*cell)
+                } else {
+                    Symbol::Blank
+                };
+                tick_result.written_different_symbol = old != *symbol;
+
+                self.set_cell(*cell, *symbol)?;
+            }
+            Intervention::SetState(state) => {
+                if !self.states.contains_key(state) {
+                    return Err(MachineError::new(MachineErrorKind::UnknownState, format!("[turing_lib] Error while changing the state. There is no state \"{state}\".")));
+                }
+
+                self.current_state.clone_from(state);
+                self.heatmap.visit_state(state);
+                self.amend_history();
+            }
+        }
+
+        Ok(tick_result)
+    }
+
+    /// Runs the machine as a function from its initial configuration with `input` on the tape, and
+    /// returns its output (following its output convention) if it halts in a final state.
+    /// It may never return if the machine does not halt, see [`TuringMachine::run_function_with_limits`].
+    pub fn run_function(&mut self, input: &str) -> Option<String> {
+        self.run_function_with_limits(input, &RunLimits::default())
+    }
+
+    /// Like [`TuringMachine::run_function`], but also returns `None` if the machine does not halt within the limits.
+    pub fn run_function_with_limits(&mut self, input: &str, limits: &RunLimits) -> Option<String> {
+        self.reset(input);
+
+        match runner::run(self, limits) {
+            RunOutcome::Halted { accepted: true, .. } => Some(self.output()),
+            _ => None,
+        }
+    }
+
+    /// The current output of the machine, following its output convention.
+    pub fn output(&self) -> String {
+        match self.output_convention {
+            OutputConvention::FromHead => self.word_at_head(),
+            OutputConvention::WholeTape => {
+                let Some((first, last)) = self.tape.storage().bounds() else {
+                    return String::new();
+                };
+
+                (first..=last)
+                    .map(|index| match self.tape.read(index) {
+                        Symbol::Mark(c) => c,
+                        _ => self.blank_symbol,
+                    })
+                    .collect::<String>()
+                    .trim_matches(self.blank_symbol)
+                    .to_string()
+            }
+        }
+    }
 
-        let file_lines = file_data
-            .lines()
-            .filter(|l| !l.is_empty())
-            .collect::<Vec<_>>();
+    /// How many times every state has been entered and every transition taken, since the
+    /// machine was loaded or reset.
+    pub fn heatmap(&self) -> &Heatmap {
+        &self.heatmap
+    }
 
-        let mut machine = parser::parse_file(&file_lines, Tape(vec![]))?;
-        let tape = Tape::parse(tape_data, machine.blank_symbol);
-        machine.tape = tape;
+    pub fn output_convention(&self) -> OutputConvention {
+        self.output_convention
+    }
 
-        Ok(machine)
+    pub fn set_output_convention(&mut self, output_convention: OutputConvention) {
+        self.output_convention = output_convention;
     }
 
     pub fn tick(&mut self) -> TickResult {
+        self.tick_with(None, true)
+    }
+
+    /// Ticks the machine like [`TuringMachine::tick`], but without describing the step in the
+    /// result (its `step` is `None`), for runs that only care about where the machine ends up.
+    /// The step is still recorded in the history, if there is one.
+    pub fn advance(&mut self) -> TickResult {
+        self.tick_with(None, false)
+    }
+
+    /// Ticks the machine taking the transition at `choice` among the ones it may take (see
+    /// [`TuringMachine::choices`]) instead of choosing a random one, to explore every branch of
+    /// a machine with random transitions (see [`crate::tree`]). Without so many choices, the
+    /// machine halts like when there is no transition.
+    pub fn tick_choosing(&mut self, choice: usize) -> TickResult {
+        self.tick_with(Some(choice), true)
+    }
+
+    /// How many transitions the next tick may take: more than one when the machine reads a
+    /// symbol with random transitions, and none when it is going to halt.
+    pub fn choices(&self) -> usize {
+        if self.halted {
+            return 0;
+        }
+
+        let state = &self.states[&self.current_state];
+        if state.query.is_some() || state.emit.is_some() {
+            1
+        } else {
+            state
+                .transition_choices(self.tape.read(self.head_idx))
+                .len()
+        }
+    }
+
+    /// Whether any run of the machine from its current configuration accepts, following every
+    /// choice of its random transitions (or of a non-deterministic machine) breadth first, see
+    /// [`tree::explore`]. `None` if no run accepts but some were cut short by the limits.
+    pub fn accepts_any(&self, limits: &TreeLimits) -> Option<bool> {
+        let tree = tree::explore(self, limits);
+
+        if tree.accepts() {
+            Some(true)
+        } else if tree.count(BranchEnd::Pending) > 0 {
+            None
+        } else {
+            Some(false)
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "tick",
+            level = "trace",
+            skip_all,
+            fields(machine = %self.name, state = %self.current_state, head = self.head_idx)
+        )
+    )]
+    fn tick_with(&mut self, choice: Option<usize>, describe: bool) -> TickResult {
+        let before = BeforeTick::of(self);
+        let tick_result = self.tick_definition(choice, describe || self.history.is_some());
+
+        if tick_result.step.is_some() {
+            if let Some(mut history) = self.history.take() {
+                history.record(self, &tick_result, before);
+                self.history = Some(history);
+            }
+        }
+
+        tick_result
+    }
+
+    /// Ticks the machine following its definition, without recording it in the history. The
+    /// step is only described if `describe` is set, as it takes copying the state names.
+    fn tick_definition(&mut self, choice: Option<usize>, describe: bool) -> TickResult {
         if self.halted {
             return TickResult {
                 written_different_symbol: false,
                 extended_tape_on_side: None,
                 head_movement: HeadMovement::Stay,
+                step: None,
             };
         }
 
-        let available_transitions = &self.states[&self.current_state].transitions;
-        let current_symbol = &self.tape.read(self.head_idx);
+        if let Some(query) = &self.states[&self.current_state].query {
+            return self.tick_query(query.clone(), describe);
+        }
+
+        if let Some(next_state) = &self.states[&self.current_state].emit {
+            let next_state = next_state.clone();
+            let word = self.word_at_head();
+            #[cfg(feature = "tracing")]
+            tracing::debug!(%word, "emitted");
+
+            let step = describe.then(|| Step::Emit {
+                state: self.current_state.clone(),
+                word: word.clone(),
+                new_state: next_state.clone(),
+            });
 
-        let transition = match current_symbol {
-            Symbol::Default => available_transitions.get(&TransitionSource::Default),
-            Symbol::Mark(c) => available_transitions.get(&TransitionSource::Mark(*c)),
-            Symbol::Blank => available_transitions.get(&TransitionSource::Blank),
+            self.emitted.push(word);
+            self.current_state = next_state;
+            self.heatmap.visit_state(&self.current_state);
+
+            return TickResult {
+                written_different_symbol: false,
+                extended_tape_on_side: None,
+                head_movement: HeadMovement::Stay,
+                step,
+            };
+        }
+
+        let current_symbol = &self.tape.read(self.head_idx);
+        let state = &self.states[&self.current_state];
+        let transition = match choice {
+            Some(choice) => state
+                .transition_choices(*current_symbol)
+                .get(choice)
+                .copied(),
+            None => state.choose_transition(*current_symbol, &mut self.rng),
         };
 
-        // Search for a default transition if none
-        let transition =
-            transition.or_else(|| available_transitions.get(&TransitionSource::Default));
+        if let Some((source, transition)) = transition {
+            let distance = transition.distance;
+            let past_left_end =
+                self.head_idx < distance && transition.head_movement == HeadMovement::Left;
+            let past_right_end = self.head_idx + distance >= self.tape.len()
+                && transition.head_movement == HeadMovement::Right;
+
+            if self.bounded && (past_left_end || past_right_end) {
+                self.halted = true;
+                self.out_of_bounds = true;
+
+                #[cfg(feature = "tracing")]
+                tracing::debug!("moved past an end marker");
+
+                return TickResult {
+                    written_different_symbol: false,
+                    extended_tape_on_side: None,
+                    head_movement: HeadMovement::Stay,
+                    step: describe.then(|| Step::OutOfBounds {
+                        state: self.current_state.clone(),
+                        read: *current_symbol,
+                        head_movement: transition.head_movement,
+                    }),
+                };
+            }
+
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                ?source,
+                new_symbol = ?transition.new_symbol,
+                head_movement = ?transition.head_movement,
+                new_state = %transition.new_state,
+                "transition"
+            );
+
+            self.heatmap.take_transition(&self.current_state, source);
 
-        if let Some(transition) = transition {
             let new_symbol = if let Symbol::Default = transition.new_symbol {
                 *current_symbol
             } else {
                 transition.new_symbol
             };
 
+            let step = describe.then(|| Step::Transition {
+                state: self.current_state.clone(),
+                read: *current_symbol,
+                written: new_symbol,
+                head_movement: transition.head_movement,
+                distance,
+                new_state: transition.new_state.clone(),
+                pacing: transition.pacing,
+            });
+
             self.tape.write(self.head_idx, new_symbol);
             self.current_state = transition.new_state.clone();
+            self.heatmap.visit_state(&self.current_state);
 
             let extended_tape_on_side = match transition.head_movement {
                 HeadMovement::Right => {
-                    self.head_idx += 1;
-                    if self.head_idx == self.tape.len() {
-                        self.tape.extend_right();
+                    self.head_idx += distance;
+                    if self.head_idx >= self.tape.len() {
+                        while self.head_idx >= self.tape.len() {
+                            self.tape.extend_right();
+                        }
                         Some(TapeSide::Right)
                     } else {
                         None
                     }
                 }
                 HeadMovement::Left => {
-                    if self.head_idx == 0 {
-                        self.tape.extend_left();
+                    if self.head_idx < distance {
+                        for _ in self.head_idx..distance {
+                            self.tape.extend_left();
+                        }
+                        self.head_idx = 0;
                         Some(TapeSide::Left)
                     } else {
-                        self.head_idx -= 1;
+                        self.head_idx -= distance;
                         None
                     }
                 }
@@ -184,20 +947,228 @@ impl TuringMachine {
                 written_different_symbol: new_symbol != *current_symbol,
                 extended_tape_on_side,
                 head_movement: transition.head_movement,
+                step,
             }
         } else {
             self.halted = true;
 
+            #[cfg(feature = "tracing")]
+            tracing::debug!(accepting = self.is_accepting(), "halted");
+
             TickResult {
                 written_different_symbol: false,
                 extended_tape_on_side: None,
                 head_movement: HeadMovement::Stay,
+                step: describe.then(|| Step::Halt {
+                    state: self.current_state.clone(),
+                    read: *current_symbol,
+                }),
+            }
+        }
+    }
+
+    /// Consults the oracle, halting if the machine has none.
+    fn tick_query(&mut self, query: OracleQuery, describe: bool) -> TickResult {
+        let word = self.word_at_head();
+        let state = describe.then(|| self.current_state.clone());
+
+        let answer = if let Some(oracle) = &self.oracle {
+            let answer = oracle.contains(&word);
+            self.current_state = if answer {
+                query.yes_state().to_string()
+            } else {
+                query.no_state().to_string()
+            };
+            self.heatmap.visit_state(&self.current_state);
+
+            Some(answer)
+        } else {
+            self.halted = true;
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!("halted on a query without an oracle");
+
+            None
+        };
+
+        TickResult {
+            written_different_symbol: false,
+            extended_tape_on_side: None,
+            head_movement: HeadMovement::Stay,
+            step: state.map(|state| Step::Query {
+                state,
+                word,
+                answer: answer.map(|answer| (answer, self.current_state.clone())),
+            }),
+        }
+    }
+
+    /// The word on the tape from the head up to the first blank.
+    fn word_at_head(&self) -> String {
+        (self.head_idx..self.tape.len())
+            .map_while(|index| match self.tape.read(index) {
+                Symbol::Mark(c) => Some(c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Words emitted so far by the states that have an `emit` line, in order.
+    pub fn emitted(&self) -> &[String] {
+        &self.emitted
+    }
+
+    /// Sets the oracle consulted by the states that have a `query` line.
+    pub fn set_oracle(&mut self, oracle: impl Oracle + 'static) {
+        self.oracle = Some(Arc::new(oracle));
+    }
+
+    pub fn has_oracle(&self) -> bool {
+        self.oracle.is_some()
+    }
+
+    /// Keeps the tape in `storage` from now on, with the cells it has now. The storage is kept
+    /// when the machine is reset or goes back in its history.
+    pub fn set_tape_storage(&mut self, storage: impl TapeStorage + 'static) {
+        self.tape.set_storage(storage);
+    }
+
+    /// Restarts the random number generator used by the random transitions from the given seed.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.amend_history();
+    }
+
+    /// Starts recording the configuration after every step, from the current one (the step 0),
+    /// so the machine can go back to any of them with [`TuringMachine::seek`]. Only what every
+    /// step changed is recorded (see [`History`]), so it can be enabled for long runs too.
+    pub fn enable_history(&mut self) {
+        if self.history.is_none() {
+            self.history = Some(History::new(self));
+        }
+    }
+
+    /// Stops recording the configurations, and forgets the recorded ones.
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// The recorded configurations, if the history is enabled.
+    pub fn history(&self) -> Option<&History> {
+        self.history.as_ref()
+    }
+
+    /// Moves the machine back (or forward again) to its configuration after `step` steps since
+    /// the history was enabled or the machine was reset, replaying at most 1024 recorded steps.
+    /// Ticking it then goes on from there, forgetting the steps after it. Fails if the history
+    /// is not enabled, or the step was not executed yet.
+    pub fn seek(&mut self, step: usize) -> Result<(), MachineError> {
+        let Some(mut history) = self.history.take() else {
+            return Err(MachineError::new(
+                MachineErrorKind::HistoryDisabled,
+                format!(
+                    "[turing_lib] Error while seeking step {step}. The history is not enabled."
+                ),
+            ));
+        };
+
+        let result = if history.seek(step, self) {
+            Ok(())
+        } else {
+            Err(MachineError::new(
+                MachineErrorKind::StepNotRecorded,
+                format!(
+                    "[turing_lib] Error while seeking step {step}. Only {} steps were executed.",
+                    history.len()
+                ),
+            ))
+        };
+        self.history = Some(history);
+
+        result
+    }
+
+    /// Takes the last step back, undoing what it wrote, the head move and the state change, so a
+    /// run can be rewound tick by tick. Fails if the history is not enabled, or the machine is
+    /// at its first recorded step.
+    pub fn step_back(&mut self) -> Result<(), MachineError> {
+        let Some(history) = &self.history else {
+            return Err(MachineError::new(
+                MachineErrorKind::HistoryDisabled,
+                "[turing_lib] Error while stepping back. The history is not enabled.",
+            ));
+        };
+
+        match history.position().checked_sub(1) {
+            Some(step) => self.seek(step),
+            None => Err(MachineError::new(MachineErrorKind::StepNotRecorded, "[turing_lib] Error while stepping back. The machine is at its first recorded step.")),
+        }
+    }
+
+    /// Finds the first recorded step after which `predicate` holds, e.g. the first step where
+    /// a cell became `x`, by bisection: it assumes that once the predicate holds, it keeps
+    /// holding. Leaves the machine at that step, or where it was if the predicate never holds.
+    /// Fails if the history is not enabled.
+    pub fn bisect<F>(&mut self, mut predicate: F) -> Result<Option<usize>, MachineError>
+    where
+        F: FnMut(&TuringMachine) -> bool,
+    {
+        let Some(history) = &self.history else {
+            return Err(MachineError::new(
+                MachineErrorKind::HistoryDisabled,
+                "[turing_lib] Error while bisecting the history. It is not enabled.",
+            ));
+        };
+        let position = history.position();
+
+        // The first step is in low..=high, where high means none
+        let (mut low, mut high) = (0, history.len() + 1);
+        while low < high {
+            let middle = (low + high) / 2;
+            self.seek(middle)?;
+            if predicate(self) {
+                high = middle;
+            } else {
+                low = middle + 1;
             }
         }
+
+        let found = self
+            .history
+            .as_ref()
+            .is_some_and(|history| low <= history.len());
+        self.seek(if found { low } else { position })?;
+
+        Ok(found.then_some(low))
+    }
+
+    /// Replaces the recorded configuration of the current step after a change between steps.
+    fn amend_history(&mut self) {
+        if let Some(mut history) = self.history.take() {
+            history.amend(self);
+            self.history = Some(history);
+        }
     }
 
     pub fn is_accepting(&self) -> bool {
-        self.halted && self.final_states.contains(&self.current_state)
+        self.halted && !self.out_of_bounds && self.final_states.contains(&self.current_state)
+    }
+
+    /// Whether it is a linear bounded automaton (`machine_type: lba`).
+    pub fn is_bounded(&self) -> bool {
+        self.bounded
+    }
+
+    /// Whether it is a non-deterministic machine (`machine_type: ndtm`), which accepts an input
+    /// if any of its runs does (see [`TuringMachine::accepts_any`]).
+    pub fn is_nondeterministic(&self) -> bool {
+        self.nondeterministic
+    }
+
+    /// The most tape cells the machine may use (`max_tape_cells` in its configuration), see
+    /// [`RunLimits::max_tape_cells`].
+    pub fn max_tape_cells(&self) -> Option<usize> {
+        self.max_tape_cells
     }
 
     pub fn name(&self) -> &str {
@@ -212,6 +1183,17 @@ impl TuringMachine {
         self.head_idx
     }
 
+    /// The index the head starts at, as set by the definition.
+    pub fn head_start(&self) -> usize {
+        self.head_start
+    }
+
+    /// The index the head started at on the current input, which is lower than
+    /// [`TuringMachine::head_start`] if the input was too short to start there.
+    pub fn effective_head_start(&self) -> usize {
+        self.effective_head_start
+    }
+
     pub fn current_state_name(&self) -> &str {
         &self.current_state
     }
@@ -223,4 +1205,145 @@ impl TuringMachine {
     pub fn tape(&self) -> &Tape {
         &self.tape
     }
+
+    pub fn states(&self) -> &HashMap<String, State> {
+        &self.states
+    }
+
+    pub fn final_states(&self) -> &HashSet<String> {
+        &self.final_states
+    }
+
+    /// Symbols (other than the blank one) that the transitions of the machine read or write.
+    pub fn alphabet(&self) -> BTreeSet<char> {
+        let mut alphabet = BTreeSet::new();
+
+        for state in self.states.values() {
+            let transitions = state.transitions.iter().chain(
+                state
+                    .random_transitions
+                    .iter()
+                    .flat_map(|(source, choices)| choices.iter().map(move |(_, t)| (source, t))),
+            );
+
+            for (source, transition) in transitions {
+                if let TransitionSource::Mark(c) = source {
+                    alphabet.insert(*c);
+                }
+                if let Symbol::Mark(c) = transition.new_symbol {
+                    alphabet.insert(c);
+                }
+            }
+        }
+
+        alphabet
+    }
+
+    /// Symbols of `tape_data` that no transition reads, so the machine halts as soon as it
+    /// reads one of them, which usually means the input is over the wrong alphabet. Empty if
+    /// some state has a `default` transition, since it may read any symbol.
+    pub fn unreadable_symbols(&self, tape_data: &str) -> BTreeSet<char> {
+        let sources = self
+            .states
+            .values()
+            .flat_map(|state| {
+                state
+                    .transitions
+                    .keys()
+                    .chain(state.random_transitions.keys())
+            })
+            .collect::<HashSet<_>>();
+
+        if sources.contains(&TransitionSource::Default) {
+            return BTreeSet::new();
+        }
+
+        tape_data
+            .chars()
+            .filter(|c| *c != self.blank_symbol && !sources.contains(&TransitionSource::Mark(*c)))
+            .collect()
+    }
+
+    /// Number of transitions, counting every weighted random transition.
+    pub fn transition_count(&self) -> usize {
+        self.states
+            .values()
+            .map(|state| {
+                state.transitions.len()
+                    + state
+                        .random_transitions
+                        .values()
+                        .map(Vec::len)
+                        .sum::<usize>()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn machine(config: &str, tape_data: &str) -> TuringMachine {
+        let file_data = format!("config {{\n    name: \"Cells\"\n    blank_symbol: '_'\n    head_start: 0\n{config}}}\n\nstates {{\n    state q0 is initial {{}}\n}}\n");
+        TuringMachine::new_from_str(&file_data, tape_data).unwrap()
+    }
+
+    fn set_cell_error(machine: &mut TuringMachine, idx: usize) -> Option<MachineErrorKind> {
+        machine
+            .set_cell(idx, Symbol::Mark('x'))
+            .err()
+            .map(|err| err.kind())
+    }
+
+    #[test]
+    fn set_cell_rejects_cells_past_max_tape_cells() {
+        let mut machine = machine("    max_tape_cells: 10\n", "ab");
+
+        assert_eq!(
+            set_cell_error(&mut machine, 10),
+            Some(MachineErrorKind::InvalidCell)
+        );
+        assert_eq!(machine.tape().len(), 2);
+
+        assert_eq!(set_cell_error(&mut machine, 9), None);
+        assert_eq!(machine.tape().len(), 10);
+    }
+
+    #[test]
+    fn set_cell_rejects_cells_too_far_from_the_tape() {
+        let mut machine = machine("", "ab");
+
+        assert_eq!(
+            set_cell_error(&mut machine, 200_000_000),
+            Some(MachineErrorKind::InvalidCell)
+        );
+        assert_eq!(machine.tape().len(), 2);
+
+        // Cells already on the tape can always be changed
+        assert_eq!(set_cell_error(&mut machine, 1), None);
+        assert_eq!(machine.tape().read(1), Symbol::Mark('x'));
+    }
+
+    #[test]
+    fn set_cell_keeps_bounded_machines_within_their_markers() {
+        let mut machine = machine("    machine_type: lba\n", "ab");
+        let tape = machine.tape().get_content().to_vec();
+
+        for idx in [0, 3, 8] {
+            assert_eq!(
+                set_cell_error(&mut machine, idx),
+                Some(MachineErrorKind::InvalidCell)
+            );
+        }
+        let intervention = Intervention::SetCell {
+            cell: 8,
+            symbol: Symbol::Mark('x'),
+        };
+        assert!(machine.intervene(&intervention).is_err());
+        assert_eq!(machine.tape().get_content(), tape);
+
+        assert_eq!(set_cell_error(&mut machine, 2), None);
+        assert_eq!(machine.tape().read(3), Symbol::Mark(RIGHT_END_MARKER));
+    }
 }
@@ -0,0 +1,236 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use crate::{
+    error::{MachineError, MachineErrorKind, ParseError},
+    machine::{HeadMovement, Symbol, TickResult},
+    parser,
+    tape::{Tape, TapeSide},
+};
+
+#[derive(Debug)]
+pub struct MultiTapeTransition {
+    new_symbols: Vec<Symbol>, // One per tape, Symbol::Default keeps the read symbol
+    head_movements: Vec<HeadMovement>,
+    new_state: String,
+}
+
+impl MultiTapeTransition {
+    pub fn new(
+        new_symbols: Vec<Symbol>,
+        head_movements: Vec<HeadMovement>,
+        new_state: String,
+    ) -> Self {
+        Self {
+            new_symbols,
+            head_movements,
+            new_state,
+        }
+    }
+
+    pub fn new_symbols(&self) -> &[Symbol] {
+        &self.new_symbols
+    }
+
+    pub fn head_movements(&self) -> &[HeadMovement] {
+        &self.head_movements
+    }
+
+    pub fn new_state(&self) -> &str {
+        &self.new_state
+    }
+}
+
+pub struct MultiTapeState {
+    name: String,
+    transitions: HashMap<Vec<Symbol>, MultiTapeTransition>, // Keyed by the symbols read on every tape
+}
+
+impl MultiTapeState {
+    pub fn new(name: String, transitions: HashMap<Vec<Symbol>, MultiTapeTransition>) -> Self {
+        Self { name, transitions }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn transitions(&self) -> &HashMap<Vec<Symbol>, MultiTapeTransition> {
+        &self.transitions
+    }
+}
+
+/// A Turing machine with several tapes, each one with its own head. The input is
+/// written on the first tape, and the rest of the tapes start blank.
+pub struct MultiTapeMachine {
+    pub(crate) name: String,
+    pub(crate) blank_symbol: char,
+    pub(crate) head_start: usize,
+
+    pub(crate) states: HashMap<String, MultiTapeState>,
+    pub(crate) final_states: HashSet<String>,
+    pub(crate) initial_state: String,
+
+    pub(crate) heads: Vec<usize>,
+    pub(crate) current_state: String,
+    pub(crate) tapes: Vec<Tape>,
+
+    pub(crate) halted: bool,
+}
+
+impl MultiTapeMachine {
+    pub fn new_from_file(
+        filename: &str,
+        tape_data: &str,
+    ) -> Result<MultiTapeMachine, MachineError> {
+        let file_data = fs::read_to_string(filename).map_err(|_| {
+            MachineError::new(
+                MachineErrorKind::Io,
+                format!("Could not open the file \"{}\"", filename),
+            )
+        })?;
+
+        Ok(Self::new_from_str(&file_data, tape_data)?)
+    }
+
+    pub fn new_from_str(file_data: &str, tape_data: &str) -> Result<MultiTapeMachine, ParseError> {
+        let file_lines = parser::file_lines(file_data);
+
+        let mut machine = parser::parse_multitape_file(&file_lines)?;
+        machine.tapes[0] = Tape::parse(tape_data, machine.blank_symbol);
+
+        // Heads always stay on an existing cell
+        for (tape, head) in machine.tapes.iter_mut().zip(&machine.heads) {
+            while tape.len() <= *head {
+                tape.extend_right();
+            }
+        }
+
+        Ok(machine)
+    }
+
+    pub fn tick(&mut self) -> TickResult {
+        let halted_result = TickResult {
+            written_different_symbol: false,
+            extended_tape_on_side: None,
+            head_movement: HeadMovement::Stay,
+            step: None,
+        };
+
+        if self.halted {
+            return halted_result;
+        }
+
+        let current_symbols = self
+            .tapes
+            .iter()
+            .zip(&self.heads)
+            .map(|(tape, head)| tape.read(*head))
+            .collect::<Vec<_>>();
+
+        let Some(transition) = self.states[&self.current_state]
+            .transitions
+            .get(&current_symbols)
+        else {
+            self.halted = true;
+            return halted_result;
+        };
+
+        // Only the first tape is reported back, as it is the one frontends show
+        let mut first_tape_result = None;
+
+        for (i, tape) in self.tapes.iter_mut().enumerate() {
+            let new_symbol = match transition.new_symbols[i] {
+                Symbol::Default => current_symbols[i],
+                symbol => symbol,
+            };
+            tape.write(self.heads[i], new_symbol);
+
+            let head = &mut self.heads[i];
+            let extended_tape_on_side = match transition.head_movements[i] {
+                HeadMovement::Right => {
+                    *head += 1;
+                    if *head == tape.len() {
+                        tape.extend_right();
+                        Some(TapeSide::Right)
+                    } else {
+                        None
+                    }
+                }
+                HeadMovement::Left => {
+                    if *head == 0 {
+                        tape.extend_left();
+                        Some(TapeSide::Left)
+                    } else {
+                        *head -= 1;
+                        None
+                    }
+                }
+                HeadMovement::Stay => None,
+            };
+
+            if i == 0 {
+                first_tape_result = Some(TickResult {
+                    written_different_symbol: new_symbol != current_symbols[i],
+                    extended_tape_on_side,
+                    head_movement: transition.head_movements[i],
+                    step: None,
+                });
+            }
+        }
+
+        self.current_state = transition.new_state.clone();
+
+        first_tape_result.unwrap()
+    }
+
+    pub fn is_accepting(&self) -> bool {
+        self.halted && self.final_states.contains(&self.current_state)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn blank_symbol(&self) -> char {
+        self.blank_symbol
+    }
+
+    pub fn head_start(&self) -> usize {
+        self.head_start
+    }
+
+    pub fn tape_count(&self) -> usize {
+        self.tapes.len()
+    }
+
+    pub fn heads(&self) -> &[usize] {
+        &self.heads
+    }
+
+    pub fn tapes(&self) -> &[Tape] {
+        &self.tapes
+    }
+
+    pub fn current_state_name(&self) -> &str {
+        &self.current_state
+    }
+
+    pub fn initial_state_name(&self) -> &str {
+        &self.initial_state
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn states(&self) -> &HashMap<String, MultiTapeState> {
+        &self.states
+    }
+
+    pub fn final_states(&self) -> &HashSet<String> {
+        &self.final_states
+    }
+}
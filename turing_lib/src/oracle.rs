@@ -0,0 +1,52 @@
+use std::collections::{BTreeSet, HashSet};
+
+/// Answers membership queries for a set of words, which a machine can consult from
+/// states declared with a `query` line (see [`OracleQuery`]).
+pub trait Oracle: Send + Sync {
+    fn contains(&self, word: &str) -> bool;
+}
+
+impl<F: Fn(&str) -> bool + Send + Sync> Oracle for F {
+    fn contains(&self, word: &str) -> bool {
+        self(word)
+    }
+}
+
+impl Oracle for HashSet<String> {
+    fn contains(&self, word: &str) -> bool {
+        HashSet::contains(self, word)
+    }
+}
+
+impl Oracle for BTreeSet<String> {
+    fn contains(&self, word: &str) -> bool {
+        BTreeSet::contains(self, word)
+    }
+}
+
+/// A state that, instead of having transitions, asks the oracle whether the word on the tape
+/// (from the head up to the first blank) belongs to its set, and enters one state or the other
+/// depending on the answer, without writing nor moving the head.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OracleQuery {
+    yes_state: String,
+    no_state: String,
+}
+
+impl OracleQuery {
+    pub fn new(yes_state: String, no_state: String) -> Self {
+        Self {
+            yes_state,
+            no_state,
+        }
+    }
+
+    pub fn yes_state(&self) -> &str {
+        &self.yes_state
+    }
+
+    pub fn no_state(&self) -> &str {
+        &self.no_state
+    }
+}
@@ -1,210 +1,618 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt,
+    ops::{Deref, Range},
+};
+
+use yaml_rust2::{Yaml, YamlLoader};
 
 use crate::{
-    machine::{HeadMovement, State, Symbol, Transition, TransitionSource, TuringMachine},
-    tape::Tape,
+    automaton::MachineType,
+    builder::{LineOrigin, StateLine, TuringMachineBuilder},
+    counter::{CounterMachine, Instruction},
+    error::{Location, ParseError, ParseErrorKind},
+    finite::{FiniteAutomaton, FiniteState},
+    machine::{
+        HeadMovement, OutputConvention, Pacing, Symbol, Transition, TransitionSource,
+        TuringMachine, LEFT_END_MARKER, RIGHT_END_MARKER,
+    },
+    multitape::{MultiTapeMachine, MultiTapeState, MultiTapeTransition},
+    pda::{PushdownAutomaton, PushdownSource, PushdownState, PushdownTransition},
+    tape::{Tape, DEFAULT_BLANK_SYMBOL},
 };
 
+// Limits that keep malformed files from allocating without bound
+const MAX_REGISTERS: usize = 1024;
+const MAX_TAPES: usize = 64;
+const MAX_JUMP_DISTANCE: usize = 1024;
+
 struct Config {
     name: String,
     blank_symbol: char,
     head_start: usize,
+    seed: u64,
+    output: OutputConvention,
+    bounded: bool,
+    nondeterministic: bool,
+    max_tape_cells: Option<usize>,
+}
+
+struct StateHeader<'sh> {
+    name: Token<'sh>,
+    description: Option<&'sh str>,
+    is_initial: bool,
+    is_final: bool,
+}
+
+/// The options of the `config` block, with their values as written in the file.
+type ConfigMap<'c> = HashMap<&'static str, Token<'c>>;
+
+/// A line of a file to parse, with its number in the file (starting at 1), so errors can tell
+/// where they are.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Line<'l> {
+    number: usize,
+    text: &'l str,
 }
 
-pub fn parse_file(file_lines: &[&str], tape: Tape) -> Result<TuringMachine, String> {
+/// Splits a file into the lines to parse, skipping the empty lines and the comments
+/// (lines starting with `//`, doc comments `///` and `//!` included).
+pub(crate) fn file_lines(file_data: &str) -> Vec<Line<'_>> {
+    file_data
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.trim_start().starts_with("//"))
+        .map(|(idx, text)| Line {
+            number: idx + 1,
+            text,
+        })
+        .collect()
+}
+
+impl<'l> Line<'l> {
+    /// The whole line, as a token.
+    fn token(self) -> Token<'l> {
+        Token {
+            line: self,
+            offset: 0,
+            text: self.text,
+        }
+    }
+}
+
+/// A piece of one of the lines, with its byte offset in the line, so errors can tell where it is.
+/// It only gets split and trimmed through its own methods, which keep the offset right.
+#[derive(Debug, Clone, Copy)]
+struct Token<'l> {
+    line: Line<'l>,
+    offset: usize,
+    text: &'l str,
+}
+
+impl<'l> Token<'l> {
+    /// The part of the token in the given byte range.
+    fn slice(self, range: Range<usize>) -> Self {
+        Token {
+            line: self.line,
+            offset: self.offset + range.start,
+            text: &self.text[range],
+        }
+    }
+
+    fn trim(self) -> Self {
+        self.trim_start().trim_end()
+    }
+
+    fn trim_start(self) -> Self {
+        let start = self.text.len() - self.text.trim_start().len();
+        self.slice(start..self.text.len())
+    }
+
+    fn trim_end(self) -> Self {
+        self.slice(0..self.text.trim_end().len())
+    }
+
+    fn trim_start_matches(self, pattern: char) -> Self {
+        let start = self.text.len() - self.text.trim_start_matches(pattern).len();
+        self.slice(start..self.text.len())
+    }
+
+    fn trim_end_matches(self, pattern: char) -> Self {
+        self.slice(0..self.text.trim_end_matches(pattern).len())
+    }
+
+    fn split(self, separator: &str) -> Vec<Self> {
+        let mut start = 0;
+        self.text
+            .split(separator)
+            .map(|part| {
+                let token = self.slice(start..start + part.len());
+                start += part.len() + separator.len();
+                token
+            })
+            .collect()
+    }
+
+    fn split_whitespace(self) -> Vec<Self> {
+        let mut start = 0;
+        self.text
+            .split_inclusive(char::is_whitespace)
+            .filter_map(|part| {
+                let word = self.slice(start..start + part.trim_end().len());
+                start += part.len();
+                (!word.text.is_empty()).then_some(word)
+            })
+            .collect()
+    }
+
+    fn split_once(self, separator: &str) -> Option<(Self, Self)> {
+        let at = self.text.find(separator)?;
+        Some((
+            self.slice(0..at),
+            self.slice(at + separator.len()..self.text.len()),
+        ))
+    }
+
+    fn rsplit_once(self, separator: &str) -> Option<(Self, Self)> {
+        let at = self.text.rfind(separator)?;
+        Some((
+            self.slice(0..at),
+            self.slice(at + separator.len()..self.text.len()),
+        ))
+    }
+
+    /// The token, with its line and column (in chars, starting at 1).
+    fn location(self) -> Location {
+        Location {
+            token: self.text.to_string(),
+            line: self.line.number,
+            column: self.line.text[..self.offset].chars().count() + 1,
+        }
+    }
+}
+
+impl Deref for Token<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.text
+    }
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text)
+    }
+}
+
+impl ParseError {
+    /// Sets the offending token, and where it is.
+    fn at_token(self, token: Token) -> Self {
+        self.at_location(&token.location())
+    }
+}
+
+pub fn parse_file(file_lines: &[Line], tape_data: &str) -> Result<TuringMachine, ParseError> {
     let config: Config = parse_config(file_lines)?;
-    let (states, final_states, starting_state) = parse_states(file_lines, config.blank_symbol)?;
+    let builder = TuringMachineBuilder::new(&config.name)
+        .set_blank(config.blank_symbol)
+        .set_head_start(config.head_start)
+        .set_seed(config.seed)
+        .set_output_convention(config.output)
+        .set_bounded(config.bounded)
+        .set_nondeterministic(config.nondeterministic)
+        .set_max_tape_cells(config.max_tape_cells);
 
-    Ok(TuringMachine {
-        name: config.name,
-        blank_symbol: config.blank_symbol,
+    parse_states(file_lines, config.blank_symbol, builder)?.build(tape_data)
+}
 
-        states,
-        final_states,
+pub(crate) fn parse_machine_type(file_lines: &[Line]) -> Result<MachineType, ParseError> {
+    let config_map = parse_config_entries(file_lines)?;
 
-        head_idx: config.head_start,
-        current_state: starting_state,
-        tape,
+    let Some(&machine_type) = config_map.get("machine_type") else {
+        return Ok(MachineType::Turing);
+    };
 
-        halted: false,
-    })
+    match machine_type.text {
+        "turing" | "lba" | "ndtm" => Ok(MachineType::Turing),
+        "pda" => Ok(MachineType::Pushdown),
+        "counter" => Ok(MachineType::Counter),
+        "finite" | "dfa" | "nfa" => Ok(MachineType::Finite),
+        "multitape" => Ok(MachineType::MultiTape),
+        _ => Err(ParseError::new(ParseErrorKind::InvalidMachineType, format!("[turing_lib] Error while parsing configuration. Unknown machine type \"{machine_type}\".")).at_token(machine_type)),
+    }
 }
 
-fn parse_config(file_data: &[&str]) -> Result<Config, String> {
-    let config_lines = file_data.iter().skip_while(|&&l| l != "config {").skip(1);
+fn parse_config_entries<'l>(file_lines: &[Line<'l>]) -> Result<ConfigMap<'l>, ParseError> {
+    let config_lines = file_lines
+        .iter()
+        .skip_while(|l| l.text != "config {")
+        .skip(1)
+        .map(|l| l.token());
     let mut config_map = HashMap::new();
 
     for line in config_lines {
-        match line.trim() {
-            "}" => {
-                break;
+        let line = line.trim();
+        if line.text == "}" {
+            break;
+        }
+
+        let (option, value) = match line.split(": ")[..] {
+            [option, value] => (option.text, value),
+            _ => return Err(ParseError::new(ParseErrorKind::UnexpectedLine, format!("[turing_lib] Error while parsing configuration. Unexpected line found: \"{line}\".")).at_token(line)),
+        };
+
+        match option {
+            "name" => {
+                if value.starts_with("\"") && value.ends_with("\"") {
+                    config_map.insert(
+                        "name",
+                        value.trim_start_matches('"').trim_end_matches('"'),
+                    );
+                } else {
+                    return Err(
+                            ParseError::new(ParseErrorKind::InvalidOption, "[turing_lib] Error while parsing configuration. Unexpected name value. It must be between double quotes (e.g. name: \"A name for the machine\").").at_token(value));
+                }
             }
-            line => match line.split(": ").collect::<Vec<_>>()[..] {
-                ["name", name] => {
-                    if name.starts_with("\"") && name.ends_with("\"") {
-                        config_map.insert(
-                            "name",
-                            name.trim_start_matches("\"")
-                                .trim_end_matches("\"")
-                                .to_string(),
-                        );
-                    } else {
-                        return Err(
-                                "[turing_lib] Error while parsing configuration. Unexpected name value. It must be between double quotes (e.g. name: \"A name for the machine\").".to_string());
-                    }
+            "blank_symbol" => match value.chars().collect::<Vec<_>>()[..] {
+                ['\'', _, '\''] => {
+                    config_map.insert("blank_symbol", value.slice(1..value.len() - 1));
                 }
-                ["blank_symbol", symbol] => match symbol.chars().collect::<Vec<_>>()[..] {
-                    ['\'', symbol, '\''] => {
-                        config_map.insert("blank_symbol", symbol.to_string());
-                    }
-                    _ => {
-                        return Err("[turing_lib] Error while parsing configuration. Unexpected blank symbol. It must be a valid char between single quotes (e.g. blank_symbol: '_').".to_string());
-                    }
-                },
-                ["head_start", index] => {
-                    config_map.insert("head_start", index.to_string());
+                _ => {
+                    return Err(ParseError::new(ParseErrorKind::InvalidOption, "[turing_lib] Error while parsing configuration. Unexpected blank symbol. It must be a valid char between single quotes (e.g. blank_symbol: '_').").at_token(value));
+                }
+            },
+            "stack_start" => match value.chars().collect::<Vec<_>>()[..] {
+                ['\'', _, '\''] => {
+                    config_map.insert("stack_start", value.slice(1..value.len() - 1));
+                }
+                _ => {
+                    return Err(ParseError::new(ParseErrorKind::InvalidOption, "[turing_lib] Error while parsing configuration. Unexpected stack start symbol. It must be a valid char between single quotes (e.g. stack_start: 'Z').").at_token(value));
                 }
-                _ => return Err(format!("[turing_lib] Error while parsing configuration. Unexpected line found: \"{line}\"."))
             },
+            "head_start" => {
+                config_map.insert("head_start", value);
+            }
+            "registers" => {
+                config_map.insert("registers", value);
+            }
+            "tapes" => {
+                config_map.insert("tapes", value);
+            }
+            "seed" => {
+                config_map.insert("seed", value);
+            }
+            "output" => {
+                config_map.insert("output", value);
+            }
+            "max_tape_cells" => {
+                config_map.insert("max_tape_cells", value);
+            }
+            "machine_type" => {
+                config_map.insert("machine_type", value);
+            }
+            _ => return Err(ParseError::new(ParseErrorKind::UnexpectedLine, format!("[turing_lib] Error while parsing configuration. Unexpected line found: \"{line}\".")).at_token(line)),
         }
     }
 
     if config_map.is_empty() {
-        return Err(
-            "[turing_lib] Error while parsing configuration. There was no configuration provided."
-                .to_string(),
-        );
+        return Err(ParseError::new(
+            ParseErrorKind::MissingConfig,
+            "[turing_lib] Error while parsing configuration. There was no configuration provided.",
+        ));
     }
 
-    let name = config_map.remove("name").ok_or_else(|| {
-        "[turing_lib] Error while parsing configuration. There was no name provided.".to_string()
+    Ok(config_map)
+}
+
+fn parse_name(config_map: &mut ConfigMap) -> Result<String, ParseError> {
+    config_map
+        .remove("name")
+        .map(|name| name.to_string())
+        .ok_or_else(|| {
+            ParseError::new(
+                ParseErrorKind::MissingOption,
+                "[turing_lib] Error while parsing configuration. There was no name provided.",
+            )
+        })
+}
+
+fn parse_blank_symbol(config_map: &ConfigMap) -> Result<char, ParseError> {
+    let &value = config_map.get("blank_symbol").ok_or_else(|| {
+        ParseError::new(
+            ParseErrorKind::MissingOption,
+            "[turing_lib] Error while parsing configuration. There was no blank symbol provided.",
+        )
     })?;
+    let symbol = value.chars().next().unwrap();
 
-    let blank_symbol = {
-        let symbol = config_map
-            .get("blank_symbol")
-            .ok_or_else(|| "[turing_lib] Error while parsing configuration. There was no blank symbol provided.".to_string())?;
-        symbol.chars().next().unwrap()
-    };
+    // Transitions are split by commas and trimmed, so these could never be told apart
+    if symbol == ',' || symbol.is_whitespace() {
+        return Err(ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while parsing configuration. Invalid blank symbol '{symbol}'. It can't be a comma nor a whitespace, since transitions couldn't read or write it.")).at_token(value));
+    }
+
+    Ok(symbol)
+}
+
+/// Fails if any of the given options, which are not used by this kind of machine, was provided.
+fn reject_options(
+    config_map: &ConfigMap,
+    options: &[&str],
+    machine_kind: &str,
+) -> Result<(), ParseError> {
+    match options.iter().find(|option| config_map.contains_key(*option)) {
+        Some(option) => Err(ParseError::new(ParseErrorKind::UnexpectedOption, format!("[turing_lib] Error while parsing configuration. The \"{option}\" option is not allowed for {machine_kind}.")).at_token(config_map[option])),
+        None => Ok(()),
+    }
+}
+
+fn parse_config(file_lines: &[Line]) -> Result<Config, ParseError> {
+    let mut config_map = parse_config_entries(file_lines)?;
+
+    if parse_machine_type(file_lines)? != MachineType::Turing {
+        return Err(ParseError::new(ParseErrorKind::InvalidMachineType, "[turing_lib] Error while parsing configuration. The machine type is not a Turing machine."));
+    }
+
+    reject_options(
+        &config_map,
+        &["stack_start", "registers", "tapes"],
+        "Turing machines",
+    )?;
+
+    let name = parse_name(&mut config_map)?;
+    let blank_symbol = parse_blank_symbol(&config_map)?;
 
     let head_start = {
-        let index = config_map
+        let &index = config_map
             .get("head_start")
-            .ok_or_else(|| "[turing_lib] Error while parsing configuration. There was no head start index provided.".to_string())?;
+            .ok_or_else(|| ParseError::new(ParseErrorKind::MissingOption, "[turing_lib] Error while parsing configuration. There was no head start index provided."))?;
+
+        index.parse().map_err(|_| ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while parsing configuration. Invalid head start index provided (\"{index}\"). It must be a non negative integer.")).at_token(index))?
+    };
+
+    let output = match config_map.get("output") {
+        None => OutputConvention::FromHead,
+        Some(&output) => match output.text {
+            "head" => OutputConvention::FromHead,
+            "tape" => OutputConvention::WholeTape,
+            _ => return Err(ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while parsing configuration. Unknown output convention \"{output}\". It must be \"head\" or \"tape\".")).at_token(output)),
+        },
+    };
+
+    let seed = match config_map.get("seed") {
+        Some(&seed) => seed.parse().map_err(|_| ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while parsing configuration. Invalid seed provided (\"{seed}\"). It must be a non negative integer.")).at_token(seed))?,
+        None => 0,
+    };
+
+    let bounded = config_map
+        .get("machine_type")
+        .is_some_and(|t| t.text == "lba");
+    let nondeterministic = config_map
+        .get("machine_type")
+        .is_some_and(|t| t.text == "ndtm");
+
+    if bounded && [LEFT_END_MARKER, RIGHT_END_MARKER].contains(&blank_symbol) {
+        return Err(ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while parsing configuration. Invalid blank symbol '{blank_symbol}'. It can't be an end marker of linear bounded automata ('{LEFT_END_MARKER}' nor '{RIGHT_END_MARKER}').")).at_token(config_map["blank_symbol"]));
+    }
 
-        index.parse().map_err(|_| format!("[turing_lib] Error while parsing configuration. Invalid head start index provided (\"{index}\"). It must be a non negative integer."))?
+    let max_tape_cells = match config_map.get("max_tape_cells") {
+        Some(&count) => Some(count.parse().ok().filter(|&count| count > 0).ok_or_else(|| ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while parsing configuration. Invalid maximum tape cells provided (\"{count}\"). It must be a positive integer.")).at_token(count))?),
+        None => None,
     };
 
     Ok(Config {
         name,
         blank_symbol,
         head_start,
+        seed,
+        output,
+        bounded,
+        nondeterministic,
+        max_tape_cells,
     })
 }
 
-fn parse_states(
-    file_data: &[&str],
-    blank_symbol: char,
-) -> Result<(HashMap<String, State>, HashSet<String>, String), String> {
-    struct ParsingState<'ps> {
-        is_initial: bool,
-        is_final: bool,
-        name: &'ps str,
-        transitions: HashMap<TransitionSource, Transition>,
-    }
+/// Parses a state declaration line (without its braces), e.g. `state q0 is initial`, or
+/// `state q0 "reads the first symbol" is initial` with a description.
+fn parse_state_header<'l>(line: Token<'l>) -> Option<StateHeader<'l>> {
+    let (words, description) = match (line.find('"'), line.rfind('"')) {
+        (Some(start), Some(end)) if start < end => {
+            // The description goes right after the name of the state
+            let mut words = line.slice(0..start).split_whitespace();
+            if words.len() != 2 {
+                return None;
+            }
 
-    let mut states = HashMap::new();
-    let mut final_states = HashSet::new();
-    let mut transition_states = HashSet::new(); // To check if all transitions are valid
-    let mut initial_state_name = None;
+            words.extend(line.slice(end + 1..line.len()).split_whitespace());
+            (words, Some(&line.text[start + 1..end]))
+        }
+        (None, None) => (line.split_whitespace(), None),
+        _ => return None,
+    };
 
-    let state_lines = file_data.iter().skip_while(|&&l| l != "states {").skip(1);
+    let (is_initial, is_final) = match words.iter().map(|word| word.text).collect::<Vec<_>>()[..] {
+        ["state", _, "is", "initial", "and", "final"]
+        | ["state", _, "is", "final", "and", "initial"] => (true, true),
+        ["state", _, "is", "final"] => (false, true),
+        ["state", _, "is", "initial"] => (true, false),
+        ["state", _] => (false, false),
+        _ => return None,
+    };
 
-    let mut current_state: Option<ParsingState> = None;
+    Some(StateHeader {
+        name: words[1],
+        description,
+        is_initial,
+        is_final,
+    })
+}
 
-    let mut append_state = |state: ParsingState<'_>| -> Result<_, String> {
-        if state.is_initial {
-            if initial_state_name.is_some() {
-                return Err("[turing_lib] Error while parsing states. There was more than one initial state provided.".to_string());
-            }
+/// Name and description of the state declared in a line, if it is a state declaration.
+pub(crate) fn declared_state(line: &str) -> Option<(&str, Option<&str>)> {
+    let line = Line {
+        number: 0,
+        text: line,
+    }
+    .token();
+    parse_state_header(split_state_line(line).0)
+        .map(|header| (header.name.text, header.description))
+}
 
-            initial_state_name = Some(state.name.to_string());
-        }
+/// Parses a head movement, `L`, `R` or `S`, or a jump of several cells like `R3` or `L2`.
+pub(crate) fn parse_head_movement(token: &str) -> Option<(HeadMovement, usize)> {
+    let (head_movement, distance) = match token.split_at_checked(1)? {
+        ("L", distance) => (HeadMovement::Left, distance),
+        ("R", distance) => (HeadMovement::Right, distance),
+        ("S", "") => return Some((HeadMovement::Stay, 1)),
+        _ => return None,
+    };
 
-        if state.is_final {
-            final_states.insert(state.name.to_string());
-        }
+    if distance.is_empty() {
+        return Some((head_movement, 1));
+    }
 
-        states.insert(
-            state.name.to_string(),
-            State::new(state.name.to_string(), state.transitions),
-        );
-        Ok(())
+    if !distance.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    distance
+        .parse()
+        .ok()
+        .filter(|distance| (1..=MAX_JUMP_DISTANCE).contains(distance))
+        .map(|distance| (head_movement, distance))
+}
+
+/// Splits a pacing hint (` @slow` or ` @fast`) from the end of a transition line.
+fn split_pacing_hint<'l>(line: Token<'l>) -> Result<(Token<'l>, Option<Pacing>), ParseError> {
+    let Some((transition, hint)) = line.rsplit_once(" @") else {
+        return Ok((line, None));
     };
 
-    for line in state_lines {
-        match line.trim() {
+    let pacing = match hint.trim().text {
+        "slow" => Pacing::Slow,
+        "fast" => Pacing::Fast,
+        _ => return Err(ParseError::new(ParseErrorKind::InvalidPacing, format!("[turing_lib] Error while parsing a transition. Unexpected pacing hint found at line \"{line}\". It must be \"@slow\" or \"@fast\".")).at_token(hint)),
+    };
+
+    Ok((transition.trim_end(), Some(pacing)))
+}
+
+/// Splits a state block line into its declaration part and whether the state is declared empty (`{}`).
+fn split_state_line<'l>(line: Token<'l>) -> (Token<'l>, bool) {
+    if line.trim().ends_with("}") {
+        (
+            line.trim().trim_end_matches('}').trim_end_matches('{'),
+            true,
+        )
+    } else {
+        (line.trim().trim_end_matches('{'), false)
+    }
+}
+
+/// Parses the `states` block of a Turing machine into `builder`, which checks the states and
+/// their lines when building the machine.
+fn parse_states(
+    file_lines: &[Line],
+    blank_symbol: char,
+    mut builder: TuringMachineBuilder,
+) -> Result<TuringMachineBuilder, ParseError> {
+    let mut initial_state_name = None;
+    let mut current_state: Option<&str> = None;
+
+    let state_lines = file_lines
+        .iter()
+        .skip_while(|l| l.text != "states {")
+        .skip(1)
+        .map(|l| l.token().trim());
+
+    for full_line in state_lines {
+        match full_line.text {
             "}" => {
-                if current_state.is_some() {
-                    append_state(current_state.take().unwrap())?;
-                } else {
+                if current_state.take().is_none() {
                     break;
                 }
             }
-            line => {
-                let (state_def_line, is_empty_state) = if line.trim().ends_with("}") {
-                    (
-                        line.trim().trim_end_matches("}").trim_end_matches("{"),
-                        true,
-                    )
-                } else {
-                    (line.trim().trim_end_matches("{"), false)
-                };
+            _ => {
+                let (line, pacing) = split_pacing_hint(full_line)?;
+                let (state_def_line, is_empty_state) = split_state_line(line);
 
-                match state_def_line.split_whitespace().collect::<Vec<_>>()[..] {
-                    ["state", state_name, "is", "initial", "and", "final"]
-                    | ["state", state_name, "is", "final", "and", "initial"] => {
-                        current_state = Some(ParsingState {
-                            is_initial: true,
-                            is_final: true,
-                            name: state_name,
-                            transitions: HashMap::new(),
-                        });
-                    }
-                    ["state", state_name, "is", "final"] => {
-                        current_state = Some(ParsingState {
-                            is_initial: false,
-                            is_final: true,
-                            name: state_name,
-                            transitions: HashMap::new(),
-                        });
-                    }
-                    ["state", state_name, "is", "initial"] => {
-                        current_state = Some(ParsingState {
-                            is_initial: true,
-                            is_final: false,
-                            name: state_name,
-                            transitions: HashMap::new(),
-                        });
-                    }
-                    ["state", state_name] => {
-                        current_state = Some(ParsingState {
-                            is_initial: false,
-                            is_final: false,
-                            name: state_name,
-                            transitions: HashMap::new(),
-                        });
+                let is_transition = parse_state_header(state_def_line).is_none()
+                    && !line.starts_with("emit,")
+                    && !line.starts_with("query,");
+                if pacing.is_some() && !is_transition {
+                    return Err(ParseError::new(ParseErrorKind::InvalidPacing, format!("[turing_lib] Error while parsing a state. Unexpected pacing hint found at line \"{full_line}\". Only transitions can have one.")).at_token(full_line));
+                }
+
+                match parse_state_header(state_def_line) {
+                    Some(header) => {
+                        if header.is_initial {
+                            if initial_state_name.is_some() {
+                                return Err(ParseError::new(ParseErrorKind::DuplicateInitialState, "[turing_lib] Error while parsing states. There was more than one initial state provided.").at_token(header.name));
+                            }
+
+                            initial_state_name = Some(header.name);
+                            builder = builder.set_initial(&header.name);
+                        }
+                        if header.is_final {
+                            builder = builder.mark_final(&header.name);
+                        }
+
+                        builder = builder.add_state(&header.name);
+                        if let Some(description) = header.description {
+                            builder = builder.set_description(&header.name, description);
+                        }
+                        current_state = Some(header.name.text);
                     }
-                    _ => match line.trim().split(",").collect::<Vec<_>>()[..] {
-                        [reading_symbol, writing_symbol, head_movement, new_state_name] => {
+                    None => match line.trim().split(",")[..] {
+                        [Token { text: "emit", .. }, next_state_name] => {
+                            let Some(state) = current_state else {
+                                return Err(ParseError::new(ParseErrorKind::UnexpectedLine, "[turing_lib] Error while parsing a state. Unexpected emit declaration outside a state.").at_token(line));
+                            };
+
+                            let origin = LineOrigin {
+                                line: line.location(),
+                                new_states: vec![next_state_name.location()],
+                                weight: None,
+                            };
+                            builder = builder.add_line(
+                                state,
+                                StateLine::Emit(next_state_name.to_string()),
+                                Some(origin),
+                            );
+                        }
+                        [Token { text: "query", .. }, yes_state_name, no_state_name] => {
+                            let Some(state) = current_state else {
+                                return Err(ParseError::new(ParseErrorKind::UnexpectedLine, "[turing_lib] Error while parsing a state. Unexpected query declaration outside a state.").at_token(line));
+                            };
+
+                            let origin = LineOrigin {
+                                line: line.location(),
+                                new_states: vec![
+                                    yes_state_name.location(),
+                                    no_state_name.location(),
+                                ],
+                                weight: None,
+                            };
+                            builder = builder.add_line(
+                                state,
+                                StateLine::Query(
+                                    yes_state_name.to_string(),
+                                    no_state_name.to_string(),
+                                ),
+                                Some(origin),
+                            );
+                        }
+                        [reading_symbol, writing_symbol, head_movement, new_state_name, ref weight @ ..]
+                            if weight.len() <= 1 =>
+                        {
                             let reading_symbol = {
-                                match &reading_symbol[..] {
+                                match reading_symbol.text {
                                     "default" => TransitionSource::Default,
                                     _ => {
-                                        if reading_symbol.len() != 1 {
-                                            return Err(format!(
+                                        if reading_symbol.chars().count() != 1 {
+                                            return Err(ParseError::new(ParseErrorKind::InvalidSymbol, format!(
                                                 "[turing_lib] Error while parsing a state. Invalid reading symbol found at line \"{line}\"."
-                                            ));
+                                            )).at_token(reading_symbol));
                                         }
 
                                         let symbol = reading_symbol.chars().next().unwrap();
@@ -219,13 +627,13 @@ fn parse_states(
                             };
 
                             let writing_symbol = {
-                                match &writing_symbol[..] {
+                                match writing_symbol.text {
                                     "default" => Symbol::Default,
                                     _ => {
-                                        if writing_symbol.len() != 1 {
-                                            return Err(format!(
+                                        if writing_symbol.chars().count() != 1 {
+                                            return Err(ParseError::new(ParseErrorKind::InvalidSymbol, format!(
                                                 "[turing_lib] Error while parsing a state. Invalid reading symbol found at line \"{line}\"."
-                                            ));
+                                            )).at_token(writing_symbol));
                                         }
 
                                         let symbol = writing_symbol.chars().next().unwrap();
@@ -239,64 +647,1024 @@ fn parse_states(
                                 }
                             };
 
-                            let head_movement = match head_movement {
-                                "L" => HeadMovement::Left,
-                                "R" => HeadMovement::Right,
-                                "S" => HeadMovement::Stay,
-                                _ => {
-                                    return Err(format!(
-                                        "[turing_lib] Error while parsing a transition. Unexpected head movement found at line \"{line}\"."
-                                    ));
-                                }
+                            let Some((head_movement, distance)) =
+                                parse_head_movement(&head_movement)
+                            else {
+                                return Err(ParseError::new(ParseErrorKind::InvalidHeadMovement, format!(
+                                    "[turing_lib] Error while parsing a transition. Unexpected head movement found at line \"{line}\"."
+                                )).at_token(head_movement));
                             };
 
-                            transition_states.insert(new_state_name);
-
-                            if let Some(ref mut cur_state) = current_state {
-                                if cur_state.transitions.contains_key(&reading_symbol) {
-                                    return Err(format!("[turing_lib] Error while parsing a state. Non-determinism not allowed. The transition source symbol {:?} has already been defined for the state \"{}\".", reading_symbol, new_state_name));
-                                } else {
-                                    cur_state.transitions.insert(
-                                        reading_symbol,
-                                        Transition::new(
-                                            head_movement,
-                                            writing_symbol,
-                                            new_state_name.to_string(),
-                                        ),
-                                    );
-                                }
-                            } else {
-                                return Err("[turing_lib] Error while parsing a state. Unexpected transition declaration outside a state."
-                                    .to_string());
-                            }
+                            // Positive weights are left to the builder to check
+                            let weight = match weight {
+                                [weight] => match weight.parse::<u32>() {
+                                    Ok(value) => Some((value, *weight)),
+                                    Err(_) => return Err(ParseError::new(ParseErrorKind::InvalidWeight, format!("[turing_lib] Error while parsing a transition. Invalid weight found at line \"{line}\". It must be a positive integer.")).at_token(*weight)),
+                                },
+                                _ => None,
+                            };
+
+                            let Some(state) = current_state else {
+                                return Err(ParseError::new(ParseErrorKind::UnexpectedLine, "[turing_lib] Error while parsing a state. Unexpected transition declaration outside a state.").at_token(line));
+                            };
+
+                            let transition = Transition::new_jump(
+                                head_movement,
+                                distance,
+                                writing_symbol,
+                                new_state_name.to_string(),
+                            )
+                            .with_pacing(pacing.unwrap_or_default());
+
+                            let origin = LineOrigin {
+                                line: line.location(),
+                                new_states: vec![new_state_name.location()],
+                                weight: weight.map(|(_, token)| token.location()),
+                            };
+                            builder = builder.add_line(
+                                state,
+                                StateLine::Transition {
+                                    read: reading_symbol,
+                                    transition,
+                                    weight: weight.map(|(value, _)| value),
+                                },
+                                Some(origin),
+                            );
                         }
                         _ => {
-                            return Err(format!("[turing_lib] Error while parsing states. Unexpected line \"{line}\"."));
+                            return Err(ParseError::new(ParseErrorKind::UnexpectedLine, format!("[turing_lib] Error while parsing states. Unexpected line \"{line}\".")).at_token(line));
                         }
                     },
                 }
 
                 if is_empty_state {
-                    append_state(current_state.take().unwrap())?;
+                    current_state = None;
                 }
             }
         }
     }
 
-    if !transition_states
+    Ok(builder)
+}
+
+struct StateBlock<'sb, T> {
+    header: StateHeader<'sb>,
+    transitions: Vec<T>,
+}
+
+/// Parses the `states` block of machines other than Turing machines, leaving the
+/// transition lines to `parse_transition`. It also returns the name of the initial state.
+fn parse_state_blocks<'l, T>(
+    file_lines: &[Line<'l>],
+    mut parse_transition: impl FnMut(Token<'l>) -> Result<T, ParseError>,
+) -> Result<(Vec<StateBlock<'l, T>>, &'l str), ParseError> {
+    let mut blocks: Vec<StateBlock<T>> = vec![];
+    let mut current_state: Option<StateBlock<T>> = None;
+
+    let state_lines = file_lines
         .iter()
-        .all(|state_name| states.contains_key(*state_name))
+        .skip_while(|l| l.text != "states {")
+        .skip(1)
+        .map(|l| l.token().trim());
+
+    for line in state_lines {
+        match line.text {
+            "}" => {
+                if current_state.is_some() {
+                    blocks.push(current_state.take().unwrap());
+                } else {
+                    break;
+                }
+            }
+            _ => {
+                let (state_def_line, is_empty_state) = split_state_line(line);
+
+                if let Some(header) = parse_state_header(state_def_line) {
+                    current_state = Some(StateBlock {
+                        header,
+                        transitions: vec![],
+                    });
+                } else if let Some(ref mut state) = current_state {
+                    state.transitions.push(parse_transition(line)?);
+                } else {
+                    return Err(ParseError::new(
+                        ParseErrorKind::UnexpectedLine,
+                        format!(
+                            "[turing_lib] Error while parsing states. Unexpected line \"{line}\"."
+                        ),
+                    )
+                    .at_token(line));
+                }
+
+                if is_empty_state {
+                    blocks.push(current_state.take().unwrap());
+                }
+            }
+        }
+    }
+
+    let mut initial_states = blocks.iter().filter(|block| block.header.is_initial);
+    let initial_state_name = initial_states
+        .next()
+        .ok_or_else(|| {
+            ParseError::new(
+                ParseErrorKind::MissingInitialState,
+                "[turing_lib] Error while parsing states. No initial state was provided.",
+            )
+        })?
+        .header
+        .name
+        .text;
+
+    if let Some(duplicate) = initial_states.next() {
+        return Err(ParseError::new(ParseErrorKind::DuplicateInitialState, "[turing_lib] Error while parsing states. There was more than one initial state provided.").at_token(duplicate.header.name));
+    }
+
+    Ok((blocks, initial_state_name))
+}
+
+fn check_transition_states<'n>(
+    defined: &HashSet<&str>,
+    mut transition_states: impl Iterator<Item = Token<'n>>,
+) -> Result<(), ParseError> {
+    if let Some(undefined) = transition_states.find(|state_name| !defined.contains(state_name.text))
     {
         return Err(
-            "[turing_lib] Error while parsing states. There are states that are transitioned into that are not defined.".to_string(),
+            ParseError::new(ParseErrorKind::UndefinedState, "[turing_lib] Error while parsing states. There are states that are transitioned into that are not defined.").at_token(undefined),
+        );
+    }
+
+    Ok(())
+}
+
+pub fn parse_pushdown_file(file_lines: &[Line]) -> Result<PushdownAutomaton, ParseError> {
+    let mut config_map = parse_config_entries(file_lines)?;
+
+    reject_options(
+        &config_map,
+        &[
+            "head_start",
+            "registers",
+            "tapes",
+            "seed",
+            "output",
+            "max_tape_cells",
+        ],
+        "pushdown automata",
+    )?;
+
+    let name = parse_name(&mut config_map)?;
+    let blank_symbol = parse_blank_symbol(&config_map)?;
+    let stack_start = {
+        let symbol = config_map
+            .get("stack_start")
+            .ok_or_else(|| ParseError::new(ParseErrorKind::MissingOption, "[turing_lib] Error while parsing configuration. There was no stack start symbol provided."))?;
+        symbol.chars().next().unwrap()
+    };
+
+    // The top of the stack is never matched against the blank symbol, so such a stack could never be read
+    if stack_start == blank_symbol {
+        return Err(ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while parsing configuration. Invalid stack start symbol '{stack_start}'. It can't be the blank symbol.")).at_token(config_map["stack_start"]));
+    }
+
+    let (blocks, initial_state_name) = parse_state_blocks(file_lines, |line| {
+        let [input_symbol, top_symbol, push_symbols, new_state_name] = line.split(",")[..] else {
+            return Err(ParseError::new(
+                ParseErrorKind::UnexpectedLine,
+                format!("[turing_lib] Error while parsing states. Unexpected line \"{line}\"."),
+            )
+            .at_token(line));
+        };
+
+        let input = match input_symbol.chars().collect::<Vec<_>>()[..] {
+            [symbol] if symbol == blank_symbol => None,
+            [symbol] => Some(symbol),
+            _ => {
+                return Err(ParseError::new(ParseErrorKind::InvalidSymbol, format!("[turing_lib] Error while parsing a state. Invalid input symbol found at line \"{line}\".")).at_token(input_symbol));
+            }
+        };
+
+        let top = match top_symbol.chars().collect::<Vec<_>>()[..] {
+            [symbol] if symbol != blank_symbol => symbol,
+            _ => {
+                return Err(ParseError::new(ParseErrorKind::InvalidSymbol, format!("[turing_lib] Error while parsing a state. Invalid stack symbol found at line \"{line}\".")).at_token(top_symbol));
+            }
+        };
+
+        let push = if push_symbols.is_empty() {
+            return Err(ParseError::new(ParseErrorKind::InvalidSymbol, format!("[turing_lib] Error while parsing a state. Invalid pushed symbols found at line \"{line}\". Use the blank symbol to push nothing.")).at_token(push_symbols));
+        } else if push_symbols.chars().eq([blank_symbol]) {
+            vec![]
+        } else if push_symbols.contains(blank_symbol) {
+            return Err(ParseError::new(ParseErrorKind::InvalidSymbol, format!("[turing_lib] Error while parsing a state. Invalid pushed symbols found at line \"{line}\". The blank symbol can only be pushed alone, to push nothing.")).at_token(push_symbols));
+        } else {
+            push_symbols.chars().collect()
+        };
+
+        Ok((
+            PushdownSource::new(input, top),
+            PushdownTransition::new(push, new_state_name.to_string()),
+            new_state_name,
+        ))
+    })?;
+
+    let defined = blocks.iter().map(|block| block.header.name.text).collect();
+    check_transition_states(
+        &defined,
+        blocks
+            .iter()
+            .flat_map(|block| block.transitions.iter().map(|&(_, _, state)| state)),
+    )?;
+
+    let mut states = HashMap::new();
+    let mut final_states = HashSet::new();
+
+    for block in blocks {
+        let mut transitions = HashMap::new();
+
+        for (source, transition, _) in block.transitions {
+            // An epsilon transition conflicts with any other transition for the same stack top
+            if transitions.contains_key(&source)
+                || (source.input().is_none()
+                    && transitions
+                        .keys()
+                        .any(|s: &PushdownSource| s.top() == source.top()))
+                || (source.input().is_some()
+                    && transitions.contains_key(&PushdownSource::new(None, source.top())))
+            {
+                return Err(ParseError::new(ParseErrorKind::Nondeterminism, format!("[turing_lib] Error while parsing a state. Non-determinism not allowed. A transition reading {:?} with {:?} on top of the stack conflicts with another one for the state \"{}\".", source.input(), source.top(), block.header.name)).at_token(block.header.name));
+            }
+
+            transitions.insert(source, transition);
+        }
+
+        if block.header.is_final {
+            final_states.insert(block.header.name.to_string());
+        }
+
+        states.insert(
+            block.header.name.to_string(),
+            PushdownState::new(block.header.name.to_string(), transitions),
         );
     }
 
-    Ok((
+    Ok(PushdownAutomaton {
+        name,
+        blank_symbol,
+        stack_start,
+
         states,
         final_states,
-        initial_state_name.ok_or_else(|| {
-            "[turing_lib] Error while parsing states. No initial state was provided.".to_string()
-        })?,
-    ))
+
+        head_idx: 0,
+        current_state: initial_state_name.to_string(),
+        input: Tape::new(vec![]),
+        stack: vec![stack_start],
+
+        halted: false,
+    })
+}
+
+pub fn parse_finite_file(file_lines: &[Line]) -> Result<FiniteAutomaton, ParseError> {
+    let mut config_map = parse_config_entries(file_lines)?;
+
+    reject_options(
+        &config_map,
+        &[
+            "head_start",
+            "stack_start",
+            "registers",
+            "tapes",
+            "seed",
+            "output",
+            "max_tape_cells",
+        ],
+        "finite automata",
+    )?;
+
+    let name = parse_name(&mut config_map)?;
+    let blank_symbol = parse_blank_symbol(&config_map)?;
+
+    let (blocks, initial_state_name) = parse_state_blocks(file_lines, |line| {
+        let [input_symbol, new_state_name] = line.split(",")[..] else {
+            return Err(ParseError::new(
+                ParseErrorKind::UnexpectedLine,
+                format!("[turing_lib] Error while parsing states. Unexpected line \"{line}\"."),
+            )
+            .at_token(line));
+        };
+
+        let input = match input_symbol.chars().collect::<Vec<_>>()[..] {
+            [symbol] if symbol == blank_symbol => None,
+            [symbol] => Some(symbol),
+            _ => {
+                return Err(ParseError::new(ParseErrorKind::InvalidSymbol, format!("[turing_lib] Error while parsing a state. Invalid input symbol found at line \"{line}\".")).at_token(input_symbol));
+            }
+        };
+
+        Ok((input, new_state_name))
+    })?;
+
+    let defined = blocks.iter().map(|block| block.header.name.text).collect();
+    check_transition_states(
+        &defined,
+        blocks
+            .iter()
+            .flat_map(|block| block.transitions.iter().map(|(_, state)| *state)),
+    )?;
+
+    let mut states = HashMap::new();
+    let mut final_states = HashSet::new();
+
+    for block in blocks {
+        let mut transitions: HashMap<Option<char>, Vec<String>> = HashMap::new();
+        for (input, new_state_name) in block.transitions {
+            transitions
+                .entry(input)
+                .or_default()
+                .push(new_state_name.to_string());
+        }
+
+        if block.header.is_final {
+            final_states.insert(block.header.name.to_string());
+        }
+
+        states.insert(
+            block.header.name.to_string(),
+            FiniteState::new(block.header.name.to_string(), transitions),
+        );
+    }
+
+    let automaton = FiniteAutomaton::new(
+        name,
+        blank_symbol,
+        states,
+        final_states,
+        initial_state_name.to_string(),
+    );
+
+    if config_map
+        .get("machine_type")
+        .is_some_and(|t| t.text == "dfa")
+        && !automaton.is_deterministic()
+    {
+        return Err(ParseError::new(ParseErrorKind::Nondeterminism, "[turing_lib] Error while parsing states. A DFA can't have epsilon transitions, nor more than one transition for the same symbol in a state."));
+    }
+
+    Ok(automaton)
+}
+
+pub fn parse_counter_file(file_lines: &[Line]) -> Result<CounterMachine, ParseError> {
+    let mut config_map = parse_config_entries(file_lines)?;
+
+    reject_options(
+        &config_map,
+        &[
+            "blank_symbol",
+            "head_start",
+            "stack_start",
+            "tapes",
+            "seed",
+            "output",
+            "max_tape_cells",
+        ],
+        "counter machines",
+    )?;
+
+    let name = parse_name(&mut config_map)?;
+    let register_count: usize = {
+        let &count = config_map
+            .get("registers")
+            .ok_or_else(|| ParseError::new(ParseErrorKind::MissingOption, "[turing_lib] Error while parsing configuration. There was no register count provided."))?;
+
+        count
+            .parse()
+            .ok()
+            .filter(|&count| count <= MAX_REGISTERS)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while parsing configuration. Invalid register count provided (\"{count}\"). It must be a non negative integer up to {MAX_REGISTERS}.")).at_token(count))?
+    };
+
+    let program_lines = file_lines
+        .iter()
+        .skip_while(|l| l.text != "program {")
+        .skip(1)
+        .map(|l| l.token().trim())
+        .take_while(|l| l.text != "}")
+        .collect::<Vec<_>>();
+
+    // Labels are resolved first, so jumps can go forward
+    let mut labels = HashMap::new();
+    let mut instruction_lines = Vec::with_capacity(program_lines.len());
+    for (index, &line) in program_lines.iter().enumerate() {
+        let instruction = match line.split_once(":") {
+            Some((label, instruction)) => {
+                let label = label.trim();
+                if label.is_empty() || label.contains(char::is_whitespace) {
+                    return Err(ParseError::new(ParseErrorKind::InvalidLabel, format!("[turing_lib] Error while parsing the program. Invalid label at line \"{line}\".")).at_token(line));
+                }
+
+                if labels.insert(label.text, index).is_some() {
+                    return Err(ParseError::new(ParseErrorKind::InvalidLabel, format!("[turing_lib] Error while parsing the program. The label \"{label}\" has already been defined.")).at_token(label));
+                }
+
+                instruction.trim()
+            }
+            None => line,
+        };
+        instruction_lines.push(instruction);
+    }
+
+    let parse_register = |register: Token, line: Token| -> Result<usize, ParseError> {
+        register
+            .strip_prefix("r")
+            .and_then(|index| index.parse().ok())
+            .filter(|&index| index < register_count)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidRegister, format!("[turing_lib] Error while parsing the program. Invalid register \"{register}\" at line \"{line}\". Registers go from r0 to r{}.", register_count.saturating_sub(1))).at_token(register))
+    };
+
+    let mut program = Vec::with_capacity(instruction_lines.len());
+    for (instruction, &line) in instruction_lines.iter().zip(&program_lines) {
+        program.push(
+            match instruction.split_whitespace()[..] {
+                [Token { text: "INC", .. }, register] => {
+                    Instruction::Inc(parse_register(register, line)?)
+                }
+                [Token { text: "DEC", .. }, register] => {
+                    Instruction::Dec(parse_register(register, line)?)
+                }
+                [Token { text: "JZ", .. }, register, label] => Instruction::Jz(
+                    parse_register(register, line)?,
+                    *labels.get(label.text).ok_or_else(|| {
+                        ParseError::new(ParseErrorKind::InvalidLabel, format!("[turing_lib] Error while parsing the program. Undefined label \"{label}\" at line \"{line}\".")).at_token(label)
+                    })?,
+                ),
+                [Token { text: "HALT", .. }] => Instruction::Halt,
+                _ => {
+                    return Err(ParseError::new(ParseErrorKind::UnexpectedLine, format!("[turing_lib] Error while parsing the program. Unexpected line \"{line}\".")).at_token(line));
+                }
+            },
+        );
+    }
+
+    if program.is_empty() {
+        return Err(ParseError::new(
+            ParseErrorKind::EmptyProgram,
+            "[turing_lib] Error while parsing the program. No instructions were provided.",
+        ));
+    }
+
+    Ok(CounterMachine {
+        name,
+
+        program,
+        instruction_names: program_lines.iter().map(|l| l.to_string()).collect(),
+
+        registers: vec![0; register_count],
+        program_counter: 0,
+
+        halted: false,
+        empty_tape: Tape::new(vec![]),
+    })
+}
+
+/// Splits a part of a multi-tape transition, which has one value per tape separated by "|".
+fn split_tape_values<'v>(
+    part: Token<'v>,
+    tape_count: usize,
+    line: Token,
+) -> Result<Vec<Token<'v>>, ParseError> {
+    let values = part.split("|");
+    if values.len() != tape_count {
+        return Err(ParseError::new(ParseErrorKind::UnexpectedLine, format!("[turing_lib] Error while parsing a transition. Expected {tape_count} values separated by \"|\" in \"{part}\", at line \"{line}\".")).at_token(part));
+    }
+    Ok(values)
+}
+
+pub fn parse_multitape_file(file_lines: &[Line]) -> Result<MultiTapeMachine, ParseError> {
+    let mut config_map = parse_config_entries(file_lines)?;
+
+    reject_options(
+        &config_map,
+        &[
+            "stack_start",
+            "registers",
+            "seed",
+            "output",
+            "max_tape_cells",
+        ],
+        "multi-tape machines",
+    )?;
+
+    let name = parse_name(&mut config_map)?;
+    let blank_symbol = parse_blank_symbol(&config_map)?;
+
+    if blank_symbol == '|' {
+        return Err(ParseError::new(ParseErrorKind::InvalidOption, "[turing_lib] Error while parsing configuration. Invalid blank symbol '|'. It can't be used by multi-tape machines, since it separates the values of every tape.").at_token(config_map["blank_symbol"]));
+    }
+
+    let tape_count: usize = {
+        let &count = config_map.get("tapes").ok_or_else(|| {
+            ParseError::new(
+                ParseErrorKind::MissingOption,
+                "[turing_lib] Error while parsing configuration. There was no tape count provided.",
+            )
+        })?;
+
+        count
+            .parse()
+            .ok()
+            .filter(|&count| count > 0 && count <= MAX_TAPES)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while parsing configuration. Invalid tape count provided (\"{count}\"). It must be a positive integer up to {MAX_TAPES}.")).at_token(count))?
+    };
+
+    let head_start: usize = {
+        let &index = config_map
+            .get("head_start")
+            .ok_or_else(|| ParseError::new(ParseErrorKind::MissingOption, "[turing_lib] Error while parsing configuration. There was no head start index provided."))?;
+
+        index.parse().map_err(|_| ParseError::new(ParseErrorKind::InvalidOption, format!("[turing_lib] Error while parsing configuration. Invalid head start index provided (\"{index}\"). It must be a non negative integer.")).at_token(index))?
+    };
+
+    let (blocks, initial_state_name) = parse_state_blocks(file_lines, |line| {
+        let [reading_symbols, writing_symbols, head_movements, new_state_name] =
+            line.split(",")[..]
+        else {
+            return Err(ParseError::new(
+                ParseErrorKind::UnexpectedLine,
+                format!("[turing_lib] Error while parsing states. Unexpected line \"{line}\"."),
+            )
+            .at_token(line));
+        };
+
+        let reading_symbols = split_tape_values(reading_symbols, tape_count, line)?
+            .into_iter()
+            .map(|symbol| match symbol.chars().collect::<Vec<_>>()[..] {
+                [symbol] if symbol == blank_symbol => Ok(Symbol::Blank),
+                [symbol] => Ok(Symbol::Mark(symbol)),
+                _ => Err(ParseError::new(ParseErrorKind::InvalidSymbol, format!("[turing_lib] Error while parsing a state. Invalid reading symbol found at line \"{line}\".")).at_token(symbol)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let writing_symbols = split_tape_values(writing_symbols, tape_count, line)?
+            .into_iter()
+            .map(|symbol| match symbol.text {
+                "default" => Ok(Symbol::Default),
+                _ => match symbol.chars().collect::<Vec<_>>()[..] {
+                    [symbol] if symbol == blank_symbol => Ok(Symbol::Blank),
+                    [symbol] => Ok(Symbol::Mark(symbol)),
+                    _ => Err(ParseError::new(ParseErrorKind::InvalidSymbol, format!("[turing_lib] Error while parsing a state. Invalid writing symbol found at line \"{line}\".")).at_token(symbol)),
+                },
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let head_movements = split_tape_values(head_movements, tape_count, line)?
+            .into_iter()
+            .map(|movement| match movement.text {
+                "L" => Ok(HeadMovement::Left),
+                "R" => Ok(HeadMovement::Right),
+                "S" => Ok(HeadMovement::Stay),
+                _ => Err(ParseError::new(ParseErrorKind::InvalidHeadMovement, format!("[turing_lib] Error while parsing a transition. Unexpected head movement found at line \"{line}\".")).at_token(movement)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((
+            reading_symbols,
+            MultiTapeTransition::new(writing_symbols, head_movements, new_state_name.to_string()),
+            new_state_name,
+        ))
+    })?;
+
+    let defined = blocks.iter().map(|block| block.header.name.text).collect();
+    check_transition_states(
+        &defined,
+        blocks
+            .iter()
+            .flat_map(|block| block.transitions.iter().map(|&(_, _, state)| state)),
+    )?;
+
+    let mut states = HashMap::new();
+    let mut final_states = HashSet::new();
+
+    for block in blocks {
+        let mut transitions = HashMap::new();
+
+        for (reading_symbols, transition, _) in block.transitions {
+            match transitions.entry(reading_symbols) {
+                Entry::Occupied(entry) => {
+                    return Err(ParseError::new(ParseErrorKind::Nondeterminism, format!("[turing_lib] Error while parsing a state. Non-determinism not allowed. The transition source symbols {:?} have already been defined for the state \"{}\".", entry.key(), block.header.name)).at_token(block.header.name));
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(transition);
+                }
+            }
+        }
+
+        if block.header.is_final {
+            final_states.insert(block.header.name.to_string());
+        }
+
+        states.insert(
+            block.header.name.to_string(),
+            MultiTapeState::new(block.header.name.to_string(), transitions),
+        );
+    }
+
+    let mut heads = vec![0; tape_count];
+    heads[0] = head_start;
+
+    Ok(MultiTapeMachine {
+        name,
+        blank_symbol,
+        head_start,
+
+        states,
+        final_states,
+        initial_state: initial_state_name.to_string(),
+
+        heads,
+        current_state: initial_state_name.to_string(),
+        tapes: vec![Tape::new(vec![]); tape_count],
+
+        halted: false,
+    })
+}
+
+const TM_YAML_ERROR: &str = "[turing_lib] Error while importing a turingmachine.io machine.";
+
+/// Parses a machine written for [turingmachine.io](https://turingmachine.io), with its `blank`,
+/// `start state` and `table` (its `input` is replaced by `tape_data`). A space as blank symbol
+/// becomes `_`, since `.tng` files can't have it, and the states without transitions, where
+/// those machines halt, are the final states.
+pub fn from_tm_yaml(file_data: &str, tape_data: &str) -> Result<TuringMachine, ParseError> {
+    let documents = YamlLoader::load_from_str(file_data).map_err(|err| {
+        ParseError::new(
+            ParseErrorKind::InvalidSyntax,
+            format!("{TM_YAML_ERROR} Invalid YAML ({}).", err.info()),
+        )
+        .with_location(err.marker().line(), err.marker().col() + 1)
+    })?;
+    let Some(document) = documents.first() else {
+        return Err(ParseError::new(
+            ParseErrorKind::MissingConfig,
+            format!("{TM_YAML_ERROR} The file is empty."),
+        ));
+    };
+
+    let yaml_option = |key: &str| match &document[key] {
+        Yaml::BadValue => Err(ParseError::new(
+            ParseErrorKind::MissingOption,
+            format!("{TM_YAML_ERROR} There was no \"{key}\" provided."),
+        )),
+        value => Ok(value),
+    };
+
+    let yaml_blank = tm_yaml_symbol(yaml_option("blank")?)?;
+    let start_state = tm_yaml_name(yaml_option("start state")?)?;
+    let Yaml::Hash(table) = yaml_option("table")? else {
+        return Err(ParseError::new(
+            ParseErrorKind::InvalidOption,
+            format!("{TM_YAML_ERROR} The \"table\" must map every state to its transitions."),
+        ));
+    };
+
+    // Transitions are split by commas and trimmed in .tng files, so these can't be the blank
+    let blank_symbol = if yaml_blank == ',' || yaml_blank.is_whitespace() {
+        DEFAULT_BLANK_SYMBOL
+    } else {
+        yaml_blank
+    };
+    let symbol = |yaml: &Yaml| {
+        let symbol = tm_yaml_symbol(yaml)?;
+        if symbol == blank_symbol && blank_symbol != yaml_blank {
+            return Err(ParseError::new(ParseErrorKind::InvalidSymbol, format!("{TM_YAML_ERROR} The symbol '{symbol}' can't be used, since it replaces the blank symbol '{yaml_blank}'.")));
+        }
+        Ok(symbol)
+    };
+
+    let name = match &document["name"] {
+        Yaml::BadValue => "turingmachine.io machine".to_string(),
+        name => tm_yaml_name(name)?,
+    };
+    let mut builder = TuringMachineBuilder::new(&name)
+        .set_blank(blank_symbol)
+        .set_initial(&start_state);
+
+    for (state, transitions) in table {
+        let state = tm_yaml_name(state)?;
+        builder = builder.add_state(&state);
+
+        let transitions = match transitions {
+            Yaml::Null => {
+                builder = builder.mark_final(&state);
+                continue;
+            }
+            Yaml::Hash(transitions) => transitions,
+            _ => return Err(ParseError::new(ParseErrorKind::UnexpectedLine, format!("{TM_YAML_ERROR} The state \"{state}\" must map symbols to actions, or have none."))),
+        };
+
+        for (read, action) in transitions {
+            let (written, head_movement, new_state) = tm_yaml_action(action, &state)?;
+            let written = match written {
+                Some(written) if symbol(written)? == yaml_blank => Symbol::Blank,
+                Some(written) => Symbol::Mark(symbol(written)?),
+                None => Symbol::Default, // Keeps the symbol read
+            };
+
+            let read = match read {
+                Yaml::Array(symbols) => symbols.iter().collect(),
+                symbol => vec![symbol],
+            };
+            for read in read {
+                let read = match symbol(read)? {
+                    read if read == yaml_blank => TransitionSource::Blank,
+                    read => TransitionSource::Mark(read),
+                };
+
+                let transition = Transition::new(head_movement, written, new_state.clone());
+                builder = builder.add_transition(&state, read, transition);
+            }
+        }
+    }
+
+    builder.build(tape_data)
+}
+
+/// A state name, which YAML may have read as a number or a boolean.
+fn tm_yaml_name(yaml: &Yaml) -> Result<String, ParseError> {
+    match yaml {
+        Yaml::String(name) | Yaml::Real(name) => Ok(name.clone()),
+        Yaml::Integer(name) => Ok(name.to_string()),
+        Yaml::Boolean(name) => Ok(name.to_string()),
+        _ => Err(ParseError::new(
+            ParseErrorKind::UnexpectedLine,
+            format!("{TM_YAML_ERROR} Invalid state name {}.", tm_yaml_text(yaml)),
+        )),
+    }
+}
+
+fn tm_yaml_symbol(yaml: &Yaml) -> Result<char, ParseError> {
+    let symbol = match yaml {
+        Yaml::String(symbol) => symbol.clone(),
+        Yaml::Integer(symbol) => symbol.to_string(),
+        _ => String::new(),
+    };
+
+    match symbol.chars().collect::<Vec<_>>()[..] {
+        [symbol] => Ok(symbol),
+        _ => Err(ParseError::new(
+            ParseErrorKind::InvalidSymbol,
+            format!(
+                "{TM_YAML_ERROR} Invalid symbol {}. Symbols must be a single char.",
+                tm_yaml_text(yaml)
+            ),
+        )),
+    }
+}
+
+/// A value as written in the file, or what it is if it isn't a single value.
+fn tm_yaml_text(yaml: &Yaml) -> String {
+    match yaml {
+        Yaml::String(text) | Yaml::Real(text) => format!("\"{text}\""),
+        Yaml::Integer(number) => number.to_string(),
+        Yaml::Boolean(boolean) => boolean.to_string(),
+        Yaml::Array(_) => "(a list)".to_string(),
+        Yaml::Hash(_) => "(a mapping)".to_string(),
+        _ => "(empty)".to_string(),
+    }
+}
+
+/// The symbol written (if any), head movement and next state of an action, which is `L`, `R`,
+/// or a mapping such as `{write: 1, L: done}` (without a state, it stays in `state`).
+fn tm_yaml_action<'a>(
+    action: &'a Yaml,
+    state: &str,
+) -> Result<(Option<&'a Yaml>, HeadMovement, String), ParseError> {
+    let invalid = || {
+        ParseError::new(
+            ParseErrorKind::InvalidHeadMovement,
+            format!("{TM_YAML_ERROR} Invalid action {} in the state \"{state}\". It must be L, R, or a mapping with \"write\" and L or R (e.g. {{write: 1, L: done}}).", tm_yaml_text(action)),
+        )
+    };
+    let head_movement = |movement: &str| match movement {
+        "L" => Ok(HeadMovement::Left),
+        "R" => Ok(HeadMovement::Right),
+        _ => Err(invalid()),
+    };
+
+    match action {
+        Yaml::String(movement) => Ok((None, head_movement(movement)?, state.to_string())),
+        Yaml::Hash(action) => {
+            let mut written = None;
+            let mut movement = None;
+
+            for (key, value) in action {
+                match key.as_str() {
+                    Some("write") => written = Some(value),
+                    Some(key) if movement.is_none() => {
+                        let new_state = match value {
+                            Yaml::Null => state.to_string(),
+                            new_state => tm_yaml_name(new_state)?,
+                        };
+                        movement = Some((head_movement(key)?, new_state));
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+
+            let (head_movement, new_state) = movement.ok_or_else(invalid)?;
+            Ok((written, head_movement, new_state))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+const JFLAP_ERROR: &str = "[turing_lib] Error while importing a JFLAP machine.";
+
+/// Parses a single tape Turing machine made with [JFLAP](https://www.jflap.org) (a `.jff` file),
+/// with `_` as its blank symbol. Its states are named after their name in JFLAP, and so are the
+/// plain `<block>`s of JFLAP 7, which are imported as states. Building blocks, which run a whole
+/// nested machine, are rejected. If a state has more than one transition reading the same
+/// symbol, the machine is imported as a non-deterministic one.
+pub fn from_jflap(file_data: &str, tape_data: &str) -> Result<TuringMachine, ParseError> {
+    let document = roxmltree::Document::parse(file_data).map_err(|err| {
+        // Without the position that ends the message, since it is the location of the error
+        let message = err.to_string();
+        let message = message.trim_end_matches(&format!(" at {}", err.pos()));
+        ParseError::new(
+            ParseErrorKind::InvalidSyntax,
+            format!("{JFLAP_ERROR} Invalid XML ({message})."),
+        )
+        .with_location(err.pos().row as usize, err.pos().col as usize)
+    })?;
+    let at = |node: roxmltree::Node, err: ParseError| {
+        let pos = document.text_pos_at(node.range().start);
+        err.with_location(pos.row as usize, pos.col as usize)
+    };
+
+    let structure = document.root_element();
+    if jflap_text(structure, "type").as_deref() != Some("turing") {
+        return Err(ParseError::new(
+            ParseErrorKind::InvalidMachineType,
+            format!("{JFLAP_ERROR} It is not a Turing machine."),
+        ));
+    }
+    if jflap_text(structure, "tapes").is_some_and(|tapes| tapes != "1") {
+        return Err(ParseError::new(
+            ParseErrorKind::InvalidMachineType,
+            format!("{JFLAP_ERROR} Only single tape Turing machines can be imported."),
+        ));
+    }
+
+    // JFLAP 7 keeps the states in an automaton element, and older versions right in the structure
+    let automaton = jflap_child(structure, "automaton").unwrap_or(structure);
+
+    // Building blocks are states that run a whole nested machine, which can't be written here
+    if let Some(block) = automaton
+        .children()
+        .find(|node| node.has_tag_name("block") && is_jflap_building_block(structure, *node))
+    {
+        let name = block
+            .attribute("name")
+            .or_else(|| block.attribute("id"))
+            .unwrap_or_default();
+        return Err(at(
+            block,
+            ParseError::new(
+                ParseErrorKind::InvalidMachineType,
+                format!("{JFLAP_ERROR} The building block \"{name}\" can't be imported, only plain states can."),
+            )
+            .at(name),
+        ));
+    }
+
+    let mut state_names = HashMap::new(); // By id
+    let mut builder = TuringMachineBuilder::new("JFLAP machine");
+
+    for state in automaton
+        .children()
+        .filter(|node| node.has_tag_name("state") || node.has_tag_name("block"))
+    {
+        let Some(id) = state.attribute("id") else {
+            return Err(at(
+                state,
+                ParseError::new(
+                    ParseErrorKind::UnexpectedLine,
+                    format!("{JFLAP_ERROR} A state has no id."),
+                ),
+            ));
+        };
+        let name = state
+            .attribute("name")
+            .map_or_else(|| format!("q{id}"), str::to_string);
+
+        if state_names.values().any(|other| *other == name) {
+            return Err(at(
+                state,
+                ParseError::new(
+                    ParseErrorKind::UnexpectedLine,
+                    format!("{JFLAP_ERROR} There is more than one state named \"{name}\"."),
+                ),
+            ));
+        }
+
+        builder = builder.add_state(&name);
+        if jflap_child(state, "initial").is_some() {
+            builder = builder.set_initial(&name);
+        }
+        if jflap_child(state, "final").is_some() {
+            builder = builder.mark_final(&name);
+        }
+        state_names.insert(id, name);
+    }
+
+    let symbol = |node: roxmltree::Node, name: &str| -> Result<Symbol, ParseError> {
+        // An empty symbol is the blank
+        match jflap_text(node, name).unwrap_or_default().chars().collect::<Vec<_>>()[..] {
+            [] => Ok(Symbol::Blank),
+            [symbol] if symbol != DEFAULT_BLANK_SYMBOL => Ok(Symbol::Mark(symbol)),
+            _ => Err(at(jflap_child(node, name).unwrap_or(node), ParseError::new(ParseErrorKind::InvalidSymbol, format!("{JFLAP_ERROR} Invalid {name} symbol. It must be a single char other than '{DEFAULT_BLANK_SYMBOL}', or empty for the blank.")))),
+        }
+    };
+
+    let mut sources = HashSet::new();
+    let mut nondeterministic = false;
+
+    for transition in automaton
+        .children()
+        .filter(|node| node.has_tag_name("transition"))
+    {
+        let state = |name: &str| {
+            jflap_text(transition, name)
+                .and_then(|id| state_names.get(id.as_str()))
+                .ok_or_else(|| {
+                    at(
+                        transition,
+                        ParseError::new(
+                            ParseErrorKind::UndefinedState,
+                            format!("{JFLAP_ERROR} A transition goes {name} a state that is not defined."),
+                        ),
+                    )
+                })
+        };
+        let (from, to) = (state("from")?, state("to")?);
+
+        let read = match symbol(transition, "read")? {
+            Symbol::Mark(symbol) => TransitionSource::Mark(symbol),
+            _ => TransitionSource::Blank,
+        };
+        let head_movement = match jflap_text(transition, "move").as_deref() {
+            Some("L") => HeadMovement::Left,
+            Some("R") => HeadMovement::Right,
+            Some("S") => HeadMovement::Stay,
+            _ => {
+                return Err(at(
+                    transition,
+                    ParseError::new(
+                        ParseErrorKind::InvalidHeadMovement,
+                        format!("{JFLAP_ERROR} Unexpected head movement. It must be L, R or S."),
+                    ),
+                ))
+            }
+        };
+
+        nondeterministic |= !sources.insert((from, read));
+
+        let transition = Transition::new(head_movement, symbol(transition, "write")?, to.clone());
+        builder = builder.add_transition(from, read, transition);
+    }
+
+    builder
+        .set_nondeterministic(nondeterministic)
+        .build(tape_data)
+}
+
+fn jflap_child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+/// Whether a `<block>` runs a nested machine, given inside it or in an element named after its
+/// tag. JFLAP 7 saves the plain states of Turing machines as blocks too, without any machine.
+fn is_jflap_building_block(structure: roxmltree::Node, block: roxmltree::Node) -> bool {
+    let has_machine = |node: roxmltree::Node| {
+        node.children().any(|child| {
+            ["automaton", "state", "block", "transition"]
+                .iter()
+                .any(|name| child.has_tag_name(*name))
+        })
+    };
+
+    has_machine(block)
+        || jflap_text(block, "tag").is_some_and(|tag| {
+            structure
+                .descendants()
+                .any(|node| node.has_tag_name(tag.as_str()) && has_machine(node))
+        })
+}
+
+/// The trimmed text of the child element `name`, which is empty if the element is.
+fn jflap_text(node: roxmltree::Node, name: &str) -> Option<String> {
+    jflap_child(node, name).map(|child| child.text().unwrap_or_default().trim().to_string())
 }
@@ -0,0 +1,218 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use crate::{
+    error::{MachineError, MachineErrorKind, ParseError},
+    machine::{HeadMovement, Symbol, TickResult},
+    parser,
+    tape::Tape,
+};
+
+/// What a pushdown transition reads: an input symbol (or nothing, for epsilon
+/// transitions) and the symbol on top of the stack, which is always popped.
+#[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
+pub struct PushdownSource {
+    input: Option<char>,
+    top: char,
+}
+
+impl PushdownSource {
+    pub fn new(input: Option<char>, top: char) -> Self {
+        Self { input, top }
+    }
+
+    pub fn input(&self) -> Option<char> {
+        self.input
+    }
+
+    pub fn top(&self) -> char {
+        self.top
+    }
+}
+
+#[derive(Debug)]
+pub struct PushdownTransition {
+    push: Vec<char>, // The first symbol ends up on top of the stack
+    new_state: String,
+}
+
+impl PushdownTransition {
+    pub fn new(push: Vec<char>, new_state: String) -> Self {
+        Self { push, new_state }
+    }
+
+    pub fn push(&self) -> &[char] {
+        &self.push
+    }
+
+    pub fn new_state(&self) -> &str {
+        &self.new_state
+    }
+}
+
+pub struct PushdownState {
+    name: String,
+    transitions: HashMap<PushdownSource, PushdownTransition>,
+}
+
+impl PushdownState {
+    pub fn new(name: String, transitions: HashMap<PushdownSource, PushdownTransition>) -> Self {
+        Self { name, transitions }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn transitions(&self) -> &HashMap<PushdownSource, PushdownTransition> {
+        &self.transitions
+    }
+}
+
+pub struct PushdownAutomaton {
+    pub(crate) name: String,
+    pub(crate) blank_symbol: char,
+    pub(crate) stack_start: char,
+
+    pub(crate) states: HashMap<String, PushdownState>,
+    pub(crate) final_states: HashSet<String>,
+
+    pub(crate) head_idx: usize,
+    pub(crate) current_state: String,
+    pub(crate) input: Tape,
+    pub(crate) stack: Vec<char>,
+
+    pub(crate) halted: bool,
+}
+
+impl PushdownAutomaton {
+    pub fn new_from_file(
+        filename: &str,
+        input_data: &str,
+    ) -> Result<PushdownAutomaton, MachineError> {
+        let file_data = fs::read_to_string(filename).map_err(|_| {
+            MachineError::new(
+                MachineErrorKind::Io,
+                format!("Could not open the file \"{}\"", filename),
+            )
+        })?;
+
+        Ok(Self::new_from_str(&file_data, input_data)?)
+    }
+
+    pub fn new_from_str(
+        file_data: &str,
+        input_data: &str,
+    ) -> Result<PushdownAutomaton, ParseError> {
+        let file_lines = parser::file_lines(file_data);
+
+        let mut automaton = parser::parse_pushdown_file(&file_lines)?;
+        automaton.input = Tape::parse(input_data, automaton.blank_symbol);
+
+        Ok(automaton)
+    }
+
+    pub fn tick(&mut self) -> TickResult {
+        let halted_result = TickResult {
+            written_different_symbol: false,
+            extended_tape_on_side: None,
+            head_movement: HeadMovement::Stay,
+            step: None,
+        };
+
+        if self.halted {
+            return halted_result;
+        }
+
+        let Some(&top) = self.stack.last() else {
+            self.halted = true;
+            return halted_result;
+        };
+
+        let available_transitions = &self.states[&self.current_state].transitions;
+        let current_input = match self.input.get_content().get(self.head_idx) {
+            Some(Symbol::Mark(c)) => Some(*c),
+            _ => None,
+        };
+
+        // Prefer consuming the input, and fall back to an epsilon transition
+        let consuming_transition = current_input
+            .and_then(|input| available_transitions.get(&PushdownSource::new(Some(input), top)));
+        let (transition, consumed) = match consuming_transition {
+            Some(transition) => (Some(transition), true),
+            None => (
+                available_transitions.get(&PushdownSource::new(None, top)),
+                false,
+            ),
+        };
+
+        let Some(transition) = transition else {
+            self.halted = true;
+            return halted_result;
+        };
+
+        self.stack.pop();
+        self.stack.extend(transition.push.iter().rev());
+        self.current_state = transition.new_state.clone();
+
+        if consumed {
+            self.head_idx += 1;
+        }
+
+        TickResult {
+            written_different_symbol: false,
+            extended_tape_on_side: None,
+            head_movement: if consumed {
+                HeadMovement::Right
+            } else {
+                HeadMovement::Stay
+            },
+            step: None,
+        }
+    }
+
+    /// A pushdown automaton accepts when it halts in a final state after reading all of its input.
+    pub fn is_accepting(&self) -> bool {
+        self.halted
+            && self.head_idx >= self.input.len()
+            && self.final_states.contains(&self.current_state)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn blank_symbol(&self) -> char {
+        self.blank_symbol
+    }
+
+    pub fn stack_start(&self) -> char {
+        self.stack_start
+    }
+
+    pub fn head_idx(&self) -> usize {
+        self.head_idx
+    }
+
+    pub fn current_state_name(&self) -> &str {
+        &self.current_state
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn input(&self) -> &Tape {
+        &self.input
+    }
+
+    pub fn stack(&self) -> &[char] {
+        &self.stack
+    }
+
+    pub fn states(&self) -> &HashMap<String, PushdownState> {
+        &self.states
+    }
+}
@@ -0,0 +1,306 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    time::Instant,
+};
+
+use crate::{
+    machine::{HeadMovement, Step, TransitionSource, TuringMachine},
+    runner::{self, RunLimits, RunOutcome},
+    tape::TapeSide,
+};
+
+const HISTOGRAM_ROWS: usize = 20;
+const HISTOGRAM_WIDTH: usize = 40; // In characters, for the most visited positions
+
+/// A run of steps where the machine stayed in the same state moving the head the same way,
+/// like a state that looks for the end of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scan {
+    pub state: String,
+    pub direction: HeadMovement,
+    pub steps: usize,
+    /// The step the scan started at (counting from 0).
+    pub start_step: usize,
+    /// The position of the head when the scan started, see [`Profile::head_positions`].
+    pub start_position: isize,
+}
+
+/// Where a run spends its steps, to find the expensive phases of a machine, see [`profile`].
+#[derive(Debug, Clone)]
+pub struct Profile {
+    name: String,
+    blank_symbol: char,
+    outcome: RunOutcome,
+    states: HashMap<String, usize>, // Steps taken from each state
+    transitions: HashMap<(String, TransitionSource), usize>, // Times each transition has been taken
+    scans: Vec<Scan>,
+    head_positions: BTreeMap<isize, usize>,
+}
+
+impl Profile {
+    pub fn outcome(&self) -> RunOutcome {
+        self.outcome
+    }
+
+    /// The steps taken from every state, the most first (and then by name).
+    pub fn hottest_states(&self) -> Vec<(&str, usize)> {
+        let mut states = self
+            .states
+            .iter()
+            .map(|(state, steps)| (state.as_str(), *steps))
+            .collect::<Vec<_>>();
+        states.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        states
+    }
+
+    /// The times every transition was taken, by their state and source symbol, the most first.
+    /// The weighted random transitions of the same source symbol are counted together.
+    pub fn hottest_transitions(&self) -> Vec<(&str, TransitionSource, usize)> {
+        let mut transitions = self
+            .transitions
+            .iter()
+            .map(|((state, source), uses)| (state.as_str(), *source, *uses))
+            .collect::<Vec<_>>();
+        transitions.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)).then(a.1.cmp(&b.1)));
+        transitions
+    }
+
+    /// Every scan of at least two steps, the longest first (and then the first one).
+    pub fn longest_scans(&self) -> &[Scan] {
+        &self.scans
+    }
+
+    /// The steps taken with the head at every position. Positions are counted from the cell
+    /// the tape started at, so they don't move when the tape grows to the left (where they are
+    /// negative).
+    pub fn head_positions(&self) -> &BTreeMap<isize, usize> {
+        &self.head_positions
+    }
+
+    fn percentage(&self, steps: usize) -> f64 {
+        match self.outcome.steps() {
+            0 => 0.0,
+            total => steps as f64 * 100.0 / total as f64,
+        }
+    }
+
+    /// Writes the first `top` of every list of the profile.
+    pub fn report(&self, top: usize) -> String {
+        let mut report = String::new();
+        let _ = self.write_report(&mut report, top);
+        report
+    }
+
+    fn write_report(&self, f: &mut impl fmt::Write, top: usize) -> fmt::Result {
+        let verdict = match self.outcome {
+            RunOutcome::Halted { accepted: true, .. } => "halted, accepts",
+            RunOutcome::Halted {
+                accepted: false, ..
+            } => "halted, rejects",
+            _ => "did not halt within limit",
+        };
+        writeln!(
+            f,
+            "Profile of \"{}\": {} steps ({verdict})",
+            self.name,
+            self.outcome.steps()
+        )?;
+
+        writeln!(f, "\nHottest states:")?;
+        for (state, steps) in self.hottest_states().into_iter().take(top) {
+            writeln!(
+                f,
+                "  {state}: {steps} steps ({:.1}%)",
+                self.percentage(steps)
+            )?;
+        }
+
+        writeln!(f, "\nHottest transitions:")?;
+        for (state, source, uses) in self.hottest_transitions().into_iter().take(top) {
+            let symbol = match source {
+                TransitionSource::Mark(c) => c.to_string(),
+                TransitionSource::Blank => self.blank_symbol.to_string(),
+                TransitionSource::Default => "default".to_string(),
+            };
+            writeln!(
+                f,
+                "  {state}, reading {symbol}: {uses} times ({:.1}%)",
+                self.percentage(uses)
+            )?;
+        }
+
+        writeln!(f, "\nLongest scans:")?;
+        if self.scans.is_empty() {
+            writeln!(f, "  None")?;
+        }
+        for scan in self.scans.iter().take(top) {
+            let direction = if scan.direction == HeadMovement::Left {
+                "left"
+            } else {
+                "right"
+            };
+            writeln!(
+                f,
+                "  {}: {} steps moving {direction}, from step {} at position {}",
+                scan.state, scan.steps, scan.start_step, scan.start_position
+            )?;
+        }
+
+        writeln!(f, "\nHead positions:")?;
+        let (Some(first), Some(last)) = (
+            self.head_positions.keys().next(),
+            self.head_positions.keys().next_back(),
+        ) else {
+            return writeln!(f, "  None");
+        };
+
+        // Positions are grouped into at most HISTOGRAM_ROWS rows of the same width
+        let span = (last - first + 1) as usize;
+        let width = span.div_ceil(HISTOGRAM_ROWS) as isize;
+        let rows = (0..span.div_ceil(width as usize) as isize)
+            .map(|row| {
+                let start = first + row * width;
+                let end = (start + width - 1).min(*last);
+                let steps = self
+                    .head_positions
+                    .range(start..=end)
+                    .map(|(_, steps)| steps)
+                    .sum();
+                (start, end, steps)
+            })
+            .collect::<Vec<(isize, isize, usize)>>();
+
+        let max = rows
+            .iter()
+            .map(|(_, _, steps)| *steps)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        for (start, end, steps) in rows {
+            let label = if start == end {
+                start.to_string()
+            } else {
+                format!("{start}..{end}")
+            };
+            writeln!(
+                f,
+                "  {label:>12} {:<HISTOGRAM_WIDTH$} {steps}",
+                "█".repeat(steps * HISTOGRAM_WIDTH / max)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_report(f, usize::MAX)
+    }
+}
+
+/// Runs the machine like [`runner::run`], and returns where the run spent its steps: in which
+/// states and transitions, in which scans, and with the head at which positions.
+pub fn profile(machine: &mut TuringMachine, limits: &RunLimits) -> Profile {
+    let start = Instant::now();
+    let mut steps = 0;
+
+    let mut states = HashMap::new();
+    let mut scans = vec![];
+    let mut scan: Option<Scan> = None;
+    let mut head_positions = BTreeMap::new();
+    let mut origin = 0; // Index of the cell the tape started at
+    let taken_before = machine.heatmap().clone();
+
+    let outcome = loop {
+        if let Some(outcome) = runner::check_limits(limits, machine, start, steps) {
+            break outcome;
+        }
+
+        let position = machine.head_idx() as isize - origin;
+        let tape_len = machine.tape().len();
+        let tick_result = machine.tick();
+
+        if machine.is_halted() {
+            break RunOutcome::Halted {
+                accepted: machine.is_accepting(),
+                steps,
+            };
+        }
+
+        if tick_result.extended_tape_on_side == Some(TapeSide::Left) {
+            origin += (machine.tape().len() - tape_len) as isize;
+        }
+
+        *head_positions.entry(position).or_default() += 1;
+
+        let (state, movement, new_state) = match &tick_result.step {
+            Some(Step::Transition {
+                state,
+                head_movement,
+                new_state,
+                ..
+            }) => (state, *head_movement, Some(new_state)),
+            Some(
+                Step::Emit { state, .. }
+                | Step::Query { state, .. }
+                | Step::Halt { state, .. }
+                | Step::OutOfBounds { state, .. },
+            ) => (state, HeadMovement::Stay, None),
+            Some(Step::Intervention(_)) | None => {
+                steps += 1;
+                continue;
+            }
+        };
+        *states.entry(state.clone()).or_default() += 1;
+
+        // A scan goes on while the machine stays in the same state moving the same way
+        let scans_on = movement != HeadMovement::Stay && new_state == Some(state);
+        match &mut scan {
+            Some(current)
+                if scans_on && current.state == *state && current.direction == movement =>
+            {
+                current.steps += 1;
+            }
+            _ => {
+                scans.extend(scan.take());
+                if scans_on {
+                    scan = Some(Scan {
+                        state: state.clone(),
+                        direction: movement,
+                        steps: 1,
+                        start_step: steps,
+                        start_position: position,
+                    });
+                }
+            }
+        }
+
+        steps += 1;
+    };
+
+    scans.extend(scan);
+    scans.retain(|scan| scan.steps >= 2);
+    scans.sort_by(|a, b| b.steps.cmp(&a.steps).then(a.start_step.cmp(&b.start_step)));
+
+    // Only the transitions taken during this run
+    let transitions = machine
+        .heatmap()
+        .transitions()
+        .filter_map(|(state, source, uses)| {
+            let uses = uses - taken_before.transition_uses(state, source);
+            (uses > 0).then(|| ((state.to_string(), source), uses))
+        })
+        .collect();
+
+    Profile {
+        name: machine.name().to_string(),
+        blank_symbol: machine.blank_symbol(),
+        outcome,
+        states,
+        transitions,
+        scans,
+        head_positions,
+    }
+}
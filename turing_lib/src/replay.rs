@@ -0,0 +1,902 @@
+//! Recordings of runs that can be played back without the machine that made them.
+//!
+//! A `.trace` file is a text file starting with a `turing trace 1` line, followed by the
+//! initial configuration of the machine (one line per field, with the name of the field, a tab
+//! and its value) and then one `tick` line per tick of the run, with tab separated `key=value`
+//! fields (or just `key`, for flags): the cells the tick wrote, where the head and the machine ended up, and what the tick
+//! did. Changes made by hooks (see [`crate::hook`]) are recorded as ticks too, marked with
+//! `step=intervention`. Tabs, line breaks and backslashes in values are escaped with a backslash.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    time::Instant,
+};
+
+use crate::{
+    automaton::Automaton,
+    hook::{self, Hook, Intervention},
+    machine::{HeadMovement, Pacing, Step, Symbol, TickResult, TuringMachine},
+    runner::{self, RunLimits, RunOutcome},
+    tape::{Tape, TapeSide, DEFAULT_BLANK_SYMBOL},
+};
+
+pub const TRACE_EXTENSION: &str = "trace";
+
+const HEADER: &str = "turing trace 1";
+
+/// A configuration of a recorded run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Configuration {
+    tape: Tape,
+    head_idx: usize,
+    state: String,
+    halted: bool,
+    accepting: bool,
+    stack: Option<Vec<char>>,
+    registers: Option<Vec<u64>>,
+}
+
+impl Configuration {
+    fn of<A: Automaton + ?Sized>(machine: &A) -> Self {
+        Self {
+            tape: machine.tape().clone(),
+            head_idx: machine.head_idx(),
+            state: machine.current_state_name().to_string(),
+            halted: machine.is_halted(),
+            accepting: machine.is_accepting(),
+            stack: machine.stack().map(<[char]>::to_vec),
+            registers: machine.registers().map(<[u64]>::to_vec),
+        }
+    }
+}
+
+/// How a tick changed the tape.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TapeChange {
+    /// The cells that changed, by their index after the tape was extended.
+    Writes(Vec<(usize, Symbol)>),
+    /// The whole tape, if it changed in another way.
+    Whole(Tape),
+}
+
+/// A tick of a recorded run: its [`TickResult`] and how it changed the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedTick {
+    pub written_different_symbol: bool,
+    pub extended_tape_on_side: Option<TapeSide>,
+    pub head_movement: HeadMovement,
+    pub step: Option<Step>,
+
+    tape_len: usize,
+    change: TapeChange,
+    head_idx: usize,
+    state: String,
+    halted: bool,
+    accepting: bool,
+    stack: Option<Vec<char>>,
+    registers: Option<Vec<u64>>,
+}
+
+impl RecordedTick {
+    fn new<A: Automaton + ?Sized>(tick_result: TickResult, before: &Tape, machine: &A) -> Self {
+        let after = machine.tape();
+        let added_cells = after.len().saturating_sub(before.len());
+
+        let mut aligned = before.get_content().to_vec();
+        match tick_result.extended_tape_on_side {
+            Some(TapeSide::Left) => {
+                aligned.splice(0..0, vec![Symbol::Blank; added_cells]);
+            }
+            Some(TapeSide::Right) => aligned.extend(vec![Symbol::Blank; added_cells]),
+            None => {}
+        }
+
+        let change = if aligned.len() == after.len() {
+            TapeChange::Writes(
+                aligned
+                    .iter()
+                    .zip(after.get_content())
+                    .enumerate()
+                    .filter(|(_, (old, new))| old != new)
+                    .map(|(idx, (_, new))| (idx, *new))
+                    .collect(),
+            )
+        } else {
+            TapeChange::Whole(after.clone())
+        };
+
+        Self {
+            written_different_symbol: tick_result.written_different_symbol,
+            extended_tape_on_side: tick_result.extended_tape_on_side,
+            head_movement: tick_result.head_movement,
+            step: tick_result.step,
+
+            tape_len: after.len(),
+            change,
+            head_idx: machine.head_idx(),
+            state: machine.current_state_name().to_string(),
+            halted: machine.is_halted(),
+            accepting: machine.is_accepting(),
+            stack: machine.stack().map(<[char]>::to_vec),
+            registers: machine.registers().map(<[u64]>::to_vec),
+        }
+    }
+
+    fn apply(&self, configuration: &mut Configuration) {
+        match &self.change {
+            TapeChange::Writes(writes) => {
+                let added_cells = self.tape_len.saturating_sub(configuration.tape.len());
+                let mut content = configuration.tape.get_content().to_vec();
+                match self.extended_tape_on_side {
+                    Some(TapeSide::Left) => {
+                        content.splice(0..0, vec![Symbol::Blank; added_cells]);
+                    }
+                    Some(TapeSide::Right) => content.extend(vec![Symbol::Blank; added_cells]),
+                    None => {}
+                }
+                for &(idx, symbol) in writes {
+                    if let Some(cell) = content.get_mut(idx) {
+                        *cell = symbol;
+                    }
+                }
+                configuration.tape = Tape::new(content);
+            }
+            TapeChange::Whole(tape) => configuration.tape = tape.clone(),
+        }
+
+        configuration.head_idx = self.head_idx;
+        configuration.state.clone_from(&self.state);
+        configuration.halted = self.halted;
+        configuration.accepting = self.accepting;
+        configuration.stack.clone_from(&self.stack);
+        configuration.registers.clone_from(&self.registers);
+    }
+
+    fn tick_result(&self) -> TickResult {
+        TickResult {
+            written_different_symbol: self.written_different_symbol,
+            extended_tape_on_side: self.extended_tape_on_side,
+            head_movement: self.head_movement,
+            step: self.step.clone(),
+        }
+    }
+}
+
+/// A recorded run, which plays back like the machine that made it (it is an [`Automaton`]
+/// whose ticks are the recorded ones) and can also be moved back and forth with
+/// [`Replay::seek`].
+#[derive(Debug, Clone)]
+pub struct Replay {
+    name: String,
+    blank_symbol: char,
+    descriptions: HashMap<String, String>, // Of the states the run went through
+
+    initial: Configuration,
+    ticks: Vec<RecordedTick>,
+
+    position: usize, // How many ticks were played
+    current: Configuration,
+}
+
+impl Replay {
+    /// Runs the machine like [`runner::run`], recording every tick.
+    pub fn record<A: Automaton + ?Sized>(
+        machine: &mut A,
+        limits: &RunLimits,
+    ) -> (Self, RunOutcome) {
+        Self::record_intervened(machine, limits, |_, _, _| Ok(()))
+            .expect("Runs without hooks can't fail")
+    }
+
+    /// Runs the machine like [`hook::run_with_hooks`], recording every tick and every change
+    /// made by the hooks, as a tick with an [`Step::Intervention`] step.
+    pub fn record_with_hooks(
+        machine: &mut TuringMachine,
+        limits: &RunLimits,
+        hooks: &mut [Box<dyn Hook>],
+    ) -> Result<(Self, RunOutcome), String> {
+        Self::record_intervened(machine, limits, |machine, steps, ticks| {
+            hook::run_hooks(machine, steps, hooks, |tick_result, before, machine| {
+                ticks.push(RecordedTick::new(tick_result, before, machine));
+            })
+        })
+    }
+
+    /// Records a run, calling `intervene` before every tick with the machine, the steps taken
+    /// so far and the recorded ticks, to change the machine and record the changes.
+    fn record_intervened<A, F>(
+        machine: &mut A,
+        limits: &RunLimits,
+        mut intervene: F,
+    ) -> Result<(Self, RunOutcome), String>
+    where
+        A: Automaton + ?Sized,
+        F: FnMut(&mut A, usize, &mut Vec<RecordedTick>) -> Result<(), String>,
+    {
+        let initial = Configuration::of(machine);
+        let mut replay = Self {
+            name: machine.name().to_string(),
+            blank_symbol: machine.blank_symbol(),
+            descriptions: HashMap::new(),
+            current: initial.clone(),
+            initial,
+            ticks: vec![],
+            position: 0,
+        };
+
+        let start = Instant::now();
+        let mut steps = 0;
+
+        let outcome = loop {
+            if let Some(description) = machine.state_description() {
+                replay.descriptions.insert(
+                    machine.current_state_name().to_string(),
+                    description.to_string(),
+                );
+            }
+
+            if let Some(outcome) = runner::check_limits(limits, machine, start, steps) {
+                break outcome;
+            }
+
+            intervene(machine, steps, &mut replay.ticks)?;
+
+            let before = machine.tape().clone();
+            let tick_result = machine.tick();
+            replay
+                .ticks
+                .push(RecordedTick::new(tick_result, &before, machine));
+
+            if machine.is_halted() {
+                break RunOutcome::Halted {
+                    accepted: machine.is_accepting(),
+                    steps,
+                };
+            }
+
+            steps += 1;
+        };
+
+        replay.seek(0);
+        Ok((replay, outcome))
+    }
+
+    /// Reads a replay from the contents of a `.trace` file.
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let mut lines = data.lines().enumerate();
+        if lines.next().map(|(_, line)| line.trim_end()) != Some(HEADER) {
+            return Err(format!(
+                "[turing_lib] Error while parsing the trace. It must start with a \"{HEADER}\" line."
+            ));
+        }
+
+        let mut fields = HashMap::new();
+        let mut descriptions = HashMap::new();
+        let mut ticks = vec![];
+
+        for (idx, line) in lines {
+            let error = |message: &str| {
+                format!(
+                    "[turing_lib] Error while parsing the trace at line {}. {message}",
+                    idx + 1
+                )
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let values = line.split('\t').map(unescape).collect::<Vec<_>>();
+            match values.as_slice() {
+                [key, ..] if key == "tick" => {
+                    let blank_symbol = parse_char(fields.get("blank"), DEFAULT_BLANK_SYMBOL)?;
+                    ticks.push(parse_tick(&values[1..], blank_symbol).map_err(|err| error(&err))?)
+                }
+                [key, state, description] if key == "description" => {
+                    descriptions.insert(state.clone(), description.clone());
+                }
+                [key, value] => {
+                    fields.insert(key.clone(), value.clone());
+                }
+                _ => return Err(error("Expected a field and its value, or a tick.")),
+            }
+        }
+
+        let field = |key: &str| {
+            fields.get(key).ok_or_else(|| {
+                format!(
+                    "[turing_lib] Error while parsing the trace. The \"{key}\" field is missing."
+                )
+            })
+        };
+
+        let blank_symbol = parse_char(fields.get("blank"), DEFAULT_BLANK_SYMBOL)?;
+        let initial = Configuration {
+            tape: Tape::parse(field("tape")?, blank_symbol),
+            head_idx: parse_number(field("head")?)?,
+            state: field("state")?.clone(),
+            halted: false,
+            accepting: false,
+            stack: fields.get("stack").map(|stack| stack.chars().collect()),
+            registers: fields
+                .get("registers")
+                .map(|registers| parse_registers(registers))
+                .transpose()?,
+        };
+
+        let mut replay = Self {
+            name: field("name")?.clone(),
+            blank_symbol,
+            descriptions,
+            current: initial.clone(),
+            initial,
+            ticks,
+            position: 0,
+        };
+        replay.seek(0);
+        Ok(replay)
+    }
+
+    /// The contents of the `.trace` file of the replay.
+    pub fn to_trace_string(&self) -> String {
+        let mut trace = format!("{HEADER}\n");
+        let mut field = |key: &str, value: &str| {
+            let _ = writeln!(trace, "{key}\t{}", escape(value));
+        };
+
+        field("name", &self.name);
+        field("blank", &self.blank_symbol.to_string());
+        field("tape", &self.tape_string(&self.initial.tape));
+        field("head", &self.initial.head_idx.to_string());
+        field("state", &self.initial.state);
+        if let Some(stack) = &self.initial.stack {
+            field("stack", &stack.iter().collect::<String>());
+        }
+        if let Some(registers) = &self.initial.registers {
+            field("registers", &registers_string(registers));
+        }
+
+        let mut descriptions = self.descriptions.iter().collect::<Vec<_>>();
+        descriptions.sort();
+        for (state, description) in descriptions {
+            let _ = writeln!(
+                trace,
+                "description\t{}\t{}",
+                escape(state),
+                escape(description)
+            );
+        }
+
+        for tick in &self.ticks {
+            trace.push_str("tick");
+            for (key, value) in self.tick_fields(tick) {
+                if value.is_empty() {
+                    let _ = write!(trace, "\t{key}");
+                } else {
+                    let _ = write!(trace, "\t{key}={}", escape(&value));
+                }
+            }
+            trace.push('\n');
+        }
+
+        trace
+    }
+
+    /// Runs the machine along the replay, and returns the first tick where it did something
+    /// else than the recorded one (`Some(0)` if it doesn't even start like the replay), or
+    /// `None` if it went through every recorded tick the same way. The machine is left after
+    /// that tick, so it can be compared with the replay moved there with [`Replay::seek`].
+    pub fn first_mismatch<A: Automaton + ?Sized>(&self, machine: &mut A) -> Option<usize> {
+        let start = Configuration::of(machine);
+        let same_start = machine.name() == self.name
+            && machine.blank_symbol() == self.blank_symbol
+            && start.tape == self.initial.tape
+            && start.head_idx == self.initial.head_idx
+            && start.state == self.initial.state
+            && start.stack == self.initial.stack
+            && start.registers == self.initial.registers;
+
+        if !same_start || !self.same_description(machine) {
+            return Some(0);
+        }
+
+        for (idx, recorded) in self.ticks.iter().enumerate() {
+            if machine.is_halted() {
+                return Some(idx + 1);
+            }
+
+            let before = machine.tape().clone();
+            let tick_result = machine.tick();
+            if RecordedTick::new(tick_result, &before, machine) != *recorded
+                || !self.same_description(machine)
+            {
+                return Some(idx + 1);
+            }
+        }
+
+        None
+    }
+
+    /// Like [`Replay::first_mismatch`], but only compares the configurations the machine goes
+    /// through (its tape, head, stack, registers and whether it halted and accepts) and not
+    /// how, and its states up to a renaming: a state of the replay must always match the same
+    /// state of the machine. So a machine whose states were renamed or whose transitions were
+    /// reordered still goes through the run of the original one.
+    pub fn first_divergence<A: Automaton + ?Sized>(&self, machine: &mut A) -> Option<usize> {
+        let mut renaming = HashMap::new(); // From the states of the replay to those of the machine
+        let mut renamed = HashSet::new();
+
+        let mut same = |expected: &Configuration, actual: &Configuration| {
+            let same_state = match renaming.get(&expected.state) {
+                Some(state) => *state == actual.state,
+                None if renamed.contains(&actual.state) => false,
+                None => {
+                    renaming.insert(expected.state.clone(), actual.state.clone());
+                    renamed.insert(actual.state.clone());
+                    true
+                }
+            };
+
+            same_state
+                && expected.tape == actual.tape
+                && expected.head_idx == actual.head_idx
+                && expected.halted == actual.halted
+                && expected.accepting == actual.accepting
+                && expected.stack == actual.stack
+                && expected.registers == actual.registers
+        };
+
+        let mut expected = self.initial.clone();
+        if !same(&expected, &Configuration::of(machine)) {
+            return Some(0);
+        }
+
+        for (idx, recorded) in self.ticks.iter().enumerate() {
+            if machine.is_halted() {
+                return Some(idx + 1);
+            }
+
+            machine.tick();
+            recorded.apply(&mut expected);
+            if !same(&expected, &Configuration::of(machine)) {
+                return Some(idx + 1);
+            }
+        }
+
+        None
+    }
+
+    fn same_description<A: Automaton + ?Sized>(&self, machine: &A) -> bool {
+        machine.state_description()
+            == self
+                .descriptions
+                .get(machine.current_state_name())
+                .map(String::as_str)
+    }
+
+    pub fn ticks(&self) -> &[RecordedTick] {
+        &self.ticks
+    }
+
+    /// How many ticks were played.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Moves to the configuration after `position` ticks (or after the last one).
+    pub fn seek(&mut self, position: usize) {
+        let position = position.min(self.ticks.len());
+        if position < self.position || position == 0 {
+            self.current = self.initial.clone();
+            self.position = 0;
+        }
+
+        for tick in &self.ticks[self.position..position] {
+            tick.apply(&mut self.current);
+        }
+        self.position = position;
+    }
+
+    fn symbol_string(&self, symbol: Symbol) -> String {
+        match symbol {
+            Symbol::Mark(c) => c.to_string(),
+            Symbol::Blank => self.blank_symbol.to_string(),
+            Symbol::Default => String::new(),
+        }
+    }
+
+    fn tape_string(&self, tape: &Tape) -> String {
+        tape.get_content()
+            .iter()
+            .map(|&symbol| self.symbol_string(symbol))
+            .collect()
+    }
+
+    fn tick_fields(&self, tick: &RecordedTick) -> Vec<(&'static str, String)> {
+        let mut fields = vec![];
+
+        if tick.written_different_symbol {
+            fields.push(("changed", String::new()));
+        }
+        match tick.extended_tape_on_side {
+            Some(TapeSide::Left) => fields.push(("extended", "left".to_string())),
+            Some(TapeSide::Right) => fields.push(("extended", "right".to_string())),
+            None => {}
+        }
+        fields.push(("moved", movement_name(tick.head_movement).to_string()));
+        fields.push(("cells", tick.tape_len.to_string()));
+        match &tick.change {
+            TapeChange::Writes(writes) => {
+                for &(idx, symbol) in writes {
+                    fields.push(("write", format!("{idx}:{}", self.symbol_string(symbol))));
+                }
+            }
+            TapeChange::Whole(tape) => fields.push(("tape", self.tape_string(tape))),
+        }
+        fields.push(("head", tick.head_idx.to_string()));
+        fields.push(("state", tick.state.clone()));
+        if tick.halted {
+            let verdict = if tick.accepting { "accepts" } else { "rejects" };
+            fields.push(("halted", verdict.to_string()));
+        }
+        if let Some(stack) = &tick.stack {
+            fields.push(("stack", stack.iter().collect()));
+        }
+        if let Some(registers) = &tick.registers {
+            fields.push(("registers", registers_string(registers)));
+        }
+
+        match &tick.step {
+            None => {}
+            Some(Step::Transition {
+                state,
+                read,
+                written,
+                head_movement,
+                distance,
+                new_state,
+                pacing,
+            }) => {
+                fields.push(("step", "transition".to_string()));
+                fields.push(("from", state.clone()));
+                fields.push(("read", self.symbol_string(*read)));
+                fields.push(("written", self.symbol_string(*written)));
+                fields.push(("move", movement_name(*head_movement).to_string()));
+                fields.push(("distance", distance.to_string()));
+                fields.push(("to", new_state.clone()));
+                let pacing = match pacing {
+                    Pacing::Normal => "normal",
+                    Pacing::Slow => "slow",
+                    Pacing::Fast => "fast",
+                };
+                fields.push(("pacing", pacing.to_string()));
+            }
+            Some(Step::Halt { state, read }) => {
+                fields.push(("step", "halt".to_string()));
+                fields.push(("from", state.clone()));
+                fields.push(("read", self.symbol_string(*read)));
+            }
+            Some(Step::Emit {
+                state,
+                word,
+                new_state,
+            }) => {
+                fields.push(("step", "emit".to_string()));
+                fields.push(("from", state.clone()));
+                fields.push(("word", word.clone()));
+                fields.push(("to", new_state.clone()));
+            }
+            Some(Step::Query {
+                state,
+                word,
+                answer,
+            }) => {
+                fields.push(("step", "query".to_string()));
+                fields.push(("from", state.clone()));
+                fields.push(("word", word.clone()));
+                if let Some((answer, new_state)) = answer {
+                    let answer = if *answer { "yes" } else { "no" };
+                    fields.push(("answer", answer.to_string()));
+                    fields.push(("to", new_state.clone()));
+                }
+            }
+            Some(Step::OutOfBounds {
+                state,
+                read,
+                head_movement,
+            }) => {
+                fields.push(("step", "out_of_bounds".to_string()));
+                fields.push(("from", state.clone()));
+                fields.push(("read", self.symbol_string(*read)));
+                fields.push(("move", movement_name(*head_movement).to_string()));
+            }
+            Some(Step::Intervention(Intervention::SetCell { cell, symbol })) => {
+                fields.push(("step", "intervention".to_string()));
+                fields.push((
+                    "set_cell",
+                    format!("{cell}:{}", self.symbol_string(*symbol)),
+                ));
+            }
+            Some(Step::Intervention(Intervention::SetState(state))) => {
+                fields.push(("step", "intervention".to_string()));
+                fields.push(("set_state", state.clone()));
+            }
+        }
+
+        fields
+    }
+}
+
+impl Automaton for Replay {
+    /// Plays the next recorded tick. At the end of the replay, the configuration stays the same.
+    fn tick(&mut self) -> TickResult {
+        match self.ticks.get(self.position) {
+            Some(tick) => {
+                tick.apply(&mut self.current);
+                self.position += 1;
+                tick.tick_result()
+            }
+            None => TickResult {
+                written_different_symbol: false,
+                extended_tape_on_side: None,
+                head_movement: HeadMovement::Stay,
+                step: None,
+            },
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn blank_symbol(&self) -> char {
+        self.blank_symbol
+    }
+
+    fn head_idx(&self) -> usize {
+        self.current.head_idx
+    }
+
+    fn current_state_name(&self) -> &str {
+        &self.current.state
+    }
+
+    fn is_halted(&self) -> bool {
+        self.current.halted
+    }
+
+    fn is_accepting(&self) -> bool {
+        self.current.accepting
+    }
+
+    fn tape(&self) -> &Tape {
+        &self.current.tape
+    }
+
+    fn stack(&self) -> Option<&[char]> {
+        self.current.stack.as_deref()
+    }
+
+    fn registers(&self) -> Option<&[u64]> {
+        self.current.registers.as_deref()
+    }
+
+    fn state_description(&self) -> Option<&str> {
+        self.descriptions
+            .get(&self.current.state)
+            .map(String::as_str)
+    }
+
+    fn timeline(&self) -> Option<(usize, usize)> {
+        Some((self.position, self.ticks.len()))
+    }
+
+    fn seek(&mut self, position: usize) {
+        self.seek(position)
+    }
+}
+
+fn movement_name(movement: HeadMovement) -> &'static str {
+    match movement {
+        HeadMovement::Left => "left",
+        HeadMovement::Right => "right",
+        HeadMovement::Stay => "stay",
+    }
+}
+
+fn parse_movement(name: &str) -> Result<HeadMovement, String> {
+    match name {
+        "left" => Ok(HeadMovement::Left),
+        "right" => Ok(HeadMovement::Right),
+        "stay" => Ok(HeadMovement::Stay),
+        _ => Err(format!("Invalid head movement \"{name}\".")),
+    }
+}
+
+fn registers_string(registers: &[u64]) -> String {
+    registers
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_registers(registers: &str) -> Result<Vec<u64>, String> {
+    if registers.is_empty() {
+        return Ok(vec![]);
+    }
+
+    registers.split(',').map(parse_number).collect()
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number \"{value}\"."))
+}
+
+fn parse_char(value: Option<&String>, default: char) -> Result<char, String> {
+    let Some(value) = value else {
+        return Ok(default);
+    };
+
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(format!(
+            "Invalid symbol \"{value}\". It must be a single character."
+        )),
+    }
+}
+
+fn parse_symbol(value: &str, blank_symbol: char) -> Result<Symbol, String> {
+    if value.is_empty() {
+        return Ok(Symbol::Default);
+    }
+
+    Ok(Symbol::from_char(
+        parse_char(Some(&value.to_string()), blank_symbol)?,
+        blank_symbol,
+    ))
+}
+
+fn parse_tick(values: &[String], blank_symbol: char) -> Result<RecordedTick, String> {
+    let mut fields: HashMap<&str, &str> = HashMap::new();
+    let mut writes = vec![];
+
+    for value in values {
+        let (key, value) = value.split_once('=').unwrap_or((value, ""));
+        if key == "write" {
+            let (idx, symbol) = value
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid write \"{value}\"."))?;
+            writes.push((parse_number(idx)?, parse_symbol(symbol, blank_symbol)?));
+        } else {
+            fields.insert(key, value);
+        }
+    }
+
+    let field = |key: &str| {
+        fields
+            .get(key)
+            .copied()
+            .ok_or_else(|| format!("The \"{key}\" field of the tick is missing."))
+    };
+    let symbol = |key: &str| parse_symbol(field(key)?, blank_symbol);
+
+    let step =
+        match fields.get("step").copied() {
+            None => None,
+            Some("transition") => Some(Step::Transition {
+                state: field("from")?.to_string(),
+                read: symbol("read")?,
+                written: symbol("written")?,
+                head_movement: parse_movement(field("move")?)?,
+                distance: parse_number(field("distance")?)?,
+                new_state: field("to")?.to_string(),
+                pacing: match field("pacing")? {
+                    "normal" => Pacing::Normal,
+                    "slow" => Pacing::Slow,
+                    "fast" => Pacing::Fast,
+                    pacing => return Err(format!("Invalid pacing \"{pacing}\".")),
+                },
+            }),
+            Some("halt") => Some(Step::Halt {
+                state: field("from")?.to_string(),
+                read: symbol("read")?,
+            }),
+            Some("emit") => Some(Step::Emit {
+                state: field("from")?.to_string(),
+                word: field("word")?.to_string(),
+                new_state: field("to")?.to_string(),
+            }),
+            Some("query") => Some(Step::Query {
+                state: field("from")?.to_string(),
+                word: field("word")?.to_string(),
+                answer: match fields.get("answer").copied() {
+                    None => None,
+                    Some(answer) => Some((answer == "yes", field("to")?.to_string())),
+                },
+            }),
+            Some("out_of_bounds") => Some(Step::OutOfBounds {
+                state: field("from")?.to_string(),
+                read: symbol("read")?,
+                head_movement: parse_movement(field("move")?)?,
+            }),
+            Some("intervention") => Some(Step::Intervention(
+                match (fields.get("set_cell"), fields.get("set_state")) {
+                    (Some(write), None) => {
+                        let (cell, symbol) = write
+                            .split_once(':')
+                            .ok_or_else(|| format!("Invalid write \"{write}\"."))?;
+                        Intervention::SetCell {
+                            cell: parse_number(cell)?,
+                            symbol: parse_symbol(symbol, blank_symbol)?,
+                        }
+                    }
+                    (None, Some(state)) => Intervention::SetState(state.to_string()),
+                    _ => return Err(
+                        "An intervention must have either a \"set_cell\" or a \"set_state\" field."
+                            .to_string(),
+                    ),
+                },
+            )),
+            Some(step) => return Err(format!("Invalid step \"{step}\".")),
+        };
+
+    let halted = fields.get("halted").copied();
+
+    Ok(RecordedTick {
+        written_different_symbol: fields.contains_key("changed"),
+        extended_tape_on_side: match fields.get("extended").copied() {
+            None => None,
+            Some("left") => Some(TapeSide::Left),
+            Some("right") => Some(TapeSide::Right),
+            Some(side) => return Err(format!("Invalid tape side \"{side}\".")),
+        },
+        head_movement: parse_movement(field("moved")?)?,
+        step,
+
+        tape_len: parse_number(field("cells")?)?,
+        change: match fields.get("tape") {
+            Some(tape) => TapeChange::Whole(Tape::parse(tape, blank_symbol)),
+            None => TapeChange::Writes(writes),
+        },
+        head_idx: parse_number(field("head")?)?,
+        state: field("state")?.to_string(),
+        halted: halted.is_some(),
+        accepting: halted == Some("accepts"),
+        stack: fields.get("stack").map(|stack| stack.chars().collect()),
+        registers: fields
+            .get("registers")
+            .map(|registers| parse_registers(registers))
+            .transpose()?,
+    })
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
@@ -0,0 +1,357 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::{
+    automaton::{self, Automaton},
+    machine::{Symbol, TuringMachine},
+    runner::{self, RunLimits, RunOutcome},
+    svg::{self, escape},
+};
+
+/// A configuration the machine went through during a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub step: usize,
+    pub state: String,
+    pub head_idx: usize,
+    pub tape: String,
+}
+
+/// A recorded run: every configuration the machine went through, and how the run ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    pub frames: Vec<Frame>,
+    pub outcome: RunOutcome,
+}
+
+impl Recording {
+    /// Runs the machine and records the run, see [`record`].
+    pub fn new<A: Automaton + ?Sized>(machine: &mut A, limits: &RunLimits) -> Self {
+        let (frames, outcome) = record(machine, limits);
+        Self { frames, outcome }
+    }
+}
+
+/// Runs the machine like [`runner::run`], recording every configuration it goes through.
+pub fn record<A: Automaton + ?Sized>(
+    machine: &mut A,
+    limits: &RunLimits,
+) -> (Vec<Frame>, RunOutcome) {
+    let mut frames = vec![];
+
+    let outcome = runner::run_observed(machine, limits, |machine, step| {
+        let tape = machine
+            .tape()
+            .get_content()
+            .iter()
+            .map(|symbol| match symbol {
+                Symbol::Mark(c) => *c,
+                _ => machine.blank_symbol(),
+            })
+            .collect();
+
+        frames.push(Frame {
+            step,
+            state: machine.current_state_name().to_string(),
+            head_idx: machine.head_idx(),
+            tape,
+        });
+    });
+
+    (frames, outcome)
+}
+
+/// Runs a machine and returns a self-contained HTML report of the run, with the definition of
+/// the machine, its state diagram (for Turing Machines), statistics and a step by step table.
+/// With `scrubber`, the report also embeds a slider to replay the recorded configurations.
+pub fn html(
+    file_data: &str,
+    tape_data: &str,
+    limits: &RunLimits,
+    scrubber: bool,
+) -> Result<String, String> {
+    let mut machine = automaton::load_from_str(file_data, tape_data)?;
+    let name = machine.name().to_string();
+    let (frames, outcome) = record(machine.as_mut(), limits);
+
+    let verdict = verdict(outcome);
+    let visited_states = frames
+        .iter()
+        .map(|frame| frame.state.as_str())
+        .collect::<BTreeSet<_>>();
+    let max_tape_len = frames
+        .iter()
+        .map(|frame| frame.tape.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n{STYLE}</head>\n<body>\n<h1>{}</h1>\n",
+        escape(&name),
+        escape(&name)
+    );
+
+    html += "<h2>Statistics</h2>\n<table>\n";
+    for (label, value) in [
+        ("Input", escape(tape_data)),
+        ("Verdict", verdict.to_string()),
+        ("Steps", outcome.steps().to_string()),
+        (
+            "Visited states",
+            format!(
+                "{} ({})",
+                visited_states.len(),
+                escape(&visited_states.into_iter().collect::<Vec<_>>().join(", "))
+            ),
+        ),
+        ("Tape cells used", max_tape_len.to_string()),
+    ] {
+        let _ = writeln!(html, "<tr><th>{label}</th><td>{value}</td></tr>");
+    }
+    html += "</table>\n";
+
+    html += "<h2>Definition</h2>\n";
+    let _ = writeln!(html, "<pre>{}</pre>", escape(file_data));
+
+    if let Ok(machine) = TuringMachine::new_from_str(file_data, tape_data) {
+        html += "<h2>State diagram</h2>\n";
+        html += &svg::diagram(&machine);
+    }
+
+    if scrubber {
+        html += "<h2>Replay</h2>\n<input id=\"scrubber\" type=\"range\" min=\"0\" value=\"0\">\n\
+                 <pre id=\"frame\"></pre>\n";
+        let _ = writeln!(
+            html,
+            "<script>\nconst frames = [{}];\n{SCRUBBER_SCRIPT}</script>",
+            frames
+                .iter()
+                .map(|frame| format!(
+                    "[{},{},{},{}]",
+                    frame.step,
+                    js_string(&frame.state),
+                    frame.head_idx,
+                    js_string(&frame.tape)
+                ))
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+    }
+
+    html +=
+        "<h2>Steps</h2>\n<table>\n<tr><th>Step</th><th>State</th><th>Head</th><th>Tape</th></tr>\n";
+    for frame in &frames {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"tape\">{}</td></tr>",
+            frame.step,
+            escape(&frame.state),
+            frame.head_idx,
+            tape_html(frame)
+        );
+    }
+    html += "</table>\n</body>\n</html>\n";
+
+    Ok(html)
+}
+
+/// How a run ended, in a few words.
+pub(crate) fn verdict(outcome: RunOutcome) -> &'static str {
+    match outcome {
+        RunOutcome::Halted { accepted: true, .. } => "Halted, accepts",
+        RunOutcome::Halted {
+            accepted: false, ..
+        } => "Halted, rejects",
+        RunOutcome::StepLimitReached { .. } => "Did not halt within the step limit",
+        RunOutcome::TimedOut { .. } => "Did not halt within the timeout",
+        RunOutcome::TapeLimitExceeded { .. } => "Used more tape cells than allowed",
+        RunOutcome::Cancelled { .. } => "Cancelled",
+        RunOutcome::ResourceExhausted { .. } => "Used more memory than allowed",
+        RunOutcome::NeverHalts { .. } => "Never halts",
+    }
+}
+
+/// Renders recorded frames (see [`record`]) as a LaTeX `align*` environment with the chain of
+/// instantaneous descriptions, one per line, like `0 1 q_{1} 1 \vdash 0 1 1 q_{1} \sqcup`,
+/// where the state is written before the symbol under the head and blanks are `\sqcup`.
+pub fn latex(frames: &[Frame], blank_symbol: char) -> String {
+    let mut latex = String::from("\\begin{align*}\n");
+
+    for (idx, frame) in frames.iter().enumerate() {
+        let mut description = frame
+            .tape
+            .chars()
+            .map(|c| {
+                if c == blank_symbol {
+                    "\\sqcup".to_string()
+                } else {
+                    format!("\\mathtt{{{}}}", latex_escape(&c.to_string()))
+                }
+            })
+            .collect::<Vec<_>>();
+        description.insert(
+            frame.head_idx.min(description.len()),
+            latex_state(&frame.state),
+        );
+
+        let prefix = if idx == 0 { "" } else { "\\vdash " };
+        let end = if idx + 1 == frames.len() { "" } else { " \\\\" };
+        let _ = writeln!(latex, "{prefix}&{}{end}", description.join("\\,"));
+    }
+
+    latex += "\\end{align*}\n";
+    latex
+}
+
+/// Renders recorded frames (see [`record`]) one per line, as the step, the state and the tape
+/// with the symbol under the head between brackets, like `3 q1 01[1]0`.
+pub fn compact(frames: &[Frame]) -> String {
+    let mut compact = String::new();
+
+    for frame in frames {
+        let tape = frame
+            .tape
+            .chars()
+            .enumerate()
+            .map(|(idx, c)| {
+                if idx == frame.head_idx {
+                    format!("[{c}]")
+                } else {
+                    c.to_string()
+                }
+            })
+            .collect::<String>();
+        let _ = writeln!(compact, "{} {} {tape}", frame.step, frame.state);
+    }
+
+    compact
+}
+
+/// Renders recorded frames (see [`record`]) as the chain of instantaneous descriptions in plain
+/// text, one per line, like `0 1 q1 1 ⊢ 0 1 1 q1 _` is written in textbooks, where the state is
+/// written before the symbol under the head. See [`latex`] for the same chain in LaTeX.
+pub fn configurations(frames: &[Frame]) -> String {
+    let mut configurations = String::new();
+
+    for (idx, frame) in frames.iter().enumerate() {
+        let mut description = frame.tape.chars().map(String::from).collect::<Vec<_>>();
+        description.insert(frame.head_idx.min(description.len()), frame.state.clone());
+
+        let prefix = if idx == 0 { "  " } else { "⊢ " };
+        let _ = writeln!(configurations, "{prefix}{}", description.join(" "));
+    }
+
+    configurations
+}
+
+/// Renders recorded frames (see [`record`]) as tab-separated values, with a header and one row
+/// per frame with the step, the state, the head, the symbol under it and the tape, to open in
+/// spreadsheets.
+pub fn tsv(frames: &[Frame]) -> String {
+    let mut tsv = String::from("step\tstate\thead\tsymbol\ttape\n");
+
+    for frame in frames {
+        let symbol = frame
+            .tape
+            .chars()
+            .nth(frame.head_idx)
+            .map(String::from)
+            .unwrap_or_default();
+        let _ = writeln!(
+            tsv,
+            "{}\t{}\t{}\t{symbol}\t{}",
+            frame.step, frame.state, frame.head_idx, frame.tape
+        );
+    }
+
+    tsv
+}
+
+/// A state name for LaTeX math mode, e.g. `q_{1}` for `q1`.
+pub(crate) fn latex_state(name: &str) -> String {
+    let digits = name.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let letters = &name[..name.len() - digits.len()];
+
+    if letters.len() == 1 && !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        format!("{letters}_{{{digits}}}")
+    } else {
+        format!("\\mathit{{{}}}", latex_escape(name))
+    }
+}
+
+pub(crate) fn latex_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => format!("\\{c}"),
+            '~' => "\\textasciitilde{}".to_string(),
+            '^' => "\\textasciicircum{}".to_string(),
+            '\\' => "\\textbackslash{}".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// The tape of a frame, with the cell under the head highlighted.
+pub(crate) fn tape_html(frame: &Frame) -> String {
+    frame
+        .tape
+        .chars()
+        .enumerate()
+        .map(|(idx, c)| {
+            if idx == frame.head_idx {
+                format!("<mark>{}</mark>", escape(&c.to_string()))
+            } else {
+                escape(&c.to_string())
+            }
+        })
+        .collect()
+}
+
+/// A JavaScript string literal, safe to embed in a `<script>` element.
+fn js_string(value: &str) -> String {
+    let mut js = String::from("\"");
+
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                js.push('\\');
+                js.push(c);
+            }
+            c if c.is_ascii_alphanumeric() || c == ' ' => js.push(c),
+            c => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    let _ = write!(js, "\\u{unit:04x}");
+                }
+            }
+        }
+    }
+
+    js.push('"');
+    js
+}
+
+pub(crate) const STYLE: &str = "<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }
+pre, .tape { font-family: monospace; }
+mark { background: #6e9dd1; }
+</style>
+";
+
+const SCRUBBER_SCRIPT: &str = "const scrubber = document.getElementById('scrubber');
+const view = document.getElementById('frame');
+scrubber.max = frames.length - 1;
+function show() {
+  const [step, state, head, tape] = frames[scrubber.value];
+  view.innerHTML = '';
+  view.append(`Step ${step}, state ${state}\\n`);
+  [...tape].forEach((c, idx) => {
+    const cell = document.createElement(idx === head ? 'mark' : 'span');
+    cell.textContent = c;
+    view.append(cell);
+  });
+}
+scrubber.addEventListener('input', show);
+show();
+";
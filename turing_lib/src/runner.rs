@@ -0,0 +1,338 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{automaton::Automaton, machine::TuringMachine};
+
+/// Runs check whether they were cancelled every this many steps
+pub const CANCELLATION_CHECK_INTERVAL: usize = 1024;
+const PROGRESS_CLOCK_INTERVAL: usize = 256; // Progress checks between every look at the clock
+
+#[derive(Debug, Clone, Default)]
+pub struct RunLimits {
+    pub max_steps: Option<usize>,
+    pub timeout: Option<Duration>,
+    /// The most tape cells the machine may use, on top of the limit set by its definition (see
+    /// [`Automaton::max_tape_cells`]), to stop runaway machines before they use all the memory.
+    pub max_tape_cells: Option<usize>,
+    /// The most bytes the configuration of the machine may take in memory (see
+    /// [`Automaton::memory_usage`]), so a server running many machines at once stops the ones
+    /// that would use too much of it, instead of running out of memory.
+    pub max_memory: Option<usize>,
+    /// Stops the run when it is cancelled, e.g. from another thread.
+    pub cancellation: Option<CancellationToken>,
+}
+
+/// Lets another thread stop a run cleanly, e.g. when the client that asked for it goes away.
+/// Clones share the same state, so cancelling any of them cancels the runs given any other one
+/// in their [`RunLimits`], which stop soon after (they check every
+/// [`CANCELLATION_CHECK_INTERVAL`] steps).
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    Halted {
+        accepted: bool,
+        steps: usize,
+    },
+    StepLimitReached {
+        steps: usize,
+    },
+    TimedOut {
+        steps: usize,
+    },
+    TapeLimitExceeded {
+        steps: usize,
+    },
+    Cancelled {
+        steps: usize,
+    },
+    /// The machine took more memory than [`RunLimits::max_memory`], `memory` bytes.
+    ResourceExhausted {
+        steps: usize,
+        memory: usize,
+    },
+    /// The machine was found to run forever after `steps` steps, without reaching the limits.
+    /// Only the fast runner finds it out, see [`crate::fast::run`].
+    NeverHalts {
+        steps: usize,
+    },
+}
+
+impl RunOutcome {
+    pub fn steps(&self) -> usize {
+        match *self {
+            RunOutcome::Halted { steps, .. }
+            | RunOutcome::StepLimitReached { steps }
+            | RunOutcome::TimedOut { steps }
+            | RunOutcome::TapeLimitExceeded { steps }
+            | RunOutcome::Cancelled { steps }
+            | RunOutcome::ResourceExhausted { steps, .. }
+            | RunOutcome::NeverHalts { steps } => steps,
+        }
+    }
+
+    pub fn halted(&self) -> bool {
+        matches!(self, RunOutcome::Halted { .. })
+    }
+}
+
+/// How often a run reports its progress, see [`run_with_progress`]: whenever `every_steps` steps
+/// or `every` time passed since the last report, whichever comes first. Runs with neither never
+/// report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProgressInterval {
+    pub every_steps: Option<usize>,
+    pub every: Option<Duration>,
+}
+
+/// How far a run got, reported by [`run_with_progress`] (and [`crate::fast::run_with_progress`]),
+/// e.g. to show a progress bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress<'a> {
+    pub steps: usize,
+    pub tape_cells: usize,
+    pub state: &'a str,
+}
+
+/// Tells when a run is due to report its progress again, see [`ProgressInterval`].
+pub(crate) struct ProgressThrottle {
+    interval: ProgressInterval,
+    last_steps: usize,
+    last_report: Instant,
+    checks: usize,
+}
+
+impl ProgressThrottle {
+    pub(crate) fn new(interval: ProgressInterval) -> Self {
+        Self {
+            interval,
+            last_steps: 0,
+            last_report: Instant::now(),
+            checks: 0,
+        }
+    }
+
+    pub(crate) fn is_due(&mut self, steps: usize) -> bool {
+        self.checks += 1;
+
+        let due = self
+            .interval
+            .every_steps
+            .is_some_and(|every| steps - self.last_steps >= every)
+            || (self.checks.is_multiple_of(PROGRESS_CLOCK_INTERVAL)
+                && self
+                    .interval
+                    .every
+                    .is_some_and(|every| self.last_report.elapsed() >= every));
+
+        if due {
+            self.last_steps = steps;
+            self.last_report = Instant::now();
+        }
+        due
+    }
+}
+
+/// Words emitted by an enumerator, see [`enumerate`].
+#[derive(Debug, Clone)]
+pub struct Enumeration {
+    pub words: Vec<String>,
+    /// How the run ended, or `None` if it was stopped after collecting the maximum number of words.
+    pub outcome: Option<RunOutcome>,
+}
+
+impl RunLimits {
+    /// The tape cell limit that applies to a machine: the lowest of the one of these limits
+    /// and the one set by its definition.
+    pub fn tape_cell_limit<A: Automaton + ?Sized>(&self, machine: &A) -> Option<usize> {
+        match (self.max_tape_cells, machine.max_tape_cells()) {
+            (Some(limit), Some(machine_limit)) => Some(limit.min(machine_limit)),
+            (limit, machine_limit) => limit.or(machine_limit),
+        }
+    }
+
+    /// Whether the run was cancelled with the token of these limits.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+}
+
+pub(crate) fn check_limits<A: Automaton + ?Sized>(
+    limits: &RunLimits,
+    machine: &A,
+    start: Instant,
+    steps: usize,
+) -> Option<RunOutcome> {
+    if limits
+        .tape_cell_limit(machine)
+        .is_some_and(|max| machine.tape_cells() > max)
+    {
+        return Some(RunOutcome::TapeLimitExceeded { steps });
+    }
+
+    if let Some(max) = limits.max_memory {
+        let memory = machine.memory_usage();
+        if memory > max {
+            return Some(RunOutcome::ResourceExhausted { steps, memory });
+        }
+    }
+
+    if limits.max_steps.is_some_and(|max| steps >= max) {
+        return Some(RunOutcome::StepLimitReached { steps });
+    }
+
+    if limits
+        .timeout
+        .is_some_and(|timeout| start.elapsed() >= timeout)
+    {
+        return Some(RunOutcome::TimedOut { steps });
+    }
+
+    if steps.is_multiple_of(CANCELLATION_CHECK_INTERVAL) && limits.is_cancelled() {
+        return Some(RunOutcome::Cancelled { steps });
+    }
+
+    None
+}
+
+/// Runs the machine until it halts or one of the limits is reached.
+/// The final halting tick (the one that finds no transition) is not counted as a step.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(machine), fields(machine = machine.name()), ret)
+)]
+pub fn run<A: Automaton + ?Sized>(machine: &mut A, limits: &RunLimits) -> RunOutcome {
+    run_observed(machine, limits, |_, _| {})
+}
+
+/// Like [`run`], but calls `observer` with the machine and the steps taken so far on every
+/// configuration the run goes through, the last one included (e.g. to render the run).
+pub fn run_observed<A, F>(machine: &mut A, limits: &RunLimits, mut observer: F) -> RunOutcome
+where
+    A: Automaton + ?Sized,
+    F: FnMut(&A, usize),
+{
+    let start = Instant::now();
+    let mut steps = 0;
+
+    loop {
+        observer(machine, steps);
+
+        if let Some(outcome) = check_limits(limits, machine, start, steps) {
+            return outcome;
+        }
+
+        machine.advance();
+
+        if machine.is_halted() {
+            return RunOutcome::Halted {
+                accepted: machine.is_accepting(),
+                steps,
+            };
+        }
+
+        steps += 1;
+    }
+}
+
+/// Like [`run`], but calls `callback` with the progress of the run as often as `interval` says,
+/// so long runs can show how far they got.
+pub fn run_with_progress<A, F>(
+    machine: &mut A,
+    limits: &RunLimits,
+    interval: ProgressInterval,
+    mut callback: F,
+) -> RunOutcome
+where
+    A: Automaton + ?Sized,
+    F: FnMut(Progress),
+{
+    let mut throttle = ProgressThrottle::new(interval);
+
+    run_observed(machine, limits, |machine, steps| {
+        if throttle.is_due(steps) {
+            callback(Progress {
+                steps,
+                tape_cells: machine.tape_cells(),
+                state: machine.current_state_name(),
+            });
+        }
+    })
+}
+
+/// Runs an enumerator, collecting the distinct words it emits in the order they are first emitted,
+/// until `max_words` words have been collected, the machine halts, or one of the limits is reached.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip(machine), fields(machine = machine.name()), ret)
+)]
+pub fn enumerate(machine: &mut TuringMachine, max_words: usize, limits: &RunLimits) -> Enumeration {
+    let start = Instant::now();
+    let mut steps = 0;
+
+    let mut words = vec![];
+    let mut seen = HashSet::new();
+    let mut emitted_count = machine.emitted().len();
+
+    loop {
+        if words.len() >= max_words {
+            return Enumeration {
+                words,
+                outcome: None,
+            };
+        }
+
+        if let Some(outcome) = check_limits(limits, machine, start, steps) {
+            return Enumeration {
+                words,
+                outcome: Some(outcome),
+            };
+        }
+
+        machine.advance();
+
+        for word in &machine.emitted()[emitted_count..] {
+            if words.len() < max_words && seen.insert(word.clone()) {
+                words.push(word.clone());
+            }
+        }
+        emitted_count = machine.emitted().len();
+
+        if machine.is_halted() {
+            return Enumeration {
+                words,
+                outcome: Some(RunOutcome::Halted {
+                    accepted: machine.is_accepting(),
+                    steps,
+                }),
+            };
+        }
+
+        steps += 1;
+    }
+}
@@ -0,0 +1,90 @@
+use std::{collections::HashMap, hash::Hash};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{machine::Symbol, tape::Tape};
+
+/// A tape is its cells. It is deserialized into the default storage, see [`Tape::set_storage`].
+impl Serialize for Tape {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get_content().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tape {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Symbol>::deserialize(deserializer).map(Tape::new)
+    }
+}
+
+/// Keeps a map as a list of `(key, value)` pairs sorted by key, for maps whose keys are not
+/// strings (like transition sources), which formats such as JSON can't have as keys.
+pub(crate) mod pairs {
+    use super::*;
+
+    pub(crate) fn serialize<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+        S: Serializer,
+    {
+        let mut pairs = map.iter().collect::<Vec<_>>();
+        pairs.sort_by_key(|(key, _)| *key);
+        pairs.serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, K, V, D>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Ok(Vec::<(K, V)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+/// Keeps a map of maps as the list of `((outer key, inner key), value)` pairs that [`pairs`]
+/// makes of a map keyed by both, sorted by the keys.
+pub(crate) mod nested_pairs {
+    use super::*;
+
+    pub(crate) fn serialize<K, L, V, S>(
+        map: &HashMap<K, HashMap<L, V>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize + Ord,
+        L: Serialize + Ord,
+        V: Serialize,
+        S: Serializer,
+    {
+        let mut pairs = map
+            .iter()
+            .flat_map(|(key, inner)| {
+                inner
+                    .iter()
+                    .map(move |(inner_key, value)| ((key, inner_key), value))
+            })
+            .collect::<Vec<_>>();
+        pairs.sort_by_key(|(keys, _)| *keys);
+        pairs.serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, K, L, V, D>(
+        deserializer: D,
+    ) -> Result<HashMap<K, HashMap<L, V>>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        L: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let mut map = HashMap::<K, HashMap<L, V>>::new();
+        for ((key, inner_key), value) in Vec::<((K, L), V)>::deserialize(deserializer)? {
+            map.entry(key).or_default().insert(inner_key, value);
+        }
+        Ok(map)
+    }
+}
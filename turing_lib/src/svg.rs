@@ -0,0 +1,342 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    f64::consts::PI,
+    fmt::Write,
+};
+
+use crate::{
+    automaton::Automaton,
+    machine::{Symbol, Transition, TransitionSource, TuringMachine},
+};
+
+const ACCENT_COLOR: &str = "#6e9dd1"; // Same as the app
+const FONT: &str = "font-family=\"monospace\" font-size=\"14\"";
+
+const VISIBLE_CELLS: usize = 15; // Odd, so the head is in the middle
+const CELL_SIZE: f64 = 40.0;
+const TAPE_MARGIN: f64 = 20.0;
+
+const STATE_RADIUS: f64 = 24.0;
+const DIAGRAM_MARGIN: f64 = 120.0; // Room for the self loops and their labels
+const LINE_HEIGHT: f64 = 16.0;
+
+type Point = (f64, f64);
+
+/// Renders the current configuration of a machine (the state, and the tape around the head).
+pub fn configuration<A: Automaton + ?Sized>(machine: &A) -> String {
+    let width = VISIBLE_CELLS as f64 * CELL_SIZE + TAPE_MARGIN * 2.0;
+    let height = CELL_SIZE * 2.0 + TAPE_MARGIN * 3.0;
+    let tape_y = TAPE_MARGIN * 2.0;
+
+    let mut svg = header(width, height);
+    let _ = writeln!(
+        svg,
+        "<text x=\"{TAPE_MARGIN}\" y=\"{}\" {FONT}>State: {}</text>",
+        TAPE_MARGIN + 4.0,
+        escape(machine.current_state_name())
+    );
+
+    let head_idx = machine.head_idx() as isize;
+    let first_idx = head_idx - (VISIBLE_CELLS / 2) as isize;
+
+    for cell in 0..VISIBLE_CELLS {
+        let idx = first_idx + cell as isize;
+        let x = TAPE_MARGIN + cell as f64 * CELL_SIZE;
+        let symbol = match machine.tape()[idx] {
+            Symbol::Mark(c) => c,
+            _ => machine.blank_symbol(),
+        };
+        let fill = if idx == head_idx {
+            ACCENT_COLOR
+        } else {
+            "white"
+        };
+
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{x}\" y=\"{tape_y}\" width=\"{CELL_SIZE}\" height=\"{CELL_SIZE}\" fill=\"{fill}\" stroke=\"black\"/>"
+        );
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" {FONT}>{}</text>",
+            x + CELL_SIZE / 2.0,
+            tape_y + CELL_SIZE / 2.0 + 5.0,
+            escape(&symbol.to_string())
+        );
+    }
+
+    // The head, pointing up to its cell
+    let head_x = TAPE_MARGIN + (VISIBLE_CELLS / 2) as f64 * CELL_SIZE + CELL_SIZE / 2.0;
+    let head_y = tape_y + CELL_SIZE + 6.0;
+    let _ = writeln!(
+        svg,
+        "<polygon points=\"{head_x},{head_y} {},{} {},{}\" fill=\"{ACCENT_COLOR}\"/>",
+        head_x - CELL_SIZE / 3.0,
+        head_y + CELL_SIZE / 2.4,
+        head_x + CELL_SIZE / 3.0,
+        head_y + CELL_SIZE / 2.4,
+    );
+
+    svg += "</svg>\n";
+    svg
+}
+
+/// Renders the state diagram of a machine, with the states laid out on a circle (the initial
+/// one first). Every arrow is labeled with its transitions as `read→write,movement`, where
+/// `*` stands for `default`.
+pub fn diagram(machine: &TuringMachine) -> String {
+    let mut names = machine.states.keys().collect::<BTreeSet<_>>();
+    names.remove(&machine.initial_state);
+    let names = std::iter::once(&machine.initial_state)
+        .chain(names)
+        .collect::<Vec<_>>();
+
+    let radius = (names.len() as f64 * 3.0 * STATE_RADIUS / (2.0 * PI)).max(100.0);
+    let center = radius + DIAGRAM_MARGIN;
+    let size = center * 2.0;
+
+    let positions = names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let angle = PI + 2.0 * PI * idx as f64 / names.len() as f64;
+            let position = (center + radius * angle.cos(), center + radius * angle.sin());
+            (name.as_str(), position)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let edges = edges(machine, "→");
+
+    let mut svg = header(size, size);
+    svg += "<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+            markerWidth=\"8\" markerHeight=\"8\" orient=\"auto-start-reverse\">\
+            <path d=\"M 0 0 L 10 5 L 0 10 z\"/></marker></defs>\n";
+
+    for ((from, to), labels) in &edges {
+        let (Some(&from_pos), Some(&to_pos)) =
+            (positions.get(from.as_str()), positions.get(to.as_str()))
+        else {
+            continue;
+        };
+
+        if from == to {
+            self_loop(&mut svg, from_pos, (center, center), labels);
+        } else {
+            let bend = if edges.contains_key(&(to.clone(), from.clone())) {
+                30.0
+            } else {
+                0.0
+            };
+            arrow(&mut svg, from_pos, to_pos, bend, labels);
+        }
+    }
+
+    for (name, (x, y)) in &positions {
+        let fill = if **name == machine.initial_state {
+            ACCENT_COLOR
+        } else {
+            "white"
+        };
+
+        // The description of the state is shown as a tooltip
+        let description = machine.states[*name].description();
+        if let Some(description) = description {
+            let _ = writeln!(svg, "<g>\n<title>{}</title>", escape(description));
+        }
+
+        let _ = writeln!(
+            svg,
+            "<circle cx=\"{x}\" cy=\"{y}\" r=\"{STATE_RADIUS}\" fill=\"{fill}\" stroke=\"black\"/>"
+        );
+        if machine.final_states.contains(*name) {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{x}\" cy=\"{y}\" r=\"{}\" fill=\"none\" stroke=\"black\"/>",
+                STATE_RADIUS - 4.0
+            );
+        }
+        let _ = writeln!(
+            svg,
+            "<text x=\"{x}\" y=\"{}\" text-anchor=\"middle\" {FONT}>{}</text>",
+            y + 5.0,
+            escape(name)
+        );
+
+        if description.is_some() {
+            svg += "</g>\n";
+        }
+    }
+
+    svg += "</svg>\n";
+    svg
+}
+
+/// Labels of the arrows between every pair of states, with `separator` between the read and
+/// the written symbols of transitions.
+pub(crate) fn edges(
+    machine: &TuringMachine,
+    separator: &str,
+) -> BTreeMap<(String, String), Vec<String>> {
+    labeled_edges(machine, |source, transition| {
+        transition_label(machine, source, transition, separator)
+    })
+}
+
+/// Labels of the arrows between every pair of states, with transitions labeled by `label`.
+pub(crate) fn labeled_edges(
+    machine: &TuringMachine,
+    label: impl Fn(&TransitionSource, &Transition) -> String,
+) -> BTreeMap<(String, String), Vec<String>> {
+    let mut edges = BTreeMap::<(String, String), Vec<String>>::new();
+    let mut add = |from: &str, to: &str, label: String| {
+        edges
+            .entry((from.to_string(), to.to_string()))
+            .or_default()
+            .push(label);
+    };
+
+    for (name, state) in &machine.states {
+        let sources = state.transitions().keys().collect::<BTreeSet<_>>();
+        for source in sources {
+            let transition = &state.transitions()[source];
+            add(name, transition.new_state(), label(source, transition));
+        }
+
+        let sources = state.random_transitions().keys().collect::<BTreeSet<_>>();
+        for source in sources {
+            for (weight, transition) in &state.random_transitions()[source] {
+                let label = label(source, transition);
+                add(name, transition.new_state(), format!("{label} ({weight})"));
+            }
+        }
+
+        if let Some(query) = state.query() {
+            add(name, query.yes_state(), "yes".to_string());
+            add(name, query.no_state(), "no".to_string());
+        }
+
+        if let Some(next_state) = state.emit() {
+            add(name, next_state, "emit".to_string());
+        }
+    }
+
+    edges
+}
+
+fn transition_label(
+    machine: &TuringMachine,
+    source: &TransitionSource,
+    transition: &Transition,
+    separator: &str,
+) -> String {
+    let read = match source {
+        TransitionSource::Default => '*',
+        TransitionSource::Mark(c) => *c,
+        TransitionSource::Blank => machine.blank_symbol,
+    };
+    let write = match transition.new_symbol() {
+        Symbol::Default => '*',
+        Symbol::Mark(c) => c,
+        Symbol::Blank => machine.blank_symbol,
+    };
+    format!("{read}{separator}{write},{}", transition.movement_label())
+}
+
+/// A (possibly bent) arrow between two states, labeled at its middle.
+fn arrow(svg: &mut String, from: Point, to: Point, bend: f64, labels: &[String]) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = dx.hypot(dy);
+    let normal = (-dy / length, dx / length);
+
+    let control = (
+        (from.0 + to.0) / 2.0 + normal.0 * bend,
+        (from.1 + to.1) / 2.0 + normal.1 * bend,
+    );
+    let start = towards(from, control, STATE_RADIUS);
+    let end = towards(to, control, STATE_RADIUS);
+
+    let _ = writeln!(
+        svg,
+        "<path d=\"M {} {} Q {} {} {} {}\" fill=\"none\" stroke=\"black\" marker-end=\"url(#arrow)\"/>",
+        start.0, start.1, control.0, control.1, end.0, end.1
+    );
+
+    let middle = (
+        (start.0 + 2.0 * control.0 + end.0) / 4.0 + normal.0 * 8.0,
+        (start.1 + 2.0 * control.1 + end.1) / 4.0 + normal.1 * 8.0,
+    );
+    label(svg, middle, labels);
+}
+
+/// An arrow from a state to itself, on the side facing away from the center of the diagram.
+fn self_loop(svg: &mut String, position: Point, center: Point, labels: &[String]) {
+    let (dx, dy) = (position.0 - center.0, position.1 - center.1);
+    let length = dx.hypot(dy);
+    let angle = if length > 0.0 {
+        dy.atan2(dx)
+    } else {
+        -PI / 2.0
+    };
+
+    let at = |angle: f64, distance: f64| {
+        (
+            position.0 + distance * angle.cos(),
+            position.1 + distance * angle.sin(),
+        )
+    };
+
+    let start = at(angle - 0.5, STATE_RADIUS);
+    let end = at(angle + 0.5, STATE_RADIUS);
+    let control_start = at(angle - 0.6, STATE_RADIUS * 3.0);
+    let control_end = at(angle + 0.6, STATE_RADIUS * 3.0);
+
+    let _ = writeln!(
+        svg,
+        "<path d=\"M {} {} C {} {} {} {} {} {}\" fill=\"none\" stroke=\"black\" marker-end=\"url(#arrow)\"/>",
+        start.0, start.1, control_start.0, control_start.1, control_end.0, control_end.1, end.0, end.1
+    );
+
+    let mut position = at(angle, STATE_RADIUS * 3.0);
+    if angle.sin() < 0.0 {
+        // Above the state, so the lines grow upwards
+        position.1 -= LINE_HEIGHT * (labels.len() - 1) as f64;
+    }
+    label(svg, position, labels);
+}
+
+fn label(svg: &mut String, (x, y): Point, lines: &[String]) {
+    let _ = write!(
+        svg,
+        "<text x=\"{x}\" y=\"{y}\" text-anchor=\"middle\" {FONT}>"
+    );
+    for (idx, line) in lines.iter().enumerate() {
+        let dy = if idx == 0 { 0.0 } else { LINE_HEIGHT };
+        let _ = write!(svg, "<tspan x=\"{x}\" dy=\"{dy}\">{}</tspan>", escape(line));
+    }
+    svg.push_str("</text>\n");
+}
+
+/// The point at `distance` from `from` in the direction of `to`.
+fn towards(from: Point, to: Point, distance: f64) -> Point {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = dx.hypot(dy);
+    (
+        from.0 + dx / length * distance,
+        from.1 + dy / length * distance,
+    )
+}
+
+fn header(width: f64, height: f64) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n"
+    )
+}
+
+/// Escapes text for XML (and HTML).
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,11 +1,233 @@
-use std::fmt::Display;
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    ops::Index,
+    str::FromStr,
+    sync::OnceLock,
+};
 
 use super::machine::Symbol;
 
-#[derive(Debug, Clone)]
-pub struct Tape(pub(crate) Vec<Symbol>);
+pub const DEFAULT_BLANK_SYMBOL: char = '_';
 
-#[derive(Debug)]
+/// Where the cells of a [`Tape`] are kept. `Vec<Symbol>` keeps every cell and is the default,
+/// [`SparseStorage`] only keeps the cells that aren't blank, and other backends (memory-mapped
+/// tapes, tapes that count their reads and writes...) can be plugged into a machine with
+/// [`crate::machine::TuringMachine::set_tape_storage`].
+pub trait TapeStorage: fmt::Debug + Send + Sync {
+    /// The symbol of the cell at `index`, which is less than [`TapeStorage::len`].
+    fn read(&self, index: usize) -> Symbol;
+
+    /// Writes `symbol` on the cell at `index`, which is less than [`TapeStorage::len`].
+    fn write(&mut self, index: usize, symbol: Symbol);
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds a blank cell after the last one.
+    fn extend_right(&mut self);
+
+    /// Adds a blank cell before the first one, which moves every other cell one index right.
+    fn extend_left(&mut self);
+
+    /// Replaces every cell with `cells`, e.g. when the machine is reset with a new input.
+    fn set_cells(&mut self, cells: &[Symbol]);
+
+    /// The indices of the first and last cells that aren't blank, if any.
+    fn bounds(&self) -> Option<(usize, usize)> {
+        let mut marked = (0..self.len()).filter(|&index| self.read(index) != Symbol::Blank);
+        let first = marked.next()?;
+        Some((first, marked.last().unwrap_or(first)))
+    }
+
+    /// Every cell in order, if they are kept next to each other, so [`Tape::get_content`]
+    /// doesn't have to copy them.
+    fn as_slice(&self) -> Option<&[Symbol]> {
+        None
+    }
+
+    /// The cell at `index`, which is less than [`TapeStorage::len`], if the storage keeps it as
+    /// a [`Symbol`] that can be borrowed, so indexing the tape doesn't have to copy the cells.
+    fn get(&self, index: usize) -> Option<&Symbol> {
+        self.as_slice()?.get(index)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn TapeStorage>;
+}
+
+impl TapeStorage for Vec<Symbol> {
+    fn read(&self, index: usize) -> Symbol {
+        *self.get(index).unwrap()
+    }
+
+    fn write(&mut self, index: usize, symbol: Symbol) {
+        self[index] = symbol
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn extend_right(&mut self) {
+        self.push(Symbol::Blank);
+    }
+
+    fn extend_left(&mut self) {
+        self.insert(0, Symbol::Blank);
+    }
+
+    fn set_cells(&mut self, cells: &[Symbol]) {
+        self.clear();
+        self.extend_from_slice(cells);
+    }
+
+    fn as_slice(&self) -> Option<&[Symbol]> {
+        Some(self)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn TapeStorage> {
+        Box::new(self.clone())
+    }
+}
+
+/// Keeps only the cells that aren't blank, for machines that spread a few symbols over a huge
+/// tape. Growing the tape to the left doesn't move the other cells either.
+#[derive(Debug, Clone, Default)]
+pub struct SparseStorage {
+    cells: BTreeMap<isize, Symbol>, // By their index when the tape was set, see `key`
+    added_left: usize,
+    len: usize,
+}
+
+impl SparseStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key(&self, index: usize) -> isize {
+        index as isize - self.added_left as isize
+    }
+
+    fn index(&self, key: isize) -> usize {
+        (key + self.added_left as isize) as usize
+    }
+}
+
+impl TapeStorage for SparseStorage {
+    fn read(&self, index: usize) -> Symbol {
+        self.cells
+            .get(&self.key(index))
+            .copied()
+            .unwrap_or(Symbol::Blank)
+    }
+
+    fn write(&mut self, index: usize, symbol: Symbol) {
+        let key = self.key(index);
+        if symbol == Symbol::Blank {
+            self.cells.remove(&key);
+        } else {
+            self.cells.insert(key, symbol);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn extend_right(&mut self) {
+        self.len += 1;
+    }
+
+    fn extend_left(&mut self) {
+        self.added_left += 1;
+        self.len += 1;
+    }
+
+    fn set_cells(&mut self, cells: &[Symbol]) {
+        self.cells = cells
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| **symbol != Symbol::Blank)
+            .map(|(index, symbol)| (index as isize, *symbol))
+            .collect();
+        self.added_left = 0;
+        self.len = cells.len();
+    }
+
+    fn get(&self, index: usize) -> Option<&Symbol> {
+        self.cells.get(&self.key(index))
+    }
+
+    fn bounds(&self) -> Option<(usize, usize)> {
+        let (first, _) = self.cells.first_key_value()?;
+        let (last, _) = self.cells.last_key_value()?;
+        Some((self.index(*first), self.index(*last)))
+    }
+
+    fn boxed_clone(&self) -> Box<dyn TapeStorage> {
+        Box::new(self.clone())
+    }
+}
+
+/// The tape of a machine, whose cells are kept in a [`TapeStorage`] (a `Vec<Symbol>` unless
+/// another one is given).
+pub struct Tape {
+    storage: Box<dyn TapeStorage>,
+    content: OnceLock<Vec<Symbol>>, // The cells of storages without a slice, see `get_content`
+}
+
+impl Clone for Tape {
+    fn clone(&self) -> Self {
+        Self {
+            storage: self.storage.boxed_clone(),
+            content: OnceLock::new(),
+        }
+    }
+}
+
+impl fmt::Debug for Tape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Tape(")?;
+        f.debug_list().entries(self.cells()).finish()?;
+        f.write_str(")")
+    }
+}
+
+/// Tapes are equal when they have the same cells, whatever their storage.
+impl PartialEq for Tape {
+    fn eq(&self, other: &Self) -> bool {
+        let bounds = self.storage.bounds();
+
+        self.len() == other.len()
+            && bounds == other.storage.bounds()
+            && bounds.is_none_or(|(first, last)| {
+                (first..=last).all(|index| self.read(index) == other.read(index))
+            })
+    }
+}
+
+impl Eq for Tape {}
+
+impl Hash for Tape {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let bounds = self.storage.bounds();
+
+        self.len().hash(state);
+        bounds.hash(state);
+        if let Some((first, last)) = bounds {
+            for index in first..=last {
+                self.read(index).hash(state);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TapeSide {
     Left,
     Right,
@@ -13,61 +235,134 @@ pub enum TapeSide {
 
 impl Display for Tape {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            self.0
-                .iter()
-                .map(|symbol| match symbol {
-                    Symbol::Mark(symbol) => format!("{}", symbol),
-                    Symbol::Blank => "△".to_string(),
-                    Symbol::Default => "".to_string(),
-                })
-                .collect::<String>()
-        )
+        for symbol in self.cells() {
+            match symbol {
+                Symbol::Mark(symbol) => write!(f, "{symbol}")?,
+                Symbol::Blank => write!(f, "△")?,
+                Symbol::Default => {}
+            }
+        }
+
+        Ok(())
     }
 }
 
 impl Tape {
     pub fn parse(data: &str, blank_symbol: char) -> Tape {
-        Tape(
+        Tape::new(
             data.chars()
-                .map(|c| {
-                    if c == blank_symbol {
-                        Symbol::Blank
-                    } else {
-                        Symbol::Mark(c)
-                    }
-                })
+                .map(|c| Symbol::from_char(c, blank_symbol))
                 .collect(),
         )
     }
 
     pub fn new(data: Vec<Symbol>) -> Self {
-        Self(data)
+        Self::new_with_storage(data)
+    }
+
+    /// A tape with the cells the storage already has.
+    pub fn new_with_storage(storage: impl TapeStorage + 'static) -> Self {
+        Self {
+            storage: Box::new(storage),
+            content: OnceLock::new(),
+        }
+    }
+
+    pub fn storage(&self) -> &dyn TapeStorage {
+        self.storage.as_ref()
+    }
+
+    /// Moves the cells of the tape into `storage`, and keeps them there from now on.
+    pub fn set_storage(&mut self, mut storage: impl TapeStorage + 'static) {
+        storage.set_cells(self.get_content());
+        self.storage = Box::new(storage);
     }
 
     pub fn read(&self, index: usize) -> Symbol {
-        *self.0.get(index).unwrap()
+        self.storage.read(index)
+    }
+
+    /// Every cell in order, read one by one from the storage, unlike [`Tape::get_content`].
+    pub fn cells(&self) -> impl Iterator<Item = Symbol> + '_ {
+        (0..self.len()).map(|index| self.storage.read(index))
     }
 
     pub fn write(&mut self, index: usize, symbol: Symbol) {
-        self.0[index] = symbol
+        self.content.take();
+        self.storage.write(index, symbol)
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.storage.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.storage.is_empty()
     }
 
     pub fn extend_right(&mut self) {
-        self.0.push(Symbol::Blank);
+        self.content.take();
+        self.storage.extend_right();
     }
 
     pub fn extend_left(&mut self) {
-        self.0.insert(0, Symbol::Blank);
+        self.content.take();
+        self.storage.extend_left();
+    }
+
+    /// Replaces every cell with `cells`, keeping the storage.
+    pub fn set_cells(&mut self, cells: &[Symbol]) {
+        self.content.take();
+        self.storage.set_cells(cells);
     }
 
+    /// Every cell in order. Storages that don't keep them next to each other copy them the
+    /// first time they are asked for after a change.
     pub fn get_content(&self) -> &[Symbol] {
-        &self.0
+        match self.storage.as_slice() {
+            Some(cells) => cells,
+            None => self.content.get_or_init(|| {
+                (0..self.storage.len())
+                    .map(|index| self.storage.read(index))
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Parses a tape using `_` as the blank symbol. Use [`Tape::parse`] for any other blank symbol.
+impl FromStr for Tape {
+    type Err = Infallible;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        Ok(Tape::parse(data, DEFAULT_BLANK_SYMBOL))
+    }
+}
+
+impl From<Vec<Symbol>> for Tape {
+    fn from(data: Vec<Symbol>) -> Self {
+        Tape::new(data)
+    }
+}
+
+/// Reads any position of the tape, as if it was infinite: the cells outside of it
+/// (including the negative positions) are blank.
+impl Index<isize> for Tape {
+    type Output = Symbol;
+
+    fn index(&self, index: isize) -> &Symbol {
+        let Some(index) = usize::try_from(index)
+            .ok()
+            .filter(|&index| index < self.len())
+        else {
+            return &Symbol::Blank;
+        };
+
+        match self.storage.get(index) {
+            Some(symbol) => symbol,
+            None if self.storage.read(index) == Symbol::Blank => &Symbol::Blank,
+            // Storages that can't lend their cells
+            None => &self.get_content()[index],
+        }
     }
 }
@@ -0,0 +1,139 @@
+//! Exports the state diagram of Turing Machines as TikZ pictures, to include them in LaTeX
+//! documents such as lecture notes.
+
+use std::fmt::Write;
+
+use crate::{
+    diagram::StateDiagram,
+    machine::{Symbol, Transition, TransitionSource, TuringMachine},
+    report::{latex_escape, latex_state},
+    svg,
+};
+
+/// How the states are named in the picture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TikzStateNames {
+    /// Their names in math mode, with the number of names like `q1` as a subscript (`q_{1}`).
+    #[default]
+    Math,
+    /// Their names as they are written in the definition, in a typewriter font.
+    Verbatim,
+    /// `q_0`, `q_1`... the initial state first, then by name, as usual in textbooks.
+    Numbered,
+}
+
+/// How [`TuringMachine::to_tikz`] draws the machine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TikzOptions {
+    /// The usual distance between states, in centimeters.
+    pub node_distance: f64,
+    pub state_names: TikzStateNames,
+}
+
+impl Default for TikzOptions {
+    fn default() -> Self {
+        Self {
+            node_distance: 2.5,
+            state_names: TikzStateNames::default(),
+        }
+    }
+}
+
+impl TuringMachine {
+    /// The state diagram of the machine as a `tikzpicture` that uses the `automata` TikZ
+    /// library (`\usetikzlibrary{automata}` in the preamble). The states are laid out like in
+    /// [`StateDiagram`], the initial one has an arrow into it and the final ones are accepting,
+    /// and every arrow is labeled with its transitions as `read/write,movement`, where blanks
+    /// are `\sqcup` and `*` stands for `default`.
+    pub fn to_tikz(&self, options: &TikzOptions) -> String {
+        let diagram = StateDiagram::new(self);
+
+        // The unit square of the layout holds about as many states per side as a square grid
+        let scale = options.node_distance * (diagram.states().len() as f64).sqrt().ceil();
+
+        let mut tikz = String::from("\\begin{tikzpicture}[>=stealth, shorten >=1pt, auto]\n");
+
+        for (idx, (name, (x, y))) in diagram.states().iter().zip(diagram.positions()).enumerate() {
+            let mut style = String::from("state");
+            if *name == self.initial_state {
+                style += ", initial";
+            }
+            if diagram.is_final(idx) {
+                style += ", accepting";
+            }
+
+            let label = match options.state_names {
+                TikzStateNames::Math => format!("${}$", latex_state(name)),
+                TikzStateNames::Verbatim => format!("\\texttt{{{}}}", latex_escape(name)),
+                TikzStateNames::Numbered => format!("$q_{{{idx}}}$"),
+            };
+
+            // The y axis of the layout goes down, and the one of TikZ goes up
+            let _ = writeln!(
+                tikz,
+                "\t\\node[{style}] (s{idx}) at ({:.2}, {:.2}) {{{label}}};",
+                x * scale,
+                -y * scale
+            );
+        }
+
+        let edges = svg::labeled_edges(self, |source, transition| {
+            self.tikz_transition_label(source, transition)
+        });
+        if !edges.is_empty() {
+            tikz += "\t\\path[->]\n";
+        }
+
+        for ((from, to), labels) in &edges {
+            let (Some(from_idx), Some(to_idx)) = (diagram.state_idx(from), diagram.state_idx(to))
+            else {
+                continue;
+            };
+
+            let style = if from == to {
+                "[loop above]"
+            } else if edges.contains_key(&(to.clone(), from.clone())) {
+                "[bend left]"
+            } else {
+                ""
+            };
+
+            let _ = writeln!(
+                tikz,
+                "\t\t(s{from_idx}) edge{style} node[align=center] {{{}}} (s{to_idx})",
+                labels.join("\\\\")
+            );
+        }
+
+        if !edges.is_empty() {
+            tikz += "\t;\n";
+        }
+
+        tikz += "\\end{tikzpicture}\n";
+        tikz
+    }
+
+    fn tikz_transition_label(&self, source: &TransitionSource, transition: &Transition) -> String {
+        let symbol = |symbol: Option<char>| match symbol {
+            None => "*".to_string(),
+            Some(c) if c == self.blank_symbol => "\\sqcup".to_string(),
+            Some(c) => format!("\\mathtt{{{}}}", latex_escape(&c.to_string())),
+        };
+
+        let read = symbol(match source {
+            TransitionSource::Default => None,
+            TransitionSource::Mark(c) => Some(*c),
+            TransitionSource::Blank => Some(self.blank_symbol),
+        });
+        let write = symbol(match transition.new_symbol() {
+            Symbol::Default => None,
+            Symbol::Mark(c) => Some(c),
+            Symbol::Blank => Some(self.blank_symbol),
+        });
+
+        format!(
+            "${read}/{write},\\mathrm{{{}}}$",
+            transition.movement_label()
+        )
+    }
+}
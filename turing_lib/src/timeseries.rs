@@ -0,0 +1,138 @@
+use std::{fmt::Write, time::Instant};
+
+use crate::{
+    automaton::Automaton,
+    runner::{self, RunLimits, RunOutcome},
+    tape::TapeSide,
+};
+
+/// The configuration of a run at one of its steps, see [`TimeSeries`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub step: usize,
+    /// The position of the head, counted from the cell the tape started at (negative on its left).
+    pub head_position: isize,
+    pub tape_len: usize,
+    /// The steps that wrote a different symbol up to this one.
+    pub writes: usize,
+}
+
+/// The head position, tape length and writes of a run over time, taken every few steps, to plot
+/// or analyse how a machine behaves, see [`record`].
+#[derive(Debug, Clone)]
+pub struct TimeSeries {
+    outcome: RunOutcome,
+    samples: Vec<Sample>,
+}
+
+impl TimeSeries {
+    pub fn outcome(&self) -> RunOutcome {
+        self.outcome
+    }
+
+    /// The samples in the order of their steps. The first one is taken before the first step,
+    /// and the last one at the end of the run.
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The series with at most `max_samples` samples, evenly spread over the run and keeping the
+    /// first and last ones, to plot long runs.
+    pub fn downsample(&self, max_samples: usize) -> TimeSeries {
+        let len = self.samples.len();
+        let samples = match max_samples {
+            _ if len <= max_samples => self.samples.clone(),
+            0 => vec![],
+            1 => vec![self.samples[len - 1]],
+            _ => (0..max_samples)
+                .map(|i| self.samples[i * (len - 1) / (max_samples - 1)])
+                .collect(),
+        };
+
+        TimeSeries {
+            outcome: self.outcome,
+            samples,
+        }
+    }
+
+    /// The series as CSV, one sample per row.
+    pub fn csv(&self) -> String {
+        let mut csv = String::from("step,head_position,tape_len,writes\n");
+
+        for sample in &self.samples {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{}",
+                sample.step, sample.head_position, sample.tape_len, sample.writes
+            );
+        }
+
+        csv
+    }
+}
+
+/// Runs the machine like [`runner::run`], taking a [`Sample`] every `every_steps` steps (at
+/// least every step) and at the end of the run.
+pub fn record<A: Automaton + ?Sized>(
+    machine: &mut A,
+    limits: &RunLimits,
+    every_steps: usize,
+) -> TimeSeries {
+    let every_steps = every_steps.max(1);
+    let start = Instant::now();
+    let mut steps: usize = 0;
+    let mut writes = 0;
+    let mut origin = 0; // Index of the cell the tape started at
+    let mut samples = vec![];
+
+    let sample = |machine: &A, steps, writes, origin| Sample {
+        step: steps,
+        head_position: machine.head_idx() as isize - origin,
+        tape_len: machine.tape().len(),
+        writes,
+    };
+
+    let outcome = loop {
+        if steps.is_multiple_of(every_steps) {
+            samples.push(sample(machine, steps, writes, origin));
+        }
+
+        if let Some(outcome) = runner::check_limits(limits, machine, start, steps) {
+            break outcome;
+        }
+
+        let tape_len = machine.tape().len();
+        let tick_result = machine.tick();
+
+        if tick_result.extended_tape_on_side == Some(TapeSide::Left) {
+            origin += (machine.tape().len() - tape_len) as isize;
+        }
+        if tick_result.written_different_symbol {
+            writes += 1;
+        }
+
+        if machine.is_halted() {
+            break RunOutcome::Halted {
+                accepted: machine.is_accepting(),
+                steps,
+            };
+        }
+
+        steps += 1;
+    };
+
+    let last = sample(machine, steps, writes, origin);
+    if samples.last() != Some(&last) {
+        samples.push(last);
+    }
+
+    TimeSeries { outcome, samples }
+}
@@ -0,0 +1,138 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::machine::{
+    OutputConvention, Pacing, Symbol, Transition, TransitionSource, TuringMachine,
+};
+
+impl TuringMachine {
+    /// The definition of the machine as the text of a `.tng` file, which parses back into an
+    /// equal machine, e.g. to save one that was built, imported or transformed. The initial
+    /// state goes first and the rest by name, and the transitions of every state are sorted by
+    /// the symbol they read. The seed is the current state of the random number generator, so
+    /// the saved machine makes the same random choices from where this one is.
+    ///
+    /// State names and symbols are written as they are, so machines built in code must use
+    /// ones that `.tng` files can have (e.g. no whitespace nor commas). Doc comments and
+    /// oracles are not part of the machine, so they are not written.
+    pub fn to_tng_string(&self) -> String {
+        let mut tng = format!(
+            "config {{\n    name: \"{}\"\n    blank_symbol: '{}'\n    head_start: {}\n",
+            self.name, self.blank_symbol, self.head_start
+        );
+
+        if self.bounded {
+            tng += "    machine_type: lba\n";
+        } else if self.nondeterministic {
+            tng += "    machine_type: ndtm\n";
+        }
+        if self.output_convention == OutputConvention::WholeTape {
+            tng += "    output: tape\n";
+        }
+        if self.rng.state() != 0 {
+            let _ = writeln!(tng, "    seed: {}", self.rng.state());
+        }
+        if let Some(max_tape_cells) = self.max_tape_cells {
+            let _ = writeln!(tng, "    max_tape_cells: {max_tape_cells}");
+        }
+        tng += "}\n\nstates {\n";
+
+        let mut names = self.states.keys().collect::<BTreeSet<_>>();
+        names.remove(&self.initial_state);
+        let names = std::iter::once(&self.initial_state).chain(names);
+
+        for (idx, name) in names.enumerate() {
+            let Some(state) = self.states.get(name) else {
+                continue;
+            };
+
+            if idx > 0 {
+                tng += "\n";
+            }
+
+            let mut header = format!("state {name}");
+            if let Some(description) = state.description() {
+                let _ = write!(header, " \"{description}\"");
+            }
+            match (
+                *name == self.initial_state,
+                self.final_states.contains(name),
+            ) {
+                (true, true) => header += " is initial and final",
+                (true, false) => header += " is initial",
+                (false, true) => header += " is final",
+                (false, false) => {}
+            }
+
+            let mut lines = vec![];
+            if let Some(query) = state.query() {
+                lines.push(format!("query,{},{}", query.yes_state(), query.no_state()));
+            } else if let Some(next_state) = state.emit() {
+                lines.push(format!("emit,{next_state}"));
+            } else {
+                let mut transitions = state
+                    .transitions()
+                    .iter()
+                    .map(|(source, transition)| {
+                        (source, self.tng_transition(source, transition, None))
+                    })
+                    .collect::<Vec<_>>();
+                for (source, choices) in state.random_transitions() {
+                    for (weight, transition) in choices {
+                        let line = self.tng_transition(source, transition, Some(*weight));
+                        transitions.push((source, line));
+                    }
+                }
+
+                // Choices keep their order, which is the one they are made in
+                transitions.sort_by_key(|(source, _)| **source);
+                lines.extend(transitions.into_iter().map(|(_, line)| line));
+            }
+
+            if lines.is_empty() {
+                let _ = writeln!(tng, "    {header} {{}}");
+            } else {
+                let _ = writeln!(tng, "    {header} {{");
+                for line in lines {
+                    let _ = writeln!(tng, "        {line}");
+                }
+                tng += "    }\n";
+            }
+        }
+
+        tng += "}\n";
+        tng
+    }
+
+    /// A transition line, with the weight of random transitions.
+    fn tng_transition(
+        &self,
+        source: &TransitionSource,
+        transition: &Transition,
+        weight: Option<u32>,
+    ) -> String {
+        let read = match source {
+            TransitionSource::Default => "default".to_string(),
+            TransitionSource::Mark(c) => c.to_string(),
+            TransitionSource::Blank => self.blank_symbol.to_string(),
+        };
+        let write = match transition.new_symbol() {
+            Symbol::Default => "default".to_string(),
+            Symbol::Mark(c) => c.to_string(),
+            Symbol::Blank => self.blank_symbol.to_string(),
+        };
+        let weight = weight
+            .map(|weight| format!(",{weight}"))
+            .unwrap_or_default();
+        let pacing = match transition.pacing() {
+            Pacing::Normal => "",
+            Pacing::Slow => " @slow",
+            Pacing::Fast => " @fast",
+        };
+
+        format!(
+            "{read},{write},{},{}{weight}{pacing}",
+            transition.movement_label(),
+            transition.new_state()
+        )
+    }
+}
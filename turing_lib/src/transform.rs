@@ -0,0 +1,1430 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+    analysis::{self, DeadCode},
+    heatmap::Heatmap,
+    machine::{
+        HeadMovement, OutputConvention, Pacing, State, Symbol, Transition, TransitionSource,
+        TuringMachine, RIGHT_END_MARKER,
+    },
+    multitape::{MultiTapeMachine, MultiTapeState, MultiTapeTransition},
+    oracle::OracleQuery,
+    parser,
+    random::Rng,
+    tape::Tape,
+};
+
+/// Returns a name based on `base` that is not used by any state of the machine nor in `taken`.
+fn unused_state_name(
+    machine: &TuringMachine,
+    taken: &HashMap<String, State>,
+    base: &str,
+) -> String {
+    let mut name = base.to_string();
+    while machine.states.contains_key(&name) || taken.contains_key(&name) {
+        name.push('\'');
+    }
+    name
+}
+
+/// Builds an equivalent machine that only moves its head left or right.
+///
+/// Every transition that stays is replaced by one that moves right into a new bounce
+/// state, which moves back left without changing the tape and enters the original target state.
+/// On linear bounded automata, the ones reading the right end marker move left and back right
+/// instead, so they don't step past it.
+pub fn remove_stay_moves(machine: &TuringMachine) -> TuringMachine {
+    let mut bounce_states: HashMap<String, State> = HashMap::new();
+    let mut bounce_names: HashMap<(String, HeadMovement), String> = HashMap::new();
+    let right_end = TransitionSource::Mark(RIGHT_END_MARKER);
+
+    let mut convert = |source: TransitionSource, transition: &Transition| {
+        if let HeadMovement::Stay = transition.head_movement() {
+            let (away, back, suffix) = if machine.bounded && source == right_end {
+                (HeadMovement::Left, HeadMovement::Right, "forth")
+            } else {
+                (HeadMovement::Right, HeadMovement::Left, "back")
+            };
+
+            let key = (transition.new_state().to_string(), back);
+            let bounce_name = match bounce_names.get(&key) {
+                Some(bounce_name) => bounce_name.clone(),
+                None => {
+                    let bounce_name = unused_state_name(
+                        machine,
+                        &bounce_states,
+                        &format!("{}_{suffix}", transition.new_state()),
+                    );
+                    bounce_states.insert(
+                        bounce_name.clone(),
+                        State::new(
+                            bounce_name.clone(),
+                            HashMap::from([(
+                                TransitionSource::Default,
+                                Transition::new(
+                                    back,
+                                    Symbol::Default,
+                                    transition.new_state().to_string(),
+                                ),
+                            )]),
+                        ),
+                    );
+                    bounce_names.insert(key, bounce_name.clone());
+                    bounce_name
+                }
+            };
+
+            Transition::new(away, transition.new_symbol(), bounce_name)
+                .with_pacing(transition.pacing())
+        } else {
+            Transition::new_jump(
+                transition.head_movement(),
+                transition.distance(),
+                transition.new_symbol(),
+                transition.new_state().to_string(),
+            )
+            .with_pacing(transition.pacing())
+        }
+    };
+
+    let mut states = HashMap::new();
+
+    for (name, state) in &machine.states {
+        if let Some(query) = state.query() {
+            states.insert(name.clone(), State::new_query(name.clone(), query.clone()));
+            continue;
+        }
+
+        if let Some(next_state) = state.emit() {
+            states.insert(
+                name.clone(),
+                State::new_emit(name.clone(), next_state.to_string()),
+            );
+            continue;
+        }
+
+        let mut transitions = state.transitions().clone();
+        let mut random_transitions = state.random_transitions().clone();
+
+        // The default transitions that stay bounce the other way on the right end marker
+        if machine.bounded
+            && !transitions.contains_key(&right_end)
+            && !random_transitions.contains_key(&right_end)
+        {
+            if let Some(transition) = transitions.get(&TransitionSource::Default) {
+                if transition.head_movement() == HeadMovement::Stay {
+                    transitions.insert(right_end, transition.clone());
+                }
+            } else if let Some(choices) = random_transitions.get(&TransitionSource::Default) {
+                if choices
+                    .iter()
+                    .any(|(_, transition)| transition.head_movement() == HeadMovement::Stay)
+                {
+                    random_transitions.insert(right_end, choices.clone());
+                }
+            }
+        }
+
+        let transitions = transitions
+            .iter()
+            .map(|(source, transition)| (*source, convert(*source, transition)))
+            .collect();
+
+        let random_transitions = random_transitions
+            .iter()
+            .map(|(source, choices)| {
+                let choices = choices
+                    .iter()
+                    .map(|(weight, transition)| (*weight, convert(*source, transition)))
+                    .collect();
+                (*source, choices)
+            })
+            .collect();
+
+        states.insert(
+            name.clone(),
+            State::new_random(name.clone(), transitions, random_transitions),
+        );
+    }
+
+    states.extend(bounce_states);
+
+    TuringMachine {
+        name: machine.name.clone(),
+        blank_symbol: machine.blank_symbol,
+
+        states,
+        final_states: machine.final_states.clone(),
+
+        head_start: machine.head_start,
+        effective_head_start: machine.effective_head_start,
+        initial_state: machine.initial_state.clone(),
+
+        head_idx: machine.head_idx,
+        current_state: machine.current_state.clone(),
+        tape: machine.tape.clone(),
+
+        halted: machine.halted,
+
+        bounded: machine.bounded,
+        nondeterministic: machine.nondeterministic,
+        out_of_bounds: machine.out_of_bounds,
+
+        max_tape_cells: machine.max_tape_cells,
+
+        oracle: machine.oracle.clone(),
+        rng: machine.rng.clone(),
+
+        emitted: machine.emitted.clone(),
+
+        output_convention: machine.output_convention,
+
+        heatmap: machine.heatmap.clone(),
+
+        history: None,
+    }
+}
+
+/// What a transition does, with its target state replaced by the block it belongs to.
+type MoveSignature = (Symbol, HeadMovement, usize, Pacing, Option<usize>);
+
+/// How a state behaves, with its target states replaced by the blocks they belong to, so two
+/// states with the same signature are equivalent if their targets are.
+#[derive(PartialEq, Eq, Hash)]
+enum StateSignature {
+    Transitions(
+        Vec<(TransitionSource, MoveSignature)>,
+        Vec<(TransitionSource, Vec<(u32, MoveSignature)>)>,
+    ),
+    Query(Option<usize>, Option<usize>),
+    Emit(Option<usize>),
+}
+
+/// Builds an equivalent machine where the states that behave the same are merged into one,
+/// and returns it along with the name of the state every merged state was merged into.
+///
+/// Two states behave the same if they are both final or not, and have the same transitions
+/// (reading the same symbols, writing the same symbols and moving the same way) into states
+/// that behave the same, recursively. Their transitions are compared as written, so e.g.
+/// writing `default` and writing back the symbol read are different. States are merged into
+/// the initial state if it is one of them, and otherwise into the first one by name, and keep
+/// its description.
+pub fn minimize(machine: &TuringMachine) -> (TuringMachine, BTreeMap<String, String>) {
+    let mut names = machine.states.keys().collect::<Vec<_>>();
+    names.sort();
+
+    // Refine the blocks of states that may behave the same until they don't split anymore
+    let mut blocks = names
+        .iter()
+        .map(|name| (name.as_str(), 0))
+        .collect::<HashMap<_, _>>();
+    let mut block_count = 1;
+
+    loop {
+        let block_of = |name: &str| blocks.get(name).copied();
+        let move_signature = |transition: &Transition| {
+            (
+                transition.new_symbol(),
+                transition.head_movement(),
+                transition.distance(),
+                transition.pacing(),
+                block_of(transition.new_state()),
+            )
+        };
+
+        let mut signatures = HashMap::new();
+        let mut new_blocks = HashMap::new();
+
+        for name in &names {
+            let state = &machine.states[name.as_str()];
+
+            let behavior = if let Some(query) = state.query() {
+                StateSignature::Query(block_of(query.yes_state()), block_of(query.no_state()))
+            } else if let Some(next_state) = state.emit() {
+                StateSignature::Emit(block_of(next_state))
+            } else {
+                let mut transitions = state
+                    .transitions()
+                    .iter()
+                    .map(|(source, transition)| (*source, move_signature(transition)))
+                    .collect::<Vec<_>>();
+                transitions.sort_by_key(|(source, _)| *source);
+
+                let mut random_transitions = state
+                    .random_transitions()
+                    .iter()
+                    .map(|(source, choices)| {
+                        let choices = choices
+                            .iter()
+                            .map(|(weight, transition)| (*weight, move_signature(transition)))
+                            .collect();
+                        (*source, choices)
+                    })
+                    .collect::<Vec<_>>();
+                random_transitions.sort_by_key(|(source, _)| *source);
+
+                StateSignature::Transitions(transitions, random_transitions)
+            };
+
+            let signature = (machine.final_states.contains(name.as_str()), behavior);
+            let block_count = signatures.len();
+            let block = *signatures.entry(signature).or_insert(block_count);
+            new_blocks.insert(name.as_str(), block);
+        }
+
+        // Blocks only ever split, so the same number of blocks means that none did
+        let stable = signatures.len() == block_count;
+        block_count = signatures.len();
+        blocks = new_blocks;
+
+        if stable {
+            break;
+        }
+    }
+
+    let mut representatives = HashMap::new();
+    if let Some(block) = blocks.get(machine.initial_state.as_str()) {
+        representatives.insert(*block, machine.initial_state.clone());
+    }
+    for name in &names {
+        representatives
+            .entry(blocks[name.as_str()])
+            .or_insert_with(|| name.to_string());
+    }
+
+    let merged = names
+        .iter()
+        .filter_map(|name| {
+            let representative = &representatives[&blocks[name.as_str()]];
+            (*name != representative).then(|| (name.to_string(), representative.clone()))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let rename = |name: &str| merged.get(name).cloned().unwrap_or(name.to_string());
+    let convert = |transition: &Transition| {
+        Transition::new_jump(
+            transition.head_movement(),
+            transition.distance(),
+            transition.new_symbol(),
+            rename(transition.new_state()),
+        )
+        .with_pacing(transition.pacing())
+    };
+
+    let mut states = HashMap::new();
+
+    for name in representatives.values() {
+        let state = &machine.states[name];
+
+        let mut new_state = if let Some(query) = state.query() {
+            State::new_query(
+                name.clone(),
+                OracleQuery::new(rename(query.yes_state()), rename(query.no_state())),
+            )
+        } else if let Some(next_state) = state.emit() {
+            State::new_emit(name.clone(), rename(next_state))
+        } else {
+            let transitions = state
+                .transitions()
+                .iter()
+                .map(|(source, transition)| (*source, convert(transition)))
+                .collect();
+
+            let random_transitions = state
+                .random_transitions()
+                .iter()
+                .map(|(source, choices)| {
+                    let choices = choices
+                        .iter()
+                        .map(|(weight, transition)| (*weight, convert(transition)))
+                        .collect();
+                    (*source, choices)
+                })
+                .collect();
+
+            State::new_random(name.clone(), transitions, random_transitions)
+        };
+
+        if let Some(description) = state.description() {
+            new_state = new_state.with_description(description.to_string());
+        }
+
+        states.insert(name.clone(), new_state);
+    }
+
+    let minimized = TuringMachine {
+        name: machine.name.clone(),
+        blank_symbol: machine.blank_symbol,
+
+        states,
+        final_states: machine
+            .final_states
+            .iter()
+            .filter(|name| !merged.contains_key(*name))
+            .cloned()
+            .collect(),
+
+        head_start: machine.head_start,
+        effective_head_start: machine.effective_head_start,
+        initial_state: machine.initial_state.clone(),
+
+        head_idx: machine.head_idx,
+        current_state: rename(&machine.current_state),
+        tape: machine.tape.clone(),
+
+        halted: machine.halted,
+
+        bounded: machine.bounded,
+        nondeterministic: machine.nondeterministic,
+        out_of_bounds: machine.out_of_bounds,
+
+        max_tape_cells: machine.max_tape_cells,
+
+        oracle: machine.oracle.clone(),
+        rng: machine.rng.clone(),
+
+        emitted: machine.emitted.clone(),
+
+        output_convention: machine.output_convention,
+
+        // The counts of the merged states can't be told apart anymore
+        heatmap: Heatmap::new(&machine.initial_state),
+
+        history: None,
+    };
+
+    (minimized, merged)
+}
+
+/// Builds an equivalent machine without the states that can't be reached from the initial
+/// state and the transitions that can never be taken, found by [`analysis::dead_code`].
+pub fn remove_dead_code(machine: &TuringMachine) -> TuringMachine {
+    let dead = analysis::dead_code(machine);
+    let dead_transitions = dead.transitions.iter().collect::<HashSet<_>>();
+    let is_dead = |name: &String, source: &TransitionSource| {
+        dead_transitions.contains(&(name.clone(), *source))
+    };
+
+    let mut states = HashMap::new();
+
+    for (name, state) in &machine.states {
+        if dead.states.contains(name) {
+            continue;
+        }
+
+        let mut new_state = if let Some(query) = state.query() {
+            State::new_query(name.clone(), query.clone())
+        } else if let Some(next_state) = state.emit() {
+            State::new_emit(name.clone(), next_state.to_string())
+        } else {
+            let transitions = state
+                .transitions()
+                .iter()
+                .filter(|(source, _)| !is_dead(name, source))
+                .map(|(source, transition)| (*source, transition.clone()))
+                .collect();
+
+            let random_transitions = state
+                .random_transitions()
+                .iter()
+                .filter(|(source, _)| !is_dead(name, source))
+                .map(|(source, choices)| (*source, choices.clone()))
+                .collect();
+
+            State::new_random(name.clone(), transitions, random_transitions)
+        };
+
+        if let Some(description) = state.description() {
+            new_state = new_state.with_description(description.to_string());
+        }
+
+        states.insert(name.clone(), new_state);
+    }
+
+    TuringMachine {
+        name: machine.name.clone(),
+        blank_symbol: machine.blank_symbol,
+
+        states,
+        final_states: machine
+            .final_states
+            .iter()
+            .filter(|name| !dead.states.contains(*name))
+            .cloned()
+            .collect(),
+
+        head_start: machine.head_start,
+        effective_head_start: machine.effective_head_start,
+        initial_state: machine.initial_state.clone(),
+
+        head_idx: machine.head_idx,
+        current_state: machine.current_state.clone(),
+        tape: machine.tape.clone(),
+
+        halted: machine.halted,
+
+        bounded: machine.bounded,
+        nondeterministic: machine.nondeterministic,
+        out_of_bounds: machine.out_of_bounds,
+
+        max_tape_cells: machine.max_tape_cells,
+
+        oracle: machine.oracle.clone(),
+        rng: machine.rng.clone(),
+
+        emitted: machine.emitted.clone(),
+
+        output_convention: machine.output_convention,
+
+        heatmap: machine.heatmap.clone(),
+
+        history: None,
+    }
+}
+
+/// Removes the dead code of a Turing Machine (see [`remove_dead_code`]) from its `.tng`
+/// definition, and returns the cleaned definition along with what was removed. Everything
+/// else is kept as written, comments included, and the doc comments of removed states are
+/// removed with them.
+pub fn remove_dead_code_from_tng(file_data: &str) -> Result<(String, DeadCode), String> {
+    let machine = TuringMachine::new_from_str(file_data, "")?;
+    let dead = analysis::dead_code(&machine);
+
+    let mut cleaned = vec![];
+    let mut in_states = false;
+    let mut current_state: Option<&str> = None;
+    let mut removing_state = false;
+    let mut removed_state = false; // To skip the empty line that separated it from the next one
+    let mut doc_comments = vec![]; // Kept until the state they document is known
+
+    for line in file_data.lines() {
+        let trimmed = line.trim();
+
+        if !in_states {
+            in_states = trimmed == "states {";
+            cleaned.push(line);
+            continue;
+        }
+
+        if trimmed.starts_with("///") {
+            doc_comments.push(line);
+            continue;
+        }
+
+        if let Some((name, _)) = parser::declared_state(line) {
+            removing_state = dead.states.iter().any(|dead| dead == name);
+            if !removing_state {
+                cleaned.append(&mut doc_comments);
+                cleaned.push(line);
+            }
+            doc_comments.clear();
+
+            if trimmed.ends_with('}') {
+                removed_state = removing_state;
+                removing_state = false;
+            } else {
+                current_state = Some(name);
+            }
+            continue;
+        }
+
+        cleaned.append(&mut doc_comments);
+
+        if trimmed == "}" {
+            match current_state.take() {
+                Some(_) => {
+                    removed_state = removing_state;
+                    if !removing_state {
+                        cleaned.push(line);
+                    }
+                    removing_state = false;
+                }
+                None => {
+                    in_states = false;
+                    cleaned.push(line);
+                }
+            }
+            continue;
+        }
+
+        if removing_state {
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            if removed_state && cleaned.last().is_some_and(|line| line.trim().is_empty()) {
+                continue;
+            }
+            cleaned.push(line);
+            continue;
+        }
+        removed_state = false;
+
+        // Transition lines start with the symbol they read
+        let source = match trimmed.split(',').next() {
+            Some("default") => Some(TransitionSource::Default),
+            Some(symbol) if symbol.chars().count() == 1 => {
+                let symbol = symbol.chars().next().unwrap();
+                Some(if symbol == machine.blank_symbol() {
+                    TransitionSource::Blank
+                } else {
+                    TransitionSource::Mark(symbol)
+                })
+            }
+            _ => None,
+        };
+
+        let is_dead = match (current_state, source) {
+            (Some(state), Some(source)) => dead
+                .transitions
+                .iter()
+                .any(|(name, dead)| name == state && *dead == source),
+            _ => false,
+        };
+
+        if !is_dead {
+            cleaned.push(line);
+        }
+    }
+
+    let mut cleaned = cleaned.join("\n");
+    if file_data.ends_with('\n') {
+        cleaned.push('\n');
+    }
+
+    Ok((cleaned, dead))
+}
+
+type TrackTransition = (String, Vec<Symbol>); // Source state and the symbols read on every tape
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum TrackState {
+    Init(usize), // Encoding the input cell at this index, up to the head start
+    InitRest,
+    Return(String), // Going back to the left end, before reading for this state
+    Read(String, Vec<Option<Symbol>>),
+    Halt(String, Vec<Symbol>),
+    Back(TrackTransition), // Going back to the left end, before writing
+    Write(TrackTransition, u64, u64), // Tapes already written, and heads to place on the next cell
+    SetLeft(TrackTransition, u64, u64, u64), // Also heads to place on the cell to the left
+    Skip(TrackTransition, u64, u64),
+}
+
+/// Compiles a multi-tape machine into an equivalent single-tape machine, following the
+/// classic construction where every cell holds one track per tape plus their head markers.
+///
+/// The resulting machine reads the same input as the original one. Each simulated step
+/// sweeps right collecting the symbols under every head, then sweeps right again from the
+/// left end writing them and moving the head markers. Every combination of track symbols
+/// and head markers becomes a symbol of the new machine, taken from the Unicode private
+/// use areas, so only machines with small alphabets and few tapes can be compiled.
+pub fn multi_to_single_tape(machine: &MultiTapeMachine) -> Result<TuringMachine, String> {
+    let tape_count = machine.tape_count();
+    if tape_count > 16 {
+        return Err(format!(
+            "[turing_lib] Error while compiling to a single tape. Too many tapes ({tape_count})."
+        ));
+    }
+    let all_tapes = (1u64 << tape_count) - 1;
+
+    // Every symbol that can ever be found on any of the tapes
+    let mut alphabet = vec![Symbol::Blank];
+    let mut marks = machine
+        .tapes()
+        .iter()
+        .flat_map(|tape| tape.get_content().iter().copied())
+        .chain(machine.states().values().flat_map(|state| {
+            state
+                .transitions()
+                .iter()
+                .flat_map(|(reading_symbols, transition)| {
+                    reading_symbols
+                        .iter()
+                        .chain(transition.new_symbols())
+                        .copied()
+                })
+        }))
+        .filter_map(|symbol| match symbol {
+            Symbol::Mark(c) => Some(c),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    marks.sort();
+    marks.dedup();
+    alphabet.extend(marks.iter().map(|c| Symbol::Mark(*c)));
+
+    let composite_count = (alphabet.len() as u64)
+        .checked_pow(tape_count as u32)
+        .and_then(|count| count.checked_mul(1 << tape_count))
+        .filter(|&count| count <= 60_000)
+        .ok_or_else(|| "[turing_lib] Error while compiling to a single tape. The machine has too many symbols or tapes to encode every combination of them.".to_string())?
+        as usize;
+
+    let mut free_chars = ('\u{E000}'..='\u{F8FF}')
+        .chain('\u{F0000}'..='\u{FFFFD}')
+        .filter(|c| !marks.contains(c) && *c != machine.blank_symbol());
+
+    let mut composites = Vec::with_capacity(composite_count);
+    let mut encoded = HashMap::with_capacity(composite_count);
+    for index in 0..composite_count {
+        let mut rest = index;
+        let markers = (rest % (1 << tape_count)) as u64;
+        rest >>= tape_count;
+
+        let symbols = (0..tape_count)
+            .map(|_| {
+                let symbol = alphabet[rest % alphabet.len()];
+                rest /= alphabet.len();
+                symbol
+            })
+            .collect::<Vec<_>>();
+
+        let c = free_chars.next().ok_or_else(|| {
+            "[turing_lib] Error while compiling to a single tape. Ran out of symbols to encode the tracks.".to_string()
+        })?;
+        encoded.insert((symbols.clone(), markers), c);
+        composites.push((symbols, markers));
+    }
+
+    let encode =
+        |symbols: &[Symbol], markers: u64| Symbol::Mark(encoded[&(symbols.to_vec(), markers)]);
+    let blank_cell = (vec![Symbol::Blank; tape_count], 0u64);
+
+    // Cells the sweeps can find: every encoded cell, and raw blanks past both ends of the tape
+    let cells = composites
+        .iter()
+        .map(|(symbols, markers)| {
+            (
+                TransitionSource::Mark(encoded[&(symbols.clone(), *markers)]),
+                symbols.clone(),
+                *markers,
+            )
+        })
+        .chain([(TransitionSource::Blank, blank_cell.0.clone(), blank_cell.1)])
+        .collect::<Vec<_>>();
+
+    let symbols_name = |symbols: &mut dyn Iterator<Item = Option<Symbol>>| -> String {
+        symbols
+            .map(|symbol| match symbol {
+                Some(Symbol::Mark(c)) => c,
+                Some(_) => machine.blank_symbol(),
+                None => '?',
+            })
+            .collect()
+    };
+    let state_name = |state: &TrackState| -> String {
+        let bits = |mask: u64| format!("{:0width$b}", mask, width = tape_count);
+        let transition_name = |(name, symbols): &TrackTransition| {
+            format!(
+                "{name}/{}",
+                symbols_name(&mut symbols.iter().map(|s| Some(*s)))
+            )
+        };
+
+        match state {
+            TrackState::Init(index) => format!("init{index}"),
+            TrackState::InitRest => "init".to_string(),
+            TrackState::Return(name) => format!("{name}/return"),
+            TrackState::Read(name, partial) => {
+                format!("{name}/read/{}", symbols_name(&mut partial.iter().copied()))
+            }
+            TrackState::Halt(name, symbols) => format!(
+                "{name}/halt/{}",
+                symbols_name(&mut symbols.iter().map(|s| Some(*s)))
+            ),
+            TrackState::Back(transition) => format!("{}/back", transition_name(transition)),
+            TrackState::Write(transition, done, right) => format!(
+                "{}/write/{}/{}",
+                transition_name(transition),
+                bits(*done),
+                bits(*right)
+            ),
+            TrackState::SetLeft(transition, done, right, left) => format!(
+                "{}/left/{}/{}/{}",
+                transition_name(transition),
+                bits(*done),
+                bits(*right),
+                bits(*left)
+            ),
+            TrackState::Skip(transition, done, right) => format!(
+                "{}/skip/{}/{}",
+                transition_name(transition),
+                bits(*done),
+                bits(*right)
+            ),
+        }
+    };
+
+    let move_all = |movement: HeadMovement, next: TrackState| {
+        Transition::new(movement, Symbol::Default, state_name(&next))
+    };
+
+    let mut states = HashMap::new();
+    let mut final_states = HashSet::new();
+    let mut pending = vec![TrackState::Init(0)];
+    let mut seen = HashSet::from([TrackState::Init(0)]);
+
+    while let Some(track_state) = pending.pop() {
+        let mut transitions = HashMap::new();
+        let mut next_states = vec![];
+
+        let mut add = |transitions: &mut HashMap<TransitionSource, Transition>,
+                       source: TransitionSource,
+                       new_symbol: Symbol,
+                       movement: HeadMovement,
+                       next: TrackState| {
+            transitions.insert(
+                source,
+                Transition::new(movement, new_symbol, state_name(&next)),
+            );
+            next_states.push(next);
+        };
+
+        match &track_state {
+            TrackState::Init(index) => {
+                let mut markers = 0;
+                if *index == machine.head_start() {
+                    markers |= 1;
+                }
+                if *index == 0 {
+                    markers |= all_tapes & !1;
+                }
+
+                let next = if *index < machine.head_start() {
+                    TrackState::Init(index + 1)
+                } else {
+                    TrackState::InitRest
+                };
+
+                for symbol in &alphabet {
+                    let mut symbols = vec![Symbol::Blank; tape_count];
+                    symbols[0] = *symbol;
+                    let source = match symbol {
+                        Symbol::Mark(c) => TransitionSource::Mark(*c),
+                        _ => TransitionSource::Blank,
+                    };
+                    add(
+                        &mut transitions,
+                        source,
+                        encode(&symbols, markers),
+                        HeadMovement::Right,
+                        next.clone(),
+                    );
+                }
+            }
+            TrackState::InitRest => {
+                for c in &marks {
+                    let mut symbols = vec![Symbol::Blank; tape_count];
+                    symbols[0] = Symbol::Mark(*c);
+                    add(
+                        &mut transitions,
+                        TransitionSource::Mark(*c),
+                        encode(&symbols, 0),
+                        HeadMovement::Right,
+                        TrackState::InitRest,
+                    );
+                }
+                add(
+                    &mut transitions,
+                    TransitionSource::Blank,
+                    Symbol::Blank,
+                    HeadMovement::Left,
+                    TrackState::Return(machine.initial_state_name().to_string()),
+                );
+            }
+            TrackState::Return(_) | TrackState::Back(_) => {
+                let next = match &track_state {
+                    TrackState::Return(name) => {
+                        TrackState::Read(name.clone(), vec![None; tape_count])
+                    }
+                    TrackState::Back(transition) => TrackState::Write(transition.clone(), 0, 0),
+                    _ => unreachable!(),
+                };
+                transitions.insert(
+                    TransitionSource::Default,
+                    move_all(HeadMovement::Left, track_state.clone()),
+                );
+                add(
+                    &mut transitions,
+                    TransitionSource::Blank,
+                    Symbol::Blank,
+                    HeadMovement::Right,
+                    next,
+                );
+            }
+            TrackState::Read(name, partial) => {
+                for (symbols, markers) in &composites {
+                    let read = partial
+                        .iter()
+                        .enumerate()
+                        .map(|(i, symbol)| {
+                            symbol.or((markers & (1 << i) != 0).then_some(symbols[i]))
+                        })
+                        .collect::<Vec<_>>();
+
+                    let c = encoded[&(symbols.clone(), *markers)];
+                    let source = TransitionSource::Mark(c);
+
+                    if read.iter().all(Option::is_some) {
+                        let read = read.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+                        if machine.states()[name].transitions().contains_key(&read) {
+                            add(
+                                &mut transitions,
+                                source,
+                                Symbol::Default,
+                                HeadMovement::Left,
+                                TrackState::Back((name.clone(), read)),
+                            );
+                        } else {
+                            add(
+                                &mut transitions,
+                                source,
+                                Symbol::Default,
+                                HeadMovement::Stay,
+                                TrackState::Halt(name.clone(), read),
+                            );
+                        }
+                    } else {
+                        add(
+                            &mut transitions,
+                            source,
+                            Symbol::Default,
+                            HeadMovement::Right,
+                            TrackState::Read(name.clone(), read),
+                        );
+                    }
+                }
+            }
+            TrackState::Halt(name, _) => {
+                if machine.final_states().contains(name) {
+                    final_states.insert(state_name(&track_state));
+                }
+            }
+            TrackState::Write(transition, done, right) => {
+                let target = &machine.states()[&transition.0].transitions()[&transition.1];
+
+                for (source, symbols, markers) in &cells {
+                    let mut new_symbols = symbols.clone();
+                    let mut new_markers = markers & done;
+                    let (mut new_done, mut new_right, mut new_left) = (*done, 0, 0);
+
+                    for i in (0..tape_count).filter(|i| markers & !done & (1 << i) != 0) {
+                        if !matches!(target.new_symbols()[i], Symbol::Default) {
+                            new_symbols[i] = target.new_symbols()[i];
+                        }
+
+                        match target.head_movements()[i] {
+                            HeadMovement::Stay => new_markers |= 1 << i,
+                            HeadMovement::Right => new_right |= 1 << i,
+                            HeadMovement::Left => new_left |= 1 << i,
+                        }
+                        new_done |= 1 << i;
+                    }
+                    new_markers |= right;
+
+                    let new_symbol = encode(&new_symbols, new_markers);
+                    if new_left != 0 {
+                        add(
+                            &mut transitions,
+                            *source,
+                            new_symbol,
+                            HeadMovement::Left,
+                            TrackState::SetLeft(transition.clone(), new_done, new_right, new_left),
+                        );
+                    } else if new_done == all_tapes && new_right == 0 {
+                        add(
+                            &mut transitions,
+                            *source,
+                            new_symbol,
+                            HeadMovement::Left,
+                            TrackState::Return(target.new_state().to_string()),
+                        );
+                    } else {
+                        add(
+                            &mut transitions,
+                            *source,
+                            new_symbol,
+                            HeadMovement::Right,
+                            TrackState::Write(transition.clone(), new_done, new_right),
+                        );
+                    }
+                }
+            }
+            TrackState::SetLeft(transition, done, right, left) => {
+                let target = &machine.states()[&transition.0].transitions()[&transition.1];
+
+                for (source, symbols, markers) in &cells {
+                    let new_symbol = encode(symbols, markers | left);
+                    if *done == all_tapes && *right == 0 {
+                        add(
+                            &mut transitions,
+                            *source,
+                            new_symbol,
+                            HeadMovement::Left,
+                            TrackState::Return(target.new_state().to_string()),
+                        );
+                    } else {
+                        add(
+                            &mut transitions,
+                            *source,
+                            new_symbol,
+                            HeadMovement::Right,
+                            TrackState::Skip(transition.clone(), *done, *right),
+                        );
+                    }
+                }
+            }
+            TrackState::Skip(transition, done, right) => {
+                add(
+                    &mut transitions,
+                    TransitionSource::Default,
+                    Symbol::Default,
+                    HeadMovement::Right,
+                    TrackState::Write(transition.clone(), *done, *right),
+                );
+            }
+        }
+
+        for next in next_states {
+            if seen.insert(next.clone()) {
+                pending.push(next);
+            }
+        }
+
+        let name = state_name(&track_state);
+        states.insert(name.clone(), State::new(name, transitions));
+    }
+
+    Ok(TuringMachine {
+        name: format!("{} (single tape)", machine.name()),
+        blank_symbol: machine.blank_symbol(),
+
+        states,
+        final_states,
+
+        head_start: 0,
+        effective_head_start: 0,
+        initial_state: state_name(&TrackState::Init(0)),
+
+        head_idx: 0,
+        current_state: state_name(&TrackState::Init(0)),
+        tape: machine.tapes()[0].clone(),
+
+        halted: false,
+
+        bounded: false,
+        out_of_bounds: false,
+
+        nondeterministic: false,
+
+        max_tape_cells: None,
+
+        oracle: None,
+        rng: Rng::new(0),
+
+        emitted: vec![],
+
+        output_convention: OutputConvention::FromHead,
+
+        heatmap: Heatmap::new(&state_name(&TrackState::Init(0))),
+
+        history: None,
+    })
+}
+
+#[derive(Clone, Copy)]
+enum ProductVerdict {
+    Intersection,
+    Union,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Component {
+    Running(String),
+    Halted(String),
+}
+
+impl Component {
+    fn name(&self) -> String {
+        match self {
+            Component::Running(name) => name.clone(),
+            Component::Halted(name) => format!("[{name}]"),
+        }
+    }
+
+    /// What this machine does when reading `symbol`: the symbol to write, the movement, and its next state.
+    fn step(&self, machine: &TuringMachine, symbol: Symbol) -> (Symbol, HeadMovement, Component) {
+        match self {
+            Component::Running(name) => match machine.states[name].transition_for(symbol) {
+                Some(transition) => (
+                    transition.new_symbol(),
+                    transition.head_movement(),
+                    Component::Running(transition.new_state().to_string()),
+                ),
+                None => (
+                    Symbol::Default,
+                    HeadMovement::Stay,
+                    Component::Halted(name.clone()),
+                ),
+            },
+            Component::Halted(_) => (Symbol::Default, HeadMovement::Stay, self.clone()),
+        }
+    }
+}
+
+/// Builds a two-tape machine that accepts the inputs accepted by both machines.
+/// See [`union`] for how the product machine works.
+pub fn intersection(a: &TuringMachine, b: &TuringMachine) -> Result<MultiTapeMachine, String> {
+    product(a, b, ProductVerdict::Intersection)
+}
+
+/// Builds a two-tape machine that accepts the inputs accepted by any of the machines.
+///
+/// The product machine copies its input to its second tape, and then runs both machines at
+/// once from their initial states over paired states, `a` on the first tape and `b` on the
+/// second one. The copy stops at the first blank, so inputs can't have blanks in them, and
+/// only the symbols the machines read or write are copied, so inputs with any other symbol
+/// are rejected. Once a machine halts it waits for the other one, so both machines must be
+/// deciders (always halting), otherwise the product machine may never halt.
+/// The result can be compiled into a single-tape machine with [`multi_to_single_tape`].
+pub fn union(a: &TuringMachine, b: &TuringMachine) -> Result<MultiTapeMachine, String> {
+    product(a, b, ProductVerdict::Union)
+}
+
+fn product(
+    a: &TuringMachine,
+    b: &TuringMachine,
+    verdict: ProductVerdict,
+) -> Result<MultiTapeMachine, String> {
+    if a.blank_symbol() != b.blank_symbol() {
+        return Err("[turing_lib] Error while building the product machine. Both machines must have the same blank symbol.".to_string());
+    }
+
+    if [a, b]
+        .iter()
+        .any(|machine| machine.states.values().any(|state| state.query().is_some()))
+    {
+        return Err("[turing_lib] Error while building the product machine. Machines that consult an oracle can't be combined.".to_string());
+    }
+
+    if [a, b]
+        .iter()
+        .any(|machine| machine.states.values().any(State::is_random))
+    {
+        return Err("[turing_lib] Error while building the product machine. Machines with random transitions can't be combined.".to_string());
+    }
+
+    if [a, b]
+        .iter()
+        .any(|machine| machine.states.values().any(|state| state.emit().is_some()))
+    {
+        return Err(
+            "[turing_lib] Error while building the product machine. Enumerators can't be combined."
+                .to_string(),
+        );
+    }
+
+    // The tapes of multi-tape machines have no end markers to stop at
+    if a.is_bounded() || b.is_bounded() {
+        return Err("[turing_lib] Error while building the product machine. Linear bounded automata can't be combined.".to_string());
+    }
+
+    // The tapes of multi-tape machines move one cell at a time
+    if [a, b].iter().any(|machine| {
+        machine.states.values().any(|state| {
+            state
+                .transitions()
+                .values()
+                .any(|transition| transition.distance() > 1)
+        })
+    }) {
+        return Err("[turing_lib] Error while building the product machine. Machines with head jumps (like `R3`) can't be combined.".to_string());
+    }
+
+    let mut alphabet = vec![Symbol::Blank];
+    let mut marks = [a, b]
+        .iter()
+        .flat_map(|machine| {
+            machine.states.values().flat_map(|state| {
+                state.transitions().iter().flat_map(|(source, transition)| {
+                    let source = match source {
+                        TransitionSource::Mark(c) => Symbol::Mark(*c),
+                        _ => Symbol::Blank,
+                    };
+                    [source, transition.new_symbol()]
+                })
+            })
+        })
+        .filter_map(|symbol| match symbol {
+            Symbol::Mark(c) => Some(c),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    marks.sort();
+    marks.dedup();
+    alphabet.extend(marks.into_iter().map(Symbol::Mark));
+
+    let symbol_pairs = alphabet
+        .iter()
+        .flat_map(|s1| alphabet.iter().map(move |s2| vec![*s1, *s2]))
+        .collect::<Vec<_>>();
+
+    let pair_name = |(ca, cb): &(Component, Component)| format!("{}+{}", ca.name(), cb.name());
+    let start = (
+        Component::Running(a.initial_state.clone()),
+        Component::Running(b.initial_state.clone()),
+    );
+
+    let mut states = HashMap::new();
+    let mut final_states = HashSet::new();
+
+    // After copying the input, move each head to where its machine starts
+    let seek_length = a.head_start.max(b.head_start);
+    let seek_name = |index: usize| {
+        if index == seek_length {
+            pair_name(&start)
+        } else {
+            format!("seek{index}")
+        }
+    };
+
+    // Copy the input to the second tape up to the first blank, then go back to its start,
+    // which is the cell right after the blank on its left
+    let marks = alphabet
+        .iter()
+        .filter(|symbol| **symbol != Symbol::Blank)
+        .collect::<Vec<_>>();
+    let copy_transitions = marks
+        .iter()
+        .map(|symbol| {
+            (
+                vec![**symbol, Symbol::Blank],
+                MultiTapeTransition::new(
+                    vec![**symbol, **symbol],
+                    vec![HeadMovement::Right, HeadMovement::Right],
+                    "copy".to_string(),
+                ),
+            )
+        })
+        .chain([(
+            vec![Symbol::Blank, Symbol::Blank],
+            MultiTapeTransition::new(
+                vec![Symbol::Default, Symbol::Default],
+                vec![HeadMovement::Left, HeadMovement::Left],
+                "rewind".to_string(),
+            ),
+        )])
+        .collect();
+    states.insert(
+        "copy".to_string(),
+        MultiTapeState::new("copy".to_string(), copy_transitions),
+    );
+
+    let rewind_transitions = marks
+        .iter()
+        .map(|symbol| {
+            (
+                vec![**symbol, **symbol],
+                MultiTapeTransition::new(
+                    vec![Symbol::Default, Symbol::Default],
+                    vec![HeadMovement::Left, HeadMovement::Left],
+                    "rewind".to_string(),
+                ),
+            )
+        })
+        .chain([(
+            vec![Symbol::Blank, Symbol::Blank],
+            MultiTapeTransition::new(
+                vec![Symbol::Default, Symbol::Default],
+                vec![HeadMovement::Right, HeadMovement::Right],
+                seek_name(0),
+            ),
+        )])
+        .collect();
+    states.insert(
+        "rewind".to_string(),
+        MultiTapeState::new("rewind".to_string(), rewind_transitions),
+    );
+
+    for index in 0..seek_length {
+        let movement = |head_start: usize| {
+            if index < head_start {
+                HeadMovement::Right
+            } else {
+                HeadMovement::Stay
+            }
+        };
+
+        let transitions = symbol_pairs
+            .iter()
+            .map(|symbols| {
+                (
+                    symbols.clone(),
+                    MultiTapeTransition::new(
+                        vec![Symbol::Default, Symbol::Default],
+                        vec![movement(a.head_start), movement(b.head_start)],
+                        seek_name(index + 1),
+                    ),
+                )
+            })
+            .collect();
+        states.insert(
+            seek_name(index),
+            MultiTapeState::new(seek_name(index), transitions),
+        );
+    }
+
+    let mut pending = vec![start.clone()];
+    let mut seen = HashSet::from([start]);
+
+    while let Some(pair) = pending.pop() {
+        let name = pair_name(&pair);
+        let mut transitions = HashMap::new();
+
+        if let (Component::Halted(name_a), Component::Halted(name_b)) = &pair {
+            let accepted_a = a.final_states.contains(name_a);
+            let accepted_b = b.final_states.contains(name_b);
+
+            let accepted = match verdict {
+                ProductVerdict::Intersection => accepted_a && accepted_b,
+                ProductVerdict::Union => accepted_a || accepted_b,
+            };
+
+            if accepted {
+                final_states.insert(name.clone());
+            }
+        } else {
+            for symbols in &symbol_pairs {
+                let (symbol_a, movement_a, next_a) = pair.0.step(a, symbols[0]);
+                let (symbol_b, movement_b, next_b) = pair.1.step(b, symbols[1]);
+
+                let next = (next_a, next_b);
+                transitions.insert(
+                    symbols.clone(),
+                    MultiTapeTransition::new(
+                        vec![symbol_a, symbol_b],
+                        vec![movement_a, movement_b],
+                        pair_name(&next),
+                    ),
+                );
+
+                if seen.insert(next.clone()) {
+                    pending.push(next);
+                }
+            }
+        }
+
+        states.insert(name.clone(), MultiTapeState::new(name, transitions));
+    }
+
+    Ok(MultiTapeMachine {
+        name: format!(
+            "{} {} {}",
+            a.name(),
+            match verdict {
+                ProductVerdict::Intersection => "and",
+                ProductVerdict::Union => "or",
+            },
+            b.name()
+        ),
+        blank_symbol: a.blank_symbol(),
+        head_start: 0,
+
+        states,
+        final_states,
+        initial_state: "copy".to_string(),
+
+        heads: vec![0, 0],
+        current_state: "copy".to_string(),
+        tapes: vec![
+            Tape::new(vec![Symbol::Blank]),
+            Tape::new(vec![Symbol::Blank]),
+        ],
+
+        halted: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{self, RunLimits, RunOutcome};
+
+    const FIRST_IS_A: &str = "config {\n    name: \"First is a\"\n    blank_symbol: '_'\n    head_start: 0\n}\n\nstates {\n    state q0 is initial {\n        a,a,R,yes\n    }\n\n    state yes is final {}\n}\n";
+    const SECOND_IS_B: &str = "config {\n    name: \"Second is b\"\n    blank_symbol: '_'\n    head_start: 0\n}\n\nstates {\n    state q0 is initial {\n        a,a,R,q1\n        b,b,R,q1\n    }\n\n    state q1 {\n        b,b,R,yes\n    }\n\n    state yes is final {}\n}\n";
+
+    fn accepts(machine: &mut TuringMachine, input: &str) -> bool {
+        machine.reset(input);
+        let limits = RunLimits {
+            max_steps: Some(1_000_000),
+            ..RunLimits::default()
+        };
+
+        matches!(
+            runner::run(machine, &limits),
+            RunOutcome::Halted { accepted: true, .. }
+        )
+    }
+
+    #[test]
+    fn product_runs_on_any_input() {
+        // Loaded with an input neither machine accepts, which must not matter
+        let a = TuringMachine::new_from_str(FIRST_IS_A, "").unwrap();
+        let b = TuringMachine::new_from_str(SECOND_IS_B, "ba").unwrap();
+
+        let mut both = multi_to_single_tape(&intersection(&a, &b).unwrap()).unwrap();
+        let mut any = multi_to_single_tape(&union(&a, &b).unwrap()).unwrap();
+
+        for (input, in_a, in_b) in [
+            ("ab", true, true),
+            ("abba", true, true),
+            ("aa", true, false),
+            ("bb", false, true),
+            ("ba", false, false),
+            ("", false, false),
+        ] {
+            assert_eq!(accepts(&mut both, input), in_a && in_b, "{input:?}");
+            assert_eq!(accepts(&mut any, input), in_a || in_b, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn remove_stay_moves_keeps_bounded_machines_within_their_markers() {
+        let source = "config {\n    name: \"Ends\"\n    blank_symbol: '_'\n    head_start: 1\n    machine_type: lba\n}\n\nstates {\n    state s is initial {\n        a,a,R,s\n        ⊣,⊣,S,y\n    }\n\n    state y is final {}\n}\n";
+        let mut machine = TuringMachine::new_from_str(source, "").unwrap();
+        assert!(accepts(&mut machine, "aa"));
+
+        let mut moving = remove_stay_moves(&machine);
+        assert!(accepts(&mut moving, "aa"));
+        assert!(accepts(&mut moving, ""));
+
+        // A default transition that stays may read the marker too
+        let mut moving = remove_stay_moves(
+            &TuringMachine::new_from_str(&source.replace("⊣,⊣,S,y", "default,default,S,y"), "")
+                .unwrap(),
+        );
+        assert!(accepts(&mut moving, "aa"));
+    }
+
+    #[test]
+    fn product_rejects_bounded_machines() {
+        let a = TuringMachine::new_from_str(FIRST_IS_A, "").unwrap();
+        let b = TuringMachine::new_from_str(
+            &SECOND_IS_B.replace("head_start: 0", "head_start: 1\n    machine_type: lba"),
+            "",
+        )
+        .unwrap();
+
+        assert!(intersection(&a, &b).is_err());
+    }
+
+    #[test]
+    fn product_rejects_head_jumps() {
+        let a = TuringMachine::new_from_str(FIRST_IS_A, "").unwrap();
+        let b =
+            TuringMachine::new_from_str(&SECOND_IS_B.replace("a,a,R,q1", "a,a,R2,q1"), "").unwrap();
+
+        assert!(intersection(&a, &b).is_err());
+        assert!(union(&b, &a).is_err());
+    }
+}
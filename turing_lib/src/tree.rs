@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+
+use crate::machine::TuringMachine;
+
+/// How far [`explore`] goes down a computation tree.
+#[derive(Debug, Clone, Copy)]
+pub struct TreeLimits {
+    /// The most choices on the way from the root to a branch.
+    pub max_depth: usize,
+    /// The most steps of a single branch, between two choices.
+    pub max_steps: usize,
+    /// The most branches of the whole tree.
+    pub max_branches: usize,
+}
+
+impl Default for TreeLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            max_steps: 10_000,
+            max_branches: 256,
+        }
+    }
+}
+
+/// How a branch of a computation tree ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchEnd {
+    Accepted,
+    Rejected,
+    /// It reached a symbol with random transitions, and goes on in one child per transition.
+    Split,
+    /// It did not halt within the limits, or was not explored because of them.
+    Pending,
+}
+
+/// A part of a run without choices: from a choice (or the initial configuration) up to the next
+/// choice or the end of the run.
+#[derive(Debug, Clone)]
+pub struct Branch {
+    parent: Option<usize>,
+    children: Vec<usize>,
+    depth: usize,
+    choice: Option<usize>,
+    steps: usize,
+    end: BranchEnd,
+    machine: TuringMachine,
+}
+
+impl Branch {
+    /// Index of the branch it comes from, `None` for the root.
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    /// Indices of the branches it splits into, in the order of the transitions they took.
+    pub fn children(&self) -> &[usize] {
+        &self.children
+    }
+
+    /// How many choices were made on the way from the root to the branch.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Index of the transition taken by the choice the branch starts with, see
+    /// [`TuringMachine::tick_choosing`]. `None` for the root.
+    pub fn choice(&self) -> Option<usize> {
+        self.choice
+    }
+
+    /// Steps made by the branch after its choice, up to its end.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn end(&self) -> BranchEnd {
+        self.end
+    }
+
+    /// The machine right after the choice the branch starts with, to run it from there.
+    pub fn machine(&self) -> &TuringMachine {
+        &self.machine
+    }
+}
+
+/// Every run a machine with random transitions may make, as a tree whose branches split
+/// wherever it chooses a transition at random, see [`explore`].
+#[derive(Debug, Clone)]
+pub struct ComputationTree {
+    branches: Vec<Branch>, // In breadth-first order, starting with the root
+}
+
+impl ComputationTree {
+    pub fn branches(&self) -> &[Branch] {
+        &self.branches
+    }
+
+    pub fn root(&self) -> &Branch {
+        &self.branches[0]
+    }
+
+    /// Whether any branch accepts.
+    pub fn accepts(&self) -> bool {
+        self.count(BranchEnd::Accepted) > 0
+    }
+
+    /// How many branches end in that way.
+    pub fn count(&self, end: BranchEnd) -> usize {
+        self.branches
+            .iter()
+            .filter(|branch| branch.end == end)
+            .count()
+    }
+}
+
+/// Explores every run the machine may make from its current configuration, breadth first,
+/// splitting the run wherever it reads a symbol with random transitions to follow all of them.
+/// Branches beyond the limits are left pending.
+pub fn explore(machine: &TuringMachine, limits: &TreeLimits) -> ComputationTree {
+    let mut root = machine.clone();
+    root.disable_history(); // Every branch would record its own
+
+    let mut branches = vec![Branch {
+        parent: None,
+        children: vec![],
+        depth: 0,
+        choice: None,
+        steps: 0,
+        end: BranchEnd::Pending,
+        machine: root,
+    }];
+    let mut queue = VecDeque::from([0]);
+
+    while let Some(idx) = queue.pop_front() {
+        let mut machine = branches[idx].machine.clone();
+        let mut steps = 0;
+
+        let end = loop {
+            if machine.is_halted() {
+                break if machine.is_accepting() {
+                    BranchEnd::Accepted
+                } else {
+                    BranchEnd::Rejected
+                };
+            }
+
+            let choices = machine.choices();
+            if choices > 1 {
+                let depth = branches[idx].depth + 1;
+                if depth > limits.max_depth || branches.len() + choices > limits.max_branches {
+                    break BranchEnd::Pending;
+                }
+
+                for choice in 0..choices {
+                    let mut child = machine.clone();
+                    child.tick_choosing(choice);
+
+                    let child_idx = branches.len();
+                    branches[idx].children.push(child_idx);
+                    queue.push_back(child_idx);
+                    branches.push(Branch {
+                        parent: Some(idx),
+                        children: vec![],
+                        depth,
+                        choice: Some(choice),
+                        steps: 0,
+                        end: BranchEnd::Pending,
+                        machine: child,
+                    });
+                }
+                break BranchEnd::Split;
+            }
+
+            if steps >= limits.max_steps {
+                break BranchEnd::Pending;
+            }
+
+            machine.advance();
+            steps += 1;
+        };
+
+        branches[idx].steps = steps;
+        branches[idx].end = end;
+    }
+
+    ComputationTree { branches }
+}
@@ -0,0 +1,183 @@
+use std::time::Instant;
+
+use crate::{
+    automaton::Automaton,
+    machine::{Symbol, TickResult},
+    runner::{self, RunLimits, RunOutcome},
+    tape::TapeSide,
+};
+
+/// Tape cells to watch during a run, to find out when something writes on them (e.g. a marker
+/// cell that gets corrupted somewhere deep in a run), see [`run_until_watchpoint`].
+///
+/// Cells are given by their index on the tape, and are followed when the tape grows to the
+/// left, so the same `Watchpoints` can be used to resume a run after it stops at one of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Watchpoints {
+    cells: Vec<usize>,
+}
+
+/// A tick that wrote a new symbol on a watched cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellWrite {
+    /// Index of the watchpoint, in the order they were added.
+    pub watchpoint: usize,
+    /// Index of the cell on the tape after the tick.
+    pub cell: usize,
+    pub old: Symbol,
+    pub new: Symbol,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchpointOutcome {
+    /// A symbol was written on a watched cell by the step `steps` (counting from 1).
+    Hit {
+        write: CellWrite,
+        steps: usize,
+    },
+    Finished(RunOutcome),
+}
+
+impl Watchpoints {
+    pub fn new(cells: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            cells: cells.into_iter().collect(),
+        }
+    }
+
+    /// Watches the cell at `cell`, returning the index of the watchpoint.
+    pub fn add(&mut self, cell: usize) -> usize {
+        self.cells.push(cell);
+        self.cells.len() - 1
+    }
+
+    /// Stops watching the cell of the watchpoint at `idx`, and returns its index on the tape.
+    /// The watchpoints after it move back by one.
+    pub fn remove(&mut self, idx: usize) -> usize {
+        self.cells.remove(idx)
+    }
+
+    /// Indices on the tape of the watched cells, in the order they were added.
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Ticks the machine, and returns which watched cells it wrote a new symbol on. Cells past
+    /// the ends of the tape hold blanks.
+    pub fn tick<A: Automaton + ?Sized>(&mut self, machine: &mut A) -> (TickResult, Vec<CellWrite>) {
+        let tape_len = machine.tape().len();
+        let before = self
+            .cells
+            .iter()
+            .map(|cell| cell_symbol(machine, *cell))
+            .collect::<Vec<_>>();
+
+        let tick_result = machine.tick();
+
+        if tick_result.extended_tape_on_side == Some(TapeSide::Left) {
+            let added = machine.tape().len() - tape_len;
+            for cell in &mut self.cells {
+                *cell += added;
+            }
+        }
+
+        let writes = self
+            .cells
+            .iter()
+            .zip(before)
+            .enumerate()
+            .filter_map(|(watchpoint, (cell, old))| {
+                let new = cell_symbol(machine, *cell);
+                (new != old).then_some(CellWrite {
+                    watchpoint,
+                    cell: *cell,
+                    old,
+                    new,
+                })
+            })
+            .collect();
+
+        (tick_result, writes)
+    }
+}
+
+fn cell_symbol<A: Automaton + ?Sized>(machine: &A, cell: usize) -> Symbol {
+    machine.tape()[cell as isize]
+}
+
+/// Runs the machine like [`crate::runner::run`], but stops right after a tick that writes a new
+/// symbol on any of the watched cells. If a tick writes on several of them, the first one is
+/// reported.
+pub fn run_until_watchpoint<A: Automaton + ?Sized>(
+    machine: &mut A,
+    limits: &RunLimits,
+    watchpoints: &mut Watchpoints,
+) -> WatchpointOutcome {
+    run(machine, limits, watchpoints, |_, _, _| false)
+}
+
+/// Runs the machine like [`crate::runner::run`], calling `on_write` with the machine, the write
+/// and the steps taken so far whenever a tick writes a new symbol on a watched cell.
+pub fn run_watched<A, F>(
+    machine: &mut A,
+    limits: &RunLimits,
+    watchpoints: &mut Watchpoints,
+    mut on_write: F,
+) -> RunOutcome
+where
+    A: Automaton + ?Sized,
+    F: FnMut(&A, &CellWrite, usize),
+{
+    let outcome = run(machine, limits, watchpoints, |machine, write, steps| {
+        on_write(machine, write, steps);
+        true
+    });
+
+    match outcome {
+        WatchpointOutcome::Finished(outcome) => outcome,
+        WatchpointOutcome::Hit { .. } => unreachable!("The run never stops at a write"),
+    }
+}
+
+/// Runs the machine until it halts, one of the limits is reached, or `on_write` returns `false`
+/// for a write on a watched cell.
+fn run<A, F>(
+    machine: &mut A,
+    limits: &RunLimits,
+    watchpoints: &mut Watchpoints,
+    mut on_write: F,
+) -> WatchpointOutcome
+where
+    A: Automaton + ?Sized,
+    F: FnMut(&A, &CellWrite, usize) -> bool,
+{
+    let start = Instant::now();
+    let mut steps = 0;
+
+    loop {
+        if let Some(outcome) = runner::check_limits(limits, machine, start, steps) {
+            return WatchpointOutcome::Finished(outcome);
+        }
+
+        let (_, writes) = watchpoints.tick(machine);
+
+        if machine.is_halted() {
+            return WatchpointOutcome::Finished(RunOutcome::Halted {
+                accepted: machine.is_accepting(),
+                steps,
+            });
+        }
+
+        steps += 1;
+
+        for write in writes {
+            if !on_write(machine, &write, steps) {
+                return WatchpointOutcome::Hit { write, steps };
+            }
+        }
+    }
+}
@@ -0,0 +1,2218 @@
+//! The animated view of the simulator: the tape, the head, the stack, the registers and the
+//! controls, drawn with [ggez](https://ggez.rs). The `turing` binary is a thin shell around it,
+//! and other frontends (a kiosk that cycles through machines, a recorder...) can build on it too:
+//!
+//! ```no_run
+//! use turing_viewer::Viewer;
+//!
+//! let definition = std::fs::read_to_string("examples/flip.tng").unwrap();
+//! let machine = turing_lib::automaton::load_from_str(&definition, "11001").unwrap();
+//!
+//! let (ctx, event_loop) = turing_viewer::context_builder().build().unwrap();
+//! ggez::event::run(ctx, event_loop, Viewer::new(machine));
+//! ```
+
+use ggez::{
+    event::{self, MouseButton},
+    glam::*,
+    graphics::{
+        self, Color, Drawable, FillOptions, PxScale, Rect, StrokeOptions, TextAlign, TextFragment,
+        TextLayout,
+    },
+    input::{
+        keyboard::{KeyCode, KeyInput},
+        mouse::{set_cursor_type, CursorIcon},
+    },
+    mint::Point2,
+    winit::window::UserAttentionType,
+    Context, GameError, GameResult,
+};
+use num_input::NumberInput;
+use std::{
+    collections::{HashMap, VecDeque},
+    env, fs, path,
+    time::{Duration, Instant},
+};
+use turing_lib::{
+    automaton::Automaton,
+    diagram::StateDiagram,
+    explain,
+    lesson::Lesson,
+    machine::{HeadMovement, Pacing, Step, Symbol, TickResult},
+    tape::{Tape, TapeSide},
+    tree::{BranchEnd, ComputationTree, TreeLimits},
+};
+
+mod notification;
+mod num_input;
+
+pub const WINDOW_WIDTH: f32 = 1000.0;
+pub const WINDOW_HEIGHT: f32 = 800.0;
+const WINDOW_TITLE: &str = "Turing Machine Simulator - by keelus";
+
+const HORIZ_MARGIN: f32 = 80.0;
+
+const DEFAULT_CELL_COUNT: usize = 7;
+const TAPE_FONT: &str = "tape";
+const WRITE_ANIM_MAX_ALPHA: f32 = 0.8;
+const SYMBOL_SIZE: f32 = 0.75; // Font size of the tape symbols, relative to the cell height
+const SYMBOL_MAX_WIDTH: f32 = 0.85; // Width the symbols fit in, relative to the cell width
+const ASPECT_MEASURE_SIZE: f32 = 100.0; // Font size the width of the symbols is measured at
+
+const FIRST_WAIT_DURATION_MS: u64 = 100;
+const HEAD_MOVE_DURATION_MS: u64 = 333;
+const LAST_WAIT_DURATION_MS: u64 = 100;
+const SEEK_WAIT_DURATION_MS: u64 = 500; // Before playing a replay again after jumping in it
+
+const TIMELINE_MARGIN: f32 = 260.0; // On both sides, to leave room for the inputs and the stack
+
+const DEFAULT_SPEED: i16 = 3;
+const MAX_SPEED: i16 = 5;
+const MAX_AUTO_ACCELERATION: f32 = 20.0; // How many times faster the auto speed goes in a scan
+const GHOST_COUNT: usize = 5; // Previous head positions marked on every tape
+const GHOST_LIFETIME: Duration = Duration::from_secs(3);
+const GHOST_MAX_ALPHA: f32 = 0.5;
+const TOOLTIP_WRITES: usize = 3; // Last writes to a cell shown when hovering it
+const DIAGRAM_STATE_RADIUS: f32 = 12.0;
+const MAX_CHART_SAMPLES: usize = 1000; // Every other one is dropped past it, see `record_sample`
+
+const REJECT_COLOR: Color = Color {
+    r: 250.0 / 255.0,
+    g: 54.0 / 255.0,
+    b: 54.0 / 255.0,
+    a: 1.0,
+};
+
+const ACCENT_COLOR: Color = Color {
+    r: 110.0 / 255.0,
+    g: 157.0 / 255.0,
+    b: 209.0 / 255.0,
+    a: 1.0,
+};
+
+struct AnimationState {
+    animation: Animation,
+    stage_begin: Instant,
+    next_stage: Instant,
+}
+
+enum Animation {
+    FirstWait,
+    HeadMove {
+        deltas: Vec<f32>, // How many cells each head is moving, negative to the left (0 if not).
+        progress: f32,    // 0.0 to 1.0, the same for every head so they move together.
+    },
+    LastWait,
+}
+
+impl Animation {
+    /// How many cells the tape at `row` has moved so far, negative to the left.
+    fn text_displacement(&self, row: usize) -> f32 {
+        match self {
+            Animation::HeadMove { deltas, progress } => deltas[row] * progress,
+            _ => 0.0,
+        }
+    }
+}
+
+struct Sizing {
+    window: Point2<f32>,
+
+    cell_size: f32,   // Width of the cells
+    cell_height: f32, // The same as their width, unless the symbols are too wide for square cells
+    symbol_aspect: f32,
+
+    head_triangle: Point2<f32>,
+    head_triangle_margin: f32,
+}
+
+impl Sizing {
+    /// `symbol_aspect` is the width of the widest symbol on the tape divided by its font size,
+    /// which makes the cells lower than wide if square cells would be too narrow for it.
+    pub fn calculate(
+        window_width: f32,
+        window_height: f32,
+        cell_count: usize,
+        symbol_aspect: f32,
+    ) -> Self {
+        const HORIZ_MARGIN: f32 = 80.0;
+        let cell_size = (window_width - HORIZ_MARGIN * 2.0) / cell_count as f32;
+        let cell_height = cell_size
+            .min(cell_size * SYMBOL_MAX_WIDTH / (SYMBOL_SIZE * symbol_aspect.max(f32::EPSILON)));
+        Self {
+            window: [window_width, window_height].into(),
+
+            cell_size,
+            cell_height,
+            symbol_aspect,
+
+            head_triangle: [cell_height / 3.0, cell_height / 2.4].into(),
+            head_triangle_margin: cell_height / 8.0,
+        }
+    }
+}
+
+/// Where the frames are written, see [`Viewer::with_frame_dump`].
+struct FrameDump {
+    dir: path::PathBuf,
+    frames: usize,  // Written so far, which numbers the next one
+    pending: bool,  // Whether the configuration shown is still to be written
+    finished: bool, // Whether the last configuration was written, after which nothing changes
+}
+
+/// The configuration after a tick, for the charts of the head and the tape.
+#[derive(Debug, Clone, Copy)]
+struct ChartSample {
+    step: usize,
+    head_position: isize, // Counted from the cell the tape started at
+    tape_len: usize,
+}
+
+/// The state of the window: the machine being simulated and what is shown of it, which lags
+/// behind the machine while a transition is animated.
+pub struct Viewer {
+    machine: Box<dyn Automaton>,
+
+    writing_animation: Option<f32>, // Where f32 is the alpha value [0.0, WRITE_ANIM_MAX_ALPHA]
+    written_tapes: Vec<bool>,       // Whether the last tick wrote a new symbol on each tape
+
+    visual_tapes: Vec<Tape>, // One per tape, the one of `Automaton::tape` first
+    visual_heads: Vec<usize>,
+    head_trails: Vec<VecDeque<(usize, Instant)>>, // Previous heads of every tape, when they left
+    visual_stack: Option<Vec<char>>,
+
+    should_update: bool,
+    animation_state: Option<AnimationState>,
+    last_tick: Option<TickResult>,
+    explanation: Option<String>, // Of the last tick, or a note about the input before the first one
+    formula: Option<String>,     // Of the last tick, in the notation of the transition function
+    formal_notation: bool,       // Whether the formula is shown in place of the explanation
+    lesson: Option<Lesson>,
+    caption: Option<String>, // Of the last annotation of the lesson that was cued
+    ticks: usize,            // Before halting, to count the steps of machines without a timeline
+    last_movement: Option<HeadMovement>,
+    scan_steps: usize, // Of the current scan, where nothing is written, for the auto speed
+    pacing: Pacing,    // Of the last transition
+    paused_at: Option<Instant>,
+    focused: bool,
+    halt_announced: bool, // Whether the halt was told in the title (and by a notification)
+    mouse: Option<Point2<f32>>,
+
+    tree: Option<ComputationTree>, // Of a machine with random transitions
+    tree_layout: Vec<Point2<f32>>, // Of every branch of the tree, in a unit square
+    followed_branch: Option<usize>,
+
+    diagram: Option<StateDiagram>,
+    diagram_positions: Option<String>, // File the positions of the states are saved to
+    dragged_state: Option<usize>,
+    show_diagram: bool,
+
+    chart_samples: Vec<ChartSample>, // Of the ticks shown, for the charts of the head and the tape
+    chart_stride: usize,             // Ticks between the samples, doubled when there are too many
+    chart_origin: isize,             // Index of the cell the tape started at
+    show_charts: bool,
+
+    speed_input: NumberInput,
+    cells_input: NumberInput,
+
+    sizing: Sizing,
+    light_theme: bool,
+    tape_font: Option<String>, // Name of the font added with with_tape_font, for the tape symbols
+    symbol_aspects: HashMap<char, f32>, // Measured once for every symbol, see `Sizing::calculate`
+    frame_dump: Option<FrameDump>,
+}
+
+impl Viewer {
+    /// Shows the machine in a window of the default size, see [`context_builder`]. The window is
+    /// laid out again when it is resized.
+    pub fn new(machine: Box<dyn Automaton>) -> Viewer {
+        let light_theme = true;
+        let (cells_input, speed_input) = Self::controls(WINDOW_HEIGHT, light_theme);
+        let tree = machine.computation_tree(&TreeLimits::default());
+
+        Viewer {
+            tree_layout: tree.as_ref().map(tree_layout).unwrap_or_default(),
+            followed_branch: tree.as_ref().map(|_| 0),
+            tree,
+
+            diagram: machine.state_diagram(),
+            diagram_positions: None,
+            dragged_state: None,
+            show_diagram: true,
+
+            chart_samples: vec![ChartSample {
+                step: 0,
+                head_position: machine.head_idx() as isize,
+                tape_len: machine.tape().len(),
+            }],
+            chart_stride: 1,
+            chart_origin: 0,
+            show_charts: false,
+
+            visual_heads: machine.heads(),
+            head_trails: vec![VecDeque::new(); machine.tapes().len()],
+            visual_tapes: machine.tapes().to_vec(),
+            visual_stack: machine.stack().map(<[char]>::to_vec),
+            machine,
+
+            writing_animation: None,
+            written_tapes: vec![],
+
+            last_tick: None,
+            explanation: None,
+            formula: None,
+            formal_notation: true,
+            lesson: None,
+            caption: None,
+            ticks: 0,
+            last_movement: None,
+            scan_steps: 0,
+            pacing: Pacing::Normal,
+
+            animation_state: Some(AnimationState {
+                animation: Animation::LastWait,
+                stage_begin: Instant::now(),
+                next_stage: Instant::now() + Duration::from_millis(1000),
+            }),
+            should_update: true,
+            paused_at: None,
+            focused: true,
+            halt_announced: false,
+            mouse: None,
+            sizing: Sizing::calculate(WINDOW_WIDTH, WINDOW_HEIGHT, DEFAULT_CELL_COUNT, 0.0),
+            tape_font: None,
+            symbol_aspects: HashMap::new(),
+            frame_dump: None,
+
+            cells_input,
+            speed_input,
+            light_theme,
+        }
+    }
+
+    /// Uses light text on a dark background.
+    pub fn with_dark_theme(mut self) -> Self {
+        self.light_theme = false;
+        (self.cells_input, self.speed_input) =
+            Self::controls(self.sizing.window.y, self.light_theme);
+        self
+    }
+
+    /// Shows a note under the tape until the first transition is explained, e.g. about how the
+    /// input was read.
+    pub fn with_note(mut self, note: String) -> Self {
+        self.explanation = Some(note);
+        self
+    }
+
+    /// Shows the captions of the lesson under the tape, each one from the moment of the run it is
+    /// cued at until the next one.
+    pub fn with_lesson(mut self, lesson: Lesson) -> Self {
+        self.lesson = Some(lesson);
+        self.cue_caption(true);
+        self
+    }
+
+    /// Places the states of the state diagram where they were saved in the file at `path` (see
+    /// [`turing_lib::diagram::positions_path`]), and saves them there whenever a state is dragged
+    /// to another place. A file that doesn't exist yet (or can't be read) is written on the first
+    /// drag.
+    pub fn with_diagram_positions(mut self, path: String) -> Self {
+        if let Some(diagram) = &mut self.diagram {
+            let _ = diagram.load_positions(&path);
+        }
+        self.diagram_positions = Some(path);
+        self
+    }
+
+    /// Writes a PNG file of the window into `dir` (created if needed) for the initial
+    /// configuration and after every step, as `frame_00000.png`, `frame_00001.png`, and so on.
+    /// The machine waits for every frame to be written before going on, so no step is missed at
+    /// any speed.
+    pub fn with_frame_dump(mut self, dir: path::PathBuf) -> GameResult<Self> {
+        fs::create_dir_all(&dir).map_err(|err| {
+            GameError::CustomError(format!(
+                "Error while creating the directory \"{}\". {err}.",
+                dir.display()
+            ))
+        })?;
+        self.frame_dump = Some(FrameDump {
+            dir,
+            frames: 0,
+            pending: true,
+            finished: false,
+        });
+        Ok(self)
+    }
+
+    /// Draws the tape symbols with the font in `font_data` (a TrueType or OpenType file), for
+    /// symbols the default font has no glyphs for.
+    pub fn with_tape_font(mut self, ctx: &mut Context, font_data: Vec<u8>) -> GameResult<Self> {
+        ctx.gfx
+            .add_font(TAPE_FONT, graphics::FontData::from_vec(font_data)?);
+        self.tape_font = Some(TAPE_FONT.to_string());
+        self.symbol_aspects.clear();
+        Ok(self)
+    }
+
+    pub fn machine(&self) -> &dyn Automaton {
+        self.machine.as_ref()
+    }
+
+    /// The "Visible cells" and "Simulation speed" inputs on the bottom left corner.
+    fn controls(window_height: f32, light_theme: bool) -> (NumberInput, NumberInput) {
+        let text_color = if light_theme {
+            Color::BLACK
+        } else {
+            Color::WHITE
+        };
+
+        (
+            NumberInput::new(
+                "Visible cells",
+                7,
+                2,
+                (3, 71),
+                Rect::new(30.0, window_height - 120.0, 100.0, 30.0),
+                text_color,
+            ),
+            NumberInput::new(
+                "Simulation speed",
+                DEFAULT_SPEED,
+                1,
+                (1, MAX_SPEED + 1), // The last one is the auto speed
+                Rect::new(30.0, window_height - 50.0, 100.0, 30.0),
+                text_color,
+            )
+            .with_max_label("Auto"),
+        )
+    }
+
+    /// Draws the stack as a column of cells on the bottom right corner, top of the stack first.
+    fn draw_stack(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        stack: &[char],
+        fg_color: Color,
+    ) -> GameResult {
+        const STACK_CELL_SIZE: f32 = 40.0;
+        const STACK_BOTTOM_MARGIN: f32 = 20.0;
+
+        let top_limit = self.sizing.window.y / 2.0
+            + self.sizing.cell_height / 2.0
+            + self.sizing.head_triangle_margin * 2.0
+            + self.sizing.head_triangle.y
+            + STACK_CELL_SIZE;
+        let visible_cells = ((self.sizing.window.y - STACK_BOTTOM_MARGIN - top_limit)
+            / STACK_CELL_SIZE)
+            .max(0.0) as usize;
+
+        let x = self.sizing.window.x - HORIZ_MARGIN - STACK_CELL_SIZE;
+        let bottom = self.sizing.window.y - STACK_BOTTOM_MARGIN;
+
+        let cell = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::Stroke(StrokeOptions::default().with_line_width(2.0)),
+            Rect::new(0.0, 0.0, STACK_CELL_SIZE, STACK_CELL_SIZE),
+            fg_color,
+        )?;
+
+        // Only the topmost symbols that fit are drawn, the top of the stack being the highest cell
+        let shown = stack.len().min(visible_cells);
+        for (i, symbol) in stack[stack.len() - shown..].iter().enumerate() {
+            let y = bottom - STACK_CELL_SIZE * (i + 1) as f32;
+            canvas.draw(&cell, [x, y]);
+
+            let text_size = STACK_CELL_SIZE * 0.6;
+            let text_piece = graphics::Text::new(TextFragment {
+                text: symbol.to_string(),
+                color: Some(if i + 1 == shown {
+                    ACCENT_COLOR
+                } else {
+                    fg_color
+                }),
+                scale: Some(PxScale {
+                    x: text_size,
+                    y: text_size,
+                }),
+                font: None,
+            });
+            let Rect {
+                w: text_width,
+                h: text_height,
+                ..
+            } = text_piece.dimensions(ctx).unwrap();
+            canvas.draw(
+                &text_piece,
+                [
+                    x + STACK_CELL_SIZE / 2.0 - text_width / 2.0,
+                    y + STACK_CELL_SIZE / 2.0 - text_height / 2.0,
+                ],
+            );
+        }
+
+        let text_size = 17.0;
+        let text_piece = graphics::Text::new(TextFragment {
+            text: format!("Stack ({})", stack.len()),
+            color: Some(fg_color),
+            scale: Some(PxScale {
+                x: text_size,
+                y: text_size,
+            }),
+            font: None,
+        });
+        let text_width = text_piece.dimensions(ctx).unwrap().w;
+        canvas.draw(
+            &text_piece,
+            [
+                x + STACK_CELL_SIZE - text_width,
+                bottom - STACK_CELL_SIZE * shown as f32 - text_size - 5.0,
+            ],
+        );
+
+        Ok(())
+    }
+
+    /// Draws every tape as a row with its own head, the first one on top.
+    fn draw_tapes(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        bg_color: Color,
+        fg_color: Color,
+    ) -> GameResult {
+        for row in 0..self.visual_tapes.len() {
+            self.draw_tape(ctx, canvas, row, bg_color, fg_color)?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_tape(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        row: usize,
+        bg_color: Color,
+        fg_color: Color,
+    ) -> GameResult {
+        let center_y = self.tape_row_y(row);
+        let stroke_width = (self.sizing.cell_size / 2.0 * 0.03).ceil().max(1.0);
+        let head_stroke_width = (self.sizing.cell_size / 2.0 * 0.07).ceil().max(1.0);
+
+        let horiz_line = graphics::Mesh::new_line(
+            ctx,
+            &[
+                [
+                    HORIZ_MARGIN - stroke_width / 2.0 - self.sizing.cell_size,
+                    0.0,
+                ],
+                [
+                    self.sizing.window.x - HORIZ_MARGIN
+                        + stroke_width / 2.0
+                        + self.sizing.cell_size,
+                    0.0,
+                ],
+            ],
+            stroke_width,
+            fg_color,
+        )?;
+        canvas.draw(&horiz_line, [0.0, center_y - self.sizing.cell_height / 2.0]);
+        canvas.draw(&horiz_line, [0.0, center_y + self.sizing.cell_height / 2.0]);
+
+        let text_displacement_percent = self.text_displacement(row);
+
+        let vert_line = graphics::Mesh::new_line(
+            ctx,
+            &[[0.0, 0.0], [0.0, self.sizing.cell_height]],
+            stroke_width,
+            fg_color,
+        )?;
+        for i in 0..=(self.cells_input.value() as usize + 1) {
+            canvas.draw(
+                &vert_line,
+                [
+                    HORIZ_MARGIN + self.sizing.cell_size * (i as f32)
+                        - self.sizing.cell_size * (text_displacement_percent % 1.0),
+                    center_y - self.sizing.cell_height / 2.0,
+                ],
+            );
+        }
+
+        let head_triangle = graphics::Mesh::new_polygon(
+            ctx,
+            graphics::DrawMode::Fill(FillOptions::default()),
+            &[
+                [self.sizing.head_triangle.x / 2.0, 0.0],
+                [0.0, self.sizing.head_triangle.y],
+                [self.sizing.head_triangle.x, self.sizing.head_triangle.y],
+            ],
+            ACCENT_COLOR,
+        )?;
+        let head_triangle_y =
+            center_y + self.sizing.cell_height / 2.0 + self.sizing.head_triangle_margin;
+        canvas.draw(
+            &head_triangle,
+            [
+                self.sizing.window.x / 2.0 - self.sizing.head_triangle.x / 2.0,
+                head_triangle_y,
+            ],
+        );
+
+        // Faded markers where the head was, fading away as time goes by
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        let visible_cells = self.cells_input.value() as f32 / 2.0;
+        for (cell, left_at) in &self.head_trails[row] {
+            let offset = *cell as f32 - self.visual_heads[row] as f32 - text_displacement_percent;
+            let age = now.saturating_duration_since(*left_at).as_secs_f32();
+            let alpha = GHOST_MAX_ALPHA * (1.0 - age / GHOST_LIFETIME.as_secs_f32());
+            if alpha <= 0.0 || offset.abs() > visible_cells - 0.5 || *cell == self.visual_heads[row]
+            {
+                continue;
+            }
+
+            canvas.draw(
+                &head_triangle,
+                graphics::DrawParam::new()
+                    .dest([
+                        self.sizing.window.x / 2.0 + offset * self.sizing.cell_size
+                            - self.sizing.head_triangle.x / 2.0,
+                        head_triangle_y,
+                    ])
+                    .color(Color::new(1.0, 1.0, 1.0, alpha)),
+            );
+        }
+
+        // + 1 to also draw non visible border cells, and more while jumping over several cells
+        let drawn_cells = self.cells_input.value() as isize / 2
+            + 1
+            + text_displacement_percent.abs().ceil() as isize;
+        for i in -drawn_cells..=drawn_cells {
+            let correct_index = self.visual_heads[row] as isize + i;
+
+            let char_at = {
+                if correct_index < 0 || correct_index >= self.visual_tapes[row].len() as isize {
+                    self.machine.blank_symbol()
+                } else {
+                    match self.visual_tapes[row].read(correct_index as usize) {
+                        Symbol::Blank => self.machine.blank_symbol(),
+                        Symbol::Mark(c) => c,
+                        _ => unreachable!("Default Symbol won't be present in the tape."),
+                    }
+                }
+            };
+            let text_content: String = format!("{char_at}");
+            let sized_text = |text_size: f32| {
+                graphics::Text::new(TextFragment {
+                    text: text_content.clone(),
+                    font: self.tape_font.clone(),
+                    scale: Some(PxScale {
+                        x: text_size,
+                        y: text_size,
+                    }),
+                    color: Some(fg_color),
+                })
+            };
+
+            // Cells are made lower for wide symbols (e.g. CJK), see `fit_cells`, so this only
+            // scales down the symbols that just came into view
+            let mut text_size = self.sizing.cell_height * SYMBOL_SIZE;
+            let mut text_piece = sized_text(text_size);
+            let max_text_width = self.sizing.cell_size * SYMBOL_MAX_WIDTH;
+            let measured_width = text_piece.dimensions(ctx).unwrap().w;
+            if measured_width > max_text_width {
+                text_size *= max_text_width / measured_width;
+                text_piece = sized_text(text_size);
+            }
+
+            let Rect {
+                w: text_width,
+                h: text_height,
+                ..
+            } = text_piece.dimensions(ctx).unwrap();
+
+            canvas.draw(
+                &text_piece,
+                [
+                    (self.sizing.cell_size * (i as f32) + self.sizing.window.x / 2.0)
+                        - text_width / 2.0
+                        - self.sizing.cell_size * text_displacement_percent,
+                    center_y - text_height / 2.0,
+                ],
+            );
+
+            if i == 0 && self.written_tapes.get(row) == Some(&true) {
+                if let Some(alpha) = self.writing_animation {
+                    let write_opacity_square = graphics::Mesh::new_rectangle(
+                        ctx,
+                        graphics::DrawMode::Fill(FillOptions::default()),
+                        Rect::new(0.0, 0.0, self.sizing.cell_size, self.sizing.cell_height),
+                        Color::new(bg_color.r, bg_color.b, bg_color.g, alpha),
+                    )?;
+
+                    canvas.draw(
+                        &write_opacity_square,
+                        [
+                            (self.sizing.cell_size * (i as f32) + self.sizing.window.x / 2.0)
+                                - self.sizing.cell_size / 2.0,
+                            center_y - self.sizing.cell_height / 2.0,
+                        ],
+                    );
+                }
+            }
+        }
+
+        // Draw hidden border squares
+        let square = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::Fill(FillOptions::default()),
+            Rect::new(0.0, 0.0, HORIZ_MARGIN, self.sizing.cell_height + 10.0),
+            bg_color,
+        )?;
+        canvas.draw(
+            &square,
+            [-1.0, center_y - (self.sizing.cell_height + 10.0) / 2.0],
+        );
+        canvas.draw(
+            &square,
+            [
+                self.sizing.window.x - HORIZ_MARGIN + 1.0,
+                center_y - (self.sizing.cell_height + 10.0) / 2.0,
+            ],
+        );
+
+        let head_square = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::Stroke(StrokeOptions::default().with_line_width(head_stroke_width)),
+            Rect::new(0.0, 0.0, self.sizing.cell_size, self.sizing.cell_height),
+            ACCENT_COLOR,
+        )?;
+        canvas.draw(
+            &head_square,
+            [
+                self.sizing.window.x / 2.0 - self.sizing.cell_size / 2.0,
+                center_y - self.sizing.cell_height / 2.0,
+            ],
+        );
+
+        Ok(())
+    }
+
+    /// Makes the cells lower than wide while a symbol too wide for square cells is in view, so
+    /// every symbol fits in its cell at the same size.
+    fn fit_cells(&mut self, ctx: &mut Context) {
+        let blank_symbol = self.machine.blank_symbol();
+        let reach = self.cells_input.value() as usize / 2 + 1;
+
+        let mut symbols = vec![blank_symbol]; // Also past the ends of the tapes
+        for (tape, head) in self.visual_tapes.iter().zip(&self.visual_heads) {
+            let visible = head.saturating_sub(reach)..(head + reach + 1).min(tape.len());
+            symbols.extend(visible.map(|cell| match tape.read(cell) {
+                Symbol::Mark(c) => c,
+                _ => blank_symbol,
+            }));
+        }
+
+        let aspect = symbols
+            .into_iter()
+            .map(|symbol| self.symbol_aspect(ctx, symbol))
+            .fold(0.0, f32::max);
+        if aspect != self.sizing.symbol_aspect {
+            self.sizing = Sizing::calculate(
+                self.sizing.window.x,
+                self.sizing.window.y,
+                self.cells_input.value() as usize,
+                aspect,
+            );
+        }
+    }
+
+    /// The width of the symbol drawn with the tape font, divided by the font size.
+    fn symbol_aspect(&mut self, ctx: &mut Context, symbol: char) -> f32 {
+        if let Some(aspect) = self.symbol_aspects.get(&symbol) {
+            return *aspect;
+        }
+
+        let text_piece = graphics::Text::new(TextFragment {
+            text: symbol.to_string(),
+            font: self.tape_font.clone(),
+            scale: Some(PxScale {
+                x: ASPECT_MEASURE_SIZE,
+                y: ASPECT_MEASURE_SIZE,
+            }),
+            color: None,
+        });
+        let aspect = text_piece
+            .dimensions(ctx)
+            .map_or(0.0, |dimensions| dimensions.w / ASPECT_MEASURE_SIZE);
+        self.symbol_aspects.insert(symbol, aspect);
+        aspect
+    }
+
+    /// The vertical center of the tape at `row`, with the rows of every tape centered in the
+    /// window.
+    fn tape_row_y(&self, row: usize) -> f32 {
+        let row_height = self.sizing.cell_height
+            + self.sizing.head_triangle_margin * 2.0
+            + self.sizing.head_triangle.y;
+        let rows = self.visual_tapes.len() as f32;
+        self.sizing.window.y / 2.0 + (row as f32 - (rows - 1.0) / 2.0) * row_height
+    }
+
+    /// How many cells the tape at `row` has moved so far in the current head move.
+    fn text_displacement(&self, row: usize) -> f32 {
+        self.animation_state
+            .as_ref()
+            .map_or(0.0, |animation_state| {
+                animation_state.animation.text_displacement(row)
+            })
+    }
+
+    /// Draws every register as a labelled cell, in place of the tape.
+    fn draw_registers(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        registers: &[u64],
+        fg_color: Color,
+    ) -> GameResult {
+        const REGISTER_CELL_WIDTH: f32 = 100.0;
+        const REGISTER_CELL_HEIGHT: f32 = 60.0;
+        const REGISTER_MARGIN: f32 = 15.0;
+
+        let per_row = (((self.sizing.window.x - HORIZ_MARGIN * 2.0 + REGISTER_MARGIN)
+            / (REGISTER_CELL_WIDTH + REGISTER_MARGIN)) as usize)
+            .max(1);
+        let rows = registers.len().div_ceil(per_row);
+        let top = self.sizing.window.y / 2.0
+            - (rows as f32 * (REGISTER_CELL_HEIGHT + REGISTER_MARGIN * 2.0)) / 2.0;
+
+        let cell = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::Stroke(StrokeOptions::default().with_line_width(2.0)),
+            Rect::new(0.0, 0.0, REGISTER_CELL_WIDTH, REGISTER_CELL_HEIGHT),
+            fg_color,
+        )?;
+
+        for (i, value) in registers.iter().enumerate() {
+            let row_len = per_row.min(registers.len() - i / per_row * per_row);
+            let row_width =
+                row_len as f32 * (REGISTER_CELL_WIDTH + REGISTER_MARGIN) - REGISTER_MARGIN;
+            let x = self.sizing.window.x / 2.0 - row_width / 2.0
+                + (i % per_row) as f32 * (REGISTER_CELL_WIDTH + REGISTER_MARGIN);
+            let y = top
+                + (i / per_row) as f32 * (REGISTER_CELL_HEIGHT + REGISTER_MARGIN * 2.0)
+                + REGISTER_MARGIN;
+
+            canvas.draw(&cell, [x, y]);
+
+            for (text, text_size, text_y) in [
+                (format!("r{i}"), 15.0, y - 18.0),
+                (
+                    format!("{value}"),
+                    30.0,
+                    y + REGISTER_CELL_HEIGHT / 2.0 - 15.0,
+                ),
+            ] {
+                let text_piece = graphics::Text::new(TextFragment {
+                    text,
+                    color: Some(fg_color),
+                    scale: Some(PxScale {
+                        x: text_size,
+                        y: text_size,
+                    }),
+                    font: None,
+                });
+                let text_width = text_piece.dimensions(ctx).unwrap().w;
+                canvas.draw(
+                    &text_piece,
+                    [x + REGISTER_CELL_WIDTH / 2.0 - text_width / 2.0, text_y],
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Where the timeline of a replay (or of the history of a machine) is drawn, between the
+    /// inputs and the stack.
+    fn timeline_rect(&self) -> Rect {
+        Rect::new(
+            TIMELINE_MARGIN,
+            self.sizing.window.y - 50.0,
+            (self.sizing.window.x - TIMELINE_MARGIN * 2.0).max(0.0),
+            30.0,
+        )
+    }
+
+    /// Draws the timeline of a replay or history: a bar filled up to the current tick, which can
+    /// be clicked to jump to another tick.
+    fn draw_timeline(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        (position, len): (usize, usize),
+        fg_color: Color,
+    ) -> GameResult {
+        let rect = self.timeline_rect();
+        let track = Rect::new(rect.x, rect.y + rect.h / 2.0 - 3.0, rect.w, 6.0);
+        let progress = if len == 0 {
+            1.0
+        } else {
+            position as f32 / len as f32
+        };
+
+        let track_mesh = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::Fill(FillOptions::default()),
+            track,
+            Color::new(0.3, 0.3, 0.3, 1.0),
+        )?;
+        canvas.draw(&track_mesh, [0.0, 0.0]);
+
+        if progress > 0.0 {
+            let progress_mesh = graphics::Mesh::new_rectangle(
+                ctx,
+                graphics::DrawMode::Fill(FillOptions::default()),
+                Rect::new(track.x, track.y, track.w * progress, track.h),
+                ACCENT_COLOR,
+            )?;
+            canvas.draw(&progress_mesh, [0.0, 0.0]);
+        }
+
+        let text_size = 17.0;
+        let text_piece = graphics::Text::new(TextFragment {
+            text: format!("Tick {position} of {len} (← → to step through it, space to pause)"),
+            color: Some(fg_color),
+            scale: Some(PxScale {
+                x: text_size,
+                y: text_size,
+            }),
+            font: None,
+        });
+        canvas.draw(&text_piece, [rect.x, rect.y - text_size - 5.0]);
+
+        Ok(())
+    }
+
+    /// Jumps to the configuration after `position` ticks of a replay or history, without
+    /// animating it.
+    fn seek(&mut self, position: usize) {
+        self.machine.seek(position);
+        self.show_current_configuration();
+    }
+
+    /// Shows the branch of the computation tree at `idx` from its start, and runs it from there.
+    fn follow_branch(&mut self, idx: usize) {
+        let Some(tree) = &self.tree else {
+            return;
+        };
+
+        let mut machine = tree.branches()[idx].machine().clone();
+        machine.enable_history();
+        self.machine = Box::new(machine);
+        self.followed_branch = Some(idx);
+        self.show_current_configuration();
+    }
+
+    /// Shows the current configuration of the machine without animating it, after it was
+    /// changed by something else than a tick.
+    fn show_current_configuration(&mut self) {
+        self.visual_heads = self.machine.heads();
+        self.visual_tapes = self.machine.tapes().to_vec();
+        self.head_trails = vec![VecDeque::new(); self.visual_tapes.len()];
+        self.visual_stack = self.machine.stack().map(<[char]>::to_vec);
+        self.rewind_samples();
+
+        self.writing_animation = None;
+        self.last_tick = None;
+        self.explanation = None;
+        self.formula = None;
+        self.caption = None;
+        self.cue_caption(true);
+        self.last_movement = None;
+        self.scan_steps = 0;
+        self.pacing = Pacing::Normal;
+        self.should_update = true;
+        if let Some(dump) = &mut self.frame_dump {
+            dump.pending = true;
+            dump.finished = false;
+        }
+        self.animation_state = Some(AnimationState {
+            animation: Animation::LastWait,
+            stage_begin: Instant::now(),
+            next_stage: Instant::now() + Duration::from_millis(SEEK_WAIT_DURATION_MS),
+        });
+    }
+
+    /// The tick the machine is at, in its timeline if it has one.
+    fn chart_step(&self) -> usize {
+        self.machine
+            .timeline()
+            .map_or(self.ticks, |(position, _)| position)
+    }
+
+    fn chart_sample(&self) -> ChartSample {
+        ChartSample {
+            step: self.chart_step(),
+            head_position: self.machine.head_idx() as isize - self.chart_origin,
+            tape_len: self.machine.tape().len(),
+        }
+    }
+
+    /// Adds the current configuration to the charts, if its tick is a multiple of the stride.
+    /// Past [`MAX_CHART_SAMPLES`], the stride is doubled and the samples off it are dropped, so
+    /// long runs are charted whole.
+    fn record_sample(&mut self) {
+        let sample = self.chart_sample();
+        if !sample.step.is_multiple_of(self.chart_stride) {
+            return;
+        }
+
+        self.chart_samples.push(sample);
+        if self.chart_samples.len() > MAX_CHART_SAMPLES {
+            self.chart_stride *= 2;
+            let stride = self.chart_stride;
+            self.chart_samples
+                .retain(|sample| sample.step.is_multiple_of(stride));
+        }
+    }
+
+    /// Drops the samples of the charts from the current tick on, after jumping to it, and adds
+    /// the current configuration.
+    fn rewind_samples(&mut self) {
+        if let Some(history) = self.machine.history() {
+            self.chart_origin = history.origin() as isize;
+        }
+
+        let sample = self.chart_sample();
+        self.chart_samples.retain(|kept| kept.step < sample.step);
+        self.chart_samples.push(sample);
+    }
+
+    /// Pauses or resumes the animation. The current stage of the animation goes on where it was
+    /// paused.
+    fn toggle_pause(&mut self) {
+        let Some(paused_at) = self.paused_at.take() else {
+            self.paused_at = Some(Instant::now());
+            return;
+        };
+
+        // The markers of the previous heads don't fade while paused
+        let paused_for = paused_at.elapsed();
+        for trail in &mut self.head_trails {
+            for (_, left_at) in trail {
+                *left_at += paused_for;
+            }
+        }
+
+        if let Some(animation_state) = &mut self.animation_state {
+            // The stage may have started while paused, after jumping to another tick
+            let now = Instant::now();
+            let elapsed = paused_at.saturating_duration_since(animation_state.stage_begin);
+            let remaining = animation_state
+                .next_stage
+                .saturating_duration_since(paused_at.max(animation_state.stage_begin));
+            animation_state.stage_begin = now - elapsed;
+            animation_state.next_stage = now + remaining;
+        }
+    }
+
+    /// Index on the tape of the cell under the mouse, if it is over the tape (negative or past
+    /// the end for the blank cells around it).
+    fn hovered_cell(&self) -> Option<isize> {
+        let mouse = self.mouse?;
+        let half_cell = self.sizing.cell_height / 2.0;
+        let center_y = self.tape_row_y(0);
+
+        if mouse.y < center_y - half_cell
+            || mouse.y > center_y + half_cell
+            || mouse.x < HORIZ_MARGIN
+            || mouse.x > self.sizing.window.x - HORIZ_MARGIN
+        {
+            return None;
+        }
+
+        let text_displacement = self.text_displacement(0);
+        let offset = ((mouse.x - self.sizing.window.x / 2.0) / self.sizing.cell_size
+            + text_displacement)
+            .round() as isize;
+
+        Some(self.visual_heads[0] as isize + offset)
+    }
+
+    /// Draws a tooltip next to the mouse with the position and symbol of the hovered cell and,
+    /// if the machine records its history, the last writes to it.
+    fn draw_cell_tooltip(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        bg_color: Color,
+        fg_color: Color,
+    ) -> GameResult {
+        let (Some(cell), Some(mouse)) = (self.hovered_cell(), self.mouse) else {
+            return Ok(());
+        };
+
+        let blank_symbol = self.machine.blank_symbol();
+        let symbol_char = |symbol: Symbol| match symbol {
+            Symbol::Mark(c) => c,
+            _ => blank_symbol,
+        };
+
+        let symbol = symbol_char(self.visual_tapes[0][cell]);
+
+        // Positions are counted from where the tape started, so they don't move when it grows
+        let history = self.machine.history();
+        let position = cell - history.map_or(0, |history| history.origin() as isize);
+
+        let mut lines = vec![format!("Cell {position}: {symbol}")];
+        if let Some(history) = history {
+            let writes = history.last_writes(position, TOOLTIP_WRITES);
+            if writes.is_empty() {
+                lines.push("Never written".to_string());
+            }
+            for (step, symbol) in writes {
+                lines.push(format!("Step {step}: wrote {}", symbol_char(symbol)));
+            }
+        }
+
+        let text_size = 15.0;
+        let padding = 8.0;
+        let text_piece = graphics::Text::new(TextFragment {
+            text: lines.join("\n"),
+            color: Some(fg_color),
+            scale: Some(PxScale {
+                x: text_size,
+                y: text_size,
+            }),
+            font: None,
+        });
+        let dimensions = text_piece.dimensions(ctx).unwrap();
+
+        let width = dimensions.w + padding * 2.0;
+        let height = dimensions.h + padding * 2.0;
+        let x = (mouse.x + 12.0).min(self.sizing.window.x - width);
+        let y = (mouse.y + 12.0).min(self.sizing.window.y - height);
+
+        let background = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::Fill(FillOptions::default()),
+            Rect::new(0.0, 0.0, width, height),
+            bg_color,
+        )?;
+        let border = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::Stroke(StrokeOptions::default().with_line_width(1.0)),
+            Rect::new(0.0, 0.0, width, height),
+            ACCENT_COLOR,
+        )?;
+        canvas.draw(&background, [x, y]);
+        canvas.draw(&border, [x, y]);
+        canvas.draw(&text_piece, [x + padding, y + padding]);
+
+        Ok(())
+    }
+
+    /// Where the computation tree is drawn, on the top right corner above the tape.
+    fn tree_rect(&self) -> Rect {
+        Rect::new(self.sizing.window.x - 340.0, 145.0, 320.0, 160.0)
+    }
+
+    /// Tells in the window title that the machine halted, and if the window is in the background,
+    /// asks for attention (e.g. flashing it in the taskbar) and shows a desktop notification, so
+    /// slow machines can be left running. Puts the title back if the machine is running again.
+    fn announce_halt(&mut self, ctx: &mut Context) {
+        self.halt_announced = self.machine.is_halted();
+        if !self.halt_announced {
+            ctx.gfx.set_window_title(WINDOW_TITLE);
+            return;
+        }
+
+        let verdict = if self.machine.is_accepting() {
+            "Halted, accepts"
+        } else {
+            "Halted, rejects"
+        };
+        ctx.gfx.set_window_title(&format!(
+            "{verdict} - {} - {WINDOW_TITLE}",
+            self.machine.name()
+        ));
+
+        if !self.focused {
+            ctx.gfx
+                .window()
+                .request_user_attention(Some(UserAttentionType::Informational));
+            notification::send(
+                &format!("\"{}\" {}", self.machine.name(), verdict.to_lowercase()),
+                &format!("In state \"{}\"", self.machine.current_state_name()),
+            );
+        }
+    }
+
+    /// Draws everything shown in the window on the canvas.
+    fn draw_frame(&mut self, ctx: &mut Context, canvas: &mut graphics::Canvas) -> GameResult {
+        let (bg_color, fg_color) = self.get_colors();
+
+        if let Some(registers) = self.machine.registers() {
+            self.draw_registers(ctx, canvas, registers, fg_color)?;
+        } else {
+            self.fit_cells(ctx);
+            self.draw_tapes(ctx, canvas, bg_color, fg_color)?;
+        }
+
+        if self.is_finished() {
+            let (text_content, text_color) = if !self.machine.is_halted() {
+                ("End of the replay", fg_color)
+            } else if self.machine.is_accepting() {
+                ("Halted, accepts", self.accept_color())
+            } else {
+                ("Halted, rejects", REJECT_COLOR)
+            };
+
+            self.animation_state = None;
+            let horiz_text_margin = 20.0;
+            let vert_text_margin = 75.0;
+
+            let text_size = 20.0;
+            let text_piece = graphics::Text::new(TextFragment {
+                text: text_content.to_string(),
+                color: Some(text_color),
+                scale: Some(PxScale {
+                    x: text_size,
+                    y: text_size,
+                }),
+                font: None,
+            });
+            canvas.draw(&text_piece, [horiz_text_margin, vert_text_margin]);
+        }
+
+        {
+            let text_margins = 20.0;
+            let text_size = 25.0;
+            let text_piece = graphics::Text::new(TextFragment {
+                text: format!("Running: \"{}\"", self.machine.name()),
+                color: Some(fg_color),
+                scale: Some(PxScale {
+                    x: text_size,
+                    y: text_size,
+                }),
+                font: None,
+            });
+            canvas.draw(&text_piece, [text_margins, text_margins]);
+        }
+
+        {
+            let text_margins = 20.0;
+            let text_size = 15.0;
+            let text_piece = graphics::Text::new(TextFragment {
+                text: match self.machine.state_description() {
+                    Some(description) => format!(
+                        "Current state: \"{}\" ({description})",
+                        self.machine.current_state_name()
+                    ),
+                    None => format!("Current state: \"{}\"", self.machine.current_state_name()),
+                },
+                color: Some(fg_color),
+                scale: Some(PxScale {
+                    x: text_size,
+                    y: text_size,
+                }),
+                font: None,
+            });
+            canvas.draw(&text_piece, [text_margins, text_margins + 30.0]);
+        }
+
+        if self.paused_at.is_some() && !self.is_finished() {
+            let text_margins = 20.0;
+            let text_size = 15.0;
+            let text_piece = graphics::Text::new(TextFragment {
+                text: "Paused (hover a cell to inspect it, space to resume)".to_string(),
+                color: Some(ACCENT_COLOR),
+                scale: Some(PxScale {
+                    x: text_size,
+                    y: text_size,
+                }),
+                font: None,
+            });
+            canvas.draw(&text_piece, [text_margins, text_margins + 55.0]);
+        }
+
+        let formula = self.formula.as_ref().filter(|_| self.formal_notation);
+        if let Some(formula) = formula {
+            self.draw_formula(ctx, canvas, formula)?;
+        } else if let Some(explanation) = &self.explanation {
+            let text_margins = 20.0;
+            let text_size = 15.0;
+            let text_piece = graphics::Text::new(TextFragment {
+                text: explanation.clone(),
+                color: Some(fg_color),
+                scale: Some(PxScale {
+                    x: text_size,
+                    y: text_size,
+                }),
+                font: None,
+            });
+            canvas.draw(&text_piece, [text_margins, text_margins + 85.0]);
+        }
+
+        if let Some(caption) = &self.caption {
+            self.draw_caption(canvas, caption, fg_color);
+        }
+
+        if let Some(stack) = &self.visual_stack {
+            self.draw_stack(ctx, canvas, stack, fg_color)?;
+        }
+
+        if let Some(timeline) = self.machine.timeline() {
+            self.draw_timeline(ctx, canvas, timeline, fg_color)?;
+        }
+
+        if self.show_charts {
+            self.draw_charts(ctx, canvas, fg_color)?;
+        } else if let Some(tree) = &self.tree {
+            self.draw_tree(ctx, canvas, tree, fg_color)?;
+        }
+
+        if let (Some(diagram), true) = (&self.diagram, self.show_diagram) {
+            self.draw_diagram(ctx, canvas, diagram, bg_color, fg_color)?;
+        }
+
+        self.cells_input.draw(ctx, canvas).unwrap();
+        self.speed_input.draw(ctx, canvas).unwrap();
+
+        if self.machine.registers().is_none() && (self.paused_at.is_some() || self.is_finished()) {
+            self.draw_cell_tooltip(ctx, canvas, bg_color, fg_color)?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the current frame as a PNG file in the directory of the frame dump, drawn the same
+    /// as in the window.
+    fn dump_frame(&mut self, ctx: &mut Context) -> GameResult {
+        let (width, height) = ctx.gfx.drawable_size();
+        let (width, height) = (width as u32, height as u32);
+        let (bg_color, _) = self.get_colors();
+
+        let frame = graphics::Image::new_canvas_image(
+            ctx,
+            graphics::ImageFormat::Rgba8UnormSrgb,
+            width,
+            height,
+            1,
+        );
+        let mut canvas = graphics::Canvas::from_image(ctx, frame.clone(), bg_color);
+        canvas.set_screen_coordinates(Rect::new(
+            0.0,
+            0.0,
+            self.sizing.window.x,
+            self.sizing.window.y,
+        ));
+        self.draw_frame(ctx, &mut canvas)?;
+        canvas.finish(ctx)?;
+        let pixels = frame.to_pixels(ctx)?;
+        let finished = self.is_finished();
+
+        let Some(dump) = &mut self.frame_dump else {
+            return Ok(());
+        };
+        let path = dump.dir.join(format!("frame_{:05}.png", dump.frames));
+        image::save_buffer(&path, &pixels, width, height, image::ColorType::Rgba8).map_err(
+            |err| {
+                GameError::CustomError(format!(
+                    "Error while saving the frame \"{}\". {err}.",
+                    path.display()
+                ))
+            },
+        )?;
+        dump.frames += 1;
+        dump.pending = false;
+        dump.finished = finished;
+
+        Ok(())
+    }
+
+    /// How many times longer than the shortest one every stage of the animation lasts, for the
+    /// chosen speed. The auto speed animates the steps at the default speed, but goes faster and
+    /// faster through long scans, where the machine doesn't write anything.
+    fn speed_multiplier(&self) -> f32 {
+        let multiplier = |speed: i16| (1.0 - speed as f32 / MAX_SPEED as f32) * 4.0 + 1.0;
+
+        if self.speed_input.is_max() {
+            let acceleration = (self.scan_steps as f32 + 1.0).min(MAX_AUTO_ACCELERATION);
+            multiplier(DEFAULT_SPEED) / acceleration
+        } else {
+            multiplier(self.speed_input.value())
+        }
+    }
+
+    /// Shows the caption of the lesson cued at the current step, if any. `entered_state` tells
+    /// whether the machine has just entered its current state.
+    fn cue_caption(&mut self, entered_state: bool) {
+        let Some(lesson) = &self.lesson else {
+            return;
+        };
+
+        let steps = self
+            .machine
+            .timeline()
+            .map_or(self.ticks, |(position, _)| position);
+        let caption = lesson.caption(
+            steps,
+            self.machine.current_state_name(),
+            entered_state,
+            self.machine.is_halted(),
+        );
+        if let Some(caption) = caption {
+            self.caption = Some(caption.to_string());
+        }
+    }
+
+    /// Draws the caption of the lesson centered under the tape, wrapped to the width of the tape.
+    fn draw_caption(&self, canvas: &mut graphics::Canvas, caption: &str, fg_color: Color) {
+        let text_size = 20.0;
+        let mut text_piece = graphics::Text::new(TextFragment {
+            text: caption.to_string(),
+            color: Some(fg_color),
+            scale: Some(PxScale {
+                x: text_size,
+                y: text_size,
+            }),
+            font: None,
+        });
+        text_piece
+            .set_bounds([self.sizing.window.x - HORIZ_MARGIN * 2.0, f32::INFINITY])
+            .set_layout(TextLayout {
+                h_align: TextAlign::Middle,
+                v_align: TextAlign::Begin,
+            });
+
+        let last_row = self.visual_tapes.len().saturating_sub(1);
+        canvas.draw(
+            &text_piece,
+            [
+                self.sizing.window.x / 2.0,
+                self.tape_row_y(last_row)
+                    + self.sizing.cell_height / 2.0
+                    + self.sizing.head_triangle_margin * 2.0
+                    + self.sizing.head_triangle.y
+                    + 20.0,
+            ],
+        );
+    }
+
+    /// Draws the last step in the notation of the transition function, centered above the tape.
+    fn draw_formula(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        formula: &str,
+    ) -> GameResult {
+        let text_size = 30.0;
+        let text_piece = graphics::Text::new(TextFragment {
+            text: formula.to_string(),
+            color: Some(ACCENT_COLOR),
+            scale: Some(PxScale {
+                x: text_size,
+                y: text_size,
+            }),
+            font: None,
+        });
+        let Rect { w, h, .. } = text_piece.dimensions(ctx).unwrap();
+        canvas.draw(
+            &text_piece,
+            [
+                self.sizing.window.x / 2.0 - w / 2.0,
+                self.tape_row_y(0) - self.sizing.cell_height / 2.0 - h - 30.0,
+            ],
+        );
+
+        Ok(())
+    }
+
+    /// Where the state diagram is drawn, on the top left corner (opposite to the computation
+    /// tree).
+    fn diagram_rect(&self) -> Rect {
+        Rect::new(20.0, 145.0, 320.0, 160.0)
+    }
+
+    /// Where the state at `idx` is drawn, keeping room for its circle on every side.
+    fn diagram_point(&self, diagram: &StateDiagram, idx: usize) -> Point2<f32> {
+        let rect = self.diagram_rect();
+        let (x, y) = diagram.positions()[idx];
+        [
+            rect.x + DIAGRAM_STATE_RADIUS + x as f32 * (rect.w - DIAGRAM_STATE_RADIUS * 2.0),
+            rect.y + DIAGRAM_STATE_RADIUS + y as f32 * (rect.h - DIAGRAM_STATE_RADIUS * 2.0),
+        ]
+        .into()
+    }
+
+    /// Moves a dragged state of the diagram under the mouse.
+    fn drag_state(&mut self, idx: usize, x: f32, y: f32) {
+        let rect = self.diagram_rect();
+        if let Some(diagram) = &mut self.diagram {
+            let position = (
+                (x - rect.x - DIAGRAM_STATE_RADIUS) / (rect.w - DIAGRAM_STATE_RADIUS * 2.0),
+                (y - rect.y - DIAGRAM_STATE_RADIUS) / (rect.h - DIAGRAM_STATE_RADIUS * 2.0),
+            );
+            diagram.set_position(idx, (position.0 as f64, position.1 as f64));
+        }
+    }
+
+    /// Draws the state diagram: every state as a circle (the current one filled, the final ones
+    /// doubled) and an arrow for every pair of states with transitions between them.
+    fn draw_diagram(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        diagram: &StateDiagram,
+        bg_color: Color,
+        fg_color: Color,
+    ) -> GameResult {
+        let radius = DIAGRAM_STATE_RADIUS;
+        let mut builder = graphics::MeshBuilder::new();
+
+        for &(from, to) in diagram.edges() {
+            let from_point = self.diagram_point(diagram, from);
+            if from == to {
+                // A loop on top of the state
+                builder.circle(
+                    graphics::DrawMode::stroke(1.0),
+                    [from_point.x, from_point.y - radius],
+                    radius * 0.6,
+                    0.1,
+                    fg_color,
+                )?;
+                continue;
+            }
+
+            let to_point = self.diagram_point(diagram, to);
+            let (dx, dy) = (to_point.x - from_point.x, to_point.y - from_point.y);
+            let length = (dx * dx + dy * dy).sqrt();
+            if length <= radius * 2.0 {
+                continue;
+            }
+            let (ux, uy) = (dx / length, dy / length);
+            let start = [from_point.x + ux * radius, from_point.y + uy * radius];
+            let end = [to_point.x - ux * radius, to_point.y - uy * radius];
+            builder.line(&[start, end], 1.0, fg_color)?;
+
+            let (tip_x, tip_y) = (end[0], end[1]);
+            builder.polygon(
+                graphics::DrawMode::fill(),
+                &[
+                    [tip_x, tip_y],
+                    [tip_x - ux * 7.0 - uy * 3.5, tip_y - uy * 7.0 + ux * 3.5],
+                    [tip_x - ux * 7.0 + uy * 3.5, tip_y - uy * 7.0 - ux * 3.5],
+                ],
+                fg_color,
+            )?;
+        }
+
+        let current_state = diagram.state_idx(self.machine.current_state_name());
+        for idx in 0..diagram.states().len() {
+            let point = self.diagram_point(diagram, idx);
+            let fill = if Some(idx) == current_state {
+                ACCENT_COLOR
+            } else {
+                bg_color
+            };
+            builder.circle(graphics::DrawMode::fill(), point, radius, 0.1, fill)?;
+            builder.circle(
+                graphics::DrawMode::stroke(1.5),
+                point,
+                radius,
+                0.1,
+                fg_color,
+            )?;
+            if diagram.is_final(idx) {
+                builder.circle(
+                    graphics::DrawMode::stroke(1.0),
+                    point,
+                    radius - 3.0,
+                    0.1,
+                    fg_color,
+                )?;
+            }
+        }
+        canvas.draw(&graphics::Mesh::from_data(ctx, builder.build()), [0.0, 0.0]);
+
+        for (idx, name) in diagram.states().iter().enumerate() {
+            let point = self.diagram_point(diagram, idx);
+            let text_size = 11.0;
+            let text_piece = graphics::Text::new(TextFragment {
+                text: name.clone(),
+                color: Some(fg_color),
+                scale: Some(PxScale {
+                    x: text_size,
+                    y: text_size,
+                }),
+                font: None,
+            });
+            let Rect { w, h, .. } = text_piece.dimensions(ctx).unwrap();
+            canvas.draw(&text_piece, [point.x - w / 2.0, point.y - h / 2.0]);
+        }
+
+        let rect = self.diagram_rect();
+        let text_size = 15.0;
+        let text_piece = graphics::Text::new(TextFragment {
+            text: "State diagram (drag the states to move them, D to hide it)".to_string(),
+            color: Some(fg_color),
+            scale: Some(PxScale {
+                x: text_size,
+                y: text_size,
+            }),
+            font: None,
+        });
+        canvas.draw(&text_piece, [rect.x, rect.y - text_size - 10.0]);
+
+        Ok(())
+    }
+
+    /// Draws the charts of the position of the head and the length of the tape over the ticks
+    /// shown so far, one above the other where the computation tree goes, with their current
+    /// values.
+    fn draw_charts(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        fg_color: Color,
+    ) -> GameResult {
+        let rect = self.tree_rect();
+        let current = self.chart_sample();
+        let mut samples = self.chart_samples.clone();
+        if samples.last().is_none_or(|last| last.step < current.step) {
+            samples.push(current);
+        }
+
+        let charts = [
+            (
+                "Head position",
+                current.head_position as f32,
+                samples
+                    .iter()
+                    .map(|sample| sample.head_position as f32)
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "Tape length",
+                current.tape_len as f32,
+                samples
+                    .iter()
+                    .map(|sample| sample.tape_len as f32)
+                    .collect(),
+            ),
+        ];
+
+        let text_size = 13.0;
+        let chart_height = rect.h / 2.0 - text_size - 10.0;
+        let first_step = samples.first().map_or(0, |sample| sample.step);
+        let steps = (current.step.max(first_step) - first_step).max(1) as f32;
+
+        let mut builder = graphics::MeshBuilder::new();
+        for (row, (title, current_value, values)) in charts.into_iter().enumerate() {
+            let top = rect.y + row as f32 * rect.h / 2.0;
+            let area = Rect::new(rect.x, top + text_size + 4.0, rect.w, chart_height);
+
+            let text_piece = graphics::Text::new(TextFragment {
+                text: format!("{title}: {current_value}"),
+                color: Some(fg_color),
+                scale: Some(PxScale {
+                    x: text_size,
+                    y: text_size,
+                }),
+                font: None,
+            });
+            canvas.draw(&text_piece, [area.x, top]);
+
+            builder.rectangle(
+                graphics::DrawMode::stroke(1.0),
+                area,
+                Color::new(0.5, 0.5, 0.5, 0.5),
+            )?;
+
+            let (min, max) = values
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(min, max), value| {
+                    (min.min(*value), max.max(*value))
+                });
+            let range = (max - min).max(1.0);
+            let mut points = samples
+                .iter()
+                .zip(&values)
+                .map(|(sample, value)| {
+                    [
+                        area.x + (sample.step - first_step) as f32 / steps * area.w,
+                        area.bottom() - 2.0 - (value - min) / range * (area.h - 4.0),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            points.dedup();
+            if points.len() >= 2 {
+                builder.line(&points, 1.5, ACCENT_COLOR)?;
+            }
+        }
+        canvas.draw(&graphics::Mesh::from_data(ctx, builder.build()), [0.0, 0.0]);
+
+        let text_size = 15.0;
+        let text_piece = graphics::Text::new(TextFragment {
+            text: "Charts (C to hide them)".to_string(),
+            color: Some(fg_color),
+            scale: Some(PxScale {
+                x: text_size,
+                y: text_size,
+            }),
+            font: None,
+        });
+        canvas.draw(&text_piece, [rect.x, rect.y - text_size - 10.0]);
+
+        Ok(())
+    }
+
+    /// Radius of the branches of the computation tree, smaller when there are many leaves.
+    fn tree_node_radius(&self) -> f32 {
+        let leaves = self.tree.as_ref().map_or(1, |tree| {
+            tree.branches()
+                .iter()
+                .filter(|branch| branch.children().is_empty())
+                .count()
+        });
+        (self.tree_rect().w / leaves.max(1) as f32 / 3.0).clamp(2.0, 6.0)
+    }
+
+    /// Draws the computation tree of a machine with random transitions: every branch as a dot,
+    /// colored by how it ends, with the followed one circled.
+    fn draw_tree(
+        &self,
+        ctx: &mut Context,
+        canvas: &mut graphics::Canvas,
+        tree: &ComputationTree,
+        fg_color: Color,
+    ) -> GameResult {
+        let rect = self.tree_rect();
+        let radius = self.tree_node_radius();
+        let point = |idx: usize| -> Point2<f32> {
+            let position = self.tree_layout[idx];
+            [rect.x + position.x * rect.w, rect.y + position.y * rect.h].into()
+        };
+
+        let mut builder = graphics::MeshBuilder::new();
+        for (idx, branch) in tree.branches().iter().enumerate() {
+            if let Some(parent) = branch.parent() {
+                builder.line(&[point(parent), point(idx)], 1.0, fg_color)?;
+            }
+        }
+        for (idx, branch) in tree.branches().iter().enumerate() {
+            let color = match branch.end() {
+                BranchEnd::Accepted => self.accept_color(),
+                BranchEnd::Rejected => REJECT_COLOR,
+                BranchEnd::Split => fg_color,
+                BranchEnd::Pending => Color::new(0.6, 0.6, 0.6, 1.0),
+            };
+            builder.circle(graphics::DrawMode::fill(), point(idx), radius, 0.1, color)?;
+        }
+        if let Some(followed) = self.followed_branch {
+            builder.circle(
+                graphics::DrawMode::stroke(2.0),
+                point(followed),
+                radius + 3.0,
+                0.1,
+                ACCENT_COLOR,
+            )?;
+        }
+        canvas.draw(&graphics::Mesh::from_data(ctx, builder.build()), [0.0, 0.0]);
+
+        let text_size = 15.0;
+        let text_piece = graphics::Text::new(TextFragment {
+            text: format!(
+                "Computation tree: {} accept, {} reject, {} pending (click one to follow it)",
+                tree.count(BranchEnd::Accepted),
+                tree.count(BranchEnd::Rejected),
+                tree.count(BranchEnd::Pending)
+            ),
+            color: Some(fg_color),
+            scale: Some(PxScale {
+                x: text_size,
+                y: text_size,
+            }),
+            font: None,
+        });
+        let text_width = text_piece.dimensions(ctx).unwrap().w;
+        canvas.draw(
+            &text_piece,
+            [
+                (rect.x + rect.w - text_width).max(0.0),
+                rect.y - text_size - 10.0,
+            ],
+        );
+
+        Ok(())
+    }
+
+    fn accept_color(&self) -> Color {
+        if self.light_theme {
+            Color::from([0.0, 0.6, 0.0, 1.0])
+        } else {
+            Color::from_rgb(148, 250, 54)
+        }
+    }
+
+    /// Whether there is nothing left to show: the machine halted, or the replay ended and its
+    /// last tick was animated. A tick at the end of a replay does nothing, while a machine that
+    /// records its history goes on and makes its timeline longer.
+    fn is_finished(&self) -> bool {
+        self.machine.is_halted()
+            || (self.should_update
+                && self
+                    .machine
+                    .timeline()
+                    .is_some_and(|(position, len)| position == len)
+                && self
+                    .last_tick
+                    .as_ref()
+                    .is_some_and(|tick_result| tick_result.step.is_none()))
+    }
+
+    pub fn get_colors(&self) -> (Color, Color) {
+        let bg_color = if self.light_theme {
+            Color::WHITE
+        } else {
+            Color::from_rgb(22, 23, 25)
+        };
+        let fg_color = if self.light_theme {
+            Color::from_rgb(68, 68, 68)
+        } else {
+            Color::from_rgb(224, 224, 224)
+        };
+        (bg_color, fg_color)
+    }
+}
+
+impl event::EventHandler<ggez::GameError> for Viewer {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        // Also when going back in the history of a halted machine, to take the halt back
+        if self.machine.is_halted() != self.halt_announced {
+            self.announce_halt(ctx);
+        }
+
+        if self.is_finished() || self.paused_at.is_some() {
+            return Ok(());
+        }
+
+        let speed_multiplier = self.speed_multiplier() * self.pacing.duration_factor();
+        if let Some(ref mut animation_state) = self.animation_state {
+            if Instant::now() >= animation_state.next_stage {
+                let (new_animation, animation_duration) = match animation_state.animation {
+                    Animation::FirstWait => {
+                        self.writing_animation = None;
+
+                        // Cells added on the left are already in the visual tape, see below
+                        let deltas = self
+                            .machine
+                            .heads()
+                            .iter()
+                            .zip(&self.visual_heads)
+                            .map(|(head, visual_head)| match self.last_tick {
+                                Some(_) => *head as f32 - *visual_head as f32,
+                                None => 0.0,
+                            })
+                            .collect::<Vec<_>>();
+                        // Jumps of several cells take proportionally longer
+                        let cells_moved = deltas
+                            .iter()
+                            .fold(1.0_f32, |moved, delta| moved.max(delta.abs()));
+                        (
+                            Animation::HeadMove {
+                                deltas,
+                                progress: 0.0,
+                            },
+                            Duration::from_millis(
+                                (HEAD_MOVE_DURATION_MS as f32 * speed_multiplier * cells_moved)
+                                    as u64,
+                            ),
+                        )
+                    }
+                    Animation::HeadMove { .. } => {
+                        let heads = self.machine.heads();
+                        for ((trail, visual_head), head) in self
+                            .head_trails
+                            .iter_mut()
+                            .zip(&self.visual_heads)
+                            .zip(&heads)
+                        {
+                            if visual_head != head {
+                                trail.push_front((*visual_head, Instant::now()));
+                                trail.truncate(GHOST_COUNT);
+                            }
+                        }
+                        self.visual_heads = heads;
+                        self.should_update = true;
+                        if let Some(dump) = &mut self.frame_dump {
+                            dump.pending = !dump.finished;
+                        }
+                        (
+                            Animation::LastWait,
+                            Duration::from_millis(
+                                (LAST_WAIT_DURATION_MS as f32 * speed_multiplier) as u64,
+                            ),
+                        )
+                    }
+                    Animation::LastWait => {
+                        self.visual_tapes = self.machine.tapes().to_vec();
+                        self.visual_stack = self.machine.stack().map(<[char]>::to_vec);
+                        (
+                            Animation::FirstWait,
+                            Duration::from_millis(
+                                (FIRST_WAIT_DURATION_MS as f32 * speed_multiplier) as u64,
+                            ),
+                        )
+                    }
+                };
+
+                *animation_state = AnimationState {
+                    animation: new_animation,
+                    stage_begin: Instant::now(),
+                    next_stage: Instant::now() + animation_duration,
+                };
+            }
+        }
+
+        if let Some(ref mut animation_state) = &mut self.animation_state {
+            let total_duration = animation_state.next_stage - animation_state.stage_begin;
+            let duration_since_begin = Instant::now() - animation_state.stage_begin;
+
+            let percent = duration_since_begin.as_millis() * 100 / total_duration.as_millis();
+
+            if let Animation::HeadMove {
+                ref mut progress, ..
+            } = &mut animation_state.animation
+            {
+                *progress = percent as f32 / 100.0;
+            } else if let Some(ref mut alpha) = self.writing_animation {
+                let percent = (percent * 2).min(100); // Speed up opacity transition by 2
+
+                let new_alpha = percent as f32 * WRITE_ANIM_MAX_ALPHA / 100.0;
+
+                if let Animation::LastWait = animation_state.animation {
+                    *alpha = new_alpha;
+                } else {
+                    *alpha = 1.0 - new_alpha;
+                }
+            }
+        }
+
+        // Update machine, once the configuration shown was written if frames are dumped
+        if !self.should_update || self.frame_dump.as_ref().is_some_and(|dump| dump.pending) {
+            return Ok(());
+        }
+
+        let prev_state = self.machine.current_state_name().to_string();
+        let was_halted = self.machine.is_halted();
+        if !was_halted {
+            self.ticks += 1;
+        }
+        let prev_tapes = self.machine.tapes().to_vec();
+        let prev_heads = self.machine.heads();
+        let prev_tape_len = self.machine.tape().len();
+        let tick_result = self.machine.tick();
+        let heads = self.machine.heads();
+
+        self.written_tapes = vec![false; prev_tapes.len()];
+        for (row, prev_tape) in prev_tapes.into_iter().enumerate() {
+            let tape = &self.machine.tapes()[row];
+            let added_cells = tape.len().saturating_sub(prev_tape.len());
+
+            // Only the first tape is reported by the tick, the others can only grow on the left
+            // by one blank with their head staying on the first cell
+            let extended_left = match row {
+                0 => tick_result.extended_tape_on_side == Some(TapeSide::Left),
+                _ => added_cells > 0 && heads[row] == 0,
+            };
+            let shift = if extended_left { added_cells } else { 0 };
+
+            self.written_tapes[row] = match row {
+                0 => tick_result.written_different_symbol,
+                _ => tape.read(prev_heads[row] + shift) != prev_tape.read(prev_heads[row]),
+            };
+
+            if extended_left {
+                let mut prev_tape_content = prev_tape.get_content().to_vec();
+                prev_tape_content.splice(0..0, vec![Symbol::Blank; added_cells]);
+                self.visual_tapes[row] = Tape::new(prev_tape_content);
+                self.visual_heads[row] += added_cells;
+                for (cell, _) in &mut self.head_trails[row] {
+                    *cell += added_cells;
+                }
+            }
+        }
+
+        if !was_halted {
+            if tick_result.extended_tape_on_side == Some(TapeSide::Left) {
+                self.chart_origin += (self.machine.tape().len() - prev_tape_len) as isize;
+            }
+            self.record_sample();
+        }
+
+        if self.written_tapes.contains(&true) {
+            self.writing_animation = Some(0.0);
+        } else {
+            self.writing_animation = None;
+        }
+        self.should_update = false;
+        self.explanation = explain::explain(&tick_result);
+        self.formula = explain::formula(&tick_result, self.machine.blank_symbol());
+        let entered_state = prev_state != self.machine.current_state_name();
+        self.cue_caption(entered_state);
+
+        // A scan goes on while the head moves the same way in the same state without writing
+        let scans_on = !tick_result.written_different_symbol
+            && !entered_state
+            && tick_result.head_movement != HeadMovement::Stay
+            && self.last_movement == Some(tick_result.head_movement);
+        self.scan_steps = if scans_on { self.scan_steps + 1 } else { 0 };
+        self.last_movement = Some(tick_result.head_movement);
+        self.pacing = match &tick_result.step {
+            Some(Step::Transition { pacing, .. }) => *pacing,
+            _ => Pacing::Normal,
+        };
+        self.last_tick = Some(tick_result);
+
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let (bg_color, _) = self.get_colors();
+        let mut canvas = graphics::Canvas::from_frame(ctx, bg_color);
+        self.draw_frame(ctx, &mut canvas)?;
+        canvas.finish(ctx)?;
+
+        if self.frame_dump.as_ref().is_some_and(|dump| dump.pending) {
+            self.dump_frame(ctx)?;
+        }
+
+        Ok(())
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        _button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult {
+        if self.cells_input.handle_mouse_click(x, y) {
+            self.sizing = Sizing::calculate(
+                self.sizing.window.x,
+                self.sizing.window.y,
+                self.cells_input.value() as usize,
+                self.sizing.symbol_aspect,
+            );
+        }
+
+        self.speed_input.handle_mouse_click(x, y);
+
+        if let Some((_, len)) = self.machine.timeline() {
+            let rect = self.timeline_rect();
+            if rect.contains([x, y]) && rect.w > 0.0 {
+                let progress = (x - rect.x) / rect.w;
+                self.seek((progress * len as f32).round() as usize);
+            }
+        }
+
+        if self.tree.is_some() && !self.show_charts {
+            let rect = self.tree_rect();
+            let reach = self.tree_node_radius() + 4.0;
+            let clicked = self.tree_layout.iter().position(|position| {
+                let dx = rect.x + position.x * rect.w - x;
+                let dy = rect.y + position.y * rect.h - y;
+                dx * dx + dy * dy <= reach * reach
+            });
+            if let Some(idx) = clicked {
+                self.follow_branch(idx);
+            }
+        }
+
+        if let (Some(diagram), true) = (&self.diagram, self.show_diagram) {
+            self.dragged_state = (0..diagram.states().len()).find(|idx| {
+                let point = self.diagram_point(diagram, *idx);
+                let (dx, dy) = (point.x - x, point.y - y);
+                dx * dx + dy * dy <= DIAGRAM_STATE_RADIUS * DIAGRAM_STATE_RADIUS
+            });
+        }
+
+        Ok(())
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        _button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) -> GameResult {
+        if self.dragged_state.take().is_none() {
+            return Ok(());
+        }
+
+        if let (Some(diagram), Some(path)) = (&self.diagram, &self.diagram_positions) {
+            if let Err(err) = diagram.save_positions(path) {
+                self.explanation = Some(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        input: KeyInput,
+        _repeated: bool,
+    ) -> GameResult {
+        let position = self.machine.timeline().map(|(position, _)| position);
+
+        match (input.keycode, position) {
+            (Some(KeyCode::Left), Some(position)) => self.seek(position.saturating_sub(1)),
+            (Some(KeyCode::Right), Some(position)) => self.seek(position + 1),
+            (Some(KeyCode::Space), _) => self.toggle_pause(),
+            (Some(KeyCode::F), _) => self.formal_notation = !self.formal_notation,
+            (Some(KeyCode::D), _) => self.show_diagram = !self.show_diagram,
+            (Some(KeyCode::C), _) => self.show_charts = !self.show_charts,
+            (Some(KeyCode::Escape), _) => ctx.request_quit(),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        ctx: &mut Context,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> Result<(), ggez::GameError> {
+        self.mouse = Some([x, y].into());
+
+        if let Some(idx) = self.dragged_state {
+            self.drag_state(idx, x, y);
+        }
+
+        set_cursor_type(
+            ctx,
+            if self.cells_input.is_mouse_over_any_button(x, y)
+                || self.speed_input.is_mouse_over_any_button(x, y)
+            {
+                CursorIcon::Hand
+            } else {
+                CursorIcon::Default
+            },
+        );
+
+        Ok(())
+    }
+
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) -> GameResult {
+        self.focused = gained;
+        Ok(())
+    }
+
+    fn resize_event(
+        &mut self,
+        _ctx: &mut Context,
+        width: f32,
+        height: f32,
+    ) -> Result<(), ggez::GameError> {
+        self.sizing = Sizing::calculate(
+            width,
+            height,
+            self.cells_input.value() as usize,
+            self.sizing.symbol_aspect,
+        );
+
+        let mut new_rect = self.cells_input.rect();
+        new_rect.y = height - 120.0;
+        self.cells_input.set_rect(new_rect);
+
+        let mut new_rect = self.speed_input.rect();
+        new_rect.y = height - 50.0;
+        self.speed_input.set_rect(new_rect);
+
+        Ok(())
+    }
+}
+
+/// Positions of the branches of a computation tree in a unit square: by depth from top to
+/// bottom, with the leaves spread evenly from left to right and every other branch centered
+/// above its children.
+fn tree_layout(tree: &ComputationTree) -> Vec<Point2<f32>> {
+    fn place(
+        tree: &ComputationTree,
+        idx: usize,
+        leaves: &mut usize,
+        positions: &mut [(f32, usize)],
+    ) -> f32 {
+        let children = tree.branches()[idx].children();
+        let x = if children.is_empty() {
+            *leaves += 1;
+            *leaves as f32 - 0.5
+        } else {
+            let xs = children
+                .iter()
+                .map(|child| place(tree, *child, leaves, positions))
+                .collect::<Vec<_>>();
+            (xs[0] + xs[xs.len() - 1]) / 2.0
+        };
+        positions[idx] = (x, tree.branches()[idx].depth());
+        x
+    }
+
+    let mut positions = vec![(0.0, 0); tree.branches().len()];
+    let mut leaves = 0;
+    place(tree, 0, &mut leaves, &mut positions);
+
+    let max_depth = positions.iter().map(|(_, depth)| *depth).max().unwrap_or(0);
+    positions
+        .into_iter()
+        .map(|(x, depth)| {
+            [
+                x / leaves as f32,
+                if max_depth == 0 {
+                    0.5
+                } else {
+                    depth as f32 / max_depth as f32
+                },
+            ]
+            .into()
+        })
+        .collect()
+}
+
+/// The window of the simulator, which loads its icon from the `resources` directory of the crate
+/// being run (or of the working directory, outside of cargo).
+pub fn context_builder() -> ggez::ContextBuilder {
+    let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
+        let mut path = path::PathBuf::from(manifest_dir);
+        path.push("resources");
+        path
+    } else {
+        path::PathBuf::from("./resources")
+    };
+
+    ggez::ContextBuilder::new("Turing Machine Simulator", "keelus")
+        .add_resource_path(resource_dir)
+        .window_mode(
+            ggez::conf::WindowMode::default()
+                .dimensions(WINDOW_WIDTH, WINDOW_HEIGHT)
+                .min_dimensions(400.0, 600.0)
+                .resizable(true),
+        )
+        .window_setup(
+            ggez::conf::WindowSetup::default()
+                .title(WINDOW_TITLE)
+                .icon("/icon.png"),
+        )
+}
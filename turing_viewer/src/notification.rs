@@ -0,0 +1,39 @@
+use std::process::{Command, Stdio};
+
+/// Shows a desktop notification with the command of the platform (`notify-send` on Linux and
+/// the BSDs, `osascript` on macOS), without waiting for it. Nothing is shown where there is no
+/// such command, as the window also asks for attention.
+pub fn send(summary: &str, body: &str) {
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification \"{}\" with title \"{}\"",
+            escape_applescript(body),
+            escape_applescript(summary)
+        );
+        let mut command = Command::new("osascript");
+        command.args(["-e", &script]);
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("notify-send");
+        command.args([
+            "--app-name",
+            "Turing Machine Simulator",
+            "--",
+            summary,
+            body,
+        ]);
+        command
+    } else {
+        return;
+    };
+
+    let _ = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+fn escape_applescript(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -16,6 +16,7 @@ pub struct NumberInput {
     value: i16,
     step: i16,
     limit: (i16, i16),
+    max_label: Option<String>, // Shown in place of the highest value
 }
 
 const MARGIN_VALUE_BUTTON: f32 = 10.0;
@@ -58,9 +59,16 @@ impl NumberInput {
 
             step,
             limit,
+            max_label: None,
         }
     }
 
+    /// Shows `label` in place of the highest value, for inputs whose last value is a mode.
+    pub fn with_max_label(mut self, label: &str) -> Self {
+        self.max_label = Some(label.to_string());
+        self
+    }
+
     pub fn draw(&self, ctx: &mut Context, canvas: &mut Canvas) -> GameResult {
         // Value rect
         let value_rect = graphics::Mesh::new_rectangle(
@@ -75,7 +83,10 @@ impl NumberInput {
         {
             let text_size = 20.0;
             let text_piece = graphics::Text::new(TextFragment {
-                text: format!("{}", self.value),
+                text: match &self.max_label {
+                    Some(label) if self.value == self.limit.1 => label.clone(),
+                    _ => format!("{}", self.value),
+                },
                 color: None,
                 scale: Some(PxScale {
                     x: text_size,
@@ -174,8 +185,8 @@ impl NumberInput {
         self.value
     }
 
-    pub fn percent(&self) -> f32 {
-        (self.value as f32 * 100.0 / self.limit.1 as f32) / 100.0
+    pub fn is_max(&self) -> bool {
+        self.value == self.limit.1
     }
 
     pub fn rect(&self) -> Rect {
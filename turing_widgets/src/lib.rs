@@ -0,0 +1,10 @@
+mod state_panel;
+mod tape_view;
+
+pub use state_panel::StatePanel;
+pub use tape_view::TapeView;
+
+use egui::Color32;
+
+/// Color of the head, the same one the simulator uses.
+pub const ACCENT_COLOR: Color32 = Color32::from_rgb(110, 157, 209);
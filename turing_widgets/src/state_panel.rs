@@ -0,0 +1,72 @@
+use egui::{Color32, Response, RichText, Ui, Widget};
+use turing_lib::automaton::Automaton;
+
+/// The name of a machine, its current state (with its description) and status, and its stack
+/// or registers if it has them, optionally with the explanation of the last step (see
+/// [`turing_lib::explain`]).
+pub struct StatePanel<'a> {
+    machine: &'a dyn Automaton,
+    explanation: Option<&'a str>,
+}
+
+impl<'a> StatePanel<'a> {
+    pub fn new(machine: &'a dyn Automaton) -> Self {
+        Self {
+            machine,
+            explanation: None,
+        }
+    }
+
+    pub fn with_explanation(mut self, explanation: &'a str) -> Self {
+        self.explanation = Some(explanation);
+        self
+    }
+}
+
+impl Widget for StatePanel<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let machine = self.machine;
+
+        ui.vertical(|ui| {
+            ui.heading(machine.name());
+
+            let state = match machine.state_description() {
+                Some(description) => format!(
+                    "Current state: \"{}\" ({description})",
+                    machine.current_state_name()
+                ),
+                None => format!("Current state: \"{}\"", machine.current_state_name()),
+            };
+            ui.label(state);
+
+            let status = match (machine.is_halted(), machine.is_accepting()) {
+                (false, _) => RichText::new("Running"),
+                (true, true) => RichText::new("Halted, accepts").color(Color32::DARK_GREEN),
+                (true, false) => RichText::new("Halted, rejects").color(Color32::DARK_RED),
+            };
+            ui.label(status);
+
+            if let Some(stack) = machine.stack() {
+                ui.label(format!(
+                    "Stack (bottom first): {}",
+                    stack.iter().collect::<String>()
+                ));
+            }
+
+            if let Some(registers) = machine.registers() {
+                let registers = registers
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, value)| format!("r{idx} = {value}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                ui.label(format!("Registers: {registers}"));
+            }
+
+            if let Some(explanation) = self.explanation {
+                ui.label(RichText::new(explanation).italics());
+            }
+        })
+        .response
+    }
+}
@@ -0,0 +1,100 @@
+use egui::{pos2, vec2, Align2, FontId, Rect, Response, Sense, Shape, Stroke, Ui, Vec2, Widget};
+use turing_lib::{automaton::Automaton, machine::Symbol};
+
+use crate::ACCENT_COLOR;
+
+const DEFAULT_VISIBLE_CELLS: usize = 7;
+const DEFAULT_CELL_SIZE: f32 = 40.0;
+const HEAD_TRIANGLE_SIZE: f32 = 10.0;
+const HEAD_TRIANGLE_MARGIN: f32 = 4.0;
+
+/// The tape of a machine around its head, like the simulator draws it, with the cell under the
+/// head highlighted and a triangle pointing at it.
+pub struct TapeView<'a> {
+    machine: &'a dyn Automaton,
+    visible_cells: usize,
+    cell_size: f32,
+}
+
+impl<'a> TapeView<'a> {
+    pub fn new(machine: &'a dyn Automaton) -> Self {
+        Self {
+            machine,
+            visible_cells: DEFAULT_VISIBLE_CELLS,
+            cell_size: DEFAULT_CELL_SIZE,
+        }
+    }
+
+    /// Number of cells shown, made odd so the head is in the middle.
+    pub fn with_visible_cells(mut self, visible_cells: usize) -> Self {
+        self.visible_cells = visible_cells | 1;
+        self
+    }
+
+    /// Width and height of every cell, in points.
+    pub fn with_cell_size(mut self, cell_size: f32) -> Self {
+        self.cell_size = cell_size;
+        self
+    }
+}
+
+impl Widget for TapeView<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let size = vec2(
+            self.cell_size * self.visible_cells as f32,
+            self.cell_size + HEAD_TRIANGLE_MARGIN + HEAD_TRIANGLE_SIZE,
+        );
+        let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        let head_idx = self.machine.head_idx() as isize;
+        let first_idx = head_idx - (self.visible_cells / 2) as isize;
+
+        for offset in 0..self.visible_cells {
+            let idx = first_idx + offset as isize;
+            let symbol = match self.machine.tape()[idx] {
+                Symbol::Mark(c) => c,
+                _ => self.machine.blank_symbol(),
+            };
+
+            let cell = Rect::from_min_size(
+                rect.min + vec2(offset as f32 * self.cell_size, 0.0),
+                Vec2::splat(self.cell_size),
+            );
+            let (fill, text_color) = if idx == head_idx {
+                (ACCENT_COLOR, visuals.strong_text_color())
+            } else {
+                (visuals.extreme_bg_color, visuals.text_color())
+            };
+
+            painter.rect_filled(cell, 0.0, fill);
+            painter.rect_stroke(cell, 0.0, visuals.widgets.noninteractive.fg_stroke);
+            painter.text(
+                cell.center(),
+                Align2::CENTER_CENTER,
+                symbol,
+                FontId::monospace(self.cell_size * 0.6),
+                text_color,
+            );
+        }
+
+        let center_x = rect.center().x;
+        let top = rect.min.y + self.cell_size + HEAD_TRIANGLE_MARGIN;
+        painter.add(Shape::convex_polygon(
+            vec![
+                pos2(center_x, top),
+                pos2(center_x + HEAD_TRIANGLE_SIZE, top + HEAD_TRIANGLE_SIZE),
+                pos2(center_x - HEAD_TRIANGLE_SIZE, top + HEAD_TRIANGLE_SIZE),
+            ],
+            ACCENT_COLOR,
+            Stroke::NONE,
+        ));
+
+        response.on_hover_text(format!("Head at cell {head_idx}"))
+    }
+}